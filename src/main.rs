@@ -4,17 +4,142 @@ extern crate sdl2;
 
 mod nes;
 use crate::nes::Nes;
+use crate::nes::frontend::headless::HeadlessFrontend;
+use crate::nes::frontend::sdl::SdlFrontend;
+use crate::nes::cassette::{Cassette, info};
+use crate::nes::nsf::{self, Nsf};
+use crate::nes::nsf::player::NsfPlayer;
+use crate::nes::ppu::frame_buffer::{FrameBuffer, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::nes::screen::Screen;
+use crate::nes::screen::config::ScreenConfig;
 
 use std::env;
+use std::time::{Duration, Instant};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let (screen_config, args) = ScreenConfig::from_args(&args).unwrap_or_else(|err| panic!("{}", err));
     let rom_path = match args.len() {
         0 | 1 => panic!("rom file path argument is not found."),
+        2 | 3 if args[1] == "bench-screen" => {
+            bench_screen(&args[2..], screen_config);
+            return;
+        },
+        2 if nsf::is_nsf_path(&args[1]) => {
+            let nsf = Nsf::open(&args[1]).unwrap_or_else(|err| panic!("{}", err));
+            NsfPlayer::new(nsf).and_then(|mut player| player.run()).unwrap_or_else(|err| panic!("{}", err));
+            return;
+        },
         2 => &args[1],
+        3 if args[1] == "--info" => {
+            print_rom_info(&args[2]);
+            return;
+        },
+        4 if args[1] == "--headless" => {
+            run_headless(&args[2..]);
+            return;
+        },
+        4..=6 if args[1] == "nsf2wav" => {
+            nsf_to_wav(&args[2..]);
+            return;
+        },
         _ => panic!("too match arguments."),
     };
 
     let mut nes = Nes::new(rom_path);
-    nes.run();
+    nes.run(&mut SdlFrontend::new(screen_config)).unwrap_or_else(|err| panic!("{}", err));
+}
+
+/// Prints the header of the rom, and the mapper numbers supported by the emulator.
+fn print_rom_info(rom_path: &str) {
+    let header = Cassette::read_header(rom_path).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    println!("{}", info::describe(&header));
+    println!();
+    print!("{}", info::coverage_table());
+}
+
+/// --headless <frames> <rom path>
+/// Runs the rom for the frames without the window and the audio device,
+/// and prints the emulation speed and the checksum of the last frame.
+fn run_headless(args: &[String]) {
+    let frames = args[0].parse().expect("frames is not a number.");
+    let mut nes = Nes::new(&args[1]);
+    let mut frontend = HeadlessFrontend::new(Some(frames));
+
+    let start = Instant::now();
+    nes.run(&mut frontend).unwrap_or_else(|err| panic!("{}", err));
+    let elapsed = start.elapsed();
+
+    println!("{} frames in {:?} ({:.1} FPS)", frontend.frame_count, elapsed, frontend.frame_count as f64 / elapsed.as_secs_f64());
+    println!("{} audio samples", frontend.samples.len());
+    if let Some(frame) = frontend.last_frame() {
+        // FNV-1a, to compare the output between the runs
+        let checksum = frame.pixels().iter().fold(0x811C_9DC5_u32, |hash, pixel| (hash ^ *pixel as u32).wrapping_mul(0x0100_0193));
+        println!("last frame checksum: {:08X}", checksum);
+    }
+}
+
+/// nsf2wav <nsf path> <wav path> [track (1 origin)] [seconds]
+/// Renders the track to the WAV file. (the starting track of the file for 120 seconds by default)
+fn nsf_to_wav(args: &[String]) {
+    const DEFAULT_SECONDS: usize = 120;
+
+    let nsf = Nsf::open(&args[0]).unwrap_or_else(|err| panic!("{}", err));
+    let track = match args.get(2) {
+        Some(track) => track.parse::<u8>().expect("track is not a number.").saturating_sub(1),
+        None => nsf.starting_track,
+    };
+    let seconds = match args.get(3) {
+        Some(seconds) => seconds.parse().expect("seconds is not a number."),
+        None => DEFAULT_SECONDS,
+    };
+
+    NsfPlayer::new(nsf)
+        .and_then(|mut player| player.render_wav(track, seconds, &args[1]))
+        .unwrap_or_else(|err| panic!("{}", err));
+}
+
+/// bench-screen [frames]
+/// Prints the frame times of the screen renderers: the texture update and the point drawing (the old one).
+/// The frames are not presented, so the vsync doesn't limit them.
+fn bench_screen(args: &[String], screen_config: ScreenConfig) {
+    const DEFAULT_FRAMES: usize = 300;
+
+    let frames = match args.first() {
+        Some(frames) => frames.parse().expect("frames is not a number."),
+        None => DEFAULT_FRAMES,
+    };
+
+    let sdl = sdl2::init().unwrap();
+    let mut screen = Screen::new(&sdl, screen_config);
+    let mut frame_buffer = FrameBuffer::new();
+    let mut measure = |draw: &dyn Fn(&mut Screen, &FrameBuffer)| -> (Duration, Duration) {
+        let mut total = Duration::from_secs(0);
+        let mut max = Duration::from_secs(0);
+        for frame in 0..frames {
+            // the pixels change every frame like a scrolling screen
+            for y in 0..SCREEN_HEIGHT {
+                for x in 0..SCREEN_WIDTH {
+                    frame_buffer.set(x, y, ((x + y + frame) & 0x3F) as u16);
+                }
+            }
+
+            let start = Instant::now();
+            draw(&mut screen, &frame_buffer);
+            let elapsed = start.elapsed();
+            total += elapsed;
+            max = max.max(elapsed);
+        }
+        (total / frames.max(1) as u32, max)
+    };
+
+    let texture = measure(&|screen, frame_buffer| screen.draw(frame_buffer));
+    let points = measure(&|screen, frame_buffer| screen.draw_points(frame_buffer));
+
+    println!("{} frames", frames);
+    println!("texture update : average {:?}, max {:?}", texture.0, texture.1);
+    println!("draw points    : average {:?}, max {:?}", points.0, points.1);
 }
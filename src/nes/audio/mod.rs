@@ -0,0 +1,100 @@
+pub mod speaker;
+pub mod wav;
+
+/// NTSC CPU clock (Hz)
+pub const CPU_CLOCK: usize = 1_789_773;
+pub const SAMPLE_RATE: usize = 44_100;
+
+/// Coefficient of the high pass filter (about 37 Hz at 44.1 kHz), as the one on the NES board.
+const HIGH_PASS_FILTER_COEFFICIENT: f32 = 0.995;
+
+/// Mixes the audio sources into the samples at the sample rate.
+///
+/// The APU is not emulated yet, so the cassette expansion audio is the only source for now.
+/// Each sample is the average level over its period, then the DC offset of
+/// the unipolar channels is removed by the high pass filter.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/APU_Mixer
+pub struct Mixer {
+    level_sum: f32,
+    level_cycle: usize,
+    cycle_remainder: usize,
+    previous_input: f32,
+    previous_output: f32,
+    samples: Vec<f32>,
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Mixer {
+            level_sum: 0.0,
+            level_cycle: 0,
+            cycle_remainder: 0,
+            previous_input: 0.0,
+            previous_output: 0.0,
+            samples: Vec::new(),
+        }
+    }
+
+    /// expansion_level is the cassette audio level during the cpu_cycle.
+    pub fn run(&mut self, cpu_cycle: usize, expansion_level: f32) {
+        self.level_sum += expansion_level * cpu_cycle as f32;
+        self.level_cycle += cpu_cycle;
+        self.cycle_remainder += cpu_cycle * SAMPLE_RATE;
+
+        while self.cycle_remainder >= CPU_CLOCK {
+            self.cycle_remainder -= CPU_CLOCK;
+
+            let level = if self.level_cycle == 0 { self.previous_input } else { self.level_sum / self.level_cycle as f32 };
+            self.level_sum = 0.0;
+            self.level_cycle = 0;
+
+            let output = HIGH_PASS_FILTER_COEFFICIENT * (self.previous_output + level - self.previous_input);
+            self.previous_input = level;
+            self.previous_output = output;
+            self.samples.push(output);
+        }
+    }
+
+    /// Takes the samples mixed since the last call.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+}
+
+#[cfg(test)]
+mod mixer_test {
+    use super::*;
+
+    #[test]
+    fn sample_rate() {
+        let mut mixer = Mixer::new();
+        for _ in 0..(CPU_CLOCK / 4) {
+            mixer.run(4, 0.0);
+        }
+        assert_eq!(mixer.take_samples().len(), SAMPLE_RATE - 1);
+        assert_eq!(mixer.take_samples().len(), 0);
+    }
+
+    #[test]
+    fn average_level() {
+        let mut mixer = Mixer::new();
+        // 1 sample takes about 40.6 cycles
+        mixer.run(20, 1.0);
+        mixer.run(21, 0.0);
+        let samples = mixer.take_samples();
+        assert_eq!(samples.len(), 1);
+        assert!((samples[0] - 0.995 * 20.0 / 41.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn high_pass_filter() {
+        let mut mixer = Mixer::new();
+        for _ in 0..CPU_CLOCK {
+            mixer.run(1, 0.5);
+        }
+        let samples = mixer.take_samples();
+        assert!(samples[0] > 0.4);
+        assert!(samples.last().unwrap().abs() < 0.001);
+    }
+}
@@ -0,0 +1,37 @@
+extern crate sdl2;
+
+use sdl2::Sdl;
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+
+use super::SAMPLE_RATE;
+
+/// Samples queued over this are dropped to keep the latency. (0.1 sec)
+const MAX_QUEUED_SAMPLES: usize = SAMPLE_RATE / 10;
+
+pub struct Speaker {
+    queue: AudioQueue<f32>,
+}
+
+impl Speaker {
+    pub fn new(sdl: &Sdl) -> Result<Speaker, String> {
+        let audio_subsystem = sdl.audio()?;
+        let spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let queue = audio_subsystem.open_queue::<f32, _>(None, &spec)?;
+        queue.resume();
+
+        Ok(Speaker { queue })
+    }
+
+    pub fn play(&mut self, samples: &[f32]) {
+        // the queue size is in bytes
+        let queued_samples = self.queue.size() as usize / std::mem::size_of::<f32>();
+        if queued_samples < MAX_QUEUED_SAMPLES {
+            self.queue.queue(samples);
+        }
+    }
+}
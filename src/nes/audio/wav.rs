@@ -0,0 +1,56 @@
+use std::io::{self, Write};
+
+use super::SAMPLE_RATE;
+
+/// Writes the samples as a WAV file. (16 bit PCM, mono, at SAMPLE_RATE)
+/// The samples are clipped to -1.0 - 1.0.
+///
+/// refer: http://soundfile.sapp.org/doc/WaveFormat/
+pub fn write<W: Write>(writer: &mut W, samples: &[f32]) -> io::Result<()> {
+    const CHANNELS: u16 = 1;
+    const BITS_PER_SAMPLE: u16 = 16;
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    let data_size = (samples.len() * block_align as usize) as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&(SAMPLE_RATE as u32).to_le_bytes())?;
+    writer.write_all(&(SAMPLE_RATE as u32 * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod wav_test {
+    use super::*;
+
+    #[test]
+    fn write_header_and_samples() {
+        let mut buf = Vec::new();
+        write(&mut buf, &[0.0, 1.0, -2.0]).unwrap();
+
+        assert_eq!(buf.len(), 44 + 6);
+        assert_eq!(&buf[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(*array_ref!(buf, 4, 4)), 36 + 6);
+        assert_eq!(&buf[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(*array_ref!(buf, 24, 4)), SAMPLE_RATE as u32);
+        assert_eq!(&buf[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(*array_ref!(buf, 40, 4)), 6);
+        assert_eq!(&buf[44..], &[0x00, 0x00, 0xFF, 0x7F, 0x01, 0x80]);
+    }
+}
@@ -1,5 +1,9 @@
 use super::CassetteInitializeError;
 
+/// Mappers of the boards without PRG RAM, the iNES default of 8 KB doesn't apply to them.
+/// (MMC2, VRC2a)
+const MAPPERS_WITHOUT_PROGRAM_RAM: [u8; 2] = [9, 22];
+
 #[derive(Debug, PartialEq)]
 pub struct INesHeader {
     /// ASCII letters 'NES' followed by 0x1A(EOF)
@@ -8,28 +12,110 @@ pub struct INesHeader {
     pub prg_size: u8,
     /// Number of pages for The character rom
     pub chr_size: u8,
+    /// Mapper number (lower nibble from flags 6, upper nibble from flags 7)
+    pub mapper_number: u8,
+    /// Nametable arrangement. false: horizontal mirroring, true: vertical mirroring
+    pub vertical_mirroring: bool,
+    /// Cartridge provides its own 2KB of VRAM for four-screen nametables
+    pub four_screen: bool,
+    /// Cartridge contains battery-backed PRG RAM
+    pub battery: bool,
+    /// Number of pages for The program ram (0 infers 1 page for compatibility)
+    pub prg_ram_size: u8,
+    /// Flags 8-15 are in the NES 2.0 format
+    pub is_nes2: bool,
+    /// Board variant of the mapper (NES 2.0 only, 0 for iNES)
+    pub submapper: u8,
+    /// PRG RAM + PRG NVRAM size in bytes (NES 2.0 only, 0 for iNES)
+    pub nes2_prg_ram_bytes: usize,
 }
 
 impl INesHeader {
-    pub fn new(buf: &Vec<u8>) -> Result<Self, CassetteInitializeError> {
+    pub fn new(buf: &[u8]) -> Result<Self, CassetteInitializeError> {
         // <iNES file format header>
         // 0-3: Constant $4E $45 $53 $1A ("NES" followed by MS-DOS end-of-file)
         // 4: Size of PRG ROM in 16 KB units
         // 5: Size of CHR ROM in 8 KB units (Value 0 means the board uses CHR RAM)
+        // 6: Flags 6 - Mapper, mirroring, battery, trainer
+        //    76543210
+        //    ||||||||
+        //    |||||||+- Mirroring: 0: horizontal, 1: vertical
+        //    ||||||+-- Cartridge contains battery-backed PRG RAM
+        //    |||||+--- 512-byte trainer at $7000-$71FF
+        //    ||||+---- Ignore mirroring control; provide four-screen VRAM
+        //    ++++----- Lower nybble of mapper number
+        // 7: Flags 7 - Mapper, VS/Playchoice, NES 2.0
+        //    ||||++--- If equal to 2, flags 8-15 are in NES 2.0 format
+        //    ++++----- Upper nybble of mapper number
+        // 8: Size of PRG RAM in 8 KB units (Value 0 infers 8 KB for compatibility)
+        //    NES 2.0: Submapper number (upper nybble), mapper number bits 8-11 (lower nybble)
+        // 10: NES 2.0: PRG NVRAM (upper nybble), PRG RAM (lower nybble) size, 64 << n bytes (0: none)
         //
         // refer: https://wiki.nesdev.com/w/index.php/INES
+        //        https://wiki.nesdev.com/w/index.php/NES_2.0
 
         let magic_numbers = *array_ref!(buf, 0, 4);
-        if &magic_numbers != "NES\x1A".as_bytes() {
+        if magic_numbers != "NES\x1A".as_bytes() {
             return Err(CassetteInitializeError::FormatError)
         };
 
+        let flags_6 = buf[6];
+        let flags_7 = buf[7];
+        let is_nes2 = flags_7 & 0b00001100 == 0b00001000;
+
         Ok(INesHeader {
-            magic_numbers: magic_numbers,
+            magic_numbers,
             prg_size: buf[4],
             chr_size: buf[5],
+            mapper_number: (flags_7 & 0xF0) | (flags_6 >> 4),
+            vertical_mirroring: flags_6 & 0b00000001 == 0b00000001,
+            four_screen: flags_6 & 0b00001000 == 0b00001000,
+            battery: flags_6 & 0b00000010 == 0b00000010,
+            prg_ram_size: if is_nes2 { 0 } else { buf[8] },
+            is_nes2,
+            submapper: if is_nes2 { buf[8] >> 4 } else { 0 },
+            nes2_prg_ram_bytes: if is_nes2 { Self::nes2_program_ram_bytes(buf.get(10).copied().unwrap_or(0)) } else { 0 },
         })
     }
+
+    /// Header for the disk images, which have no iNES header.
+    /// NES 2.0 reserves the mapper 20 for the disk system, and the disk is saved as the battery backed RAM.
+    pub fn disk_system() -> Self {
+        INesHeader {
+            magic_numbers: *b"FDS\x1A",
+            prg_size: 0,
+            chr_size: 0,
+            mapper_number: 20,
+            vertical_mirroring: false,
+            four_screen: false,
+            battery: true,
+            prg_ram_size: 0,
+            is_nes2: true,
+            submapper: 0,
+            nes2_prg_ram_bytes: 0x8000,
+        }
+    }
+
+    /// Byte 10 of the NES 2.0 header to the PRG RAM bytes, the volatile and the battery backed one together.
+    fn nes2_program_ram_bytes(byte: u8) -> usize {
+        let shift_to_bytes = |shift: u8| if shift == 0 { 0 } else { 64 << shift };
+        shift_to_bytes(byte & 0x0F) + shift_to_bytes(byte >> 4)
+    }
+
+    pub fn program_ram_bytes(&self) -> usize {
+        const PROGRAM_RAM_UNIT_SIZE: usize = 0x2000; // 8192 byte
+
+        if self.is_nes2 {
+            return self.nes2_prg_ram_bytes;
+        }
+
+        // The battery implies the PRG RAM even on the boards usually without it.
+        match self.prg_ram_size {
+            0 if !self.battery && MAPPERS_WITHOUT_PROGRAM_RAM.contains(&self.mapper_number) => 0,
+            0 => PROGRAM_RAM_UNIT_SIZE,
+            n => n as usize * PROGRAM_RAM_UNIT_SIZE,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -38,11 +124,11 @@ mod ines_header_test {
 
     #[test]
     fn new_success() {
-        // "N" "E" "S" "\x1A" "5" "3"
-        let rom_bytes = [78, 69, 83, 26, 53, 51];
-        assert_eq!(rom_bytes, *"NES\x1A53".as_bytes());
+        // "N" "E" "S" "\x1A" "5" "3" + flags 6 / flags 7 / prg ram size
+        let rom_bytes = [78, 69, 83, 26, 53, 51, 0x00, 0x00, 0x00];
+        assert_eq!(rom_bytes[0..6], *"NES\x1A53".as_bytes());
 
-        let ines_header = INesHeader::new(&rom_bytes.to_vec()).unwrap();
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
         assert_eq!(ines_header, INesHeader {
             magic_numbers: [
                 rom_bytes[0],
@@ -52,21 +138,88 @@ mod ines_header_test {
             ],
             prg_size: rom_bytes[4],
             chr_size: rom_bytes[5],
+            mapper_number: 0,
+            vertical_mirroring: false,
+            four_screen: false,
+            battery: false,
+            prg_ram_size: 0,
+            is_nes2: false,
+            submapper: 0,
+            nes2_prg_ram_bytes: 0,
         });
     }
 
+    #[test]
+    fn new_parse_flags() {
+        // flags 6: mapper lower 0x1, four screen, battery, vertical mirroring
+        // flags 7: mapper upper 0x4
+        // prg ram size: 4 pages
+        let rom_bytes = [78, 69, 83, 26, 1, 1, 0b00011011, 0b01000000, 4];
+
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
+        assert_eq!(ines_header.mapper_number, 0x41);
+        assert!(ines_header.vertical_mirroring);
+        assert!(ines_header.four_screen);
+        assert!(ines_header.battery);
+        assert_eq!(ines_header.program_ram_bytes(), 0x8000);
+    }
+
+    #[test]
+    fn new_parse_nes2_submapper() {
+        // flags 7: mapper upper 0x1, NES 2.0
+        // byte 8: submapper 2
+        let rom_bytes = [78, 69, 83, 26, 1, 1, 0b01010000, 0b00011000, 0x20];
+
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
+        assert_eq!(ines_header.mapper_number, 21);
+        assert_eq!(ines_header.submapper, 2);
+        assert_eq!(ines_header.program_ram_bytes(), 0);
+
+        // iNES: byte 8 is the PRG RAM size
+        let rom_bytes = [78, 69, 83, 26, 1, 1, 0b01010000, 0b00010000, 0x02];
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
+        assert_eq!(ines_header.submapper, 0);
+        assert_eq!(ines_header.program_ram_bytes(), 0x4000);
+    }
+
+    #[test]
+    fn new_parse_nes2_program_ram_size() {
+        // byte 10: PRG NVRAM 32 KB (64 << 9), PRG RAM none
+        let rom_bytes = [78, 69, 83, 26, 1, 1, 0x02, 0x08, 0x00, 0x00, 0x90];
+
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
+        assert_eq!(ines_header.program_ram_bytes(), 0x8000);
+
+        // 8 KB RAM + 2 KB NVRAM
+        let rom_bytes = [78, 69, 83, 26, 1, 1, 0x02, 0x08, 0x00, 0x00, 0x57];
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
+        assert_eq!(ines_header.program_ram_bytes(), 0x2800);
+    }
+
+    #[test]
+    fn program_ram_bytes_compatibility() {
+        let rom_bytes = [78, 69, 83, 26, 1, 1, 0x00, 0x00, 0];
+
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
+        assert_eq!(ines_header.program_ram_bytes(), 0x2000);
+
+        // VRC2a has no PRG RAM, unless the battery is there
+        let rom_bytes = [78, 69, 83, 26, 1, 1, 0x60, 0x10, 0];
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
+        assert_eq!(ines_header.program_ram_bytes(), 0);
+
+        let rom_bytes = [78, 69, 83, 26, 1, 1, 0x62, 0x10, 0];
+        let ines_header = INesHeader::new(&rom_bytes).unwrap();
+        assert_eq!(ines_header.program_ram_bytes(), 0x2000);
+    }
+
     #[test]
     fn new_format_error() {
-        // "N" "N" "S" "\x1A" "5" "3"
-        let rom_bytes = [78, 78, 83, 26, 53, 51];
-        assert_eq!(rom_bytes, *"NNS\x1A53".as_bytes());
-
-        let ines_header = INesHeader::new(&rom_bytes.to_vec());
-        assert!(
-            match ines_header {
-                Err(CassetteInitializeError::FormatError) => true,
-                _ => false,
-            }
-        );
+        // "N" "N" "S" "\x1A" "5" "3" + flags 6 / flags 7 / prg ram size
+        let rom_bytes = [78, 78, 83, 26, 53, 51, 0x00, 0x00, 0x00];
+        assert_eq!(rom_bytes[0..6], *"NNS\x1A53".as_bytes());
+
+        let ines_header = INesHeader::new(&rom_bytes);
+        assert!(matches!(ines_header, Err(CassetteInitializeError::FormatError)));
     }
 }
\ No newline at end of file
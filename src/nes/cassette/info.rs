@@ -0,0 +1,81 @@
+use super::header::INesHeader;
+use super::mapper::{Mirroring, SUPPORTED_MAPPERS};
+
+/// Board name of the mapper number. None: the mapper is not supported.
+pub fn mapper_name(mapper_number: u8) -> Option<&'static str> {
+    SUPPORTED_MAPPERS
+        .iter()
+        .find(|(number, _)| *number == mapper_number)
+        .map(|(_, name)| *name)
+}
+
+/// ROM information from the header, shown by the --info option.
+pub fn describe(header: &INesHeader) -> String {
+    let mapper = match mapper_name(header.mapper_number) {
+        Some(name) => format!("{} ({})", header.mapper_number, name),
+        None => format!("{} (not supported)", header.mapper_number),
+    };
+    let character_memory = match header.chr_size {
+        0 => "8 KB (RAM)".to_string(),
+        n => format!("{} KB", n as usize * 8),
+    };
+    let battery = if header.battery { " (battery backed)" } else { "" };
+
+    [
+        format!("Mapper    : {}", mapper),
+        format!("Submapper : {}", header.submapper),
+        format!("PRG ROM   : {} KB", header.prg_size as usize * 16),
+        format!("CHR       : {}", character_memory),
+        format!("PRG RAM   : {} KB{}", header.program_ram_bytes() / 1024, battery),
+        format!("Mirroring : {:?}", Mirroring::from_header(header)),
+    ].join("\n")
+}
+
+/// Table of the supported mapper numbers.
+pub fn coverage_table() -> String {
+    let mut table = format!("Supported mappers ({}):\n", SUPPORTED_MAPPERS.len());
+    for (number, name) in SUPPORTED_MAPPERS.iter() {
+        table.push_str(&format!("  {:>3}  {}\n", number, name));
+    }
+    table
+}
+
+#[cfg(test)]
+mod info_test {
+    use super::*;
+    use super::super::mapper;
+
+    fn header_of_mapper(mapper_number: u8) -> INesHeader {
+        INesHeader::new(&[
+            "NES\x1A".as_bytes().to_vec(),
+            vec![2, 0, (mapper_number << 4) | 0x03, mapper_number & 0xF0, 0],
+        ].concat()).unwrap()
+    }
+
+    #[test]
+    fn describe_header() {
+        let text = describe(&header_of_mapper(4));
+        assert!(text.contains("Mapper    : 4 (MMC3)"));
+        assert!(text.contains("PRG ROM   : 32 KB"));
+        assert!(text.contains("CHR       : 8 KB (RAM)"));
+        assert!(text.contains("PRG RAM   : 8 KB (battery backed)"));
+        assert!(text.contains("Mirroring : Vertical"));
+
+        assert!(describe(&header_of_mapper(0xFF)).contains("Mapper    : 255 (not supported)"));
+    }
+
+    #[test]
+    fn coverage_table_lists_mappers() {
+        let table = coverage_table();
+        assert!(table.starts_with(&format!("Supported mappers ({}):", SUPPORTED_MAPPERS.len())));
+        assert!(table.contains("  206  Namco 118\n"));
+    }
+
+    #[test]
+    fn supported_mappers_are_built() {
+        for (number, name) in SUPPORTED_MAPPERS.iter() {
+            let built = mapper::build(&header_of_mapper(*number), vec![0; 0x8000], vec![0; 0x2000]);
+            assert!(built.is_ok(), "mapper {} ({}) is not built", number, name);
+        }
+    }
+}
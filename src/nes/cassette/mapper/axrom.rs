@@ -0,0 +1,128 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x8000; // 32 KB
+
+/// Mapper 7 (AxROM)
+///
+/// CPU $8000-$FFFF: 32 KB switchable PRG ROM bank
+/// PPU $0000-$1FFF: 8 KB CHR RAM (fixed)
+///
+/// Writing to $8000-$FFFF:
+///   7  bit  0
+///   ---- ----
+///   xxxM xPPP
+///      |  |||
+///      |  +++- Select 32 KB PRG ROM bank
+///      +------ Select 1 KB VRAM page for all 4 nametables
+///
+/// AMROM / AOROM have bus conflicts, ANROM doesn't.
+/// NES 2.0 submapper 2 is the board with the bus conflict, 1 is without it.
+/// ANROM (no bus conflict) is the common board in the iNES dumps without the submapper.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/AxROM
+pub struct Axrom {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    bus_conflict: bool,
+    register: u8,
+}
+
+impl Axrom {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, submapper: u8) -> Self {
+        Axrom {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            bus_conflict: submapper == 2,
+            register: 0,
+        }
+    }
+
+    fn program_bank_count(&self) -> usize {
+        (self.program_rom.len() / PROGRAM_BANK_SIZE).max(1)
+    }
+}
+
+impl Mapper for Axrom {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0xFFFF => {
+                let bank = (self.register & 0b0111) as usize % self.program_bank_count();
+                let offset = bank * PROGRAM_BANK_SIZE + (addr as usize - 0x8000);
+                self.program_rom[offset % self.program_rom.len()]
+            },
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        if addr >= 0x8000 {
+            self.register = if self.bus_conflict {
+                data & self.read_cpu(addr)
+            } else {
+                data
+            };
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(addr as usize)
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        self.character_memory.write(addr as usize, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.register & 0b10000 == 0b10000 {
+            Mirroring::SingleScreenUpper
+        } else {
+            Mirroring::SingleScreenLower
+        }
+    }
+}
+
+#[cfg(test)]
+mod axrom_test {
+    use super::*;
+    use crate::nes::cassette::mapper::bank_filled_rom;
+    use crate::nes::cpu::{Bus, CpuBus};
+    use crate::nes::ppu::Ppu;
+    use crate::nes::ram::Ram;
+
+    #[test]
+    fn switch_program_bank() {
+        let mut axrom = Axrom::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], 0);
+        let mut ppu = Ppu::new();
+        let mut ram = Ram::new(vec![0; 0x0800]);
+        let mut bus = Bus::new(&mut axrom, &mut ppu, &mut ram);
+
+        bus.write(0x8000, 6);
+        assert_eq!(bus.read(0x8000), 6);
+        assert_eq!(bus.read(0xFFFF), 6);
+    }
+
+    #[test]
+    fn single_screen_mirroring() {
+        let mut axrom = Axrom::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], 0);
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+
+        axrom.write_cpu(0x8000, 0b10000);
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenUpper);
+
+        axrom.write_cpu(0x8000, 0b00000);
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+    }
+
+    #[test]
+    fn bus_conflict() {
+        // bank 0 is filled with 0x00, so every write is masked to bank 0
+        let mut axrom = Axrom::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], 2);
+        let mut ppu = Ppu::new();
+        let mut ram = Ram::new(vec![0; 0x0800]);
+        let mut bus = Bus::new(&mut axrom, &mut ppu, &mut ram);
+
+        bus.write(0x8000, 0b10101);
+        assert_eq!(bus.read(0x8000), 0);
+    }
+}
@@ -0,0 +1,252 @@
+const EEPROM_SIZE: usize = 0x100; // 24C02: 256 byte
+const PAGE_MASK: u8 = 0x07;       // 8 byte page write
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum State {
+    Idle,
+    DeviceAddress,
+    WordAddress,
+    Write,
+    Read,
+}
+
+/// Serial EEPROM (24C02) on the I2C bus.
+///
+/// The master (the CPU through the mapper) drives SCL and SDA, and reads SDA back.
+/// Start: SDA falls while SCL is high, Stop: SDA rises while SCL is high.
+/// The bits are taken on the rising edges of SCL, 8 bits and an acknowledge bit per byte.
+///
+///   write: start, device address ($A0), word address, data..., stop
+///   read : start, device address ($A1), data..., stop (from the current address)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/Bandai_FCG_board#Serial_EEPROM
+pub struct Eeprom {
+    data: Vec<u8>,
+    state: State,
+    /// State after the acknowledge bit
+    next_state: State,
+    address: u8,
+    shift_register: u8,
+    bit: u8,
+    scl: bool,
+    sda: bool,
+    output: bool,
+}
+
+impl Eeprom {
+    pub fn new() -> Self {
+        Eeprom {
+            data: vec![0; EEPROM_SIZE],
+            state: State::Idle,
+            next_state: State::Idle,
+            address: 0,
+            shift_register: 0,
+            bit: 0,
+            scl: false,
+            sda: false,
+            output: true,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn restore(&mut self, data: &[u8]) {
+        if data.len() == self.data.len() {
+            self.data.copy_from_slice(data);
+        }
+    }
+
+    /// SDA driven by the EEPROM. (the line is pulled up while released)
+    pub fn output(&self) -> bool {
+        self.output
+    }
+
+    pub fn write(&mut self, scl: bool, sda: bool) {
+        if self.scl && scl && self.sda != sda {
+            if sda {
+                self.stop();
+            } else {
+                self.start();
+            }
+        } else if !self.scl && scl {
+            self.rise(sda);
+        } else if self.scl && !scl {
+            self.fall();
+        }
+
+        self.scl = scl;
+        self.sda = sda;
+    }
+
+    fn start(&mut self) {
+        self.state = State::DeviceAddress;
+        self.bit = 0;
+        self.shift_register = 0;
+        self.output = true;
+    }
+
+    fn stop(&mut self) {
+        self.state = State::Idle;
+        self.output = true;
+    }
+
+    fn is_receiving(&self) -> bool {
+        matches!(self.state, State::DeviceAddress | State::WordAddress | State::Write)
+    }
+
+    fn rise(&mut self, sda: bool) {
+        match self.state {
+            State::Idle => {},
+            State::Read if self.bit < 8 => self.bit += 1,
+            State::Read => {
+                // acknowledge from the master: low to continue the sequential read
+                if sda {
+                    self.state = State::Idle;
+                } else {
+                    self.address = self.address.wrapping_add(1);
+                    self.shift_register = self.data[self.address as usize];
+                    self.bit = 0;
+                }
+            },
+            _ if self.bit < 8 => {
+                self.shift_register = (self.shift_register << 1) | sda as u8;
+                self.bit += 1;
+            },
+            _ => {
+                self.bit = 0;
+                self.state = self.next_state;
+                if self.state == State::Read {
+                    self.shift_register = self.data[self.address as usize];
+                }
+            },
+        }
+    }
+
+    fn fall(&mut self) {
+        if self.is_receiving() {
+            if self.bit == 8 {
+                self.receive_byte();
+            } else {
+                self.output = true;
+            }
+        } else if self.state == State::Read {
+            self.output = self.bit >= 8 || (self.shift_register >> (7 - self.bit)) & 0x01 == 0x01;
+        }
+    }
+
+    /// Takes the received byte, and drives the acknowledge bit when accepted.
+    fn receive_byte(&mut self) {
+        let byte = self.shift_register;
+        self.next_state = match self.state {
+            State::DeviceAddress if byte & 0xF0 != 0xA0 => State::Idle,
+            State::DeviceAddress if byte & 0x01 == 0x01 => State::Read,
+            State::DeviceAddress => State::WordAddress,
+            State::WordAddress => {
+                self.address = byte;
+                State::Write
+            },
+            _ => {
+                self.data[self.address as usize] = byte;
+                self.address = (self.address & !PAGE_MASK) | (self.address.wrapping_add(1) & PAGE_MASK);
+                State::Write
+            },
+        };
+
+        self.output = self.next_state == State::Idle;
+    }
+}
+
+#[cfg(test)]
+mod eeprom_test {
+    use super::*;
+
+    fn send_bit(eeprom: &mut Eeprom, bit: bool) {
+        eeprom.write(false, bit);
+        eeprom.write(true, bit);
+        eeprom.write(false, bit);
+    }
+
+    fn start(eeprom: &mut Eeprom) {
+        eeprom.write(false, true);
+        eeprom.write(true, true);
+        eeprom.write(true, false);
+    }
+
+    fn stop(eeprom: &mut Eeprom) {
+        eeprom.write(false, false);
+        eeprom.write(true, false);
+        eeprom.write(true, true);
+    }
+
+    /// Sends a byte, returns the acknowledge bit. (true: acknowledged)
+    fn send_byte(eeprom: &mut Eeprom, byte: u8) -> bool {
+        for bit in (0..8).rev() {
+            send_bit(eeprom, (byte >> bit) & 0x01 == 0x01);
+        }
+        eeprom.write(false, true);
+        let ack = !eeprom.output();
+        eeprom.write(true, true);
+        eeprom.write(false, true);
+        ack
+    }
+
+    fn receive_byte(eeprom: &mut Eeprom, is_last: bool) -> u8 {
+        let mut byte = 0;
+        for _ in 0..8 {
+            eeprom.write(false, true);
+            eeprom.write(true, true);
+            byte = (byte << 1) | eeprom.output() as u8;
+            eeprom.write(false, true);
+        }
+        send_bit(eeprom, is_last);
+        byte
+    }
+
+    #[test]
+    fn write_and_read() {
+        let mut eeprom = Eeprom::new();
+
+        start(&mut eeprom);
+        assert!(send_byte(&mut eeprom, 0xA0));
+        assert!(send_byte(&mut eeprom, 0x10));
+        assert!(send_byte(&mut eeprom, 0x12));
+        assert!(send_byte(&mut eeprom, 0x34));
+        stop(&mut eeprom);
+        assert_eq!(&eeprom.data()[0x10..0x12], &[0x12, 0x34]);
+
+        // random read: the word address, then the read from a repeated start
+        start(&mut eeprom);
+        assert!(send_byte(&mut eeprom, 0xA0));
+        assert!(send_byte(&mut eeprom, 0x10));
+        start(&mut eeprom);
+        assert!(send_byte(&mut eeprom, 0xA1));
+        assert_eq!(receive_byte(&mut eeprom, false), 0x12);
+        assert_eq!(receive_byte(&mut eeprom, true), 0x34);
+        stop(&mut eeprom);
+    }
+
+    #[test]
+    fn page_write_wraps() {
+        let mut eeprom = Eeprom::new();
+        start(&mut eeprom);
+        send_byte(&mut eeprom, 0xA0);
+        send_byte(&mut eeprom, 0x0F);
+        send_byte(&mut eeprom, 0x01);
+        send_byte(&mut eeprom, 0x02);
+        stop(&mut eeprom);
+
+        assert_eq!(eeprom.data()[0x0F], 0x01);
+        assert_eq!(eeprom.data()[0x08], 0x02);
+    }
+
+    #[test]
+    fn other_device_is_not_acknowledged() {
+        let mut eeprom = Eeprom::new();
+        start(&mut eeprom);
+        assert!(!send_byte(&mut eeprom, 0x50));
+        assert_eq!(eeprom.state, State::Idle);
+        assert!(eeprom.output());
+    }
+}
@@ -0,0 +1,271 @@
+pub mod eeprom;
+
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+use eeprom::Eeprom;
+
+const PROGRAM_BANK_SIZE: usize = 0x4000;   // 16 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 16 (Bandai FCG-1 / FCG-2 / LZ93D50)
+///
+/// CPU $6000-$7FFF: serial EEPROM read (bit 4)
+/// CPU $8000-$BFFF: 16 KB switchable PRG ROM bank
+/// CPU $C000-$FFFF: 16 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: eight 1 KB switchable CHR banks
+///
+/// Registers (A0-A3):
+///   $0-$7: CHR banks
+///   $8   : PRG ROM bank
+///   $9   : mirroring
+///   $A   : IRQ control (bit 0: enable), acknowledges the IRQ
+///   $B-$C: IRQ counter (low / high)
+///   $D   : EEPROM control (bit 5: SCL, bit 6: SDA, bit 7: SDA as input)
+///
+/// FCG-1/2 (submapper 4) decodes the registers at $6000-$7FFF, and $B-$C writes the counter.
+/// LZ93D50 (submapper 5) decodes them at $8000-$FFFF, $B-$C writes the latch, then $A copies it to the counter.
+/// Without the submapper, both are decoded, and the boards with the battery have the 24C02 EEPROM.
+///
+/// The IRQ counter counts down every CPU cycle, and the IRQ is triggered when it reaches 0.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_016
+///        https://wiki.nesdev.com/w/index.php/Bandai_FCG_board
+pub struct BandaiFcg {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    battery: bool,
+    submapper: u8,
+    program_bank: u8,
+    character_banks: [u8; 8],
+    mirroring: Mirroring,
+    irq_latch: u16,
+    irq_counter: u16,
+    is_irq_enabled: bool,
+    is_irq_pending: bool,
+    eeprom: Option<Eeprom>,
+}
+
+impl BandaiFcg {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, submapper: u8, battery: bool) -> Self {
+        let has_eeprom = submapper == 5 || (submapper == 0 && battery);
+
+        BandaiFcg {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            battery,
+            submapper,
+            program_bank: 0,
+            character_banks: [0; 8],
+            mirroring: Mirroring::Vertical,
+            irq_latch: 0,
+            irq_counter: 0,
+            is_irq_enabled: false,
+            is_irq_pending: false,
+            eeprom: if has_eeprom { Some(Eeprom::new()) } else { None },
+        }
+    }
+
+    fn is_register(&self, addr: u16) -> bool {
+        match (self.submapper, addr) {
+            (4, 0x6000..=0x7FFF) | (5, 0x8000..=0xFFFF) => true,
+            (4, _) | (5, _) => false,
+            (_, addr) => addr >= 0x6000,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match addr & 0x000F {
+            register @ 0x0..=0x7 => self.character_banks[register as usize] = data,
+            0x8 => self.program_bank = data & 0x0F,
+            0x9 => {
+                self.mirroring = match data & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            },
+            0xA => {
+                self.is_irq_enabled = data & 0x01 == 0x01;
+                self.is_irq_pending = false;
+                if self.submapper != 4 {
+                    self.irq_counter = self.irq_latch;
+                }
+            },
+            0xB => {
+                self.irq_latch = (self.irq_latch & 0xFF00) | data as u16;
+                if self.submapper != 5 {
+                    self.irq_counter = (self.irq_counter & 0xFF00) | data as u16;
+                }
+            },
+            0xC => {
+                self.irq_latch = (self.irq_latch & 0x00FF) | (data as u16) << 8;
+                if self.submapper != 5 {
+                    self.irq_counter = (self.irq_counter & 0x00FF) | (data as u16) << 8;
+                }
+            },
+            0xD => {
+                if let Some(eeprom) = self.eeprom.as_mut() {
+                    let is_input = data & 0x80 == 0x80;
+                    eeprom.write(data & 0x20 == 0x20, is_input || data & 0x40 == 0x40);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xBFFF => self.program_bank as usize,
+            _ => self.program_rom.len() / PROGRAM_BANK_SIZE - 1,
+        };
+
+        (bank * PROGRAM_BANK_SIZE + (addr as usize & 0x3FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let bank = self.character_banks[(addr >> 10) as usize & 0x07];
+        bank as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+}
+
+impl Mapper for BandaiFcg {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF => match self.eeprom.as_ref() {
+                Some(eeprom) if eeprom.output() => 0x10,
+                _ => 0x00,
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        if self.is_register(addr) {
+            self.write_register(addr, data);
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.is_irq_pending
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        if !self.is_irq_enabled {
+            return;
+        }
+
+        if self.irq_counter != 0 && self.irq_counter as usize <= cycle {
+            self.is_irq_pending = true;
+        }
+        self.irq_counter = self.irq_counter.wrapping_sub(cycle as u16);
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        match self.eeprom.as_ref() {
+            Some(eeprom) if self.battery => Some(eeprom.data()),
+            _ => None,
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if let (Some(eeprom), true) = (self.eeprom.as_mut(), self.battery) {
+            eeprom.restore(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod bandai_fcg_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_bandai_fcg(submapper: u8, battery: bool) -> BandaiFcg {
+        BandaiFcg::new(bank_filled_rom(16, PROGRAM_BANK_SIZE), bank_filled_rom(256, CHARACTER_BANK_SIZE), submapper, battery)
+    }
+
+    #[test]
+    fn register_address_by_submapper() {
+        let mut fcg = new_bandai_fcg(4, false);
+        fcg.write_cpu(0x8008, 3);
+        assert_eq!(fcg.read_cpu(0x8000), 0);
+        fcg.write_cpu(0x6008, 3);
+        assert_eq!(fcg.read_cpu(0x8000), 3);
+        assert_eq!(fcg.read_cpu(0xC000), 15);
+
+        let mut lz93d50 = new_bandai_fcg(5, false);
+        lz93d50.write_cpu(0x6008, 3);
+        assert_eq!(lz93d50.read_cpu(0x8000), 0);
+        lz93d50.write_cpu(0xFFF8, 3);
+        assert_eq!(lz93d50.read_cpu(0x8000), 3);
+
+        let mut ines = new_bandai_fcg(0, false);
+        ines.write_cpu(0x7FF0, 10);
+        ines.write_cpu(0x8007, 20);
+        ines.write_cpu(0x8009, 1);
+        assert_eq!(ines.read_ppu(0x0000), 10);
+        assert_eq!(ines.read_ppu(0x1C00), 20);
+        assert_eq!(ines.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn irq_counter() {
+        // FCG-1/2: the counter is written directly
+        let mut fcg = new_bandai_fcg(4, false);
+        fcg.write_cpu(0x600B, 0x10);
+        fcg.write_cpu(0x600C, 0x00);
+        fcg.write_cpu(0x600A, 0x01);
+        fcg.notify_cpu_cycle(15);
+        assert!(!fcg.irq());
+        fcg.notify_cpu_cycle(1);
+        assert!(fcg.irq());
+        fcg.write_cpu(0x600A, 0x00);
+        assert!(!fcg.irq());
+
+        // LZ93D50: the latch is copied by enabling
+        let mut lz93d50 = new_bandai_fcg(5, false);
+        lz93d50.write_cpu(0x800B, 0x10);
+        assert_eq!(lz93d50.irq_counter, 0);
+        lz93d50.write_cpu(0x800A, 0x01);
+        assert_eq!(lz93d50.irq_counter, 0x10);
+        lz93d50.notify_cpu_cycle(16);
+        assert!(lz93d50.irq());
+    }
+
+    #[test]
+    fn eeprom_through_registers() {
+        let mut fcg = new_bandai_fcg(5, true);
+        // start: SDA falls while SCL is high
+        for data in [0x60, 0x20].iter() {
+            fcg.write_cpu(0x800D, *data);
+        }
+        // device address $A0
+        for bit in (0..8).rev() {
+            let sda = ((0xA0 >> bit) & 0x01) << 6;
+            for scl in [0x00, 0x20, 0x00].iter() {
+                fcg.write_cpu(0x800D, sda | scl);
+            }
+        }
+        // acknowledged: SDA low while the master releases it
+        fcg.write_cpu(0x800D, 0x80);
+        assert_eq!(fcg.read_cpu(0x6000) & 0x10, 0x00);
+
+        assert_eq!(fcg.battery_backed_ram().unwrap().len(), 0x100);
+        assert!(new_bandai_fcg(4, true).battery_backed_ram().is_none());
+    }
+}
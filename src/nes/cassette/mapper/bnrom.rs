@@ -0,0 +1,173 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x8000;   // 32 KB
+const CHARACTER_BANK_SIZE: usize = 0x1000; // 4 KB
+
+/// Mapper 34 (BNROM / NINA-001)
+///
+/// BNROM
+/// CPU $8000-$FFFF: 32 KB switchable PRG ROM bank (written to $8000-$FFFF, with bus conflict)
+/// PPU $0000-$1FFF: 8 KB CHR RAM
+///
+/// NINA-001
+/// CPU $6000-$7FFF: 8 KB PRG RAM
+/// CPU $8000-$FFFF: 32 KB switchable PRG ROM bank ($7FFD)
+/// PPU $0000-$0FFF: 4 KB switchable CHR ROM bank ($7FFE)
+/// PPU $1000-$1FFF: 4 KB switchable CHR ROM bank ($7FFF)
+///
+/// The registers of the NINA-001 are written through to the PRG RAM.
+/// Without the submapper (1: NINA-001, 2: BNROM), the boards with more than 8 KB CHR ROM are NINA-001.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_034
+pub struct Bnrom {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    mirroring: Mirroring,
+    is_nina001: bool,
+    program_bank: u8,
+    character_banks: [u8; 2],
+}
+
+impl Bnrom {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring, submapper: u8, program_ram_size: usize, battery: bool) -> Self {
+        let is_nina001 = match submapper {
+            1 => true,
+            2 => false,
+            _ => character_rom.len() > 0x2000,
+        };
+
+        Bnrom {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: if is_nina001 { vec![0; program_ram_size] } else { Vec::new() },
+            battery,
+            mirroring,
+            is_nina001,
+            program_bank: 0,
+            character_banks: [0, 1],
+        }
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        if self.is_nina001 {
+            let bank = self.character_banks[(addr >> 12) as usize & 0x01];
+            bank as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x0FFF)
+        } else {
+            addr as usize
+        }
+    }
+}
+
+impl Mapper for Bnrom {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => {
+                let offset = self.program_bank as usize * PROGRAM_BANK_SIZE + (addr as usize - 0x8000);
+                self.program_rom[offset % self.program_rom.len()]
+            },
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+
+                match addr {
+                    0x7FFD => self.program_bank = data & 0x01,
+                    0x7FFE => self.character_banks[0] = data & 0x0F,
+                    0x7FFF => self.character_banks[1] = data & 0x0F,
+                    _ => {},
+                }
+            },
+            0x8000..=0xFFFF if !self.is_nina001 => self.program_bank = data & self.read_cpu(addr),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery && !self.program_ram.is_empty() {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod bnrom_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    #[test]
+    fn bnrom_program_bank() {
+        let mut program_rom = bank_filled_rom(4, PROGRAM_BANK_SIZE);
+        program_rom[0x0000] = 0xFF; // no bus conflict on the written address
+        let mut bnrom = Bnrom::new(program_rom, vec![], Mirroring::Vertical, 0, 0x2000, false);
+        assert!(!bnrom.is_nina001);
+
+        bnrom.write_cpu(0x8000, 3);
+        assert_eq!(bnrom.read_cpu(0x8001), 3);
+        assert_eq!(bnrom.read_cpu(0xFFFF), 3);
+
+        // CHR RAM, no PRG RAM
+        bnrom.write_ppu(0x1234, 0x56);
+        assert_eq!(bnrom.read_ppu(0x1234), 0x56);
+        bnrom.write_cpu(0x6000, 0x12);
+        assert_eq!(bnrom.read_cpu(0x6000), 0x00);
+    }
+
+    #[test]
+    fn nina001_registers() {
+        let mut nina001 = Bnrom::new(
+            bank_filled_rom(2, PROGRAM_BANK_SIZE),
+            bank_filled_rom(16, CHARACTER_BANK_SIZE),
+            Mirroring::Horizontal,
+            0,
+            0x2000,
+            false,
+        );
+        assert!(nina001.is_nina001);
+
+        nina001.write_cpu(0x7FFD, 1);
+        nina001.write_cpu(0x7FFE, 5);
+        nina001.write_cpu(0x7FFF, 9);
+        assert_eq!(nina001.read_cpu(0x8000), 1);
+        assert_eq!(nina001.read_ppu(0x0000), 5);
+        assert_eq!(nina001.read_ppu(0x1000), 9);
+        // written through to the PRG RAM
+        assert_eq!(nina001.read_cpu(0x7FFF), 9);
+
+        // writes to the ROM don't switch the bank
+        nina001.write_cpu(0x8000, 0);
+        assert_eq!(nina001.read_cpu(0x8000), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x4000; // 16 KB
+
+/// Mapper 71 (Camerica / Codemasters BF909x)
+///
+/// CPU $8000-$BFFF: 16 KB switchable PRG ROM bank ($C000-$FFFF)
+/// CPU $C000-$FFFF: 16 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: 8 KB CHR RAM
+///
+/// The BF9097 board (Fire Hawk, submapper 1) has a one-screen mirroring register at $8000-$9FFF (bit 4).
+/// Without the submapper, the mirroring of the header is used until the register is written.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_071
+pub struct Camerica {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    mirroring: Mirroring,
+    has_mirroring_register: bool,
+    program_bank: u8,
+}
+
+impl Camerica {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring, submapper: u8) -> Self {
+        Camerica {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            mirroring: if submapper == 1 { Mirroring::SingleScreenLower } else { mirroring },
+            // the other boards ignore the writes, but Fire Hawk is often dumped without the submapper.
+            has_mirroring_register: submapper != 2,
+            program_bank: 0,
+        }
+    }
+
+    fn program_bank_count(&self) -> usize {
+        (self.program_rom.len() / PROGRAM_BANK_SIZE).max(1)
+    }
+}
+
+impl Mapper for Camerica {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        let bank = match addr {
+            0x8000..=0xBFFF => self.program_bank as usize % self.program_bank_count(),
+            0xC000..=0xFFFF => self.program_bank_count() - 1,
+            _ => return 0,
+        };
+
+        self.program_rom[bank * PROGRAM_BANK_SIZE + (addr as usize & 0x3FFF)]
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            // $8000-$8FFF is the CIC stun of the other boards, only $9000-$9FFF is decoded on them
+            0x9000..=0x9FFF if self.has_mirroring_register => {
+                self.mirroring = if data & 0x10 == 0 { Mirroring::SingleScreenLower } else { Mirroring::SingleScreenUpper };
+            },
+            0xC000..=0xFFFF => self.program_bank = data,
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(addr as usize)
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        self.character_memory.write(addr as usize, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod camerica_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    #[test]
+    fn program_bank() {
+        let mut camerica = Camerica::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], Mirroring::Vertical, 0);
+        camerica.write_cpu(0xC000, 3);
+        assert_eq!(camerica.read_cpu(0x8000), 3);
+        assert_eq!(camerica.read_cpu(0xC000), 7);
+
+        // the register ignores the bus conflict
+        camerica.write_cpu(0xFFFF, 5);
+        assert_eq!(camerica.read_cpu(0xBFFF), 5);
+    }
+
+    #[test]
+    fn mirroring_register() {
+        let mut camerica = Camerica::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], Mirroring::Vertical, 0);
+        assert_eq!(camerica.mirroring(), Mirroring::Vertical);
+        camerica.write_cpu(0x9000, 0x10);
+        assert_eq!(camerica.mirroring(), Mirroring::SingleScreenUpper);
+
+        let mut fire_hawk = Camerica::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], Mirroring::Vertical, 1);
+        assert_eq!(fire_hawk.mirroring(), Mirroring::SingleScreenLower);
+        fire_hawk.write_cpu(0x9FFF, 0x10);
+        assert_eq!(fire_hawk.mirroring(), Mirroring::SingleScreenUpper);
+
+        // BF9093 has no mirroring register
+        let mut bf9093 = Camerica::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], Mirroring::Horizontal, 2);
+        bf9093.write_cpu(0x9000, 0x10);
+        assert_eq!(bf9093.mirroring(), Mirroring::Horizontal);
+    }
+}
@@ -0,0 +1,67 @@
+const CHARACTER_RAM_SIZE: usize = 0x2000;
+
+/// Pattern table memory on the cassette.
+/// CHR ROM, or 8 KB CHR RAM when the cassette has no CHR ROM.
+pub struct CharacterMemory {
+    buf: Vec<u8>,
+    is_ram: bool,
+}
+
+impl CharacterMemory {
+    pub fn new(character_rom: Vec<u8>) -> Self {
+        if character_rom.is_empty() {
+            CharacterMemory {
+                buf: vec![0; CHARACTER_RAM_SIZE],
+                is_ram: true,
+            }
+        } else {
+            CharacterMemory {
+                buf: character_rom,
+                is_ram: false,
+            }
+        }
+    }
+
+    /// Offsets over the memory size wrap around, as unconnected bank bits do on the board.
+    pub fn read(&self, offset: usize) -> u8 {
+        self.buf[offset % self.buf.len()]
+    }
+
+    pub fn write(&mut self, offset: usize, data: u8) {
+        if self.is_ram {
+            let len = self.buf.len();
+            self.buf[offset % len] = data;
+        }
+    }
+}
+
+#[cfg(test)]
+mod character_memory_test {
+    use super::*;
+
+    #[test]
+    fn ram_when_character_rom_is_empty() {
+        let mut memory = CharacterMemory::new(vec![]);
+        memory.write(0x1FFF, 0xFF);
+
+        assert_eq!(memory.read(0x1FFF), 0xFF);
+        assert_eq!(memory.read(0x3FFF), 0xFF); // 8 KB RAM
+    }
+
+    #[test]
+    fn ignore_write_to_rom() {
+        let mut memory = CharacterMemory::new(vec![0x10; 0x2000]);
+        memory.write(0x0000, 0xFF);
+
+        assert_eq!(memory.read(0x0000), 0x10);
+    }
+
+    #[test]
+    fn wrap_around_offset() {
+        let mut rom = vec![0; 0x2000];
+        rom[0x0001] = 0xFF;
+        let memory = CharacterMemory::new(rom);
+
+        assert_eq!(memory.read(0x2001), 0xFF);
+    }
+}
@@ -0,0 +1,118 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const CHARACTER_BANK_SIZE: usize = 0x2000; // 8 KB
+
+/// Mapper 3 (CNROM)
+///
+/// CPU $8000-$FFFF: 16 KB or 32 KB PRG ROM (fixed, 16 KB is mirrored)
+/// PPU $0000-$1FFF: 8 KB switchable CHR ROM bank
+///
+/// Writing to $8000-$FFFF selects the CHR bank. The written value is ANDed with
+/// the ROM byte at the same address (bus conflict).
+/// NES 2.0 submapper 1 is the board without the bus conflict, 2 is with it. (the default)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/CNROM
+pub struct Cnrom {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    mirroring: Mirroring,
+    bus_conflict: bool,
+    character_bank: u8,
+}
+
+impl Cnrom {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring, submapper: u8) -> Self {
+        Cnrom {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            mirroring,
+            bus_conflict: submapper != 1,
+            character_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0xFFFF => self.program_rom[(addr as usize - 0x8000) % self.program_rom.len()],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        if addr >= 0x8000 {
+            self.character_bank = if self.bus_conflict {
+                data & self.read_cpu(addr)
+            } else {
+                data
+            };
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_bank as usize * CHARACTER_BANK_SIZE + addr as usize)
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        self.character_memory.write(self.character_bank as usize * CHARACTER_BANK_SIZE + addr as usize, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod cnrom_test {
+    use super::*;
+    use crate::nes::cassette::mapper::bank_filled_rom;
+    use crate::nes::cpu::{Bus, CpuBus};
+    use crate::nes::ppu::Ppu;
+    use crate::nes::ram::Ram;
+
+    #[test]
+    fn mirror_program_rom_16k() {
+        let mut program_rom = vec![0; 0x4000];
+        program_rom[0x0010] = 0x4F;
+        let mut cnrom = Cnrom::new(program_rom, bank_filled_rom(4, CHARACTER_BANK_SIZE), Mirroring::Horizontal, 0);
+        let mut ppu = Ppu::new();
+        let mut ram = Ram::new(vec![0; 0x0800]);
+        let mut bus = Bus::new(&mut cnrom, &mut ppu, &mut ram);
+
+        assert_eq!(bus.read(0x8010), 0x4F);
+        assert_eq!(bus.read(0xC010), 0x4F);
+    }
+
+    #[test]
+    fn switch_character_bank() {
+        let mut cnrom = Cnrom::new(vec![0xFF; 0x8000], bank_filled_rom(4, CHARACTER_BANK_SIZE), Mirroring::Horizontal, 0);
+
+        {
+            let mut ppu = Ppu::new();
+            let mut ram = Ram::new(vec![0; 0x0800]);
+            let mut bus = Bus::new(&mut cnrom, &mut ppu, &mut ram);
+            bus.write(0x8000, 2);
+        }
+
+        assert_eq!(cnrom.read_ppu(0x0000), 2);
+        assert_eq!(cnrom.read_ppu(0x1FFF), 2);
+    }
+
+    #[test]
+    fn bus_conflict() {
+        let mut cnrom = Cnrom::new(vec![0x01; 0x8000], bank_filled_rom(4, CHARACTER_BANK_SIZE), Mirroring::Horizontal, 0);
+        cnrom.write_cpu(0x8000, 3);
+        assert_eq!(cnrom.read_ppu(0x0000), 1);
+
+        let mut cnrom = Cnrom::new(vec![0x01; 0x8000], bank_filled_rom(4, CHARACTER_BANK_SIZE), Mirroring::Horizontal, 2);
+        cnrom.write_cpu(0x8000, 3);
+        assert_eq!(cnrom.read_ppu(0x0000), 1);
+
+        // submapper 1: no bus conflict
+        let mut cnrom = Cnrom::new(vec![0x01; 0x8000], bank_filled_rom(4, CHARACTER_BANK_SIZE), Mirroring::Horizontal, 1);
+        cnrom.write_cpu(0x8000, 3);
+        assert_eq!(cnrom.read_ppu(0x0000), 3);
+    }
+}
@@ -0,0 +1,102 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x8000;   // 32 KB
+const CHARACTER_BANK_SIZE: usize = 0x2000; // 8 KB
+
+/// Mapper 11 (Color Dreams)
+///
+/// CPU $8000-$FFFF: 32 KB switchable PRG ROM bank
+/// PPU $0000-$1FFF: 8 KB switchable CHR ROM bank
+///
+/// Writing to $8000-$FFFF (with bus conflict):
+///   7  bit  0
+///   ---- ----
+///   CCCC LLPP
+///   |||| ||||
+///   |||| ||++- Select 32 KB PRG ROM bank
+///   |||| ++--- Lockout defeat (not used by the emulator)
+///   ++++------ Select 8 KB CHR ROM bank
+///
+/// refer: https://wiki.nesdev.com/w/index.php/Color_Dreams
+pub struct ColorDreams {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    mirroring: Mirroring,
+    register: u8,
+}
+
+impl ColorDreams {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+        ColorDreams {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            mirroring,
+            register: 0,
+        }
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        (self.register >> 4) as usize * CHARACTER_BANK_SIZE + addr as usize
+    }
+}
+
+impl Mapper for ColorDreams {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0xFFFF => {
+                let bank = (self.register & 0b11) as usize;
+                let offset = bank * PROGRAM_BANK_SIZE + (addr as usize - 0x8000);
+                self.program_rom[offset % self.program_rom.len()]
+            },
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        if addr >= 0x8000 {
+            self.register = data & self.read_cpu(addr);
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod color_dreams_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    #[test]
+    fn switch_program_and_character_bank() {
+        let mut program_rom = bank_filled_rom(4, PROGRAM_BANK_SIZE);
+        program_rom[0x0000] = 0xFF; // no bus conflict on the written address
+        let mut color_dreams = ColorDreams::new(program_rom, bank_filled_rom(16, CHARACTER_BANK_SIZE), Mirroring::Vertical);
+
+        color_dreams.write_cpu(0x8000, 0xA2);
+        assert_eq!(color_dreams.read_cpu(0x8001), 2);
+        assert_eq!(color_dreams.read_cpu(0xFFFF), 2);
+        assert_eq!(color_dreams.read_ppu(0x0000), 10);
+    }
+
+    #[test]
+    fn bus_conflict() {
+        let mut color_dreams = ColorDreams::new(vec![0x31; 0x20000], bank_filled_rom(16, CHARACTER_BANK_SIZE), Mirroring::Vertical);
+        color_dreams.write_cpu(0x8000, 0xF3);
+
+        assert_eq!(color_dreams.register, 0x31);
+        assert_eq!(color_dreams.read_ppu(0x0000), 3);
+    }
+}
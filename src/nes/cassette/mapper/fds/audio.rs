@@ -0,0 +1,359 @@
+const WAVE_TABLE_SIZE: usize = 64;
+const MODULATION_TABLE_SIZE: usize = 64;
+
+/// The gain over 32 is output as 32.
+const MAX_OUTPUT_GAIN: u32 = 32;
+
+/// Master volume 2/2, 2/3, 2/4, 2/5 in 1/60.
+const MASTER_VOLUMES: [u32; 4] = [60, 40, 30, 24];
+
+/// The full output (6 bit wave x gain 32) is about 2.4 times as loud as
+/// an APU pulse at the full volume. (15 x 0.00752)
+const LEVEL: f32 = 0.000134;
+
+/// Volume / modulation envelope of the FDS audio.
+struct Envelope {
+    gain: u8,
+    speed: u8,
+    is_increase: bool,
+    is_disabled: bool,
+    timer: usize,
+}
+
+impl Envelope {
+    fn new() -> Self {
+        Envelope {
+            gain: 0,
+            speed: 0,
+            is_increase: false,
+            is_disabled: true,
+            timer: 0,
+        }
+    }
+
+    /// $4080 / $4084 (bits 0-5: speed or gain, bit 6: increase, bit 7: disable the envelope)
+    fn write(&mut self, data: u8, master_speed: u8) {
+        self.speed = data & 0x3F;
+        self.is_increase = data & 0x40 == 0x40;
+        self.is_disabled = data & 0x80 == 0x80;
+        if self.is_disabled {
+            self.gain = self.speed;
+        }
+        self.timer = self.period(master_speed);
+    }
+
+    /// CPU cycles per envelope step.
+    fn period(&self, master_speed: u8) -> usize {
+        8 * (self.speed as usize + 1) * master_speed as usize
+    }
+
+    fn clock(&mut self, master_speed: u8) {
+        if self.is_disabled || master_speed == 0 {
+            return;
+        }
+
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period(master_speed);
+        if self.is_increase && self.gain < 32 {
+            self.gain += 1;
+        } else if !self.is_increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+    }
+}
+
+/// FDS expansion audio: a 64 step wavetable channel with the frequency modulation unit.
+///
+/// $4040-$407F: wavetable (6 bit samples), writable while $4089 bit 7 is set
+/// $4080      : volume envelope
+/// $4082-$4083: wave frequency (12 bit), $4083 bit 6: halt the envelopes, bit 7: halt and reset the wave
+/// $4084      : modulation envelope (the modulation gain)
+/// $4085      : modulation counter (7 bit signed)
+/// $4086-$4087: modulation frequency (12 bit), $4087 bit 7: halt the modulation
+/// $4088      : modulation table input (3 bit), written twice per write while the modulation is halted
+/// $4089      : bits 0-1: master volume, bit 7: wavetable write enable (holds the output)
+/// $408A      : master envelope speed
+/// $4090/$4092: volume / modulation gain read
+///
+/// The wave steps when the 16 bit accumulator of the frequency overflows,
+/// and the modulation table shifts the frequency by the modulation counter x gain.
+/// The low pass filter of the RAM adapter is not emulated.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/FDS_audio
+pub struct FdsAudio {
+    wave_table: [u8; WAVE_TABLE_SIZE],
+    is_wave_write_enabled: bool,
+    wave_frequency: u16,
+    wave_accumulator: u32,
+    wave_position: usize,
+    is_wave_halted: bool,
+    is_envelope_halted: bool,
+    volume: Envelope,
+    modulation: Envelope,
+    modulation_table: [u8; MODULATION_TABLE_SIZE],
+    modulation_position: usize,
+    modulation_frequency: u16,
+    modulation_accumulator: u32,
+    modulation_counter: i8,
+    is_modulation_halted: bool,
+    master_volume: usize,
+    master_envelope_speed: u8,
+    output: u32,
+}
+
+impl FdsAudio {
+    pub fn new() -> Self {
+        FdsAudio {
+            wave_table: [0; WAVE_TABLE_SIZE],
+            is_wave_write_enabled: false,
+            wave_frequency: 0,
+            wave_accumulator: 0,
+            wave_position: 0,
+            is_wave_halted: true,
+            is_envelope_halted: false,
+            volume: Envelope::new(),
+            modulation: Envelope::new(),
+            modulation_table: [0; MODULATION_TABLE_SIZE],
+            modulation_position: 0,
+            modulation_frequency: 0,
+            modulation_accumulator: 0,
+            modulation_counter: 0,
+            is_modulation_halted: true,
+            master_volume: 0,
+            master_envelope_speed: 0xE8,
+            output: 0,
+        }
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0x4040..=0x407F => 0x40 | self.wave_table[addr as usize & 0x3F],
+            0x4090 => 0x40 | self.volume.gain,
+            0x4092 => 0x40 | self.modulation.gain,
+            _ => 0x40,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4040..=0x407F if self.is_wave_write_enabled => self.wave_table[addr as usize & 0x3F] = data & 0x3F,
+            0x4080 => self.volume.write(data, self.master_envelope_speed),
+            0x4082 => self.wave_frequency = (self.wave_frequency & 0x0F00) | data as u16,
+            0x4083 => {
+                self.wave_frequency = (self.wave_frequency & 0x00FF) | (data as u16 & 0x0F) << 8;
+                self.is_envelope_halted = data & 0x40 == 0x40;
+                self.is_wave_halted = data & 0x80 == 0x80;
+                if self.is_wave_halted {
+                    self.wave_accumulator = 0;
+                    self.wave_position = 0;
+                }
+            },
+            0x4084 => self.modulation.write(data, self.master_envelope_speed),
+            0x4085 => self.modulation_counter = Self::wrap_counter(data as i16),
+            0x4086 => self.modulation_frequency = (self.modulation_frequency & 0x0F00) | data as u16,
+            0x4087 => {
+                self.modulation_frequency = (self.modulation_frequency & 0x00FF) | (data as u16 & 0x0F) << 8;
+                self.is_modulation_halted = data & 0x80 == 0x80;
+                if self.is_modulation_halted {
+                    self.modulation_accumulator = 0;
+                }
+            },
+            0x4088 if self.is_modulation_halted => {
+                self.modulation_table[self.modulation_position] = data & 0x07;
+                self.modulation_table[self.modulation_position + 1] = data & 0x07;
+                self.modulation_position = (self.modulation_position + 2) % MODULATION_TABLE_SIZE;
+            },
+            0x4089 => {
+                self.master_volume = data as usize & 0x03;
+                self.is_wave_write_enabled = data & 0x80 == 0x80;
+            },
+            0x408A => self.master_envelope_speed = data,
+            _ => {},
+        }
+    }
+
+    /// The modulation counter is 7 bit signed, -64 to 63.
+    fn wrap_counter(counter: i16) -> i8 {
+        (((counter + 64) & 0x7F) - 64) as i8
+    }
+
+    pub fn clock(&mut self, cpu_cycle: usize) {
+        for _ in 0..cpu_cycle {
+            self.clock_once();
+        }
+    }
+
+    fn clock_once(&mut self) {
+        if !self.is_envelope_halted && !self.is_wave_halted {
+            self.volume.clock(self.master_envelope_speed);
+            self.modulation.clock(self.master_envelope_speed);
+        }
+
+        if !self.is_modulation_halted && self.modulation_frequency > 0 {
+            self.modulation_accumulator += self.modulation_frequency as u32;
+            if self.modulation_accumulator >= 0x10000 {
+                self.modulation_accumulator &= 0xFFFF;
+                self.step_modulation();
+            }
+        }
+
+        if self.is_wave_halted {
+            return;
+        }
+
+        if !self.is_wave_write_enabled {
+            self.wave_accumulator += self.wave_pitch();
+            if self.wave_accumulator >= 0x10000 {
+                self.wave_accumulator &= 0xFFFF;
+                self.wave_position = (self.wave_position + 1) % WAVE_TABLE_SIZE;
+            }
+
+            let gain = (self.volume.gain as u32).min(MAX_OUTPUT_GAIN);
+            self.output = self.wave_table[self.wave_position] as u32 * gain;
+        }
+    }
+
+    fn step_modulation(&mut self) {
+        let counter = self.modulation_counter as i16;
+        let counter = match self.modulation_table[self.modulation_position] {
+            0 => counter,
+            1 => counter + 1,
+            2 => counter + 2,
+            3 => counter + 4,
+            4 => 0,
+            5 => counter - 4,
+            6 => counter - 2,
+            _ => counter - 1,
+        };
+        self.modulation_counter = Self::wrap_counter(counter);
+        self.modulation_position = (self.modulation_position + 1) % MODULATION_TABLE_SIZE;
+    }
+
+    /// Wave frequency shifted by the modulation, with the rounding of the hardware.
+    fn wave_pitch(&self) -> u32 {
+        let pitch = self.wave_frequency as i32;
+        if self.is_modulation_halted {
+            return pitch as u32;
+        }
+
+        let counter = self.modulation_counter as i32;
+        let product = counter * self.modulation.gain as i32;
+        let mut offset = product >> 4;
+        if product & 0x0F != 0 && offset & 0x80 == 0 {
+            offset += if counter < 0 { -1 } else { 2 };
+        }
+        if offset >= 192 {
+            offset -= 256;
+        } else if offset < -64 {
+            offset += 256;
+        }
+
+        let product = pitch * offset;
+        let mut offset = product >> 6;
+        if product & 0x3F >= 0x20 {
+            offset += 1;
+        }
+
+        (pitch + offset).max(0) as u32
+    }
+
+    pub fn output(&self) -> f32 {
+        (self.output * MASTER_VOLUMES[self.master_volume]) as f32 / 60.0 * LEVEL
+    }
+}
+
+#[cfg(test)]
+mod audio_test {
+    use super::*;
+
+    fn audio_with_wave() -> FdsAudio {
+        let mut audio = FdsAudio::new();
+        audio.write(0x4089, 0x80);
+        for addr in 0x4040..=0x407F {
+            // square wave: 63 x 32, 0 x 32
+            audio.write(addr, if addr < 0x4060 { 0x3F } else { 0x00 });
+        }
+        audio.write(0x4089, 0x00);
+        audio
+    }
+
+    #[test]
+    fn wavetable() {
+        let mut audio = audio_with_wave();
+        assert_eq!(audio.read(0x4040), 0x7F);
+        // gain 32 without the envelope, frequency $400: 1 step per 64 cycles
+        audio.write(0x4080, 0xA0);
+        audio.write(0x4082, 0x00);
+        audio.write(0x4083, 0x04);
+
+        audio.clock(1);
+        assert_eq!(audio.output(), (63 * 32) as f32 * LEVEL);
+        audio.clock(64 * 32);
+        assert_eq!(audio.wave_position, 32);
+        assert_eq!(audio.output(), 0.0);
+
+        // master volume 2/4
+        audio.clock(64 * 32);
+        audio.write(0x4089, 0x02);
+        audio.clock(1);
+        assert_eq!(audio.output(), (63 * 32 / 2) as f32 * LEVEL);
+
+        // halting resets the wave
+        audio.write(0x4083, 0x80);
+        assert_eq!(audio.wave_position, 0);
+    }
+
+    #[test]
+    fn volume_envelope() {
+        let mut audio = audio_with_wave();
+        audio.write(0x4083, 0x00);
+        audio.write(0x408A, 0x01);
+        // increase, speed 0: 8 cycles per step
+        audio.write(0x4080, 0x40);
+        audio.clock(9);
+        assert_eq!(audio.read(0x4090), 0x41);
+        audio.clock(9 * 40);
+        // the gain stops at 32
+        assert_eq!(audio.read(0x4090), 0x40 | 32);
+
+        // halted by $4083 bit 6
+        audio.write(0x4080, 0x00);
+        audio.write(0x4083, 0x40);
+        audio.clock(9 * 4);
+        assert_eq!(audio.read(0x4090), 0x40 | 32);
+        audio.write(0x4083, 0x00);
+        audio.clock(9 * 4);
+        assert_eq!(audio.read(0x4090), 0x40 | 28);
+    }
+
+    #[test]
+    fn modulation() {
+        let mut audio = FdsAudio::new();
+        // the table is written while halted, 2 entries per write
+        audio.write(0x4087, 0x80);
+        for _ in 0..32 {
+            audio.write(0x4088, 0x01);
+        }
+        assert_eq!(audio.modulation_position, 0);
+
+        audio.write(0x4082, 0x00);
+        audio.write(0x4083, 0x01);
+        audio.write(0x4084, 0x80 | 0x10);
+        audio.write(0x4085, 0x3F);
+        audio.write(0x4086, 0x00);
+        audio.write(0x4087, 0x08);
+        // counter 63 x gain 16 / 16 = 63, pitch $100 + $100 x 63 / 64
+        assert_eq!(audio.wave_pitch(), 0x100 + 0xFC);
+        audio.write(0x4084, 0x80 | 0x11);
+        // the rounding of the remainder: 63 x 17 / 16 + 2 = 68
+        assert_eq!(audio.wave_pitch(), 0x100 + 0x110);
+
+        // +1 wraps the counter to -64
+        audio.clock(0x20);
+        assert_eq!(audio.modulation_counter, -64);
+    }
+}
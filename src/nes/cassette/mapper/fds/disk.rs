@@ -0,0 +1,196 @@
+use super::super::super::CassetteInitializeError;
+
+/// fwNES header: "FDS" + $1A, the side count, then 11 zero bytes.
+const HEADER_SIZE: usize = 0x10;
+/// One side of the disk in the .fds image, without the gaps and the CRCs.
+const IMAGE_SIDE_SIZE: usize = 65500;
+/// The disk starts with about 28300 bits of the gap before the first block.
+const LEADING_GAP_SIZE: usize = 28300 / 8;
+/// About 976 bits of the gap between the blocks.
+const BLOCK_GAP_SIZE: usize = 976 / 8;
+/// The first 1 bit after the gap, the drive starts the byte transfer from it.
+pub const BLOCK_START_MARK: u8 = 0x80;
+
+pub fn is_disk_image(buf: &[u8]) -> bool {
+    buf.starts_with(b"FDS\x1A") || buf.get(1..15) == Some(&b"*NINTENDO-HVC*"[..])
+}
+
+/// CRC-16 of the disk blocks. (polynomial $8408 bit reversed, initial value 0)
+/// The CRC is stored low byte first after the block, the start mark is included in the calculation.
+pub fn update_crc(crc: u16, data: u8) -> u16 {
+    let mut crc = crc ^ data as u16;
+    for _ in 0..8 {
+        crc = if crc & 0x0001 == 0x0001 { (crc >> 1) ^ 0x8408 } else { crc >> 1 };
+    }
+    crc
+}
+
+/// Disk sides as the drive sees them, the blocks of the .fds image with the gaps, start marks and CRCs.
+///
+/// The sides are padded to the same size, and stored back to back in one buffer
+/// to be saved as it is. The free space after the last block stays writable.
+///
+/// Blocks of the image:
+///   1: disk info (56 bytes)
+///   2: file amount (2 bytes)
+///   3: file header (16 bytes, the file size at +13)
+///   4: file data (1 + file size bytes)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/FDS_disk_format
+///        https://wiki.nesdev.com/w/index.php/FDS_file_format
+pub struct Disk {
+    data: Vec<u8>,
+    side_size: usize,
+}
+
+impl Disk {
+    pub fn new(image: &[u8]) -> Result<Self, CassetteInitializeError> {
+        let body = if image.starts_with(b"FDS\x1A") { image.get(HEADER_SIZE..).unwrap_or(&[]) } else { image };
+
+        let sides: Vec<Vec<u8>> = body
+            .chunks_exact(IMAGE_SIDE_SIZE)
+            .map(Self::add_gaps)
+            .collect::<Option<_>>()
+            .ok_or(CassetteInitializeError::FormatError)?;
+        if sides.is_empty() {
+            return Err(CassetteInitializeError::FormatError);
+        }
+
+        let side_size = sides.iter().map(Vec::len).fold(LEADING_GAP_SIZE + IMAGE_SIDE_SIZE, usize::max);
+        let mut data = Vec::with_capacity(side_size * sides.len());
+        for side in sides {
+            data.extend_from_slice(&side);
+            data.resize(data.len() + side_size - side.len(), 0);
+        }
+
+        Ok(Disk { data, side_size })
+    }
+
+    /// Blocks of the image side with the gaps. None: the side doesn't start with the disk info block.
+    fn add_gaps(image_side: &[u8]) -> Option<Vec<u8>> {
+        if image_side[0] != 1 {
+            return None;
+        }
+
+        let mut side = vec![0; LEADING_GAP_SIZE];
+        let mut position = 0;
+        let mut file_size = 0;
+        while position < image_side.len() {
+            let length = match image_side[position] {
+                1 => 56,
+                2 => 2,
+                3 => 16,
+                4 => 1 + file_size,
+                _ => break,
+            };
+            let block = image_side.get(position..position + length)?;
+            if block[0] == 3 {
+                file_size = block[13] as usize | (block[14] as usize) << 8;
+            }
+
+            let crc = block.iter().fold(update_crc(0, BLOCK_START_MARK), |crc, data| update_crc(crc, *data));
+            side.push(BLOCK_START_MARK);
+            side.extend_from_slice(block);
+            side.extend_from_slice(&[crc as u8, (crc >> 8) as u8]);
+            side.extend(std::iter::repeat_n(0, BLOCK_GAP_SIZE));
+            position += length;
+        }
+
+        Some(side)
+    }
+
+    pub fn side_count(&self) -> usize {
+        self.data.len() / self.side_size
+    }
+
+    pub fn side_size(&self) -> usize {
+        self.side_size
+    }
+
+    pub fn read(&self, side: usize, position: usize) -> u8 {
+        self.data[side * self.side_size + position]
+    }
+
+    pub fn write(&mut self, side: usize, position: usize, data: u8) {
+        self.data[side * self.side_size + position] = data;
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Restores the disk saved by data(), ignoring the one of a different image.
+    pub fn restore(&mut self, data: &[u8]) {
+        if data.len() == self.data.len() {
+            self.data.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod disk_test {
+    use super::*;
+
+    /// A side with the disk info, the file amount, and a file of 3 bytes.
+    fn image_side() -> Vec<u8> {
+        let mut side = vec![0; IMAGE_SIDE_SIZE];
+        let mut blocks = vec![1];
+        blocks.extend_from_slice(b"*NINTENDO-HVC*");
+        blocks.resize(56, 0);
+        blocks.extend_from_slice(&[2, 1]);
+        blocks.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0]);
+        blocks.extend_from_slice(&[4, 0x12, 0x34, 0x56]);
+        side[..blocks.len()].copy_from_slice(&blocks);
+        side
+    }
+
+    #[test]
+    fn detect_image() {
+        assert!(is_disk_image(&image_side()));
+        assert!(is_disk_image(b"FDS\x1A\x01"));
+        assert!(!is_disk_image(b"NES\x1A"));
+    }
+
+    #[test]
+    fn new_with_and_without_header() {
+        let without_header = Disk::new(&[image_side(), image_side()].concat()).unwrap();
+        assert_eq!(without_header.side_count(), 2);
+
+        let header = [b"FDS\x1A\x02".to_vec(), vec![0; 11]].concat();
+        let with_header = Disk::new(&[header, image_side(), image_side()].concat()).unwrap();
+        assert_eq!(with_header.data(), without_header.data());
+
+        assert!(Disk::new(&[0; 100]).is_err());
+        assert!(Disk::new(&vec![0; IMAGE_SIDE_SIZE]).is_err());
+    }
+
+    #[test]
+    fn gaps_and_crc() {
+        let disk = Disk::new(&image_side()).unwrap();
+        assert_eq!(disk.side_size(), LEADING_GAP_SIZE + IMAGE_SIDE_SIZE);
+        assert_eq!(disk.read(0, LEADING_GAP_SIZE - 1), 0);
+        assert_eq!(disk.read(0, LEADING_GAP_SIZE), BLOCK_START_MARK);
+        assert_eq!(disk.read(0, LEADING_GAP_SIZE + 1), 1);
+
+        // the CRC over the block and its CRC is 0
+        let block_end = LEADING_GAP_SIZE + 1 + 56 + 2;
+        let crc = (LEADING_GAP_SIZE..block_end).fold(0, |crc, position| update_crc(crc, disk.read(0, position)));
+        assert_eq!(crc, 0);
+
+        // the file amount block after the gap
+        assert_eq!(disk.read(0, block_end + BLOCK_GAP_SIZE), BLOCK_START_MARK);
+        assert_eq!(disk.read(0, block_end + BLOCK_GAP_SIZE + 1), 2);
+    }
+
+    #[test]
+    fn restore_same_size_only() {
+        let mut disk = Disk::new(&image_side()).unwrap();
+        let mut saved = disk.data().to_vec();
+        saved[0] = 0xFF;
+
+        disk.restore(&saved[1..]);
+        assert_eq!(disk.read(0, 0), 0);
+        disk.restore(&saved);
+        assert_eq!(disk.read(0, 0), 0xFF);
+    }
+}
@@ -0,0 +1,526 @@
+pub mod audio;
+pub mod disk;
+
+use super::{Mapper, Mirroring};
+use audio::FdsAudio;
+use disk::{Disk, update_crc};
+
+const PROGRAM_RAM_SIZE: usize = 0x8000;   // 32 KB
+const CHARACTER_RAM_SIZE: usize = 0x2000; // 8 KB
+
+/// CPU cycles between the bytes on the disk. (about 96.4 kbit/s)
+const BYTE_TRANSFER_CYCLES: usize = 149;
+/// CPU cycles for the head to get back to the start of the disk.
+const HEAD_RETURN_CYCLES: usize = 50000;
+/// CPU cycles the disk stays ejected while switching the sides, for the BIOS to notice it. (about 0.5 s)
+const DISK_SWAP_CYCLES: usize = 894_886;
+
+/// Famicom Disk System (RAM adapter + disk drive)
+///
+/// CPU $6000-$DFFF: 32 KB PRG RAM
+/// CPU $E000-$FFFF: 8 KB BIOS ROM (disksys.rom)
+/// PPU $0000-$1FFF: 8 KB CHR RAM
+///
+/// Registers:
+///   $4020-$4021: timer IRQ reload value (low / high)
+///   $4022      : timer IRQ control (bit 0: repeat, bit 1: enable)
+///   $4023      : master I/O enable (bit 0: disk registers, bit 1: sound registers)
+///   $4024      : write data
+///   $4025      : control (bit 0: motor, bit 1: transfer reset, bit 2: read mode, bit 3: mirroring,
+///                bit 4: CRC, bit 6: start the transfer, bit 7: disk IRQ enable)
+///   $4030      : status (bit 0: timer IRQ, bit 1: byte transferred, bit 6: end of the head), acknowledges the IRQs
+///   $4031      : read data
+///   $4032      : drive status (bit 0: no disk, bit 1: not ready, bit 2: write protected)
+///   $4033      : external connector (bit 7: battery good)
+///   $4040-$4097: audio
+///
+/// The drive moves the head over the disk a byte per BYTE_TRANSFER_CYCLES while the motor is on.
+/// Once the transfer is started, the read skips the gap up to the start mark, then transfers the bytes.
+/// The CRC errors are not reported, the written blocks get the CRC calculated while writing.
+///
+/// The modified disk is saved as the battery backed RAM, in the format of Disk.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/Family_Computer_Disk_System
+pub struct Fds {
+    bios: Vec<u8>,
+    program_ram: Vec<u8>,
+    character_ram: Vec<u8>,
+    disk: Disk,
+    /// Inserted side. None: the disk is ejected.
+    side: Option<usize>,
+    /// Side inserted after the swap.
+    next_side: usize,
+    swap_timer: usize,
+    is_disk_modified: bool,
+    mirroring: Mirroring,
+    is_disk_io_enabled: bool,
+    is_sound_io_enabled: bool,
+    timer_reload: u16,
+    timer_counter: u16,
+    is_timer_repeat: bool,
+    is_timer_enabled: bool,
+    is_timer_irq_pending: bool,
+    is_motor_on: bool,
+    is_transfer_reset: bool,
+    is_read_mode: bool,
+    is_crc_control: bool,
+    is_transfer_started: bool,
+    is_disk_irq_enabled: bool,
+    is_disk_irq_pending: bool,
+    is_byte_transferred: bool,
+    read_data: u8,
+    write_data: u8,
+    head_position: usize,
+    head_timer: usize,
+    is_end_of_head: bool,
+    is_scanning: bool,
+    is_gap_ended: bool,
+    crc: u16,
+    audio: FdsAudio,
+}
+
+impl Fds {
+    pub fn new(bios: Vec<u8>, disk: Disk) -> Self {
+        Fds {
+            bios,
+            program_ram: vec![0; PROGRAM_RAM_SIZE],
+            character_ram: vec![0; CHARACTER_RAM_SIZE],
+            disk,
+            side: Some(0),
+            next_side: 0,
+            swap_timer: 0,
+            is_disk_modified: false,
+            mirroring: Mirroring::Horizontal,
+            is_disk_io_enabled: false,
+            is_sound_io_enabled: false,
+            timer_reload: 0,
+            timer_counter: 0,
+            is_timer_repeat: false,
+            is_timer_enabled: false,
+            is_timer_irq_pending: false,
+            is_motor_on: false,
+            is_transfer_reset: false,
+            is_read_mode: true,
+            is_crc_control: false,
+            is_transfer_started: false,
+            is_disk_irq_enabled: false,
+            is_disk_irq_pending: false,
+            is_byte_transferred: false,
+            read_data: 0,
+            write_data: 0,
+            head_position: 0,
+            head_timer: 0,
+            is_end_of_head: true,
+            is_scanning: false,
+            is_gap_ended: false,
+            crc: 0,
+            audio: FdsAudio::new(),
+        }
+    }
+
+    fn read_register(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x4030 => {
+                let mut status = 0;
+                if self.is_timer_irq_pending {
+                    status |= 0x01;
+                }
+                if self.is_byte_transferred {
+                    status |= 0x02;
+                }
+                if self.is_end_of_head {
+                    status |= 0x40;
+                }
+                self.is_timer_irq_pending = false;
+                self.is_byte_transferred = false;
+                self.is_disk_irq_pending = false;
+                status
+            },
+            0x4031 => {
+                self.is_byte_transferred = false;
+                self.is_disk_irq_pending = false;
+                self.read_data
+            },
+            0x4032 => {
+                let is_ejected = self.side.is_none();
+                let mut status = 0x40;
+                if is_ejected {
+                    status |= 0x01 | 0x04;
+                }
+                if is_ejected || !self.is_scanning {
+                    status |= 0x02;
+                }
+                status
+            },
+            0x4033 => 0x80,
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4020 => self.timer_reload = (self.timer_reload & 0xFF00) | data as u16,
+            0x4021 => self.timer_reload = (self.timer_reload & 0x00FF) | (data as u16) << 8,
+            0x4022 => {
+                self.is_timer_repeat = data & 0x01 == 0x01;
+                self.is_timer_enabled = data & 0x02 == 0x02 && self.is_disk_io_enabled;
+                if self.is_timer_enabled {
+                    self.timer_counter = self.timer_reload;
+                } else {
+                    self.is_timer_irq_pending = false;
+                }
+            },
+            0x4024 => {
+                self.write_data = data;
+                self.is_byte_transferred = false;
+                self.is_disk_irq_pending = false;
+            },
+            0x4025 => {
+                self.is_motor_on = data & 0x01 == 0x01;
+                self.is_transfer_reset = data & 0x02 == 0x02;
+                self.is_read_mode = data & 0x04 == 0x04;
+                self.mirroring = if data & 0x08 == 0x08 { Mirroring::Horizontal } else { Mirroring::Vertical };
+                self.is_crc_control = data & 0x10 == 0x10;
+                self.is_transfer_started = data & 0x40 == 0x40;
+                self.is_disk_irq_enabled = data & 0x80 == 0x80;
+                self.is_disk_irq_pending = false;
+            },
+            _ => {},
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if !self.is_timer_enabled {
+            return;
+        }
+
+        if self.timer_counter == 0 {
+            self.is_timer_irq_pending = true;
+            self.timer_counter = self.timer_reload;
+            self.is_timer_enabled = self.is_timer_repeat;
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    fn clock_swap(&mut self) {
+        if self.side.is_none() && self.swap_timer > 0 {
+            self.swap_timer -= 1;
+            if self.swap_timer == 0 {
+                self.side = Some(self.next_side);
+            }
+        }
+    }
+
+    fn clock_drive(&mut self) {
+        let side = match self.side {
+            Some(side) if self.is_motor_on => side,
+            _ => {
+                self.is_end_of_head = true;
+                self.is_scanning = false;
+                return;
+            },
+        };
+
+        if self.is_transfer_reset && !self.is_scanning {
+            return;
+        }
+
+        if self.is_end_of_head {
+            self.is_end_of_head = false;
+            self.head_timer = HEAD_RETURN_CYCLES;
+            self.head_position = 0;
+            self.is_gap_ended = false;
+            return;
+        }
+
+        if self.head_timer > 0 {
+            self.head_timer -= 1;
+            return;
+        }
+
+        self.is_scanning = true;
+        if self.is_read_mode {
+            self.read_disk(side);
+        } else {
+            self.write_disk(side);
+        }
+
+        self.head_position += 1;
+        if self.head_position >= self.disk.side_size() {
+            self.is_motor_on = false;
+            self.is_end_of_head = true;
+        } else {
+            self.head_timer = BYTE_TRANSFER_CYCLES;
+        }
+    }
+
+    fn read_disk(&mut self, side: usize) {
+        let data = self.disk.read(side, self.head_position);
+
+        if !self.is_transfer_started {
+            self.is_gap_ended = false;
+        } else if !self.is_gap_ended && data != 0 {
+            // the start mark is not transferred
+            self.is_gap_ended = true;
+            return;
+        }
+
+        if self.is_gap_ended {
+            self.read_data = data;
+            self.transfer_byte();
+        }
+    }
+
+    fn write_disk(&mut self, side: usize) {
+        let data = if !self.is_transfer_started {
+            self.crc = 0;
+            0x00
+        } else if self.is_crc_control {
+            // the CRC follows the data, low byte first
+            let data = self.crc as u8;
+            self.crc >>= 8;
+            data
+        } else {
+            self.transfer_byte();
+            self.crc = update_crc(self.crc, self.write_data);
+            self.write_data
+        };
+
+        if self.disk.read(side, self.head_position) != data {
+            self.disk.write(side, self.head_position, data);
+            self.is_disk_modified = true;
+        }
+        self.is_gap_ended = false;
+    }
+
+    fn transfer_byte(&mut self) {
+        self.is_byte_transferred = true;
+        if self.is_disk_irq_enabled {
+            self.is_disk_irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for Fds {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x4030..=0x4033 if self.is_disk_io_enabled => self.read_register(addr),
+            0x4040..=0x4097 if self.is_sound_io_enabled => self.audio.read(addr),
+            0x6000..=0xDFFF => self.program_ram[addr as usize - 0x6000],
+            0xE000..=0xFFFF => self.bios[(addr as usize - 0xE000) % self.bios.len()],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4023 => {
+                self.is_disk_io_enabled = data & 0x01 == 0x01;
+                self.is_sound_io_enabled = data & 0x02 == 0x02;
+                if !self.is_disk_io_enabled {
+                    self.is_timer_enabled = false;
+                    self.is_timer_irq_pending = false;
+                }
+            },
+            0x4020..=0x4026 if self.is_disk_io_enabled => self.write_register(addr, data),
+            0x4040..=0x408A if self.is_sound_io_enabled => self.audio.write(addr, data),
+            0x6000..=0xDFFF => self.program_ram[addr as usize - 0x6000] = data,
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_ram[addr as usize & 0x1FFF]
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        self.character_ram[addr as usize & 0x1FFF] = data;
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn irq(&self) -> bool {
+        self.is_timer_irq_pending || self.is_disk_irq_pending
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        for _ in 0..cycle {
+            self.clock_timer();
+            self.clock_swap();
+            self.clock_drive();
+        }
+        self.audio.clock(cycle);
+    }
+
+    fn switch_disk_side(&mut self) {
+        let current = self.side.unwrap_or(self.next_side);
+        self.next_side = (current + 1) % self.disk.side_count();
+        self.side = None;
+        self.swap_timer = DISK_SWAP_CYCLES;
+    }
+
+    /// The disk, once it is written.
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.is_disk_modified { Some(self.disk.data()) } else { None }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        self.disk.restore(data);
+    }
+}
+
+#[cfg(test)]
+mod fds_test {
+    use super::*;
+
+    /// A side with the disk info block and the file amount block.
+    fn new_fds() -> Fds {
+        let mut side = vec![0; 65500];
+        side[0] = 1;
+        side[1..15].copy_from_slice(b"*NINTENDO-HVC*");
+        side[56] = 2;
+        side[57] = 0x05;
+        let disk = Disk::new(&[side.clone(), side].concat()).unwrap();
+        Fds::new((0..0x2000).map(|n| (n >> 8) as u8).collect(), disk)
+    }
+
+    /// Runs the drive until the next byte is transferred.
+    fn next_byte(fds: &mut Fds) {
+        for _ in 0..1_000_000 {
+            fds.notify_cpu_cycle(1);
+            if fds.is_byte_transferred {
+                return;
+            }
+        }
+        panic!("no byte is transferred");
+    }
+
+    #[test]
+    fn memory_map() {
+        let mut fds = new_fds();
+        fds.write_cpu(0x6000, 0x12);
+        fds.write_cpu(0xDFFF, 0x34);
+        assert_eq!(fds.read_cpu(0x6000), 0x12);
+        assert_eq!(fds.read_cpu(0xDFFF), 0x34);
+        assert_eq!(fds.read_cpu(0xE000), 0x00);
+        assert_eq!(fds.read_cpu(0xFFFF), 0x1F);
+        // the BIOS is ROM
+        fds.write_cpu(0xE000, 0x56);
+        assert_eq!(fds.read_cpu(0xE000), 0x00);
+
+        fds.write_ppu(0x1FFF, 0x78);
+        assert_eq!(fds.read_ppu(0x1FFF), 0x78);
+    }
+
+    #[test]
+    fn registers_need_master_io_enable() {
+        let mut fds = new_fds();
+        fds.write_cpu(0x4025, 0x26);
+        assert_eq!(fds.mirroring(), Mirroring::Horizontal);
+        assert_eq!(fds.read_cpu(0x4033), 0x00);
+
+        fds.write_cpu(0x4023, 0x01);
+        fds.write_cpu(0x4025, 0x26);
+        assert_eq!(fds.mirroring(), Mirroring::Vertical);
+        assert_eq!(fds.read_cpu(0x4033), 0x80);
+        // the disk is inserted, but the drive is not ready
+        assert_eq!(fds.read_cpu(0x4032), 0x42);
+    }
+
+    #[test]
+    fn timer_irq() {
+        let mut fds = new_fds();
+        fds.write_cpu(0x4023, 0x01);
+        fds.write_cpu(0x4020, 0x02);
+        fds.write_cpu(0x4021, 0x00);
+        fds.write_cpu(0x4022, 0x03);
+
+        fds.notify_cpu_cycle(2);
+        assert!(!fds.irq());
+        fds.notify_cpu_cycle(1);
+        assert!(fds.irq());
+        assert_eq!(fds.read_cpu(0x4030) & 0x01, 0x01);
+        assert!(!fds.irq());
+
+        // repeat
+        fds.notify_cpu_cycle(3);
+        assert!(fds.irq());
+
+        // disabling the disk registers stops the timer
+        fds.write_cpu(0x4023, 0x00);
+        assert!(!fds.irq());
+        fds.notify_cpu_cycle(3);
+        assert!(!fds.irq());
+    }
+
+    #[test]
+    fn read_disk() {
+        let mut fds = new_fds();
+        fds.write_cpu(0x4023, 0x01);
+        // motor on, read mode, start the transfer with the IRQ
+        fds.write_cpu(0x4025, 0xE5);
+
+        next_byte(&mut fds);
+        assert!(fds.irq());
+        assert_eq!(fds.read_cpu(0x4032) & 0x02, 0x00);
+        // the start mark is skipped
+        assert_eq!(fds.read_cpu(0x4031), 0x01);
+        assert!(!fds.irq());
+
+        let info: Vec<u8> = (0..14).map(|_| {
+            next_byte(&mut fds);
+            fds.read_cpu(0x4031)
+        }).collect();
+        assert_eq!(&info[..], b"*NINTENDO-HVC*");
+    }
+
+    #[test]
+    fn write_disk() {
+        let mut fds = new_fds();
+        assert!(fds.battery_backed_ram().is_none());
+
+        fds.write_cpu(0x4023, 0x01);
+        // motor on, write mode, start the transfer
+        fds.write_cpu(0x4024, 0x80);
+        fds.write_cpu(0x4025, 0x41);
+        next_byte(&mut fds);
+        let start = fds.head_position - 1;
+        fds.write_cpu(0x4024, 0x03);
+        next_byte(&mut fds);
+        // CRC
+        fds.write_cpu(0x4025, 0x51);
+        for _ in 0..2 {
+            fds.notify_cpu_cycle(BYTE_TRANSFER_CYCLES + 1);
+        }
+
+        assert!(fds.battery_backed_ram().is_some());
+        assert_eq!(fds.disk.read(0, start), 0x80);
+        assert_eq!(fds.disk.read(0, start + 1), 0x03);
+        let crc = (start..start + 4).fold(0, |crc, position| update_crc(crc, fds.disk.read(0, position)));
+        assert_eq!(crc, 0);
+    }
+
+    #[test]
+    fn switch_disk_side() {
+        let mut fds = new_fds();
+        fds.write_cpu(0x4023, 0x01);
+
+        fds.switch_disk_side();
+        assert_eq!(fds.read_cpu(0x4032) & 0x01, 0x01);
+        fds.notify_cpu_cycle(DISK_SWAP_CYCLES);
+        assert_eq!(fds.read_cpu(0x4032) & 0x01, 0x00);
+        assert_eq!(fds.side, Some(1));
+
+        // wraps around to the first side
+        fds.switch_disk_side();
+        fds.notify_cpu_cycle(DISK_SWAP_CYCLES);
+        assert_eq!(fds.side, Some(0));
+    }
+}
@@ -0,0 +1,283 @@
+/// The tone, noise and envelope generators are clocked every 16 CPU cycles.
+const PRESCALER_CYCLES: usize = 16;
+
+/// A channel at the volume 12 is about as loud as an APU pulse at the full volume. (15 x 0.00752)
+const LEVEL: f32 = 0.32;
+
+/// 5 bit level to the amplitude, 1.5 dB per step. The level 0 is silent.
+fn amplitude(level: u8) -> f32 {
+    if level == 0 {
+        0.0
+    } else {
+        10f32.powf((level as f32 - 31.0) * 1.5 / 20.0)
+    }
+}
+
+/// Square wave generator: the output is toggled every period.
+#[derive(Default)]
+struct Tone {
+    period: u16,
+    timer: u16,
+    output: bool,
+}
+
+impl Tone {
+    fn clock(&mut self) {
+        self.timer += 1;
+        if self.timer >= self.period.max(1) {
+            self.timer = 0;
+            self.output = !self.output;
+        }
+    }
+}
+
+/// Noise generator: 17 bit LFSR, shifted every 2 periods of the prescaler.
+struct Noise {
+    period: u8,
+    timer: u8,
+    shift_register: u32,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            period: 0,
+            timer: 0,
+            shift_register: 1,
+        }
+    }
+}
+
+impl Noise {
+    fn clock(&mut self) {
+        self.timer += 1;
+        if self.timer >= self.period.max(1) * 2 {
+            self.timer = 0;
+            let feedback = (self.shift_register ^ (self.shift_register >> 3)) & 0x01;
+            self.shift_register = (self.shift_register >> 1) | (feedback << 16);
+        }
+    }
+
+    fn output(&self) -> bool {
+        self.shift_register & 0x01 == 0x01
+    }
+}
+
+/// Envelope generator: 32 steps of the 5 bit level per cycle.
+///
+/// shape ($0D) bit 3: continue, bit 2: attack, bit 1: alternate, bit 0: hold
+#[derive(Default)]
+struct Envelope {
+    period: u16,
+    timer: u16,
+    shape: u8,
+    step: u8,
+    is_attack: bool,
+    is_holding: bool,
+}
+
+impl Envelope {
+    fn write_shape(&mut self, data: u8) {
+        self.shape = data & 0x0F;
+        self.timer = 0;
+        self.step = 0;
+        self.is_attack = self.shape & 0x04 == 0x04;
+        self.is_holding = false;
+    }
+
+    fn clock(&mut self) {
+        if self.is_holding {
+            return;
+        }
+
+        self.timer += 1;
+        if self.timer < self.period.max(1) {
+            return;
+        }
+        self.timer = 0;
+
+        if self.step < 31 {
+            self.step += 1;
+            return;
+        }
+
+        // end of a cycle
+        if self.shape & 0x08 == 0 {
+            // one cycle, then silent
+            self.is_attack = false;
+            self.is_holding = true;
+        } else if self.shape & 0x01 == 0x01 {
+            if self.shape & 0x02 == 0x02 {
+                self.is_attack = !self.is_attack;
+            }
+            self.is_holding = true;
+        } else {
+            if self.shape & 0x02 == 0x02 {
+                self.is_attack = !self.is_attack;
+            }
+            self.step = 0;
+        }
+    }
+
+    fn level(&self) -> u8 {
+        if self.is_attack { self.step } else { 31 - self.step }
+    }
+}
+
+/// Sunsoft 5B expansion audio (YM2149F, a variant of the AY-3-8910): 3 square channels with a shared noise and envelope.
+///
+/// $C000-$DFFF: register select
+/// $E000-$FFFF: register write
+///
+/// $00-$05: tone periods (12 bit, low / high) of the channel A, B, C
+/// $06    : noise period (5 bit)
+/// $07    : disables (bits 0-2: tone of A-C, bits 3-5: noise of A-C)
+/// $08-$0A: volumes of A-C (bits 0-3: volume, bit 4: envelope)
+/// $0B-$0C: envelope period (16 bit, low / high)
+/// $0D    : envelope shape
+///
+/// refer: https://wiki.nesdev.com/w/index.php/Sunsoft_5B_audio
+#[derive(Default)]
+pub struct Sunsoft5bAudio {
+    register: u8,
+    tones: [Tone; 3],
+    noise: Noise,
+    envelope: Envelope,
+    disables: u8,
+    volumes: [u8; 3],
+    prescaler: usize,
+}
+
+impl Sunsoft5bAudio {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn select(&mut self, data: u8) {
+        self.register = data;
+    }
+
+    pub fn write(&mut self, data: u8) {
+        match self.register {
+            register @ 0x00..=0x05 => {
+                let tone = &mut self.tones[register as usize / 2];
+                tone.period = if register & 0x01 == 0 {
+                    (tone.period & 0x0F00) | data as u16
+                } else {
+                    (tone.period & 0x00FF) | (data as u16 & 0x0F) << 8
+                };
+            },
+            0x06 => self.noise.period = data & 0x1F,
+            0x07 => self.disables = data,
+            register @ 0x08..=0x0A => self.volumes[register as usize - 0x08] = data & 0x1F,
+            0x0B => self.envelope.period = (self.envelope.period & 0xFF00) | data as u16,
+            0x0C => self.envelope.period = (self.envelope.period & 0x00FF) | (data as u16) << 8,
+            0x0D => self.envelope.write_shape(data),
+            // $0E, $0F: I/O ports, unused on the 5B. writes with the upper bits set are ignored.
+            _ => {},
+        }
+    }
+
+    pub fn clock(&mut self, cpu_cycle: usize) {
+        self.prescaler += cpu_cycle;
+        while self.prescaler >= PRESCALER_CYCLES {
+            self.prescaler -= PRESCALER_CYCLES;
+            for tone in self.tones.iter_mut() {
+                tone.clock();
+            }
+            self.noise.clock();
+            self.envelope.clock();
+        }
+    }
+
+    fn channel_level(&self, channel: usize) -> u8 {
+        let is_tone_high = self.tones[channel].output || self.disables & (0x01 << channel) != 0;
+        let is_noise_high = self.noise.output() || self.disables & (0x08 << channel) != 0;
+        if !(is_tone_high && is_noise_high) {
+            return 0;
+        }
+
+        let volume = self.volumes[channel];
+        if volume & 0x10 == 0x10 {
+            self.envelope.level()
+        } else if volume == 0 {
+            0
+        } else {
+            // 4 bit volume steps by 3 dB, on the 5 bit scale
+            volume * 2 + 1
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        let level: f32 = (0..3).map(|channel| amplitude(self.channel_level(channel))).sum();
+        level * LEVEL
+    }
+}
+
+#[cfg(test)]
+mod audio_test {
+    use super::*;
+
+    fn write_register(audio: &mut Sunsoft5bAudio, register: u8, data: u8) {
+        audio.select(register);
+        audio.write(data);
+    }
+
+    #[test]
+    fn tone() {
+        let mut audio = Sunsoft5bAudio::new();
+        // channel A: period 2, volume 15, noise disabled
+        write_register(&mut audio, 0x00, 0x02);
+        write_register(&mut audio, 0x07, 0x3E);
+        write_register(&mut audio, 0x08, 0x0F);
+
+        let outputs: Vec<bool> = (0..4).map(|_| {
+            audio.clock(PRESCALER_CYCLES * 2);
+            audio.output() > 0.0
+        }).collect();
+        assert_eq!(outputs, vec![true, false, true, false]);
+
+        // the volume 12 is at the APU pulse level
+        audio.clock(PRESCALER_CYCLES * 2);
+        write_register(&mut audio, 0x08, 0x0C);
+        assert!((audio.output() - 15.0 * 0.00752).abs() < 0.001);
+    }
+
+    #[test]
+    fn tone_disabled() {
+        let mut audio = Sunsoft5bAudio::new();
+        // both of the tone and the noise disabled: constant volume
+        write_register(&mut audio, 0x07, 0x3F);
+        write_register(&mut audio, 0x08, 0x0F);
+        audio.clock(PRESCALER_CYCLES * 3);
+        assert_eq!(audio.output(), amplitude(31) * LEVEL);
+    }
+
+    #[test]
+    fn envelope_shape() {
+        let mut audio = Sunsoft5bAudio::new();
+        write_register(&mut audio, 0x0B, 0x01);
+        // attack, then hold
+        write_register(&mut audio, 0x0D, 0x0D);
+
+        assert_eq!(audio.envelope.level(), 0);
+        audio.clock(PRESCALER_CYCLES * 31);
+        assert_eq!(audio.envelope.level(), 31);
+        audio.clock(PRESCALER_CYCLES * 10);
+        assert_eq!(audio.envelope.level(), 31);
+
+        // decay, then silent
+        write_register(&mut audio, 0x0D, 0x00);
+        audio.clock(PRESCALER_CYCLES * 16);
+        assert_eq!(audio.envelope.level(), 15);
+        audio.clock(PRESCALER_CYCLES * 20);
+        assert_eq!(audio.envelope.level(), 0);
+
+        // sawtooth repeats
+        write_register(&mut audio, 0x0D, 0x0C);
+        audio.clock(PRESCALER_CYCLES * 32);
+        assert_eq!(audio.envelope.level(), 0);
+        audio.clock(PRESCALER_CYCLES * 5);
+        assert_eq!(audio.envelope.level(), 5);
+    }
+}
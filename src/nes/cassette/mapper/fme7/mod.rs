@@ -0,0 +1,269 @@
+pub mod audio;
+
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+use audio::Sunsoft5bAudio;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 69 (Sunsoft FME-7 / 5A / 5B)
+///
+/// CPU $6000-$7FFF: 8 KB switchable PRG ROM bank, or PRG RAM
+/// CPU $8000-$9FFF, $A000-$BFFF, $C000-$DFFF: 8 KB switchable PRG ROM banks
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: eight 1 KB switchable CHR banks
+///
+/// $8000-$9FFF: command, $A000-$BFFF: parameter of the command
+///   $0-$7: CHR banks
+///   $8   : $6000 bank (bit 7: RAM enable, bit 6: RAM select, bits 0-5: ROM bank)
+///   $9-$B: PRG ROM banks
+///   $C   : mirroring
+///   $D   : IRQ control (bit 0: IRQ enable, bit 7: counter enable), acknowledges the IRQ
+///   $E-$F: 16 bit IRQ counter (low / high), counting down every CPU cycle
+///
+/// $C000-$FFFF: 5B expansion audio (the audio is just silent on the FME-7 boards)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/Sunsoft_FME-7
+pub struct Fme7 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    command: u8,
+    character_banks: [u8; 8],
+    /// $6000, $8000, $A000, $C000
+    program_banks: [u8; 4],
+    mirroring: Mirroring,
+    irq_counter: u16,
+    is_irq_enabled: bool,
+    is_counter_enabled: bool,
+    is_irq_pending: bool,
+    audio: Sunsoft5bAudio,
+}
+
+impl Fme7 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, program_ram_size: usize, battery: bool) -> Self {
+        Fme7 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            command: 0,
+            character_banks: [0; 8],
+            program_banks: [0; 4],
+            mirroring: Mirroring::Vertical,
+            irq_counter: 0,
+            is_irq_enabled: false,
+            is_counter_enabled: false,
+            is_irq_pending: false,
+            audio: Sunsoft5bAudio::new(),
+        }
+    }
+
+    fn write_parameter(&mut self, data: u8) {
+        match self.command {
+            command @ 0x0..=0x7 => self.character_banks[command as usize] = data,
+            command @ 0x8..=0xB => self.program_banks[command as usize - 0x8] = data,
+            0xC => {
+                self.mirroring = match data & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            },
+            0xD => {
+                self.is_irq_enabled = data & 0x01 == 0x01;
+                self.is_counter_enabled = data & 0x80 == 0x80;
+                self.is_irq_pending = false;
+            },
+            0xE => self.irq_counter = (self.irq_counter & 0xFF00) | data as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00FF) | (data as u16) << 8,
+        }
+    }
+
+    fn is_program_ram_selected(&self) -> bool {
+        self.program_banks[0] & 0x40 == 0x40
+    }
+
+    fn is_program_ram_enabled(&self) -> bool {
+        self.is_program_ram_selected() && self.program_banks[0] & 0x80 == 0x80 && !self.program_ram.is_empty()
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let offset = match addr {
+            0x6000..=0xDFFF => {
+                let bank = self.program_banks[((addr - 0x6000) >> 13) as usize] & 0x3F;
+                bank as usize * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)
+            },
+            _ => self.program_rom.len() - PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF),
+        };
+
+        offset % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let bank = self.character_banks[(addr >> 10) as usize & 0x07];
+        bank as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+}
+
+impl Mapper for Fme7 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x6000..=0x7FFF if !self.is_program_ram_selected() => self.program_rom[self.program_offset(addr)],
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            0x8000..=0x9FFF => self.command = data & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(data),
+            0xC000..=0xDFFF => self.audio.select(data),
+            0xE000..=0xFFFF => self.audio.write(data),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.is_irq_pending
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        if self.is_counter_enabled {
+            // the IRQ is triggered when the counter wraps from $0000 to $FFFF
+            if self.is_irq_enabled && (self.irq_counter as usize) < cycle {
+                self.is_irq_pending = true;
+            }
+            self.irq_counter = self.irq_counter.wrapping_sub(cycle as u16);
+        }
+        self.audio.clock(cycle);
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod fme7_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_fme7() -> Fme7 {
+        Fme7::new(
+            bank_filled_rom(32, PROGRAM_BANK_SIZE),
+            bank_filled_rom(256, CHARACTER_BANK_SIZE),
+            0x2000,
+            false,
+        )
+    }
+
+    fn write_command(fme7: &mut Fme7, command: u8, parameter: u8) {
+        fme7.write_cpu(0x8000, command);
+        fme7.write_cpu(0xA000, parameter);
+    }
+
+    #[test]
+    fn program_bank() {
+        let mut fme7 = new_fme7();
+        write_command(&mut fme7, 0x9, 3);
+        write_command(&mut fme7, 0xA, 4);
+        write_command(&mut fme7, 0xB, 5);
+
+        assert_eq!(fme7.read_cpu(0x8000), 3);
+        assert_eq!(fme7.read_cpu(0xA000), 4);
+        assert_eq!(fme7.read_cpu(0xC000), 5);
+        assert_eq!(fme7.read_cpu(0xE000), 31);
+    }
+
+    #[test]
+    fn program_ram_or_rom_at_6000() {
+        let mut fme7 = new_fme7();
+        write_command(&mut fme7, 0x8, 7);
+        assert_eq!(fme7.read_cpu(0x6000), 7);
+        fme7.write_cpu(0x6000, 0x12);
+        assert_eq!(fme7.read_cpu(0x6000), 7);
+
+        // RAM selected, but disabled
+        write_command(&mut fme7, 0x8, 0x40);
+        assert_eq!(fme7.read_cpu(0x6000), 0);
+
+        write_command(&mut fme7, 0x8, 0xC0);
+        fme7.write_cpu(0x6000, 0x12);
+        assert_eq!(fme7.read_cpu(0x6000), 0x12);
+    }
+
+    #[test]
+    fn character_bank_and_mirroring() {
+        let mut fme7 = new_fme7();
+        write_command(&mut fme7, 0x0, 10);
+        write_command(&mut fme7, 0x7, 200);
+        assert_eq!(fme7.read_ppu(0x0000), 10);
+        assert_eq!(fme7.read_ppu(0x1FFF), 200);
+
+        write_command(&mut fme7, 0xC, 1);
+        assert_eq!(fme7.mirroring(), Mirroring::Horizontal);
+        write_command(&mut fme7, 0xC, 3);
+        assert_eq!(fme7.mirroring(), Mirroring::SingleScreenUpper);
+    }
+
+    #[test]
+    fn irq_counter() {
+        let mut fme7 = new_fme7();
+        write_command(&mut fme7, 0xE, 0x10);
+        write_command(&mut fme7, 0xF, 0x00);
+        write_command(&mut fme7, 0xD, 0x81);
+
+        fme7.notify_cpu_cycle(16);
+        assert!(!fme7.irq());
+        fme7.notify_cpu_cycle(1);
+        assert!(fme7.irq());
+        assert_eq!(fme7.irq_counter, 0xFFFF);
+
+        write_command(&mut fme7, 0xD, 0x80);
+        assert!(!fme7.irq());
+        // the counter keeps counting without the IRQ
+        fme7.notify_cpu_cycle(0x10000 - 1);
+        assert!(!fme7.irq());
+        assert_eq!(fme7.irq_counter, 0x0000);
+    }
+}
@@ -0,0 +1,112 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x8000;   // 32 KB
+const CHARACTER_BANK_SIZE: usize = 0x2000; // 8 KB
+
+/// Mapper 66 (GxROM)
+///
+/// CPU $8000-$FFFF: 32 KB switchable PRG ROM bank
+/// PPU $0000-$1FFF: 8 KB switchable CHR ROM bank
+///
+/// Writing to $8000-$FFFF (with bus conflict):
+///   7  bit  0
+///   ---- ----
+///   xxPP xxCC
+///     ||   ||
+///     ||   ++- Select 8 KB CHR ROM bank
+///     ++------ Select 32 KB PRG ROM bank
+///
+/// refer: https://wiki.nesdev.com/w/index.php/GxROM
+pub struct Gxrom {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    mirroring: Mirroring,
+    register: u8,
+}
+
+impl Gxrom {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+        Gxrom {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            mirroring,
+            register: 0,
+        }
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        (self.register & 0b11) as usize * CHARACTER_BANK_SIZE + addr as usize
+    }
+}
+
+impl Mapper for Gxrom {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0xFFFF => {
+                let bank = ((self.register >> 4) & 0b11) as usize;
+                let offset = bank * PROGRAM_BANK_SIZE + (addr as usize - 0x8000);
+                self.program_rom[offset % self.program_rom.len()]
+            },
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        if addr >= 0x8000 {
+            self.register = data & self.read_cpu(addr);
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod gxrom_test {
+    use super::*;
+    use crate::nes::cassette::mapper::bank_filled_rom;
+    use crate::nes::cpu::{Bus, CpuBus};
+    use crate::nes::ppu::Ppu;
+    use crate::nes::ram::Ram;
+
+    #[test]
+    fn switch_program_and_character_bank() {
+        let mut program_rom = bank_filled_rom(4, PROGRAM_BANK_SIZE);
+        program_rom[0x0000] = 0xFF; // no bus conflict on the written address
+        let character_rom = bank_filled_rom(4, CHARACTER_BANK_SIZE);
+        let mut gxrom = Gxrom::new(program_rom, character_rom, Mirroring::Vertical);
+
+        {
+            let mut ppu = Ppu::new();
+            let mut ram = Ram::new(vec![0; 0x0800]);
+            let mut bus = Bus::new(&mut gxrom, &mut ppu, &mut ram);
+
+            bus.write(0x8000, 0b00100011);
+            assert_eq!(bus.read(0x8001), 2);
+            assert_eq!(bus.read(0xFFFF), 2);
+        }
+
+        assert_eq!(gxrom.read_ppu(0x0000), 3);
+    }
+
+    #[test]
+    fn bus_conflict() {
+        // program rom is filled with 0b00010001
+        let mut gxrom = Gxrom::new(vec![0b00010001; 0x20000], bank_filled_rom(4, CHARACTER_BANK_SIZE), Mirroring::Vertical);
+        gxrom.write_cpu(0x8000, 0b00110011);
+
+        assert_eq!(gxrom.read_cpu(0x8000), 0b00010001);
+        assert_eq!(gxrom.read_ppu(0x0000), 1);
+    }
+}
@@ -0,0 +1,166 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 32 (Irem G-101)
+///
+/// CPU $6000-$7FFF: 8 KB PRG RAM
+/// CPU $8000-$9FFF: 8 KB switchable PRG ROM bank ($8000-$8FFF), or fixed to the second-last bank
+/// CPU $A000-$BFFF: 8 KB switchable PRG ROM bank ($A000-$AFFF)
+/// CPU $C000-$DFFF: 8 KB PRG ROM bank, fixed to the second-last bank, or switchable ($8000-$8FFF)
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: eight 1 KB switchable CHR banks ($B000-$B007)
+///
+/// $9000-$9FFF: bit 0: mirroring (0: vertical, 1: horizontal), bit 1: PRG ROM bank mode
+///
+/// Major League (submapper 1) has one-screen mirroring and ignores $9000-$9FFF.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_032
+pub struct IremG101 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    is_major_league: bool,
+    program_banks: [u8; 2],
+    is_program_bank_swapped: bool,
+    character_banks: [u8; 8],
+    mirroring: Mirroring,
+}
+
+impl IremG101 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, submapper: u8, program_ram_size: usize, battery: bool) -> Self {
+        let is_major_league = submapper == 1;
+
+        IremG101 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            is_major_league,
+            program_banks: [0, 0],
+            is_program_bank_swapped: false,
+            character_banks: [0; 8],
+            mirroring: if is_major_league { Mirroring::SingleScreenUpper } else { Mirroring::Vertical },
+        }
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank_count = self.program_rom.len() / PROGRAM_BANK_SIZE;
+        let bank = match (addr, self.is_program_bank_swapped) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => self.program_banks[0] as usize,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => bank_count - 2,
+            (0xA000..=0xBFFF, _) => self.program_banks[1] as usize,
+            _ => bank_count - 1,
+        };
+
+        (bank * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let bank = self.character_banks[(addr >> 10) as usize & 0x07];
+        bank as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+}
+
+impl Mapper for IremG101 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            0x8000..=0x8FFF => self.program_banks[0] = data & 0x1F,
+            0x9000..=0x9FFF if !self.is_major_league => {
+                self.mirroring = if data & 0x01 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+                self.is_program_bank_swapped = data & 0x02 == 0x02;
+            },
+            0xA000..=0xAFFF => self.program_banks[1] = data & 0x1F,
+            0xB000..=0xBFFF => self.character_banks[(addr & 0x0007) as usize] = data,
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod irem_g101_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_irem_g101(submapper: u8) -> IremG101 {
+        IremG101::new(bank_filled_rom(16, PROGRAM_BANK_SIZE), bank_filled_rom(128, CHARACTER_BANK_SIZE), submapper, 0x2000, false)
+    }
+
+    #[test]
+    fn program_bank_mode() {
+        let mut irem_g101 = new_irem_g101(0);
+        irem_g101.write_cpu(0x8000, 3);
+        irem_g101.write_cpu(0xA000, 4);
+        assert_eq!(irem_g101.read_cpu(0x8000), 3);
+        assert_eq!(irem_g101.read_cpu(0xA000), 4);
+        assert_eq!(irem_g101.read_cpu(0xC000), 14);
+        assert_eq!(irem_g101.read_cpu(0xE000), 15);
+
+        irem_g101.write_cpu(0x9000, 0x03);
+        assert_eq!(irem_g101.read_cpu(0x8000), 14);
+        assert_eq!(irem_g101.read_cpu(0xC000), 3);
+        assert_eq!(irem_g101.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn character_bank() {
+        let mut irem_g101 = new_irem_g101(0);
+        irem_g101.write_cpu(0xB000, 10);
+        irem_g101.write_cpu(0xB007, 100);
+        assert_eq!(irem_g101.read_ppu(0x0000), 10);
+        assert_eq!(irem_g101.read_ppu(0x1FFF), 100);
+    }
+
+    #[test]
+    fn major_league() {
+        let mut major_league = new_irem_g101(1);
+        major_league.write_cpu(0x9000, 0x03);
+        assert_eq!(major_league.mirroring(), Mirroring::SingleScreenUpper);
+        assert!(!major_league.is_program_bank_swapped);
+    }
+}
@@ -0,0 +1,170 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 65 (Irem H3001)
+///
+/// CPU $8000-$9FFF, $A000-$BFFF, $C000-$DFFF: 8 KB switchable PRG ROM banks ($8000, $A000, $C000)
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: eight 1 KB switchable CHR banks ($B000-$B007)
+///
+/// $9001: mirroring (bit 7, 0: vertical, 1: horizontal)
+/// $9003: IRQ enable (bit 7), acknowledges the IRQ
+/// $9004: reloads the IRQ counter, acknowledges the IRQ
+/// $9005, $9006: IRQ reload value (high / low)
+///
+/// The 16 bit IRQ counter counts down every CPU cycle, and stops at 0 with the IRQ.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_065
+pub struct IremH3001 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_banks: [u8; 3],
+    character_banks: [u8; 8],
+    mirroring: Mirroring,
+    irq_reload: u16,
+    irq_counter: u16,
+    is_irq_enabled: bool,
+    is_irq_pending: bool,
+}
+
+impl IremH3001 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+        IremH3001 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_banks: [0, 1, 0xFE],
+            character_banks: [0; 8],
+            mirroring,
+            irq_reload: 0,
+            irq_counter: 0,
+            is_irq_enabled: false,
+            is_irq_pending: false,
+        }
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xDFFF => self.program_banks[((addr - 0x8000) >> 13) as usize] as usize,
+            _ => self.program_rom.len() / PROGRAM_BANK_SIZE - 1,
+        };
+
+        (bank * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let bank = self.character_banks[(addr >> 10) as usize & 0x07];
+        bank as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+}
+
+impl Mapper for IremH3001 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x8000 => self.program_banks[0] = data,
+            0x9001 => self.mirroring = if data & 0x80 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal },
+            0x9003 => {
+                self.is_irq_enabled = data & 0x80 == 0x80;
+                self.is_irq_pending = false;
+            },
+            0x9004 => {
+                self.irq_counter = self.irq_reload;
+                self.is_irq_pending = false;
+            },
+            0x9005 => self.irq_reload = (self.irq_reload & 0x00FF) | (data as u16) << 8,
+            0x9006 => self.irq_reload = (self.irq_reload & 0xFF00) | data as u16,
+            0xA000 => self.program_banks[1] = data,
+            0xB000..=0xB007 => self.character_banks[(addr & 0x0007) as usize] = data,
+            0xC000 => self.program_banks[2] = data,
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.is_irq_pending
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        if self.is_irq_enabled && self.irq_counter > 0 {
+            self.irq_counter = self.irq_counter.saturating_sub(cycle as u16);
+            if self.irq_counter == 0 {
+                self.is_irq_pending = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod irem_h3001_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_irem_h3001() -> IremH3001 {
+        IremH3001::new(bank_filled_rom(16, PROGRAM_BANK_SIZE), bank_filled_rom(128, CHARACTER_BANK_SIZE), Mirroring::Vertical)
+    }
+
+    #[test]
+    fn program_and_character_bank() {
+        let mut irem_h3001 = new_irem_h3001();
+        // power on state: $C000 at the second-last bank
+        assert_eq!(irem_h3001.read_cpu(0xC000), 14);
+
+        irem_h3001.write_cpu(0x8000, 3);
+        irem_h3001.write_cpu(0xA000, 4);
+        irem_h3001.write_cpu(0xC000, 5);
+        irem_h3001.write_cpu(0xB003, 70);
+        assert_eq!(irem_h3001.read_cpu(0x8000), 3);
+        assert_eq!(irem_h3001.read_cpu(0xA000), 4);
+        assert_eq!(irem_h3001.read_cpu(0xC000), 5);
+        assert_eq!(irem_h3001.read_cpu(0xE000), 15);
+        assert_eq!(irem_h3001.read_ppu(0x0C00), 70);
+
+        irem_h3001.write_cpu(0x9001, 0x80);
+        assert_eq!(irem_h3001.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn irq_counter() {
+        let mut irem_h3001 = new_irem_h3001();
+        irem_h3001.write_cpu(0x9005, 0x01);
+        irem_h3001.write_cpu(0x9006, 0x00);
+        irem_h3001.write_cpu(0x9004, 0);
+        irem_h3001.write_cpu(0x9003, 0x80);
+
+        irem_h3001.notify_cpu_cycle(0xFF);
+        assert!(!irem_h3001.irq());
+        irem_h3001.notify_cpu_cycle(3);
+        assert!(irem_h3001.irq());
+        assert_eq!(irem_h3001.irq_counter, 0);
+
+        // stays stopped at 0 until reloaded
+        irem_h3001.write_cpu(0x9003, 0x80);
+        irem_h3001.notify_cpu_cycle(10);
+        assert!(!irem_h3001.irq());
+        irem_h3001.write_cpu(0x9004, 0);
+        assert_eq!(irem_h3001.irq_counter, 0x0100);
+    }
+}
@@ -0,0 +1,267 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 18 (Jaleco SS88006)
+///
+/// CPU $6000-$7FFF: 8 KB PRG RAM (enabled by $9002)
+/// CPU $8000-$9FFF, $A000-$BFFF, $C000-$DFFF: 8 KB switchable PRG ROM banks
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: eight 1 KB switchable CHR banks
+///
+/// The bank registers are written by nibbles, A0 selects the low / high nibble:
+///   $8000-$8003, $9000-$9001: PRG ROM banks
+///   $A000-$D003             : CHR banks (2 banks per $1000)
+///   $E000-$E003             : IRQ reload value (4 nibbles from the lowest)
+///
+/// $9002: PRG RAM (bit 0: enable, bit 1: write enable)
+/// $F000: reloads the IRQ counter, acknowledges the IRQ
+/// $F001: IRQ control (bit 0: enable, bits 1-3: counter size 12 / 8 / 4 bits), acknowledges the IRQ
+/// $F002: mirroring
+/// $F003: ADPCM sound chip (uPD7755 / uPD7756, not supported)
+///
+/// The IRQ counter counts down every CPU cycle, only the bits of the counter size are decremented.
+/// The IRQ is triggered when they wrap from 0.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_018
+pub struct JalecoSs88006 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    program_banks: [u8; 3],
+    character_banks: [u8; 8],
+    program_ram_control: u8,
+    mirroring: Mirroring,
+    irq_reload: u16,
+    irq_counter: u16,
+    irq_control: u8,
+    is_irq_pending: bool,
+}
+
+impl JalecoSs88006 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, program_ram_size: usize, battery: bool) -> Self {
+        JalecoSs88006 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            program_banks: [0; 3],
+            character_banks: [0; 8],
+            program_ram_control: 0,
+            mirroring: Mirroring::Horizontal,
+            irq_reload: 0,
+            irq_counter: 0,
+            irq_control: 0,
+            is_irq_pending: false,
+        }
+    }
+
+    fn write_nibble(register: &mut u8, addr: u16, data: u8) {
+        *register = if addr & 0x0001 == 0 {
+            (*register & 0xF0) | (data & 0x0F)
+        } else {
+            (*register & 0x0F) | (data << 4)
+        };
+    }
+
+    fn irq_counter_mask(&self) -> u16 {
+        match self.irq_control {
+            control if control & 0x08 == 0x08 => 0x000F,
+            control if control & 0x04 == 0x04 => 0x00FF,
+            control if control & 0x02 == 0x02 => 0x0FFF,
+            _ => 0xFFFF,
+        }
+    }
+
+    fn is_program_ram_enabled(&self) -> bool {
+        self.program_ram_control & 0x01 == 0x01 && !self.program_ram.is_empty()
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xDFFF => self.program_banks[((addr - 0x8000) >> 13) as usize] as usize,
+            _ => self.program_rom.len() / PROGRAM_BANK_SIZE - 1,
+        };
+
+        (bank * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let bank = self.character_banks[(addr >> 10) as usize & 0x07];
+        bank as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+}
+
+impl Mapper for JalecoSs88006 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr & 0xF003 {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() && self.program_ram_control & 0x02 == 0x02 => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            register @ 0x8000..=0x8003 => {
+                Self::write_nibble(&mut self.program_banks[(register >> 1) as usize & 0x01], register, data);
+            },
+            register @ 0x9000..=0x9001 => Self::write_nibble(&mut self.program_banks[2], register, data),
+            0x9002 => self.program_ram_control = data,
+            register @ 0xA000..=0xDFFF => {
+                let index = ((register >> 12) - 0xA) * 2 + ((register >> 1) & 0x01);
+                Self::write_nibble(&mut self.character_banks[index as usize], register, data);
+            },
+            register @ 0xE000..=0xE003 => {
+                let shift = (register & 0x0003) * 4;
+                self.irq_reload = (self.irq_reload & !(0x000F << shift)) | (data as u16 & 0x0F) << shift;
+            },
+            0xF000 => {
+                self.irq_counter = self.irq_reload;
+                self.is_irq_pending = false;
+            },
+            0xF001 => {
+                self.irq_control = data & 0x0F;
+                self.is_irq_pending = false;
+            },
+            0xF002 => {
+                self.mirroring = match data & 0x03 {
+                    0 => Mirroring::Horizontal,
+                    1 => Mirroring::Vertical,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.is_irq_pending
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        if self.irq_control & 0x01 == 0 {
+            return;
+        }
+
+        let mask = self.irq_counter_mask();
+        let counter = self.irq_counter & mask;
+        if (counter as usize) < cycle {
+            self.is_irq_pending = true;
+        }
+        self.irq_counter = (self.irq_counter & !mask) | (counter.wrapping_sub(cycle as u16) & mask);
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod jaleco_ss88006_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_jaleco_ss88006() -> JalecoSs88006 {
+        JalecoSs88006::new(bank_filled_rom(32, PROGRAM_BANK_SIZE), bank_filled_rom(256, CHARACTER_BANK_SIZE), 0x2000, false)
+    }
+
+    #[test]
+    fn nibble_bank_registers() {
+        let mut jaleco = new_jaleco_ss88006();
+        // PRG bank 0 = $13, bank 1 = $05, bank 2 = $1E
+        for (addr, data) in [(0x8000, 0x3), (0x8001, 0x1), (0x8002, 0x5), (0x9000, 0xE), (0x9001, 0x1)].iter() {
+            jaleco.write_cpu(*addr, *data);
+        }
+        assert_eq!(jaleco.read_cpu(0x8000), 0x13);
+        assert_eq!(jaleco.read_cpu(0xA000), 0x05);
+        assert_eq!(jaleco.read_cpu(0xC000), 0x1E);
+        assert_eq!(jaleco.read_cpu(0xE000), 0x1F);
+
+        // CHR bank 1 ($A002 / $A003) = $A4, CHR bank 7 ($D002 / $D003) = $C8
+        for (addr, data) in [(0xA002, 0x4), (0xA003, 0xA), (0xD002, 0x8), (0xD003, 0xC)].iter() {
+            jaleco.write_cpu(*addr, *data);
+        }
+        assert_eq!(jaleco.read_ppu(0x0400), 0xA4);
+        assert_eq!(jaleco.read_ppu(0x1C00), 0xC8);
+    }
+
+    #[test]
+    fn program_ram_control() {
+        let mut jaleco = new_jaleco_ss88006();
+        jaleco.write_cpu(0x9002, 0x01);
+        jaleco.write_cpu(0x6000, 0x12);
+        assert_eq!(jaleco.read_cpu(0x6000), 0x00);
+
+        jaleco.write_cpu(0x9002, 0x03);
+        jaleco.write_cpu(0x6000, 0x12);
+        assert_eq!(jaleco.read_cpu(0x6000), 0x12);
+    }
+
+    #[test]
+    fn irq_counter_size() {
+        let mut jaleco = new_jaleco_ss88006();
+        // reload $1234
+        for (addr, data) in [(0xE000, 0x4), (0xE001, 0x3), (0xE002, 0x2), (0xE003, 0x1)].iter() {
+            jaleco.write_cpu(*addr, *data);
+        }
+        jaleco.write_cpu(0xF000, 0);
+        assert_eq!(jaleco.irq_counter, 0x1234);
+
+        // 4 bit counter: wraps after 5 cycles
+        jaleco.write_cpu(0xF001, 0x09);
+        jaleco.notify_cpu_cycle(4);
+        assert!(!jaleco.irq());
+        assert_eq!(jaleco.irq_counter, 0x1230);
+        jaleco.notify_cpu_cycle(1);
+        assert!(jaleco.irq());
+        // the upper bits are kept
+        assert_eq!(jaleco.irq_counter, 0x123F);
+
+        jaleco.write_cpu(0xF001, 0x01);
+        assert!(!jaleco.irq());
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut jaleco = new_jaleco_ss88006();
+        jaleco.write_cpu(0xF002, 1);
+        assert_eq!(jaleco.mirroring(), Mirroring::Vertical);
+        jaleco.write_cpu(0xF002, 2);
+        assert_eq!(jaleco.mirroring(), Mirroring::SingleScreenLower);
+    }
+}
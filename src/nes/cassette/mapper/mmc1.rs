@@ -0,0 +1,391 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x4000;   // 16 KB
+const CHARACTER_BANK_SIZE: usize = 0x1000; // 4 KB
+const PROGRAM_RAM_BANK_SIZE: usize = 0x2000; // 8 KB
+const OUTER_PROGRAM_BANK_SIZE: usize = 0x40000; // 256 KB
+
+/// Mapper 1 (MMC1 / SxROM)
+///
+/// CPU $6000-$7FFF: 8 KB PRG RAM bank (optional, battery-backed on many boards)
+/// CPU $8000-$BFFF: 16 KB PRG ROM bank, either switchable or fixed to the first bank
+/// CPU $C000-$FFFF: 16 KB PRG ROM bank, either fixed to the last bank or switchable
+/// PPU $0000-$0FFF: 4 KB switchable CHR bank
+/// PPU $1000-$1FFF: 4 KB switchable CHR bank
+///
+/// The registers are written through a 5 bit serial shift register.
+/// Board variants reuse the CHR bank bits when the CHR is small.
+///   SUROM / SXROM: CHR bank bit 4 selects the 256 KB PRG ROM half.
+///   SOROM: CHR bank bit 3 selects the 8 KB PRG RAM bank (16 KB RAM).
+///   SXROM: CHR bank bits 2-3 select the 8 KB PRG RAM bank (32 KB RAM).
+///
+/// refer: https://wiki.nesdev.com/w/index.php/MMC1
+pub struct Mmc1 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    shift_register: u8,
+    shift_count: u8,
+    control: u8,
+    character_bank_0: u8,
+    character_bank_1: u8,
+    program_bank: u8,
+}
+
+impl Mmc1 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, program_ram_size: usize, battery: bool) -> Self {
+        Mmc1 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            shift_register: 0,
+            shift_count: 0,
+            // power on in "fix last bank at $C000" mode
+            control: 0x0C,
+            character_bank_0: 0,
+            character_bank_1: 0,
+            program_bank: 0,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        // bit 7 set: clear the shift register and lock PRG ROM at $C000 to the last bank
+        if data & 0x80 == 0x80 {
+            self.shift_register = 0;
+            self.shift_count = 0;
+            self.control |= 0x0C;
+            return;
+        }
+
+        self.shift_register |= (data & 0x01) << self.shift_count;
+        self.shift_count += 1;
+        if self.shift_count < 5 {
+            return;
+        }
+
+        let value = self.shift_register;
+        match addr {
+            0x8000..=0x9FFF => self.control = value,
+            0xA000..=0xBFFF => self.character_bank_0 = value,
+            0xC000..=0xDFFF => self.character_bank_1 = value,
+            0xE000..=0xFFFF => self.program_bank = value,
+            _ => unreachable!(),
+        }
+
+        self.shift_register = 0;
+        self.shift_count = 0;
+    }
+
+    fn program_bank_mode(&self) -> u8 {
+        (self.control >> 2) & 0b11
+    }
+
+    fn is_character_4k_mode(&self) -> bool {
+        self.control & 0b10000 == 0b10000
+    }
+
+    fn is_program_ram_enabled(&self) -> bool {
+        !self.program_ram.is_empty() && self.program_bank & 0b10000 == 0
+    }
+
+    fn program_bank_offset(&self, addr: u16) -> usize {
+        // SUROM / SXROM: 512 KB PRG ROM is selected in 256 KB halves by CHR bank 0 bit 4
+        let outer_offset = if self.program_rom.len() > OUTER_PROGRAM_BANK_SIZE {
+            ((self.character_bank_0 >> 4) & 1) as usize * OUTER_PROGRAM_BANK_SIZE
+        } else {
+            0
+        };
+
+        let bank_count = (self.program_rom.len().min(OUTER_PROGRAM_BANK_SIZE) / PROGRAM_BANK_SIZE).max(1);
+        let bank = (self.program_bank & 0x0F) as usize;
+        let last_bank = bank_count - 1;
+
+        let selected_bank = match (self.program_bank_mode(), addr) {
+            // 0, 1: switch 32 KB at $8000, ignoring low bit of bank number
+            (0, 0x8000..=0xBFFF) | (1, 0x8000..=0xBFFF) => bank & !1,
+            (0, _) | (1, _) => bank | 1,
+            // 2: fix first bank at $8000 and switch 16 KB bank at $C000
+            (2, 0x8000..=0xBFFF) => 0,
+            (2, _) => bank,
+            // 3: fix last bank at $C000 and switch 16 KB bank at $8000
+            (_, 0x8000..=0xBFFF) => bank,
+            (_, _) => last_bank,
+        };
+
+        outer_offset + (selected_bank % bank_count) * PROGRAM_BANK_SIZE + (addr as usize & 0x3FFF)
+    }
+
+    fn program_ram_offset(&self, addr: u16) -> usize {
+        let bank = match self.program_ram.len() / PROGRAM_RAM_BANK_SIZE {
+            // SOROM: 16 KB PRG RAM
+            2 => (self.character_bank_0 >> 3) & 0b01,
+            // SXROM: 32 KB PRG RAM
+            4 => (self.character_bank_0 >> 2) & 0b11,
+            _ => 0,
+        } as usize;
+
+        (bank * PROGRAM_RAM_BANK_SIZE + (addr as usize - 0x6000)) % self.program_ram.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let bank = if self.is_character_4k_mode() {
+            match addr {
+                0x0000..=0x0FFF => self.character_bank_0 as usize,
+                _ => self.character_bank_1 as usize,
+            }
+        } else {
+            // 8 KB mode ignores low bit of bank number
+            (self.character_bank_0 as usize & !1) + (addr as usize >> 12)
+        };
+
+        bank * CHARACTER_BANK_SIZE + (addr as usize & 0x0FFF)
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                self.program_ram[self.program_ram_offset(addr)]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_bank_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                let offset = self.program_ram_offset(addr);
+                self.program_ram[offset] = data;
+            },
+            0x8000..=0xFFFF => self.write_register(addr, data),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod mmc1_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn serial_write(mmc1: &mut Mmc1, addr: u16, value: u8) {
+        for i in 0..5 {
+            mmc1.write_cpu(addr, (value >> i) & 0x01);
+        }
+    }
+
+    #[test]
+    fn power_on_fix_last_bank() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), bank_filled_rom(4, CHARACTER_BANK_SIZE), 0x2000, false);
+
+        assert_eq!(mmc1.read_cpu(0x8000), 0);
+        assert_eq!(mmc1.read_cpu(0xC000), 7);
+    }
+
+    #[test]
+    fn shift_register_needs_five_writes() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), bank_filled_rom(4, CHARACTER_BANK_SIZE), 0x2000, false);
+
+        // 4 writes of bank 5 (0b00101) do not commit
+        for i in 0..4 {
+            mmc1.write_cpu(0xE000, (0b00101 >> i) & 0x01);
+        }
+        assert_eq!(mmc1.read_cpu(0x8000), 0);
+
+        // 5th write commits
+        mmc1.write_cpu(0xE000, 0);
+        assert_eq!(mmc1.read_cpu(0x8000), 5);
+    }
+
+    #[test]
+    fn reset_shift_register() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), bank_filled_rom(4, CHARACTER_BANK_SIZE), 0x2000, false);
+
+        // 32 KB mode, then interrupt a write sequence with a reset
+        serial_write(&mut mmc1, 0x8000, 0b00000);
+        mmc1.write_cpu(0xE000, 1);
+        mmc1.write_cpu(0xE000, 1);
+        mmc1.write_cpu(0xE000, 0x80);
+
+        // control is back to "fix last bank" and the next 5 writes start a new sequence
+        serial_write(&mut mmc1, 0xE000, 3);
+        assert_eq!(mmc1.program_bank_mode(), 3);
+        assert_eq!(mmc1.read_cpu(0x8000), 3);
+        assert_eq!(mmc1.read_cpu(0xC000), 7);
+    }
+
+    #[test]
+    fn program_bank_mode_32k() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), bank_filled_rom(4, CHARACTER_BANK_SIZE), 0x2000, false);
+        serial_write(&mut mmc1, 0x8000, 0b00000);
+        serial_write(&mut mmc1, 0xE000, 5); // low bit is ignored
+
+        assert_eq!(mmc1.read_cpu(0x8000), 4);
+        assert_eq!(mmc1.read_cpu(0xC000), 5);
+    }
+
+    #[test]
+    fn program_bank_mode_fix_first() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), bank_filled_rom(4, CHARACTER_BANK_SIZE), 0x2000, false);
+        serial_write(&mut mmc1, 0x8000, 0b01000);
+        serial_write(&mut mmc1, 0xE000, 6);
+
+        assert_eq!(mmc1.read_cpu(0x8000), 0);
+        assert_eq!(mmc1.read_cpu(0xC000), 6);
+    }
+
+    #[test]
+    fn program_bank_mode_fix_last() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), bank_filled_rom(4, CHARACTER_BANK_SIZE), 0x2000, false);
+        serial_write(&mut mmc1, 0x8000, 0b01100);
+        serial_write(&mut mmc1, 0xE000, 2);
+
+        assert_eq!(mmc1.read_cpu(0x8000), 2);
+        assert_eq!(mmc1.read_cpu(0xFFFF), 7);
+    }
+
+    #[test]
+    fn character_bank_8k() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(2, PROGRAM_BANK_SIZE), bank_filled_rom(8, CHARACTER_BANK_SIZE), 0x2000, false);
+        serial_write(&mut mmc1, 0x8000, 0b00000);
+        serial_write(&mut mmc1, 0xA000, 3); // low bit is ignored
+
+        assert_eq!(mmc1.read_ppu(0x0000), 2);
+        assert_eq!(mmc1.read_ppu(0x1000), 3);
+    }
+
+    #[test]
+    fn character_bank_4k() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(2, PROGRAM_BANK_SIZE), bank_filled_rom(8, CHARACTER_BANK_SIZE), 0x2000, false);
+        serial_write(&mut mmc1, 0x8000, 0b10000);
+        serial_write(&mut mmc1, 0xA000, 5);
+        serial_write(&mut mmc1, 0xC000, 2);
+
+        assert_eq!(mmc1.read_ppu(0x0000), 5);
+        assert_eq!(mmc1.read_ppu(0x1000), 2);
+    }
+
+    #[test]
+    fn runtime_mirroring() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(2, PROGRAM_BANK_SIZE), bank_filled_rom(2, CHARACTER_BANK_SIZE), 0x2000, false);
+
+        serial_write(&mut mmc1, 0x8000, 0b01100);
+        assert_eq!(mmc1.mirroring(), Mirroring::SingleScreenLower);
+        serial_write(&mut mmc1, 0x8000, 0b01101);
+        assert_eq!(mmc1.mirroring(), Mirroring::SingleScreenUpper);
+        serial_write(&mut mmc1, 0x8000, 0b01110);
+        assert_eq!(mmc1.mirroring(), Mirroring::Vertical);
+        serial_write(&mut mmc1, 0x8000, 0b01111);
+        assert_eq!(mmc1.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn program_ram_enable() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(2, PROGRAM_BANK_SIZE), bank_filled_rom(2, CHARACTER_BANK_SIZE), 0x2000, false);
+        mmc1.write_cpu(0x6000, 0x4F);
+        assert_eq!(mmc1.read_cpu(0x6000), 0x4F);
+
+        // disable PRG RAM
+        serial_write(&mut mmc1, 0xE000, 0b10000);
+        mmc1.write_cpu(0x6000, 0x50);
+        assert_eq!(mmc1.read_cpu(0x6000), 0x00);
+
+        // enable PRG RAM again
+        serial_write(&mut mmc1, 0xE000, 0b00000);
+        assert_eq!(mmc1.read_cpu(0x6000), 0x4F);
+    }
+
+    #[test]
+    fn surom_outer_program_bank() {
+        // 512 KB PRG ROM with CHR RAM
+        let mut mmc1 = Mmc1::new(bank_filled_rom(32, PROGRAM_BANK_SIZE), vec![], 0x2000, false);
+        serial_write(&mut mmc1, 0x8000, 0b01100);
+        serial_write(&mut mmc1, 0xE000, 1);
+
+        assert_eq!(mmc1.read_cpu(0x8000), 1);
+        assert_eq!(mmc1.read_cpu(0xC000), 15);
+
+        // select the second 256 KB half, fixed bank follows it
+        serial_write(&mut mmc1, 0xA000, 0b10000);
+        assert_eq!(mmc1.read_cpu(0x8000), 17);
+        assert_eq!(mmc1.read_cpu(0xC000), 31);
+    }
+
+    #[test]
+    fn sorom_program_ram_bank() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(16, PROGRAM_BANK_SIZE), vec![], 0x4000, false);
+        mmc1.write_cpu(0x6000, 0x01);
+
+        serial_write(&mut mmc1, 0xA000, 0b01000);
+        assert_eq!(mmc1.read_cpu(0x6000), 0x00);
+        mmc1.write_cpu(0x6000, 0x02);
+
+        serial_write(&mut mmc1, 0xA000, 0b00000);
+        assert_eq!(mmc1.read_cpu(0x6000), 0x01);
+    }
+
+    #[test]
+    fn sxrom_program_ram_bank() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(32, PROGRAM_BANK_SIZE), vec![], 0x8000, false);
+        for bank in 0..4 {
+            serial_write(&mut mmc1, 0xA000, bank << 2);
+            mmc1.write_cpu(0x7FFF, bank);
+        }
+
+        serial_write(&mut mmc1, 0xA000, 0b1000);
+        assert_eq!(mmc1.read_cpu(0x7FFF), 2);
+        serial_write(&mut mmc1, 0xA000, 0b0100);
+        assert_eq!(mmc1.read_cpu(0x7FFF), 1);
+    }
+
+    #[test]
+    fn battery_backed_ram() {
+        let mut mmc1 = Mmc1::new(bank_filled_rom(2, PROGRAM_BANK_SIZE), bank_filled_rom(2, CHARACTER_BANK_SIZE), 0x2000, true);
+        mmc1.restore_battery_backed_ram(&vec![0x4F; 0x2000]);
+        assert_eq!(mmc1.read_cpu(0x6000), 0x4F);
+
+        mmc1.write_cpu(0x7FFF, 0x50);
+        assert_eq!(mmc1.battery_backed_ram().unwrap()[0x1FFF], 0x50);
+
+        let mmc1 = Mmc1::new(bank_filled_rom(2, PROGRAM_BANK_SIZE), bank_filled_rom(2, CHARACTER_BANK_SIZE), 0x2000, false);
+        assert_eq!(mmc1.battery_backed_ram(), None);
+    }
+}
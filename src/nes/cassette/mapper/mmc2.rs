@@ -0,0 +1,244 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x1000; // 4 KB
+
+const LATCH_FD: u8 = 0xFD;
+const LATCH_FE: u8 = 0xFE;
+
+/// Mapper 9 (MMC2 / PxROM), Mapper 10 (MMC4 / FxROM)
+///
+/// MMC2
+/// CPU $8000-$9FFF: 8 KB switchable PRG ROM bank
+/// CPU $A000-$FFFF: three 8 KB PRG ROM banks, fixed to the last three banks
+///
+/// MMC4
+/// CPU $6000-$7FFF: 8 KB PRG RAM
+/// CPU $8000-$BFFF: 16 KB switchable PRG ROM bank
+/// CPU $C000-$FFFF: 16 KB PRG ROM bank, fixed to the last bank
+///
+/// PPU $0000-$0FFF: 4 KB CHR bank, switchable by the latch 0
+/// PPU $1000-$1FFF: 4 KB CHR bank, switchable by the latch 1
+///
+/// Each latch selects one of 2 CHR bank registers ($FD / $FE),
+/// it is set after the PPU fetches the tile $FD or $FE of the pattern table.
+///
+///   latch 0: $0FD8 -> $FD, $0FE8 -> $FE ($0FD8-$0FDF, $0FE8-$0FEF on the MMC4)
+///   latch 1: $1FD8-$1FDF -> $FD, $1FE8-$1FEF -> $FE
+///
+/// refer: https://wiki.nesdev.com/w/index.php/MMC2
+///        https://wiki.nesdev.com/w/index.php/MMC4
+pub struct Mmc2 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    is_mmc4: bool,
+    program_bank: u8,
+    /// CHR banks: [latch 0 $FD, latch 0 $FE, latch 1 $FD, latch 1 $FE]
+    character_banks: [u8; 4],
+    latches: [u8; 2],
+    mirroring: Mirroring,
+}
+
+impl Mmc2 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring, is_mmc4: bool, program_ram_size: usize, battery: bool) -> Self {
+        Mmc2 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            // the MMC2 board has no PRG RAM
+            program_ram: if is_mmc4 { vec![0; program_ram_size] } else { Vec::new() },
+            battery,
+            is_mmc4,
+            program_bank: 0,
+            character_banks: [0; 4],
+            latches: [LATCH_FE, LATCH_FE],
+            mirroring,
+        }
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank_count = self.program_rom.len() / PROGRAM_BANK_SIZE;
+        let addr = addr as usize;
+
+        let offset = match (self.is_mmc4, addr) {
+            (false, 0x8000..=0x9FFF) => self.program_bank as usize * PROGRAM_BANK_SIZE + (addr & 0x1FFF),
+            // the last three banks
+            (false, _) => (bank_count - 4) * PROGRAM_BANK_SIZE + (addr - 0x8000),
+            (true, 0x8000..=0xBFFF) => self.program_bank as usize * PROGRAM_BANK_SIZE * 2 + (addr & 0x3FFF),
+            (true, _) => (bank_count - 2) * PROGRAM_BANK_SIZE + (addr & 0x3FFF),
+        };
+
+        offset % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let table = (addr >> 12) as usize & 0x01;
+        let register = table * 2 + if self.latches[table] == LATCH_FD { 0 } else { 1 };
+        self.character_banks[register] as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x0FFF)
+    }
+}
+
+impl Mapper for Mmc2 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            0xA000..=0xAFFF => self.program_bank = data & 0x0F,
+            0xB000..=0xEFFF => self.character_banks[((addr >> 12) - 0xB) as usize] = data & 0x1F,
+            0xF000..=0xFFFF => {
+                self.mirroring = if data & 0x01 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn notify_pattern_fetch(&mut self, addr: u16) {
+        match (addr, self.is_mmc4) {
+            (0x0FD8, _) | (0x0FD9..=0x0FDF, true) => self.latches[0] = LATCH_FD,
+            (0x0FE8, _) | (0x0FE9..=0x0FEF, true) => self.latches[0] = LATCH_FE,
+            (0x1FD8..=0x1FDF, _) => self.latches[1] = LATCH_FD,
+            (0x1FE8..=0x1FEF, _) => self.latches[1] = LATCH_FE,
+            _ => {},
+        }
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery && !self.program_ram.is_empty() {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod mmc2_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_mmc2(is_mmc4: bool) -> Mmc2 {
+        let mut mmc2 = Mmc2::new(
+            bank_filled_rom(16, PROGRAM_BANK_SIZE),
+            bank_filled_rom(32, CHARACTER_BANK_SIZE),
+            Mirroring::Vertical,
+            is_mmc4,
+            0x2000,
+            false,
+        );
+        for (index, addr) in [0xB000, 0xC000, 0xD000, 0xE000].iter().enumerate() {
+            mmc2.write_cpu(*addr, 4 + index as u8);
+        }
+        mmc2
+    }
+
+    #[test]
+    fn program_bank() {
+        let mut mmc2 = new_mmc2(false);
+        mmc2.write_cpu(0xA000, 3);
+        assert_eq!(mmc2.read_cpu(0x8000), 3);
+        assert_eq!(mmc2.read_cpu(0xA000), 13);
+        assert_eq!(mmc2.read_cpu(0xC000), 14);
+        assert_eq!(mmc2.read_cpu(0xE000), 15);
+
+        // no PRG RAM on the MMC2 board
+        mmc2.write_cpu(0x6000, 0x12);
+        assert_eq!(mmc2.read_cpu(0x6000), 0x00);
+
+        let mut mmc4 = new_mmc2(true);
+        mmc4.write_cpu(0xA000, 3);
+        assert_eq!(mmc4.read_cpu(0x8000), 6);
+        assert_eq!(mmc4.read_cpu(0xA000), 7);
+        assert_eq!(mmc4.read_cpu(0xC000), 14);
+        assert_eq!(mmc4.read_cpu(0xE000), 15);
+
+        mmc4.write_cpu(0x6000, 0x12);
+        assert_eq!(mmc4.read_cpu(0x6000), 0x12);
+    }
+
+    #[test]
+    fn latch_by_pattern_fetches() {
+        let mut mmc2 = new_mmc2(false);
+        assert_eq!(mmc2.latches, [LATCH_FE, LATCH_FE]);
+        assert_eq!(mmc2.read_ppu(0x0000), 5);
+        assert_eq!(mmc2.read_ppu(0x1000), 7);
+
+        // fetches of the tile $FD (low plane $0FD0-$0FD7, high plane $0FD8-$0FDF)
+        for addr in 0x0FD0..0x0FD8 {
+            mmc2.notify_pattern_fetch(addr);
+        }
+        assert_eq!(mmc2.latches[0], LATCH_FE);
+        mmc2.notify_pattern_fetch(0x0FD8);
+        assert_eq!(mmc2.latches, [LATCH_FD, LATCH_FE]);
+        assert_eq!(mmc2.read_ppu(0x0000), 4);
+
+        // the MMC2 needs exactly $0FE8 for the latch 0
+        mmc2.notify_pattern_fetch(0x0FE9);
+        assert_eq!(mmc2.latches[0], LATCH_FD);
+        mmc2.notify_pattern_fetch(0x0FE8);
+        assert_eq!(mmc2.latches[0], LATCH_FE);
+
+        // the latch 1 accepts the whole high plane
+        mmc2.notify_pattern_fetch(0x1FDC);
+        assert_eq!(mmc2.latches, [LATCH_FE, LATCH_FD]);
+        assert_eq!(mmc2.read_ppu(0x1000), 6);
+        mmc2.notify_pattern_fetch(0x1FEF);
+        assert_eq!(mmc2.latches[1], LATCH_FE);
+
+        // other tiles don't affect the latches
+        mmc2.notify_pattern_fetch(0x0FC8);
+        mmc2.notify_pattern_fetch(0x1FF8);
+        assert_eq!(mmc2.latches, [LATCH_FE, LATCH_FE]);
+    }
+
+    #[test]
+    fn latch_by_pattern_fetches_mmc4() {
+        let mut mmc4 = new_mmc2(true);
+        mmc4.notify_pattern_fetch(0x0FDB);
+        assert_eq!(mmc4.latches[0], LATCH_FD);
+        mmc4.notify_pattern_fetch(0x0FEF);
+        assert_eq!(mmc4.latches[0], LATCH_FE);
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut mmc2 = new_mmc2(false);
+        mmc2.write_cpu(0xF000, 1);
+        assert_eq!(mmc2.mirroring(), Mirroring::Horizontal);
+        mmc2.write_cpu(0xF000, 0);
+        assert_eq!(mmc2.mirroring(), Mirroring::Vertical);
+    }
+}
@@ -0,0 +1,445 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// PPU A12 must stay low for this many PPU cycles (about 3 CPU cycles)
+/// before a rising edge clocks the scanline counter.
+const A12_LOW_FILTER_CYCLE: usize = 10;
+
+/// Mapper 4 (MMC3 / TxROM)
+///
+/// CPU $6000-$7FFF: 8 KB PRG RAM bank (optional)
+/// CPU $8000-$9FFF: 8 KB switchable PRG ROM bank, or fixed to the second-last bank
+/// CPU $A000-$BFFF: 8 KB switchable PRG ROM bank
+/// CPU $C000-$DFFF: 8 KB PRG ROM bank, fixed to the second-last bank, or switchable
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$0FFF: two 2 KB switchable CHR banks (swapped with $1000-$1FFF by inversion)
+/// PPU $1000-$1FFF: four 1 KB switchable CHR banks
+///
+/// The scanline counter is clocked by rising edges of PPU A12, which happen once
+/// per line when the background and sprites use different pattern tables.
+/// The IRQ follows the MMC3B/C ("new") behaviour: it is asserted whenever the
+/// counter is 0 after clocking, including a reload with the latch 0.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/MMC3
+pub struct Mmc3 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    four_screen: bool,
+    bank_select: u8,
+    bank_registers: [u8; 8],
+    mirroring: Mirroring,
+    is_program_ram_enabled: bool,
+    is_program_ram_write_protected: bool,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12: bool,
+    a12_low_since: usize,
+}
+
+impl Mmc3 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring, program_ram_size: usize, battery: bool) -> Self {
+        Mmc3 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            four_screen: mirroring == Mirroring::FourScreen,
+            bank_select: 0,
+            bank_registers: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring,
+            is_program_ram_enabled: true,
+            is_program_ram_write_protected: false,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12: false,
+            a12_low_since: 0,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        let is_even = addr & 0x0001 == 0;
+
+        match (addr, is_even) {
+            (0x8000..=0x9FFF, true) => self.bank_select = data,
+            (0x8000..=0x9FFF, false) => self.bank_registers[(self.bank_select & 0b111) as usize] = data,
+            (0xA000..=0xBFFF, true) if !self.four_screen => {
+                self.mirroring = if data & 0x01 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+            },
+            (0xA000..=0xBFFF, true) => {},
+            (0xA000..=0xBFFF, false) => {
+                self.is_program_ram_enabled = data & 0x80 == 0x80;
+                self.is_program_ram_write_protected = data & 0x40 == 0x40;
+            },
+            (0xC000..=0xDFFF, true) => self.irq_latch = data,
+            (0xC000..=0xDFFF, false) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            (0xE000..=0xFFFF, true) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            },
+            (0xE000..=0xFFFF, false) => self.irq_enabled = true,
+            _ => unreachable!(),
+        }
+    }
+
+    fn clock_scanline_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank_count = (self.program_rom.len() / PROGRAM_BANK_SIZE).max(2);
+        let second_last_bank = bank_count - 2;
+        let last_bank = bank_count - 1;
+        let r6 = (self.bank_registers[6] & 0x3F) as usize;
+        let r7 = (self.bank_registers[7] & 0x3F) as usize;
+        let is_program_mode_swapped = self.bank_select & 0x40 == 0x40;
+
+        let bank = match (addr, is_program_mode_swapped) {
+            (0x8000..=0x9FFF, false) => r6,
+            (0x8000..=0x9FFF, true) => second_last_bank,
+            (0xA000..=0xBFFF, _) => r7,
+            (0xC000..=0xDFFF, false) => second_last_bank,
+            (0xC000..=0xDFFF, true) => r6,
+            _ => last_bank,
+        };
+
+        ((bank % bank_count) * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        // CHR A12 inversion swaps the 2 KB banks and the 1 KB banks
+        let addr = if self.bank_select & 0x80 == 0x80 {
+            addr ^ 0x1000
+        } else {
+            addr
+        };
+
+        let bank = match addr >> 10 {
+            0 => self.bank_registers[0] & 0xFE,
+            1 => self.bank_registers[0] | 0x01,
+            2 => self.bank_registers[1] & 0xFE,
+            3 => self.bank_registers[1] | 0x01,
+            4 => self.bank_registers[2],
+            5 => self.bank_registers[3],
+            6 => self.bank_registers[4],
+            _ => self.bank_registers[5],
+        } as usize;
+
+        bank * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled && !self.program_ram.is_empty() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled && !self.is_program_ram_write_protected && !self.program_ram.is_empty() => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            0x8000..=0xFFFF => self.write_register(addr, data),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn notify_ppu_address(&mut self, addr: u16, ppu_cycle: usize) {
+        let a12 = addr & 0x1000 == 0x1000;
+
+        if a12 && !self.a12 && ppu_cycle.saturating_sub(self.a12_low_since) >= A12_LOW_FILTER_CYCLE {
+            self.clock_scanline_counter();
+        }
+
+        if !a12 && self.a12 {
+            self.a12_low_since = ppu_cycle;
+        }
+
+        self.a12 = a12;
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod mmc3_test {
+    use super::*;
+    use crate::nes::cassette::mapper::bank_filled_rom;
+    use crate::nes::ppu::Ppu;
+
+    fn new_mmc3() -> Mmc3 {
+        Mmc3::new(
+            bank_filled_rom(16, PROGRAM_BANK_SIZE),
+            bank_filled_rom(128, CHARACTER_BANK_SIZE),
+            Mirroring::Vertical,
+            0x2000,
+            false,
+        )
+    }
+
+    /// Toggle A12 like the mmc3_test ROMs do by writing $2006 ($1000, then $0000).
+    fn clock_a12(mmc3: &mut Mmc3, ppu_cycle: &mut usize) {
+        *ppu_cycle += A12_LOW_FILTER_CYCLE;
+        mmc3.notify_ppu_address(0x1000, *ppu_cycle);
+        *ppu_cycle += A12_LOW_FILTER_CYCLE;
+        mmc3.notify_ppu_address(0x0000, *ppu_cycle);
+    }
+
+    #[test]
+    fn program_bank_mode() {
+        let mut mmc3 = new_mmc3();
+        mmc3.write_cpu(0x8000, 6);
+        mmc3.write_cpu(0x8001, 3);
+        mmc3.write_cpu(0x8000, 7);
+        mmc3.write_cpu(0x8001, 5);
+
+        assert_eq!(mmc3.read_cpu(0x8000), 3);
+        assert_eq!(mmc3.read_cpu(0xA000), 5);
+        assert_eq!(mmc3.read_cpu(0xC000), 14);
+        assert_eq!(mmc3.read_cpu(0xE000), 15);
+
+        // swap $8000 and $C000
+        mmc3.write_cpu(0x8000, 0x40);
+        assert_eq!(mmc3.read_cpu(0x8000), 14);
+        assert_eq!(mmc3.read_cpu(0xA000), 5);
+        assert_eq!(mmc3.read_cpu(0xC000), 3);
+        assert_eq!(mmc3.read_cpu(0xE000), 15);
+    }
+
+    #[test]
+    fn character_bank_and_inversion() {
+        let mut mmc3 = new_mmc3();
+        for (register, bank) in [(0, 9), (1, 20), (2, 30), (3, 31), (4, 32), (5, 33)].iter() {
+            mmc3.write_cpu(0x8000, *register);
+            mmc3.write_cpu(0x8001, *bank);
+        }
+
+        // 2 KB banks ignore the low bit
+        assert_eq!(mmc3.read_ppu(0x0000), 8);
+        assert_eq!(mmc3.read_ppu(0x0400), 9);
+        assert_eq!(mmc3.read_ppu(0x0800), 20);
+        assert_eq!(mmc3.read_ppu(0x0C00), 21);
+        assert_eq!(mmc3.read_ppu(0x1000), 30);
+        assert_eq!(mmc3.read_ppu(0x1C00), 33);
+
+        mmc3.write_cpu(0x8000, 0x80);
+        assert_eq!(mmc3.read_ppu(0x0000), 30);
+        assert_eq!(mmc3.read_ppu(0x0C00), 33);
+        assert_eq!(mmc3.read_ppu(0x1000), 8);
+        assert_eq!(mmc3.read_ppu(0x1C00), 21);
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut mmc3 = new_mmc3();
+        mmc3.write_cpu(0xA000, 1);
+        assert_eq!(mmc3.mirroring(), Mirroring::Horizontal);
+        mmc3.write_cpu(0xA000, 0);
+        assert_eq!(mmc3.mirroring(), Mirroring::Vertical);
+
+        let mut mmc3 = Mmc3::new(vec![0; 0x8000], vec![], Mirroring::FourScreen, 0x2000, false);
+        mmc3.write_cpu(0xA000, 1);
+        assert_eq!(mmc3.mirroring(), Mirroring::FourScreen);
+    }
+
+    #[test]
+    fn program_ram_protect() {
+        let mut mmc3 = new_mmc3();
+        mmc3.write_cpu(0x6000, 0x4F);
+        assert_eq!(mmc3.read_cpu(0x6000), 0x4F);
+
+        // enabled, write protected
+        mmc3.write_cpu(0xA001, 0xC0);
+        mmc3.write_cpu(0x6000, 0x50);
+        assert_eq!(mmc3.read_cpu(0x6000), 0x4F);
+
+        // disabled
+        mmc3.write_cpu(0xA001, 0x00);
+        assert_eq!(mmc3.read_cpu(0x6000), 0x00);
+    }
+
+    #[test]
+    fn counter_clocking() {
+        // 1-clocking: the counter is reloaded, then decremented on every A12 rise
+        let mut mmc3 = new_mmc3();
+        let mut ppu_cycle = 0;
+        mmc3.write_cpu(0xC000, 3);
+        mmc3.write_cpu(0xC001, 0);
+
+        clock_a12(&mut mmc3, &mut ppu_cycle);
+        assert_eq!(mmc3.irq_counter, 3);
+        clock_a12(&mut mmc3, &mut ppu_cycle);
+        assert_eq!(mmc3.irq_counter, 2);
+
+        // writing the latch doesn't affect the counter
+        mmc3.write_cpu(0xC000, 10);
+        clock_a12(&mut mmc3, &mut ppu_cycle);
+        assert_eq!(mmc3.irq_counter, 1);
+
+        // the counter is reloaded by $C001 on the next clock
+        mmc3.write_cpu(0xC001, 0);
+        clock_a12(&mut mmc3, &mut ppu_cycle);
+        assert_eq!(mmc3.irq_counter, 10);
+    }
+
+    #[test]
+    fn irq_details() {
+        // 2-details: IRQ when the counter reaches 0, acknowledged / disabled by $E000
+        let mut mmc3 = new_mmc3();
+        let mut ppu_cycle = 0;
+        mmc3.write_cpu(0xC000, 2);
+        mmc3.write_cpu(0xC001, 0);
+        mmc3.write_cpu(0xE001, 0);
+
+        clock_a12(&mut mmc3, &mut ppu_cycle); // 2
+        clock_a12(&mut mmc3, &mut ppu_cycle); // 1
+        assert!(!mmc3.irq());
+        clock_a12(&mut mmc3, &mut ppu_cycle); // 0
+        assert!(mmc3.irq());
+
+        mmc3.write_cpu(0xE000, 0);
+        assert!(!mmc3.irq());
+
+        // disabled counter still counts, but doesn't assert IRQ
+        clock_a12(&mut mmc3, &mut ppu_cycle); // 2
+        clock_a12(&mut mmc3, &mut ppu_cycle); // 1
+        clock_a12(&mut mmc3, &mut ppu_cycle); // 0
+        assert_eq!(mmc3.irq_counter, 0);
+        assert!(!mmc3.irq());
+    }
+
+    #[test]
+    fn irq_with_latch_zero() {
+        // 5-MMC3: with the latch 0, IRQ is asserted on every clock
+        let mut mmc3 = new_mmc3();
+        let mut ppu_cycle = 0;
+        mmc3.write_cpu(0xC000, 0);
+        mmc3.write_cpu(0xC001, 0);
+        mmc3.write_cpu(0xE001, 0);
+
+        clock_a12(&mut mmc3, &mut ppu_cycle);
+        assert!(mmc3.irq());
+
+        mmc3.write_cpu(0xE000, 0);
+        mmc3.write_cpu(0xE001, 0);
+        clock_a12(&mut mmc3, &mut ppu_cycle);
+        assert!(mmc3.irq());
+    }
+
+    #[test]
+    fn a12_low_filter() {
+        // 3-A12_clocking: a rise after a short low period is ignored
+        let mut mmc3 = new_mmc3();
+        mmc3.write_cpu(0xC000, 5);
+        mmc3.write_cpu(0xC001, 0);
+
+        mmc3.notify_ppu_address(0x1000, 20);
+        assert_eq!(mmc3.irq_counter, 5);
+        mmc3.notify_ppu_address(0x0000, 22);
+        mmc3.notify_ppu_address(0x1000, 24);
+        assert_eq!(mmc3.irq_counter, 5);
+        mmc3.notify_ppu_address(0x0000, 26);
+        mmc3.notify_ppu_address(0x1000, 26 + A12_LOW_FILTER_CYCLE);
+        assert_eq!(mmc3.irq_counter, 4);
+    }
+
+    #[test]
+    fn irq_scanline_timing() {
+        // 4-scanline_timing: background at $0000 and sprites at $1000 clock
+        // the counter on the first sprite pattern fetch (cycle 261) of every line.
+        let mut mmc3 = new_mmc3();
+        let mut ppu = Ppu::new();
+        ppu.write(0x0000, 0b00001000, &mut mmc3); // sprites at $1000
+        ppu.write(0x0001, 0b00011000, &mut mmc3); // show background and sprites
+
+        mmc3.write_cpu(0xC000, 1);
+        mmc3.write_cpu(0xC001, 0);
+        mmc3.write_cpu(0xE001, 0);
+
+        while !mmc3.irq() {
+            ppu.run(1, &mut mmc3);
+        }
+
+        // line 0: reloaded to 1, line 1: decremented to 0
+        assert_eq!(ppu.line, 1);
+        assert_eq!(ppu.cycle, 262);
+    }
+
+    #[test]
+    fn no_clock_while_rendering_disabled() {
+        let mut mmc3 = new_mmc3();
+        let mut ppu = Ppu::new();
+        ppu.write(0x0000, 0b00001000, &mut mmc3);
+
+        mmc3.write_cpu(0xC000, 0);
+        mmc3.write_cpu(0xC001, 0);
+        mmc3.write_cpu(0xE001, 0);
+
+        for _ in 0..(341 * 262) {
+            ppu.run(1, &mut mmc3);
+        }
+        assert!(!mmc3.irq());
+    }
+}
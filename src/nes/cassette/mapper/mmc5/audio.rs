@@ -0,0 +1,266 @@
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0], // 12.5 %
+    [0, 1, 1, 0, 0, 0, 0, 0], // 25 %
+    [0, 1, 1, 1, 1, 0, 0, 0], // 50 %
+    [1, 0, 0, 1, 1, 1, 1, 1], // 25 % negated
+];
+
+/// The MMC5 has no frame counter, envelopes and length counters are clocked at a fixed 240 Hz.
+const QUARTER_FRAME_CPU_CYCLE: usize = 7457;
+
+/// Linear approximation of the APU mixer. (refer: https://wiki.nesdev.com/w/index.php/APU_Mixer)
+const PULSE_LEVEL: f32 = 0.00752;
+const PCM_LEVEL: f32 = 0.00335;
+
+/// Pulse channel of the MMC5, same as the APU one without the sweep unit.
+#[derive(Default)]
+struct Pulse {
+    duty: u8,
+    is_length_halted: bool, // also the envelope loop flag
+    is_constant_volume: bool,
+    volume: u8,             // also the envelope period
+    timer_period: u16,
+    timer: u16,
+    sequence_step: usize,
+    length_counter: u8,
+    is_enabled: bool,
+    envelope_start: bool,
+    envelope_divider: u8,
+    envelope_decay: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.duty = data >> 6;
+                self.is_length_halted = data & 0x20 == 0x20;
+                self.is_constant_volume = data & 0x10 == 0x10;
+                self.volume = data & 0x0F;
+            },
+            1 => {}, // no sweep unit on the MMC5
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                if self.is_enabled {
+                    self.length_counter = LENGTH_TABLE[(data >> 3) as usize];
+                }
+                self.sequence_step = 0;
+                self.envelope_start = true;
+            },
+        }
+    }
+
+    fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+        if !is_enabled {
+            self.length_counter = 0;
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_step = (self.sequence_step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        if self.envelope_start {
+            self.envelope_start = false;
+            self.envelope_decay = 15;
+            self.envelope_divider = self.volume;
+        } else if self.envelope_divider == 0 {
+            self.envelope_divider = self.volume;
+            if self.envelope_decay > 0 {
+                self.envelope_decay -= 1;
+            } else if self.is_length_halted {
+                self.envelope_decay = 15;
+            }
+        } else {
+            self.envelope_divider -= 1;
+        }
+
+        if !self.is_length_halted && self.length_counter > 0 {
+            self.length_counter -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.length_counter == 0 || DUTY_TABLE[self.duty as usize][self.sequence_step] == 0 {
+            0
+        } else if self.is_constant_volume {
+            self.volume
+        } else {
+            self.envelope_decay
+        }
+    }
+}
+
+/// MMC5 expansion audio: 2 pulse channels and a raw 8 bit PCM channel.
+///
+/// $5000-$5003: pulse 1
+/// $5004-$5007: pulse 2
+/// $5010      : PCM mode (bit 0: read mode) / IRQ enable (bit 7), read: IRQ status
+/// $5011      : PCM level (write mode)
+/// $5015      : channel enable, read: length counter status
+///
+/// In the read mode, CPU reads from $8000-$BFFF set the PCM level.
+/// A level $00 doesn't change the output and trips the IRQ instead.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/MMC5_audio
+#[derive(Default)]
+pub struct Mmc5Audio {
+    pulses: [Pulse; 2],
+    is_pcm_read_mode: bool,
+    is_pcm_irq_enabled: bool,
+    is_pcm_irq_tripped: bool,
+    pcm_level: u8,
+    is_odd_cycle: bool,
+    quarter_frame_cycle: usize,
+}
+
+impl Mmc5Audio {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x5010 => {
+                let data = ((self.irq() as u8) << 7) | self.is_pcm_read_mode as u8;
+                self.is_pcm_irq_tripped = false;
+                data
+            },
+            0x5015 => {
+                (self.pulses[0].length_counter > 0) as u8 | ((self.pulses[1].length_counter > 0) as u8) << 1
+            },
+            _ => 0,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x5000..=0x5003 => self.pulses[0].write(addr - 0x5000, data),
+            0x5004..=0x5007 => self.pulses[1].write(addr - 0x5004, data),
+            0x5010 => {
+                self.is_pcm_read_mode = data & 0x01 == 0x01;
+                self.is_pcm_irq_enabled = data & 0x80 == 0x80;
+            },
+            0x5011 if !self.is_pcm_read_mode => self.set_pcm_level(data),
+            0x5015 => {
+                self.pulses[0].set_enabled(data & 0x01 == 0x01);
+                self.pulses[1].set_enabled(data & 0x02 == 0x02);
+            },
+            _ => {},
+        }
+    }
+
+    /// Called on CPU reads from $8000-$BFFF.
+    pub fn notify_program_read(&mut self, data: u8) {
+        if self.is_pcm_read_mode {
+            self.set_pcm_level(data);
+        }
+    }
+
+    pub fn clock(&mut self, cpu_cycle: usize) {
+        for _ in 0..cpu_cycle {
+            // pulse timers are clocked every other CPU cycle, as the APU ones.
+            if self.is_odd_cycle {
+                self.pulses.iter_mut().for_each(|pulse| pulse.clock_timer());
+            }
+            self.is_odd_cycle = !self.is_odd_cycle;
+
+            self.quarter_frame_cycle += 1;
+            if self.quarter_frame_cycle == QUARTER_FRAME_CPU_CYCLE {
+                self.quarter_frame_cycle = 0;
+                self.pulses.iter_mut().for_each(|pulse| pulse.clock_quarter_frame());
+            }
+        }
+    }
+
+    pub fn irq(&self) -> bool {
+        self.is_pcm_irq_enabled && self.is_pcm_irq_tripped
+    }
+
+    pub fn output(&self) -> f32 {
+        let pulse = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+        pulse * PULSE_LEVEL + self.pcm_level as f32 * PCM_LEVEL
+    }
+
+    fn set_pcm_level(&mut self, data: u8) {
+        if data == 0 {
+            self.is_pcm_irq_tripped = true;
+        } else {
+            self.is_pcm_irq_tripped = false;
+            self.pcm_level = data;
+        }
+    }
+}
+
+#[cfg(test)]
+mod audio_test {
+    use super::*;
+
+    #[test]
+    fn pulse_output() {
+        let mut audio = Mmc5Audio::new();
+        audio.write(0x5015, 0x01);
+        // duty 50 %, constant volume 10, timer period 0
+        audio.write(0x5000, 0b1011_1010);
+        audio.write(0x5002, 0x00);
+        audio.write(0x5003, 0x08);
+        assert_eq!(audio.read(0x5015), 0x01);
+
+        let outputs: Vec<u8> = (0..8).map(|_| {
+            audio.clock(2);
+            audio.pulses[0].output()
+        }).collect();
+        assert_eq!(outputs, vec![10, 10, 10, 10, 0, 0, 0, 0]);
+
+        // disabling clears the length counter
+        audio.write(0x5015, 0x00);
+        assert_eq!(audio.read(0x5015), 0x00);
+        assert_eq!(audio.output(), 0.0);
+    }
+
+    #[test]
+    fn length_counter_clocked_at_240hz() {
+        let mut audio = Mmc5Audio::new();
+        audio.write(0x5015, 0x02);
+        audio.write(0x5004, 0x10);
+        audio.write(0x5007, 0x18); // length index 3: 2
+
+        audio.clock(QUARTER_FRAME_CPU_CYCLE);
+        assert_eq!(audio.pulses[1].length_counter, 1);
+        audio.clock(QUARTER_FRAME_CPU_CYCLE);
+        assert_eq!(audio.read(0x5015), 0x00);
+    }
+
+    #[test]
+    fn pcm() {
+        let mut audio = Mmc5Audio::new();
+        audio.write(0x5011, 0x80);
+        assert_eq!(audio.output(), 0x80 as f32 * PCM_LEVEL);
+
+        // read mode with IRQ
+        audio.write(0x5010, 0x81);
+        audio.write(0x5011, 0x10);
+        audio.notify_program_read(0x40);
+        assert_eq!(audio.output(), 0x40 as f32 * PCM_LEVEL);
+        assert!(!audio.irq());
+
+        audio.notify_program_read(0x00);
+        assert_eq!(audio.output(), 0x40 as f32 * PCM_LEVEL);
+        assert!(audio.irq());
+        assert_eq!(audio.read(0x5010), 0x81);
+        assert!(!audio.irq());
+    }
+}
@@ -0,0 +1,721 @@
+pub mod audio;
+
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+use audio::Mmc5Audio;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+const EXRAM_SIZE: usize = 0x0400;          // 1 KB
+
+/// Pattern fetches of a line counted from the scanline detection.
+/// 0-63: background (x 32 tiles), 64-79: sprites (x 8 sprites), 80-83: background of the next line.
+const SPRITE_PATTERN_FETCHES: std::ops::Range<usize> = 64..80;
+
+/// Mapper 5 (MMC5 / ExROM)
+///
+/// CPU $5000-$5015: expansion audio
+/// CPU $5100-$5130: bank / mode registers
+/// CPU $5200-$5206: vertical split, scanline IRQ, multiplier
+/// CPU $5C00-$5FFF: 1 KB ExRAM
+/// CPU $6000-$7FFF: 8 KB switchable PRG RAM bank
+/// CPU $8000-$FFFF: PRG ROM / RAM banks (32 KB x 1, 16 KB x 2, 16 KB + 8 KB x 2 or 8 KB x 4)
+/// PPU $0000-$1FFF: CHR banks (8 KB, 4 KB, 2 KB or 1 KB), two register sets for 8x16 sprites
+/// PPU $2000-$2FFF: each nametable from CIRAM, ExRAM or the fill mode
+///
+/// The MMC5 doesn't see the PPU registers (except by snooping $2000 / $2001 writes),
+/// it detects a scanline by three consecutive fetches from the same nametable address,
+/// which happen at the end of each rendering line.
+/// The frame is over when the PPU stops fetching for a CPU cycle.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/MMC5
+pub struct Mmc5 {
+    program_rom: Vec<u8>,
+    program_ram: Vec<u8>,
+    character_memory: CharacterMemory,
+    battery: bool,
+    exram: Vec<u8>,
+    program_mode: u8,
+    character_mode: u8,
+    program_ram_protect: [u8; 2],
+    exram_mode: u8,
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    program_banks: [u8; 5],
+    character_banks_a: [usize; 8],
+    character_banks_b: [usize; 4],
+    character_bank_upper: u8,
+    is_character_set_b_last_written: bool,
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    in_frame: bool,
+    scanline: u8,
+    last_ppu_addr: u16,
+    ppu_addr_match_count: u8,
+    pattern_fetch_count: usize,
+    has_ppu_fetched: bool,
+    is_sprite_8x16: bool,
+    is_rendering_enabled: bool,
+    extended_attribute: u8,
+    split_tile: Option<(usize, usize)>,
+    multiplicand: u8,
+    multiplier: u8,
+    audio: Mmc5Audio,
+}
+
+impl Mmc5 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, program_ram_size: usize, battery: bool) -> Self {
+        Mmc5 {
+            program_rom,
+            program_ram: vec![0; program_ram_size],
+            character_memory: CharacterMemory::new(character_rom),
+            battery,
+            exram: vec![0; EXRAM_SIZE],
+            program_mode: 3,
+            character_mode: 3,
+            program_ram_protect: [0; 2],
+            exram_mode: 0,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            program_banks: [0, 0, 0, 0, 0xFF],
+            character_banks_a: [0; 8],
+            character_banks_b: [0; 4],
+            character_bank_upper: 0,
+            is_character_set_b_last_written: false,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            in_frame: false,
+            scanline: 0,
+            last_ppu_addr: 0,
+            ppu_addr_match_count: 0,
+            pattern_fetch_count: 0,
+            has_ppu_fetched: false,
+            is_sprite_8x16: false,
+            is_rendering_enabled: false,
+            extended_attribute: 0,
+            split_tile: None,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            audio: Mmc5Audio::new(),
+        }
+    }
+
+    fn is_rendering(&self) -> bool {
+        self.in_frame && self.is_rendering_enabled
+    }
+
+    /// The PPU puts the address on the bus before reading it, so the fetch being read is counted already.
+    fn is_sprite_fetch(&self) -> bool {
+        SPRITE_PATTERN_FETCHES.contains(&self.pattern_fetch_count.wrapping_sub(1))
+    }
+
+    fn is_program_ram_writable(&self) -> bool {
+        self.program_ram_protect[0] & 0x03 == 0x02 && self.program_ram_protect[1] & 0x03 == 0x01
+    }
+
+    fn detect_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline = self.scanline.wrapping_add(1);
+            if self.irq_compare != 0 && self.scanline == self.irq_compare {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline = 0;
+            self.irq_pending = false;
+        }
+
+        self.pattern_fetch_count = 0;
+    }
+
+    /// Returns the offset in PRG ROM (true) or PRG RAM (false).
+    fn program_offset(&self, addr: u16) -> (usize, bool) {
+        let banks = &self.program_banks;
+
+        // (bank register, bank size in 8 KB)
+        let (register, size) = match (self.program_mode, addr) {
+            (_, 0x6000..=0x7FFF) => (banks[0] & 0x7F, 1),
+            (0, _) => (banks[4] | 0x80, 4),
+            (1, 0x8000..=0xBFFF) | (2, 0x8000..=0xBFFF) => (banks[2], 2),
+            (1, _) => (banks[4] | 0x80, 2),
+            (2, 0xC000..=0xDFFF) | (3, 0xC000..=0xDFFF) => (banks[3], 1),
+            (3, 0x8000..=0x9FFF) => (banks[1], 1),
+            (3, 0xA000..=0xBFFF) => (banks[2], 1),
+            _ => (banks[4] | 0x80, 1),
+        };
+
+        let bank = (register & 0x7F) as usize & !(size - 1);
+        let offset = bank * PROGRAM_BANK_SIZE + (addr as usize & (size * PROGRAM_BANK_SIZE - 1));
+        (offset, register & 0x80 == 0x80)
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let addr = addr as usize;
+
+        if self.is_rendering() && !self.is_sprite_fetch() {
+            if self.split_tile.is_some() {
+                return self.split_bank as usize * 0x1000 + (addr & 0x0FFF);
+            }
+            if self.exram_mode == 1 {
+                let bank = (self.extended_attribute & 0x3F) as usize | (self.character_bank_upper as usize) << 6;
+                return bank * 0x1000 + (addr & 0x0FFF);
+            }
+        }
+
+        // set A is for sprites, set B is for the background. (only with 8x16 sprites)
+        // Out of rendering, the last written set is used.
+        let is_set_b = match (self.is_sprite_8x16, self.is_rendering()) {
+            (false, _) => false,
+            (true, true) => !self.is_sprite_fetch(),
+            (true, false) => self.is_character_set_b_last_written,
+        };
+
+        let (bank, size) = if is_set_b {
+            let banks = &self.character_banks_b;
+            match self.character_mode {
+                0 => (banks[3], 8),
+                1 => (banks[3], 4),
+                2 => (banks[(addr >> 10) & 0x02 | 0x01], 2),
+                _ => (banks[(addr >> 10) & 0x03], 1),
+            }
+        } else {
+            let banks = &self.character_banks_a;
+            match self.character_mode {
+                0 => (banks[7], 8),
+                1 => (banks[(addr >> 10) | 0x03], 4),
+                2 => (banks[(addr >> 10) | 0x01], 2),
+                _ => (banks[addr >> 10], 1),
+            }
+        };
+
+        let bank_size = size * CHARACTER_BANK_SIZE;
+        bank * bank_size + (addr & (bank_size - 1))
+    }
+
+    /// Latches the ExRAM data for a background tile, on its nametable fetch.
+    fn latch_background_tile(&mut self, offset: usize) {
+        /*
+            The nametable column moves with the horizontal scroll, so the screen column
+            is counted from the pattern fetches since the start of the line:
+            the tiles 2-33 of this line, the sprites, then the tiles 0-1 of the next line.
+        */
+        let (line, column) = match self.pattern_fetch_count {
+            count if count < SPRITE_PATTERN_FETCHES.start => (self.scanline, (count / 2 + 2) % 32),
+            count => (self.scanline.wrapping_add(1), (count.saturating_sub(SPRITE_PATTERN_FETCHES.end) / 2) % 32),
+        };
+        let split_count = (self.split_control & 0x1F) as usize;
+        let is_split_column = if self.split_control & 0x40 == 0x40 {
+            column >= split_count
+        } else {
+            column < split_count
+        };
+
+        self.split_tile = if self.split_control & 0x80 == 0x80 && self.exram_mode <= 1 && is_split_column {
+            let row = ((line as usize + self.split_scroll as usize) / 8) % 30;
+            Some((row, column))
+        } else {
+            None
+        };
+
+        self.extended_attribute = self.exram[offset];
+    }
+
+    fn split_nametable(&self, row: usize, column: usize, is_attribute: bool) -> u8 {
+        if is_attribute {
+            let attribute = self.exram[0x03C0 + (row / 4) * 8 + column / 4];
+            let shift = (row & 0x02) << 1 | (column & 0x02);
+            ((attribute >> shift) & 0x03) * 0x55
+        } else {
+            self.exram[row * 32 + column]
+        }
+    }
+}
+
+impl Mapper for Mmc5 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x5010 | 0x5015 => self.audio.read(addr),
+            0x5204 => {
+                let data = (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6;
+                self.irq_pending = false;
+                data
+            },
+            0x5205 => (self.multiplicand as u16 * self.multiplier as u16) as u8,
+            0x5206 => ((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8,
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => self.exram[addr as usize - 0x5C00],
+            0x6000..=0xFFFF => {
+                let data = match self.program_offset(addr) {
+                    (offset, true) => self.program_rom[offset % self.program_rom.len()],
+                    (_, false) if self.program_ram.is_empty() => 0,
+                    (offset, false) => self.program_ram[offset % self.program_ram.len()],
+                };
+                if addr < 0xC000 {
+                    self.audio.notify_program_read(data);
+                }
+                data
+            },
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x5000..=0x5015 => self.audio.write(addr, data),
+            0x5100 => self.program_mode = data & 0x03,
+            0x5101 => self.character_mode = data & 0x03,
+            0x5102 | 0x5103 => self.program_ram_protect[addr as usize - 0x5102] = data,
+            0x5104 => self.exram_mode = data & 0x03,
+            0x5105 => self.nametable_mapping = data,
+            0x5106 => self.fill_tile = data,
+            0x5107 => self.fill_attribute = data & 0x03,
+            0x5113..=0x5117 => self.program_banks[addr as usize - 0x5113] = data,
+            0x5120..=0x5127 => {
+                self.character_banks_a[addr as usize - 0x5120] = data as usize | (self.character_bank_upper as usize) << 8;
+                self.is_character_set_b_last_written = false;
+            },
+            0x5128..=0x512B => {
+                self.character_banks_b[addr as usize - 0x5128] = data as usize | (self.character_bank_upper as usize) << 8;
+                self.is_character_set_b_last_written = true;
+            },
+            0x5130 => self.character_bank_upper = data & 0x03,
+            0x5200 => self.split_control = data,
+            0x5201 => self.split_scroll = data,
+            0x5202 => self.split_bank = data,
+            0x5203 => self.irq_compare = data,
+            0x5204 => self.irq_enabled = data & 0x80 == 0x80,
+            0x5205 => self.multiplicand = data,
+            0x5206 => self.multiplier = data,
+            0x5C00..=0x5FFF => match self.exram_mode {
+                // as nametable, writable only while rendering
+                0 | 1 => self.exram[addr as usize - 0x5C00] = if self.in_frame { data } else { 0 },
+                2 => self.exram[addr as usize - 0x5C00] = data,
+                _ => {},
+            },
+            0x6000..=0xFFFF if self.is_program_ram_writable() && !self.program_ram.is_empty() => {
+                if let (offset, false) = self.program_offset(addr) {
+                    let len = self.program_ram.len();
+                    self.program_ram[offset % len] = data;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    /// Approximates $5105 to a standard mirroring for the CIRAM nametables.
+    fn mirroring(&self) -> Mirroring {
+        let mut pages = [None; 4];
+        for (quadrant, page) in pages.iter_mut().enumerate() {
+            let source = (self.nametable_mapping >> (quadrant * 2)) & 0x03;
+            if source < 2 {
+                *page = Some(source);
+            }
+        }
+        Mirroring::from_pages(pages)
+    }
+
+    fn read_nametable(&mut self, addr: u16) -> Option<u8> {
+        let offset = (addr & 0x03FF) as usize;
+        let is_attribute = offset >= 0x03C0;
+
+        if self.is_rendering() {
+            if !is_attribute {
+                self.latch_background_tile(offset);
+            }
+            if let Some((row, column)) = self.split_tile {
+                return Some(self.split_nametable(row, column, is_attribute));
+            }
+            if is_attribute && self.exram_mode == 1 {
+                return Some((self.extended_attribute >> 6) * 0x55);
+            }
+        }
+
+        let quadrant = (addr >> 10) & 0x03;
+        match (self.nametable_mapping >> (quadrant * 2)) & 0x03 {
+            0 | 1 => None,
+            2 if self.exram_mode <= 1 => Some(self.exram[offset]),
+            2 => Some(0),
+            _ if is_attribute => Some(self.fill_attribute * 0x55),
+            _ => Some(self.fill_tile),
+        }
+    }
+
+    fn write_nametable(&mut self, addr: u16, data: u8) -> bool {
+        let quadrant = (addr >> 10) & 0x03;
+        match (self.nametable_mapping >> (quadrant * 2)) & 0x03 {
+            0 | 1 => false,
+            2 => {
+                if self.exram_mode <= 1 {
+                    self.exram[(addr & 0x03FF) as usize] = data;
+                }
+                true
+            },
+            _ => true,
+        }
+    }
+
+    fn notify_ppu_register_write(&mut self, addr: u16, data: u8) {
+        match addr & 0x2007 {
+            0x2000 => self.is_sprite_8x16 = data & 0x20 == 0x20,
+            0x2001 => self.is_rendering_enabled = data & 0x18 != 0,
+            _ => {},
+        }
+    }
+
+    fn irq(&self) -> bool {
+        (self.irq_pending && self.irq_enabled) || self.audio.irq()
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        if self.in_frame && !self.has_ppu_fetched {
+            self.in_frame = false;
+            self.ppu_addr_match_count = 0;
+        }
+        self.has_ppu_fetched = false;
+
+        self.audio.clock(cycle);
+    }
+
+    fn notify_ppu_address(&mut self, addr: u16, _ppu_cycle: usize) {
+        self.has_ppu_fetched = true;
+
+        if (0x2000..=0x2FFF).contains(&addr) && addr == self.last_ppu_addr {
+            self.ppu_addr_match_count += 1;
+            if self.ppu_addr_match_count == 2 {
+                self.detect_scanline();
+            }
+        } else {
+            self.ppu_addr_match_count = 0;
+        }
+        self.last_ppu_addr = addr;
+
+        if addr < 0x2000 {
+            self.pattern_fetch_count += 1;
+        }
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod mmc5_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_mmc5() -> Mmc5 {
+        Mmc5::new(
+            bank_filled_rom(16, PROGRAM_BANK_SIZE),
+            bank_filled_rom(256, CHARACTER_BANK_SIZE),
+            0x10000,
+            false,
+        )
+    }
+
+    /// Feeds the nametable fetches at the end of a line and the start of the next one.
+    fn fetch_line_start(mmc5: &mut Mmc5) {
+        for _ in 0..3 {
+            mmc5.notify_ppu_address(0x2000, 0);
+        }
+        mmc5.notify_ppu_address(0x23C0, 0);
+    }
+
+    fn start_rendering(mmc5: &mut Mmc5) {
+        mmc5.notify_ppu_register_write(0x2001, 0x18);
+        fetch_line_start(mmc5);
+    }
+
+    #[test]
+    fn program_bank_mode() {
+        let mut mmc5 = new_mmc5();
+        for (addr, bank) in [(0x5114, 0x81), (0x5115, 0x83), (0x5116, 0x85), (0x5117, 0x07)].iter() {
+            mmc5.write_cpu(*addr, *bank);
+        }
+
+        // mode 3: 8 KB x 4
+        assert_eq!(mmc5.read_cpu(0x8000), 1);
+        assert_eq!(mmc5.read_cpu(0xA000), 3);
+        assert_eq!(mmc5.read_cpu(0xC000), 5);
+        assert_eq!(mmc5.read_cpu(0xE000), 7);
+
+        // mode 2: 16 KB + 8 KB x 2
+        mmc5.write_cpu(0x5100, 2);
+        assert_eq!(mmc5.read_cpu(0x8000), 2);
+        assert_eq!(mmc5.read_cpu(0xA000), 3);
+        assert_eq!(mmc5.read_cpu(0xC000), 5);
+        assert_eq!(mmc5.read_cpu(0xE000), 7);
+
+        // mode 1: 16 KB x 2
+        mmc5.write_cpu(0x5100, 1);
+        assert_eq!(mmc5.read_cpu(0x8000), 2);
+        assert_eq!(mmc5.read_cpu(0xC000), 6);
+        assert_eq!(mmc5.read_cpu(0xE000), 7);
+
+        // mode 0: 32 KB
+        mmc5.write_cpu(0x5100, 0);
+        assert_eq!(mmc5.read_cpu(0x8000), 4);
+        assert_eq!(mmc5.read_cpu(0xE000), 7);
+    }
+
+    #[test]
+    fn program_ram() {
+        let mut mmc5 = new_mmc5();
+
+        // write protected by default
+        mmc5.write_cpu(0x6000, 0x12);
+        assert_eq!(mmc5.read_cpu(0x6000), 0x00);
+
+        mmc5.write_cpu(0x5102, 0x02);
+        mmc5.write_cpu(0x5103, 0x01);
+        mmc5.write_cpu(0x5113, 0x03);
+        mmc5.write_cpu(0x6000, 0x12);
+        assert_eq!(mmc5.read_cpu(0x6000), 0x12);
+
+        // the same RAM bank at $8000 (bit 7 clear)
+        mmc5.write_cpu(0x5114, 0x03);
+        assert_eq!(mmc5.read_cpu(0x8000), 0x12);
+        mmc5.write_cpu(0x8001, 0x34);
+        assert_eq!(mmc5.read_cpu(0x6001), 0x34);
+
+        // ROM bank isn't writable
+        mmc5.write_cpu(0x5114, 0x83);
+        mmc5.write_cpu(0x8000, 0x56);
+        assert_eq!(mmc5.read_cpu(0x8000), 3);
+    }
+
+    #[test]
+    fn character_bank_mode() {
+        let mut mmc5 = new_mmc5();
+        for index in 0..8 {
+            mmc5.write_cpu(0x5120 + index, 0x10 + index as u8);
+        }
+
+        assert_eq!(mmc5.read_ppu(0x0000), 0x10);
+        assert_eq!(mmc5.read_ppu(0x1C00), 0x17);
+
+        // 2 KB banks use the odd registers
+        mmc5.write_cpu(0x5101, 2);
+        assert_eq!(mmc5.read_ppu(0x0000), 0x11 * 2);
+        assert_eq!(mmc5.read_ppu(0x0400), 0x11 * 2 + 1);
+        assert_eq!(mmc5.read_ppu(0x1800), 0x17 * 2);
+
+        // upper bits
+        mmc5.write_cpu(0x5101, 3);
+        mmc5.write_cpu(0x5130, 0x01);
+        mmc5.write_cpu(0x5120, 0x02);
+        assert_eq!(mmc5.character_offset(0x0000), 0x102 * CHARACTER_BANK_SIZE);
+    }
+
+    #[test]
+    fn character_sets_with_8x16_sprites() {
+        let mut mmc5 = new_mmc5();
+        mmc5.write_cpu(0x5120, 0x01);
+        mmc5.write_cpu(0x5128, 0x02);
+        mmc5.notify_ppu_register_write(0x2000, 0x20);
+
+        // out of rendering, the last written set
+        assert_eq!(mmc5.read_ppu(0x0000), 0x02);
+        mmc5.write_cpu(0x5120, 0x01);
+        assert_eq!(mmc5.read_ppu(0x0000), 0x01);
+
+        // while rendering, set B for the background and set A for sprites
+        start_rendering(&mut mmc5);
+        assert_eq!(mmc5.read_ppu(0x0000), 0x02);
+        // the 65th pattern fetch of the line is the first sprite fetch
+        for _ in 0..64 {
+            mmc5.notify_ppu_address(0x0000, 0);
+        }
+        assert_eq!(mmc5.read_ppu(0x0000), 0x02);
+        mmc5.notify_ppu_address(0x0000, 0);
+        assert_eq!(mmc5.read_ppu(0x0000), 0x01);
+        for _ in 0..16 {
+            mmc5.notify_ppu_address(0x0000, 0);
+        }
+        assert_eq!(mmc5.read_ppu(0x0000), 0x02);
+    }
+
+    #[test]
+    fn nametable_mapping() {
+        let mut mmc5 = new_mmc5();
+        // CIRAM 0, CIRAM 1, ExRAM, fill mode
+        mmc5.write_cpu(0x5105, 0b11_10_01_00);
+        mmc5.write_cpu(0x5106, 0x42);
+        mmc5.write_cpu(0x5107, 0x02);
+
+        assert_eq!(mmc5.mirroring(), Mirroring::Vertical);
+        assert_eq!(mmc5.read_nametable(0x2000), None);
+        assert_eq!(mmc5.read_nametable(0x2400), None);
+
+        assert!(mmc5.write_nametable(0x2805, 0x99));
+        assert_eq!(mmc5.read_nametable(0x2805), Some(0x99));
+        assert_eq!(mmc5.exram[0x05], 0x99);
+
+        assert_eq!(mmc5.read_nametable(0x2C00), Some(0x42));
+        assert_eq!(mmc5.read_nametable(0x2FC0), Some(0xAA));
+
+        mmc5.write_cpu(0x5105, 0b01_01_00_00);
+        assert_eq!(mmc5.mirroring(), Mirroring::Horizontal);
+        mmc5.write_cpu(0x5105, 0b11_11_11_11);
+        assert_eq!(mmc5.mirroring(), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn exram_mode() {
+        let mut mmc5 = new_mmc5();
+
+        // nametable mode: zero is written out of rendering
+        mmc5.write_cpu(0x5C00, 0x11);
+        assert_eq!(mmc5.exram[0], 0x00);
+        assert_eq!(mmc5.read_cpu(0x5C00), 0x00);
+        start_rendering(&mut mmc5);
+        mmc5.write_cpu(0x5C00, 0x11);
+        assert_eq!(mmc5.exram[0], 0x11);
+
+        // RAM mode
+        mmc5.write_cpu(0x5104, 2);
+        mmc5.write_cpu(0x5C01, 0x22);
+        assert_eq!(mmc5.read_cpu(0x5C01), 0x22);
+
+        // read only mode
+        mmc5.write_cpu(0x5104, 3);
+        mmc5.write_cpu(0x5C01, 0x33);
+        assert_eq!(mmc5.read_cpu(0x5C01), 0x22);
+    }
+
+    #[test]
+    fn extended_attribute() {
+        let mut mmc5 = new_mmc5();
+        mmc5.write_cpu(0x5104, 2);
+        mmc5.write_cpu(0x5C21, 0b10_000101);
+        mmc5.write_cpu(0x5104, 1);
+        mmc5.write_cpu(0x5130, 0x01);
+        start_rendering(&mut mmc5);
+
+        assert_eq!(mmc5.read_nametable(0x2021), None);
+        assert_eq!(mmc5.read_nametable(0x23C0), Some(0xAA));
+        // 4 KB bank 0x45
+        assert_eq!(mmc5.character_offset(0x0000), 0x45 * 0x1000);
+        assert_eq!(mmc5.character_offset(0x1C10), 0x45 * 0x1000 + 0x0C10);
+    }
+
+    #[test]
+    fn vertical_split() {
+        let mut mmc5 = new_mmc5();
+        mmc5.write_cpu(0x5104, 2);
+        mmc5.write_cpu(0x5C00 + 32 * 2 + 2, 0x77);
+        mmc5.write_cpu(0x5C00 + 32 * 3, 0x66);
+        mmc5.write_cpu(0x5C00 + 0x03C0, 0b11_00_00_00);
+        mmc5.write_cpu(0x5104, 0);
+
+        // left 4 tiles, scrolled 23 lines, 4 KB bank 3
+        mmc5.write_cpu(0x5200, 0x80 | 0x04);
+        mmc5.write_cpu(0x5201, 23);
+        mmc5.write_cpu(0x5202, 3);
+        start_rendering(&mut mmc5);
+
+        // the third tile of the line, whatever the horizontal scroll is
+        assert_eq!(mmc5.read_nametable(0x2011), Some(0x77));
+        assert_eq!(mmc5.read_nametable(0x23C4), Some(0xFF));
+        assert_eq!(mmc5.read_ppu(0x0000), 3 * 4);
+
+        // out of the split region
+        for _ in 0..4 {
+            mmc5.notify_ppu_address(0x0000, 0);
+        }
+        assert_eq!(mmc5.read_nametable(0x2014), None);
+        assert_eq!(mmc5.read_ppu(0x0000), 0);
+
+        // the first tile of the next line, fetched after the sprites
+        for _ in 4..80 {
+            mmc5.notify_ppu_address(0x0000, 0);
+        }
+        assert_eq!(mmc5.read_nametable(0x2002), Some(0x66));
+    }
+
+    #[test]
+    fn scanline_irq() {
+        let mut mmc5 = new_mmc5();
+        mmc5.write_cpu(0x5203, 2);
+        mmc5.write_cpu(0x5204, 0x80);
+
+        start_rendering(&mut mmc5);
+        mmc5.notify_cpu_cycle(2);
+        assert_eq!(mmc5.read_cpu(0x5204), 0x40);
+
+        fetch_line_start(&mut mmc5); // scanline 1
+        assert!(!mmc5.irq());
+        fetch_line_start(&mut mmc5); // scanline 2
+        assert!(mmc5.irq());
+        assert_eq!(mmc5.read_cpu(0x5204), 0xC0);
+        assert!(!mmc5.irq());
+
+        // end of the frame
+        mmc5.notify_cpu_cycle(2);
+        mmc5.notify_cpu_cycle(2);
+        assert_eq!(mmc5.read_cpu(0x5204), 0x00);
+    }
+
+    #[test]
+    fn multiplier() {
+        let mut mmc5 = new_mmc5();
+        assert_eq!(mmc5.read_cpu(0x5205), 0x01);
+        assert_eq!(mmc5.read_cpu(0x5206), 0xFE);
+
+        mmc5.write_cpu(0x5205, 200);
+        mmc5.write_cpu(0x5206, 3);
+        assert_eq!(mmc5.read_cpu(0x5205), (600 & 0xFF) as u8);
+        assert_eq!(mmc5.read_cpu(0x5206), (600 >> 8) as u8);
+    }
+
+    #[test]
+    fn pcm_read_mode_and_irq() {
+        let mut mmc5 = new_mmc5();
+        mmc5.write_cpu(0x5010, 0x81);
+        mmc5.write_cpu(0x5114, 0x80);
+        mmc5.write_cpu(0x5115, 0x81);
+
+        mmc5.read_cpu(0xA000);
+        assert!(!mmc5.irq());
+        assert!(mmc5.audio_output() > 0.0);
+
+        // reading $00 trips the IRQ
+        mmc5.read_cpu(0x8000);
+        assert!(mmc5.irq());
+        mmc5.read_cpu(0x5010);
+        assert!(!mmc5.irq());
+    }
+}
@@ -0,0 +1,287 @@
+pub mod character_memory;
+pub mod nrom;
+pub mod mmc1;
+pub mod mmc2;
+pub mod mmc3;
+pub mod mmc5;
+pub mod namco163;
+pub mod fme7;
+pub mod uxrom;
+pub mod cnrom;
+pub mod axrom;
+pub mod gxrom;
+pub mod vrc_irq;
+pub mod vrc4;
+pub mod vrc6;
+pub mod vrc7;
+pub mod color_dreams;
+pub mod bnrom;
+pub mod camerica;
+pub mod irem_g101;
+pub mod irem_h3001;
+pub mod jaleco_ss88006;
+pub mod taito_tc0190;
+pub mod taito_x1_005;
+pub mod bandai_fcg;
+pub mod namco118;
+pub mod fds;
+
+use self::nrom::Nrom;
+use self::mmc1::Mmc1;
+use self::mmc2::Mmc2;
+use self::mmc3::Mmc3;
+use self::mmc5::Mmc5;
+use self::namco163::Namco163;
+use self::fme7::Fme7;
+use self::uxrom::Uxrom;
+use self::cnrom::Cnrom;
+use self::axrom::Axrom;
+use self::gxrom::Gxrom;
+use self::vrc4::{Vrc4, VrcBoard};
+use self::vrc6::Vrc6;
+use self::vrc7::Vrc7;
+use self::color_dreams::ColorDreams;
+use self::bnrom::Bnrom;
+use self::camerica::Camerica;
+use self::irem_g101::IremG101;
+use self::irem_h3001::IremH3001;
+use self::jaleco_ss88006::JalecoSs88006;
+use self::taito_tc0190::TaitoTc0190;
+use self::taito_x1_005::TaitoX1005;
+use self::bandai_fcg::BandaiFcg;
+use self::namco118::Namco118;
+use super::header::INesHeader;
+use super::CassetteInitializeError;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    SingleScreenLower,
+    SingleScreenUpper,
+    FourScreen,
+    /// CIRAM pages (0 / 1) of the 4 nametables, set by the mapper
+    MapperControlled([u8; 4]),
+}
+
+impl Mirroring {
+    pub fn from_header(header: &INesHeader) -> Self {
+        if header.four_screen {
+            Mirroring::FourScreen
+        } else if header.vertical_mirroring {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        }
+    }
+
+    /// Approximates the CIRAM pages (0 / 1) of the 4 nametables to a standard mirroring,
+    /// or the pages as they are when none matches.
+    /// None is a nametable not served by the CIRAM, it matches any mirroring.
+    pub fn from_pages(pages: [Option<u8>; 4]) -> Self {
+        let patterns = [
+            (Mirroring::Vertical, [0, 1, 0, 1]),
+            (Mirroring::Horizontal, [0, 0, 1, 1]),
+            (Mirroring::SingleScreenLower, [0, 0, 0, 0]),
+            (Mirroring::SingleScreenUpper, [1, 1, 1, 1]),
+        ];
+
+        patterns
+            .iter()
+            .find(|(_, pattern)| {
+                pages.iter().zip(pattern.iter()).all(|(page, expected)| page.is_none_or(|page| page == *expected))
+            })
+            .map(|(mirroring, _)| *mirroring)
+            .unwrap_or_else(|| Mirroring::MapperControlled(pages.map(|page| page.unwrap_or(0))))
+    }
+
+    /// Nametable address ($2000-$2FFF) to the offset in the VRAM.
+    ///
+    /// The console has 2KB VRAM (CIRAM) for 2 nametables (A, B),
+    /// the cassette wires the 4 nametables to them. (or adds 2KB for the four-screen)
+    ///
+    ///   Horizontal   Vertical   Single screen   Four-screen
+    ///   +---+---+    +---+---+  +---+---+       +---+---+
+    ///   | A | A |    | A | B |  | A | A |       | A | B |
+    ///   +---+---+    +---+---+  +---+---+       +---+---+
+    ///   | B | B |    | A | B |  | A | A |       | C | D |
+    ///   +---+---+    +---+---+  +---+---+       +---+---+
+    ///
+    /// refer: https://wiki.nesdev.com/w/index.php/Mirroring#Nametable_Mirroring
+    pub fn nametable_to_vram(self, addr: u16) -> u16 {
+        let nametable = (addr >> 10) & 0x03;
+        let page = match self {
+            Mirroring::Horizontal => nametable >> 1,
+            Mirroring::Vertical => nametable & 0x01,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            Mirroring::FourScreen => nametable,
+            Mirroring::MapperControlled(pages) => pages[nametable as usize] as u16 & 0x01,
+        };
+        page * 0x0400 + (addr & 0x03FF)
+    }
+}
+
+/// The cartridge board logic sitting between the cassette memory and the buses.
+///
+/// CPU side : $4020-$FFFF (expansion area, PRG-RAM, PRG-ROM)
+/// PPU side : $0000-$1FFF (pattern tables), $2000-$2FFF (nametables, when the board overrides them)
+pub trait Mapper {
+    fn read_cpu(&mut self, addr: u16) -> u8;
+    fn write_cpu(&mut self, addr: u16, data: u8);
+    fn read_ppu(&mut self, addr: u16) -> u8;
+    fn write_ppu(&mut self, addr: u16, data: u8);
+    fn mirroring(&self) -> Mirroring;
+
+    /// Nametable read ($2000-$2FFF) served by the cassette. None: read the console VRAM.
+    fn read_nametable(&mut self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    /// Nametable write ($2000-$2FFF) taken by the cassette. false: write the console VRAM.
+    fn write_nametable(&mut self, _addr: u16, _data: u8) -> bool {
+        false
+    }
+
+    /// Called on CPU writes to the PPU registers ($2000-$3FFF), for boards snooping them.
+    fn notify_ppu_register_write(&mut self, _addr: u16, _data: u8) {}
+
+    /// Expansion audio level mixed into the audio output, in the scale of the APU output. (about 0.0 - 1.0)
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
+
+    /// IRQ line output. true: asserting IRQ to the CPU.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// Called by the PPU at the end of every rendered scanline.
+    fn notify_scanline(&mut self) {}
+
+    /// Called after the CPU ran the given cycles.
+    fn notify_cpu_cycle(&mut self, _cycle: usize) {}
+
+    /// Called when the PPU puts an address on its address bus.
+    /// ppu_cycle is the PPU clock count since power on, for boards watching the bus timing.
+    fn notify_ppu_address(&mut self, _addr: u16, _ppu_cycle: usize) {}
+
+    /// Called after each pattern table fetch of the rendering, with the fetched address.
+    /// For boards switching banks by the fetched tiles. (e.g. MMC2 / MMC4)
+    fn notify_pattern_fetch(&mut self, _addr: u16) {}
+
+    /// PRG RAM contents to be preserved while the power is off. None: the board has no battery.
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn restore_battery_backed_ram(&mut self, _data: &[u8]) {}
+
+    /// Ejects the disk and inserts the next side, for the disk systems.
+    fn switch_disk_side(&mut self) {}
+}
+
+/// Mapper numbers supported by build(), with the board names.
+pub const SUPPORTED_MAPPERS: &[(u8, &str)] = &[
+    (0, "NROM"),
+    (1, "MMC1"),
+    (2, "UxROM"),
+    (3, "CNROM"),
+    (4, "MMC3"),
+    (5, "MMC5"),
+    (7, "AxROM"),
+    (9, "MMC2"),
+    (10, "MMC4"),
+    (11, "Color Dreams"),
+    (16, "Bandai FCG"),
+    (18, "Jaleco SS88006"),
+    (19, "Namco 163"),
+    (21, "VRC4a / VRC4c"),
+    (22, "VRC2a"),
+    (23, "VRC2b / VRC4e / VRC4f"),
+    (24, "VRC6a"),
+    (25, "VRC2c / VRC4b / VRC4d"),
+    (26, "VRC6b"),
+    (32, "Irem G-101"),
+    (33, "Taito TC0190"),
+    (34, "BNROM / NINA-001"),
+    (48, "Taito TC0690"),
+    (65, "Irem H3001"),
+    (66, "GxROM"),
+    (69, "Sunsoft FME-7"),
+    (71, "Camerica"),
+    (80, "Taito X1-005"),
+    (85, "VRC7"),
+    (206, "Namco 118"),
+];
+
+pub fn build(header: &INesHeader, program_rom: Vec<u8>, character_rom: Vec<u8>) -> Result<Box<dyn Mapper>, CassetteInitializeError> {
+    let mirroring = Mirroring::from_header(header);
+
+    match header.mapper_number {
+        0 => Ok(Box::new(Nrom::new(program_rom, character_rom, mirroring, header.program_ram_bytes(), header.battery))),
+        1 => Ok(Box::new(Mmc1::new(program_rom, character_rom, header.program_ram_bytes(), header.battery))),
+        2 => Ok(Box::new(Uxrom::new(program_rom, character_rom, mirroring, header.submapper))),
+        3 => Ok(Box::new(Cnrom::new(program_rom, character_rom, mirroring, header.submapper))),
+        4 => Ok(Box::new(Mmc3::new(program_rom, character_rom, mirroring, header.program_ram_bytes(), header.battery))),
+        5 => Ok(Box::new(Mmc5::new(program_rom, character_rom, header.program_ram_bytes(), header.battery))),
+        7 => Ok(Box::new(Axrom::new(program_rom, character_rom, header.submapper))),
+        9 => Ok(Box::new(Mmc2::new(program_rom, character_rom, mirroring, false, header.program_ram_bytes(), header.battery))),
+        10 => Ok(Box::new(Mmc2::new(program_rom, character_rom, mirroring, true, header.program_ram_bytes(), header.battery))),
+        11 => Ok(Box::new(ColorDreams::new(program_rom, character_rom, mirroring))),
+        16 => Ok(Box::new(BandaiFcg::new(program_rom, character_rom, header.submapper, header.battery))),
+        18 => Ok(Box::new(JalecoSs88006::new(program_rom, character_rom, header.program_ram_bytes(), header.battery))),
+        19 => Ok(Box::new(Namco163::new(program_rom, character_rom, header.program_ram_bytes(), header.battery))),
+        21 | 22 | 23 | 25 => {
+            let board = VrcBoard::new(header.mapper_number, header.submapper);
+            Ok(Box::new(Vrc4::new(program_rom, character_rom, board, header.program_ram_bytes(), header.battery)))
+        },
+        24 => Ok(Box::new(Vrc6::new(program_rom, character_rom, false, header.program_ram_bytes(), header.battery))),
+        26 => Ok(Box::new(Vrc6::new(program_rom, character_rom, true, header.program_ram_bytes(), header.battery))),
+        32 => Ok(Box::new(IremG101::new(program_rom, character_rom, header.submapper, header.program_ram_bytes(), header.battery))),
+        33 => Ok(Box::new(TaitoTc0190::new(program_rom, character_rom, mirroring, false))),
+        34 => Ok(Box::new(Bnrom::new(program_rom, character_rom, mirroring, header.submapper, header.program_ram_bytes(), header.battery))),
+        48 => Ok(Box::new(TaitoTc0190::new(program_rom, character_rom, mirroring, true))),
+        65 => Ok(Box::new(IremH3001::new(program_rom, character_rom, mirroring))),
+        66 => Ok(Box::new(Gxrom::new(program_rom, character_rom, mirroring))),
+        69 => Ok(Box::new(Fme7::new(program_rom, character_rom, header.program_ram_bytes(), header.battery))),
+        71 => Ok(Box::new(Camerica::new(program_rom, character_rom, mirroring, header.submapper))),
+        80 => Ok(Box::new(TaitoX1005::new(program_rom, character_rom, header.battery))),
+        85 => Ok(Box::new(Vrc7::new(program_rom, character_rom, header.submapper, header.program_ram_bytes(), header.battery))),
+        206 => Ok(Box::new(Namco118::new(program_rom, character_rom, mirroring))),
+        n => Err(CassetteInitializeError::UnsupportedMapper(n)),
+    }
+}
+
+/// ROM whose banks are filled with their bank numbers, for the bank switching tests.
+#[cfg(test)]
+pub(crate) fn bank_filled_rom(bank_count: usize, bank_size: usize) -> Vec<u8> {
+    (0..bank_count).flat_map(|bank| vec![bank as u8; bank_size]).collect()
+}
+
+#[cfg(test)]
+mod mirroring_test {
+    use super::*;
+
+    #[test]
+    fn nametable_to_vram_test() {
+        let nametables = [0x2005, 0x2405, 0x2805, 0x2C05];
+        let vram_addrs = |mirroring: Mirroring| -> Vec<u16> {
+            nametables.iter().map(|addr| mirroring.nametable_to_vram(*addr)).collect()
+        };
+
+        assert_eq!(vram_addrs(Mirroring::Horizontal), vec![0x0005, 0x0005, 0x0405, 0x0405]);
+        assert_eq!(vram_addrs(Mirroring::Vertical), vec![0x0005, 0x0405, 0x0005, 0x0405]);
+        assert_eq!(vram_addrs(Mirroring::SingleScreenLower), vec![0x0005; 4]);
+        assert_eq!(vram_addrs(Mirroring::SingleScreenUpper), vec![0x0405; 4]);
+        assert_eq!(vram_addrs(Mirroring::FourScreen), vec![0x0005, 0x0405, 0x0805, 0x0C05]);
+        assert_eq!(vram_addrs(Mirroring::MapperControlled([1, 0, 0, 1])), vec![0x0405, 0x0005, 0x0005, 0x0405]);
+    }
+
+    #[test]
+    fn from_pages_test() {
+        assert_eq!(Mirroring::from_pages([Some(0), None, Some(1), None]), Mirroring::Horizontal);
+        assert_eq!(Mirroring::from_pages([None; 4]), Mirroring::Vertical);
+        assert_eq!(Mirroring::from_pages([Some(1), Some(0), None, Some(1)]), Mirroring::MapperControlled([1, 0, 0, 1]));
+    }
+}
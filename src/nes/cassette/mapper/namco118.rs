@@ -0,0 +1,140 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 206 (Namco 118 / Namcot 108 / DxROM)
+///
+/// CPU $8000-$9FFF: 8 KB switchable PRG ROM bank (R6)
+/// CPU $A000-$BFFF: 8 KB switchable PRG ROM bank (R7)
+/// CPU $C000-$FFFF: two 8 KB PRG ROM banks, fixed to the last two banks
+/// PPU $0000-$0FFF: two 2 KB switchable CHR banks (R0, R1)
+/// PPU $1000-$1FFF: four 1 KB switchable CHR banks (R2-R5)
+///
+/// The predecessor of the MMC3: the same bank registers ($8000: select, $8001: data)
+/// without the bank modes, the mirroring control, the PRG RAM and the IRQ.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_206
+pub struct Namco118 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    mirroring: Mirroring,
+    bank_select: u8,
+    bank_registers: [u8; 8],
+}
+
+impl Namco118 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+        Namco118 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            mirroring,
+            bank_select: 0,
+            bank_registers: [0, 2, 4, 5, 6, 7, 0, 1],
+        }
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank_count = self.program_rom.len() / PROGRAM_BANK_SIZE;
+        let bank = match addr {
+            0x8000..=0x9FFF => self.bank_registers[6] as usize,
+            0xA000..=0xBFFF => self.bank_registers[7] as usize,
+            0xC000..=0xDFFF => bank_count - 2,
+            _ => bank_count - 1,
+        };
+
+        (bank * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let addr = addr as usize;
+        let bank = match addr {
+            0x0000..=0x0FFF => (self.bank_registers[addr >> 11] & 0xFE) as usize + ((addr >> 10) & 0x01),
+            _ => self.bank_registers[2 + ((addr >> 10) & 0x03)] as usize,
+        };
+
+        bank * CHARACTER_BANK_SIZE + (addr & 0x03FF)
+    }
+}
+
+impl Mapper for Namco118 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match (addr, addr & 0x0001 == 0) {
+            (0x8000..=0x9FFF, true) => self.bank_select = data & 0x07,
+            (0x8000..=0x9FFF, false) => {
+                // R6, R7: 4 bit PRG banks, R0-R5: 6 bit CHR banks
+                let mask = if self.bank_select >= 6 { 0x0F } else { 0x3F };
+                self.bank_registers[self.bank_select as usize] = data & mask;
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod namco118_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_namco118() -> Namco118 {
+        Namco118::new(bank_filled_rom(16, PROGRAM_BANK_SIZE), bank_filled_rom(64, CHARACTER_BANK_SIZE), Mirroring::Vertical)
+    }
+
+    #[test]
+    fn program_bank() {
+        let mut namco118 = new_namco118();
+        namco118.write_cpu(0x8000, 6);
+        namco118.write_cpu(0x8001, 3);
+        namco118.write_cpu(0x8000, 7);
+        namco118.write_cpu(0x8001, 4);
+
+        assert_eq!(namco118.read_cpu(0x8000), 3);
+        assert_eq!(namco118.read_cpu(0xA000), 4);
+        assert_eq!(namco118.read_cpu(0xC000), 14);
+        assert_eq!(namco118.read_cpu(0xE000), 15);
+
+        // no bank mode on the Namco 118
+        namco118.write_cpu(0x8000, 0x46);
+        assert_eq!(namco118.read_cpu(0x8000), 3);
+    }
+
+    #[test]
+    fn character_bank() {
+        let mut namco118 = new_namco118();
+        namco118.write_cpu(0x8000, 0);
+        namco118.write_cpu(0x8001, 9);
+        namco118.write_cpu(0x8000, 5);
+        namco118.write_cpu(0x8001, 40);
+
+        // 2 KB bank ignores the lowest bit
+        assert_eq!(namco118.read_ppu(0x0000), 8);
+        assert_eq!(namco118.read_ppu(0x0400), 9);
+        assert_eq!(namco118.read_ppu(0x1C00), 40);
+
+        // no register at $A000-$FFFF
+        namco118.write_cpu(0xA000, 1);
+        assert_eq!(namco118.mirroring(), Mirroring::Vertical);
+    }
+}
@@ -0,0 +1,198 @@
+const INTERNAL_RAM_SIZE: usize = 0x80;
+
+/// The channel registers are at the top of the internal RAM, 8 bytes per channel.
+const CHANNEL_REGISTERS_BASE: usize = 0x40;
+
+/// One channel is updated per 15 CPU cycles, the channels take turns.
+const CYCLES_PER_CHANNEL: usize = 15;
+
+/// A wave at the full volume (4 bit samples x 4 bit volume) is about
+/// twice as loud as an APU pulse at the full volume. (15 x 0.00752)
+const LEVEL: f32 = 0.001;
+
+/// Namco 163 expansion audio: up to 8 wavetable channels in the 128 byte internal RAM.
+///
+/// $4800-$4FFF: data port of the internal RAM
+/// $F800-$FFFF: address port (bits 0-6: address, bit 7: auto increment)
+///
+/// Channel n registers at $40 + n * 8 of the internal RAM:
+///   +0, +2, +4 (bits 0-1): 18 bit frequency
+///   +1, +3, +5           : 24 bit phase
+///   +4 (bits 2-7)        : wave length (256 - value) in 4 bit samples
+///   +6                   : wave address in 4 bit samples (low nibble first)
+///   +7 (bits 0-3)        : volume ($7F bits 4-6: enabled channel count - 1)
+///
+/// The channels are time multiplexed on the chip, the output is the average of the enabled channels.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/Namco_163_audio
+pub struct Namco163Audio {
+    ram: [u8; INTERNAL_RAM_SIZE],
+    address: u8,
+    is_auto_increment: bool,
+    is_enabled: bool,
+    timer: usize,
+    /// Channel updated next, counting down from the channel 7.
+    channel: usize,
+    outputs: [i16; 8],
+}
+
+impl Namco163Audio {
+    pub fn new() -> Self {
+        Namco163Audio {
+            ram: [0; INTERNAL_RAM_SIZE],
+            address: 0,
+            is_auto_increment: false,
+            is_enabled: true,
+            timer: 0,
+            channel: 7,
+            outputs: [0; 8],
+        }
+    }
+
+    pub fn write_address(&mut self, data: u8) {
+        self.address = data & 0x7F;
+        self.is_auto_increment = data & 0x80 == 0x80;
+    }
+
+    pub fn read_data(&mut self) -> u8 {
+        let data = self.ram[self.address as usize];
+        self.increment_address();
+        data
+    }
+
+    pub fn write_data(&mut self, data: u8) {
+        self.ram[self.address as usize] = data;
+        self.increment_address();
+    }
+
+    /// Sound disable bit of $E000 (bit 6).
+    pub fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    pub fn restore_ram(&mut self, data: &[u8]) {
+        self.ram.copy_from_slice(data);
+    }
+
+    fn increment_address(&mut self) {
+        if self.is_auto_increment {
+            self.address = (self.address + 1) & 0x7F;
+        }
+    }
+
+    fn channel_count(&self) -> usize {
+        ((self.ram[0x7F] >> 4) & 0x07) as usize + 1
+    }
+
+    pub fn clock(&mut self, cpu_cycle: usize) {
+        if !self.is_enabled {
+            return;
+        }
+
+        self.timer += cpu_cycle;
+        while self.timer >= CYCLES_PER_CHANNEL {
+            self.timer -= CYCLES_PER_CHANNEL;
+            self.update_channel(self.channel);
+
+            let last_channel = 8 - self.channel_count();
+            self.channel = if self.channel <= last_channel { 7 } else { self.channel - 1 };
+        }
+    }
+
+    fn update_channel(&mut self, channel: usize) {
+        let base = CHANNEL_REGISTERS_BASE + channel * 8;
+        let registers = &self.ram[base..base + 8];
+
+        let frequency = registers[0] as u32 | (registers[2] as u32) << 8 | (registers[4] as u32 & 0x03) << 16;
+        let phase = registers[1] as u32 | (registers[3] as u32) << 8 | (registers[5] as u32) << 16;
+        let length = 256 - (registers[4] as u32 & 0xFC);
+        let wave_address = registers[6] as u32;
+        let volume = (registers[7] & 0x0F) as i16;
+
+        let phase = (phase + frequency) % (length << 16);
+        let sample_address = (((phase >> 16) + wave_address) & 0xFF) as usize;
+        let sample = (self.ram[sample_address >> 1] >> ((sample_address & 0x01) * 4)) & 0x0F;
+
+        self.ram[base + 1] = phase as u8;
+        self.ram[base + 3] = (phase >> 8) as u8;
+        self.ram[base + 5] = (phase >> 16) as u8;
+        self.outputs[channel] = (sample as i16 - 8) * volume;
+    }
+
+    pub fn output(&self) -> f32 {
+        if !self.is_enabled {
+            return 0.0;
+        }
+
+        let count = self.channel_count();
+        let sum: i16 = self.outputs[(8 - count)..].iter().sum();
+        sum as f32 / count as f32 * LEVEL
+    }
+}
+
+#[cfg(test)]
+mod audio_test {
+    use super::*;
+
+    fn write_ram(audio: &mut Namco163Audio, addr: u8, data: &[u8]) {
+        audio.write_address(0x80 | addr);
+        for byte in data {
+            audio.write_data(*byte);
+        }
+    }
+
+    #[test]
+    fn internal_ram_port() {
+        let mut audio = Namco163Audio::new();
+        write_ram(&mut audio, 0x7E, &[0x12, 0x34, 0x56]);
+        assert_eq!(audio.ram[0x7E], 0x12);
+        assert_eq!(audio.ram[0x7F], 0x34);
+        // the address wraps around in the 128 bytes
+        assert_eq!(audio.ram[0x00], 0x56);
+
+        // without the auto increment
+        audio.write_address(0x7E);
+        assert_eq!(audio.read_data(), 0x12);
+        assert_eq!(audio.read_data(), 0x12);
+    }
+
+    #[test]
+    fn wavetable() {
+        let mut audio = Namco163Audio::new();
+        // 4 samples wave at $00: 15, 0, 15, 0
+        write_ram(&mut audio, 0x00, &[0x0F, 0x0F]);
+        // channel 7: frequency $10000 (1 sample per update), length 4, wave address 0, volume 15, 1 channel
+        write_ram(&mut audio, 0x78, &[0x00, 0x00, 0x00, 0x00, 0xFD, 0x00, 0x00, 0x0F]);
+
+        let outputs: Vec<i16> = (0..4).map(|_| {
+            audio.clock(CYCLES_PER_CHANNEL);
+            audio.outputs[7]
+        }).collect();
+        assert_eq!(outputs, vec![-120, 105, -120, 105]);
+        assert_eq!(audio.output(), 105.0 * LEVEL);
+
+        audio.set_enabled(false);
+        assert_eq!(audio.output(), 0.0);
+    }
+
+    #[test]
+    fn channel_multiplexing() {
+        let mut audio = Namco163Audio::new();
+        write_ram(&mut audio, 0x00, &[0xFF]);
+        // channel 7 and 6 at the volume 15, 2 channels
+        write_ram(&mut audio, 0x78, &[0x00, 0x00, 0x00, 0x00, 0xFC, 0x00, 0x00, 0x1F]);
+        write_ram(&mut audio, 0x70, &[0x00, 0x00, 0x00, 0x00, 0xFC, 0x00, 0x00, 0x0F]);
+
+        audio.clock(CYCLES_PER_CHANNEL);
+        assert_eq!(audio.outputs, [0, 0, 0, 0, 0, 0, 0, 105]);
+        audio.clock(CYCLES_PER_CHANNEL);
+        assert_eq!(audio.outputs, [0, 0, 0, 0, 0, 0, 105, 105]);
+        assert_eq!(audio.channel, 7);
+        // the level of 2 channels is averaged
+        assert_eq!(audio.output(), 105.0 * LEVEL);
+    }
+}
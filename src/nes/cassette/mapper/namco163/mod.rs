@@ -0,0 +1,308 @@
+pub mod audio;
+
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+use audio::Namco163Audio;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Nametable bank numbers from $E0 select the CIRAM page (bit 0) instead of the CHR ROM.
+const CIRAM_BANK: u8 = 0xE0;
+const IRQ_COUNTER_MAX: u16 = 0x7FFF;
+
+/// Mapper 19 (Namco 129 / 163)
+///
+/// CPU $4800-$4FFF: data port of the internal RAM (expansion audio)
+/// CPU $5000-$5FFF: 15 bit IRQ counter, counting up every CPU cycle to $7FFF
+/// CPU $6000-$7FFF: 8 KB PRG RAM (write protected by $F800)
+/// CPU $8000-$9FFF, $A000-$BFFF, $C000-$DFFF: 8 KB switchable PRG ROM banks ($E000, $E800, $F000)
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: eight 1 KB switchable CHR banks ($8000-$BFFF)
+/// PPU $2000-$2FFF: four 1 KB nametables ($C000-$DFFF), CHR ROM or CIRAM ($E0-$FF)
+///
+/// The CIRAM as pattern tables (CHR banks $E0-$FF, unless disabled by $E800) is not supported.
+/// The 128 byte internal RAM is battery backed on the boards without PRG RAM.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_019
+pub struct Namco163 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    program_banks: [u8; 3],
+    character_banks: [u8; 8],
+    nametable_banks: [u8; 4],
+    write_protect: u8,
+    irq_counter: u16,
+    is_irq_enabled: bool,
+    is_irq_pending: bool,
+    audio: Namco163Audio,
+}
+
+impl Namco163 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, program_ram_size: usize, battery: bool) -> Self {
+        Namco163 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            program_banks: [0, 0, 0],
+            character_banks: [0; 8],
+            nametable_banks: [CIRAM_BANK, CIRAM_BANK + 1, CIRAM_BANK, CIRAM_BANK + 1],
+            write_protect: 0,
+            irq_counter: 0,
+            is_irq_enabled: false,
+            is_irq_pending: false,
+            audio: Namco163Audio::new(),
+        }
+    }
+
+    /// $F800 bits 6-7 must be 01, then bits 0-3 protect each 2 KB of the PRG RAM.
+    fn is_program_ram_writable(&self, addr: u16) -> bool {
+        let window = (addr - 0x6000) >> 11;
+        self.write_protect & 0xC0 == 0x40 && self.write_protect & (1 << window) == 0
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let offset = match addr {
+            0x8000..=0xDFFF => {
+                let bank = self.program_banks[((addr - 0x8000) >> 13) as usize];
+                bank as usize * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)
+            },
+            _ => self.program_rom.len() - PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF),
+        };
+
+        offset % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let bank = self.character_banks[(addr >> 10) as usize & 0x07];
+        bank as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+
+    /// CHR ROM offset of the nametable. None: the nametable is on the CIRAM.
+    fn nametable_offset(&self, addr: u16) -> Option<usize> {
+        let bank = self.nametable_banks[((addr >> 10) & 0x03) as usize];
+        if bank >= CIRAM_BANK {
+            None
+        } else {
+            Some(bank as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF))
+        }
+    }
+}
+
+impl Mapper for Namco163 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x4800..=0x4FFF => self.audio.read_data(),
+            0x5000..=0x57FF => self.irq_counter as u8,
+            0x5800..=0x5FFF => (self.irq_counter >> 8) as u8 | if self.is_irq_enabled { 0x80 } else { 0x00 },
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4800..=0x4FFF => self.audio.write_data(data),
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | data as u16;
+                self.is_irq_pending = false;
+            },
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | (data as u16 & 0x7F) << 8;
+                self.is_irq_enabled = data & 0x80 == 0x80;
+                self.is_irq_pending = false;
+            },
+            0x6000..=0x7FFF if !self.program_ram.is_empty() && self.is_program_ram_writable(addr) => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            0x8000..=0xBFFF => self.character_banks[((addr - 0x8000) >> 11) as usize] = data,
+            0xC000..=0xDFFF => self.nametable_banks[((addr - 0xC000) >> 11) as usize] = data,
+            0xE000..=0xE7FF => {
+                self.program_banks[0] = data & 0x3F;
+                self.audio.set_enabled(data & 0x40 == 0);
+            },
+            0xE800..=0xEFFF => self.program_banks[1] = data & 0x3F,
+            0xF000..=0xF7FF => self.program_banks[2] = data & 0x3F,
+            0xF800..=0xFFFF => {
+                self.write_protect = data;
+                self.audio.write_address(data);
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    /// Approximates the CIRAM nametables to a standard mirroring.
+    fn mirroring(&self) -> Mirroring {
+        let mut pages = [None; 4];
+        for (page, bank) in pages.iter_mut().zip(self.nametable_banks.iter()) {
+            if *bank >= CIRAM_BANK {
+                *page = Some(bank & 0x01);
+            }
+        }
+        Mirroring::from_pages(pages)
+    }
+
+    fn read_nametable(&mut self, addr: u16) -> Option<u8> {
+        self.nametable_offset(addr).map(|offset| self.character_memory.read(offset))
+    }
+
+    fn write_nametable(&mut self, addr: u16, data: u8) -> bool {
+        match self.nametable_offset(addr) {
+            Some(offset) => {
+                self.character_memory.write(offset, data);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.is_irq_pending
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        if self.is_irq_enabled && self.irq_counter < IRQ_COUNTER_MAX {
+            self.irq_counter = (self.irq_counter as usize + cycle).min(IRQ_COUNTER_MAX as usize) as u16;
+            if self.irq_counter == IRQ_COUNTER_MAX {
+                self.is_irq_pending = true;
+            }
+        }
+        self.audio.clock(cycle);
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        match (self.battery, self.program_ram.is_empty()) {
+            (false, _) => None,
+            (true, false) => Some(&self.program_ram),
+            (true, true) => Some(self.audio.ram()),
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if !self.battery {
+            return;
+        }
+
+        if data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        } else if self.program_ram.is_empty() && data.len() == self.audio.ram().len() {
+            self.audio.restore_ram(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod namco163_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_namco163(program_ram_size: usize, battery: bool) -> Namco163 {
+        Namco163::new(
+            bank_filled_rom(16, PROGRAM_BANK_SIZE),
+            bank_filled_rom(256, CHARACTER_BANK_SIZE),
+            program_ram_size,
+            battery,
+        )
+    }
+
+    #[test]
+    fn program_bank() {
+        let mut namco163 = new_namco163(0x2000, false);
+        namco163.write_cpu(0xE000, 3);
+        namco163.write_cpu(0xE800, 0xC4);
+        namco163.write_cpu(0xF000, 5);
+
+        assert_eq!(namco163.read_cpu(0x8000), 3);
+        assert_eq!(namco163.read_cpu(0xA000), 4);
+        assert_eq!(namco163.read_cpu(0xC000), 5);
+        assert_eq!(namco163.read_cpu(0xE000), 15);
+    }
+
+    #[test]
+    fn program_ram_write_protect() {
+        let mut namco163 = new_namco163(0x2000, false);
+        namco163.write_cpu(0x6000, 0x12);
+        assert_eq!(namco163.read_cpu(0x6000), 0x00);
+
+        // writable except $6800-$6FFF
+        namco163.write_cpu(0xF800, 0x42);
+        namco163.write_cpu(0x6000, 0x12);
+        namco163.write_cpu(0x6800, 0x34);
+        assert_eq!(namco163.read_cpu(0x6000), 0x12);
+        assert_eq!(namco163.read_cpu(0x6800), 0x00);
+    }
+
+    #[test]
+    fn character_and_nametable_bank() {
+        let mut namco163 = new_namco163(0x2000, false);
+        namco163.write_cpu(0x8000, 10);
+        namco163.write_cpu(0xB800, 17);
+        assert_eq!(namco163.read_ppu(0x0000), 10);
+        assert_eq!(namco163.read_ppu(0x1C00), 17);
+
+        // CIRAM pages on all the nametables
+        for (addr, bank) in [(0xC000, 0xE0), (0xC800, 0xE0), (0xD000, 0xE1), (0xD800, 0xE1)].iter() {
+            namco163.write_cpu(*addr, *bank);
+        }
+        assert_eq!(namco163.mirroring(), Mirroring::Horizontal);
+        assert_eq!(namco163.read_nametable(0x2000), None);
+        assert!(!namco163.write_nametable(0x2000, 0x12));
+
+        // CHR ROM at the nametable 3
+        namco163.write_cpu(0xD800, 0x20);
+        assert_eq!(namco163.read_nametable(0x2C10), Some(0x20));
+        assert_eq!(namco163.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn irq_counter() {
+        let mut namco163 = new_namco163(0x2000, false);
+        namco163.write_cpu(0x5000, 0xF0);
+        namco163.write_cpu(0x5800, 0xFF);
+        assert_eq!(namco163.read_cpu(0x5800), 0xFF);
+
+        namco163.notify_cpu_cycle(14);
+        assert!(!namco163.irq());
+        namco163.notify_cpu_cycle(10);
+        assert!(namco163.irq());
+        // the counter stops at $7FFF
+        assert_eq!(namco163.read_cpu(0x5000), 0xFF);
+
+        namco163.write_cpu(0x5000, 0x00);
+        assert!(!namco163.irq());
+    }
+
+    #[test]
+    fn audio_ram_as_battery_backed_ram() {
+        let mut namco163 = new_namco163(0, true);
+        namco163.write_cpu(0xF800, 0x80);
+        namco163.write_cpu(0x4800, 0x12);
+        assert_eq!(namco163.battery_backed_ram().unwrap()[0], 0x12);
+
+        let data = vec![0x34; 0x80];
+        namco163.restore_battery_backed_ram(&data);
+        namco163.write_cpu(0xF800, 0x00);
+        assert_eq!(namco163.read_cpu(0x4800), 0x34);
+    }
+}
@@ -0,0 +1,138 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+/// Mapper 0 (NROM)
+///
+/// CPU $6000-$7FFF: 8 KB PRG RAM (Family BASIC, absent on the most boards)
+/// CPU $8000-$BFFF: First 16 KB of PRG ROM
+/// CPU $C000-$FFFF: Last 16 KB of PRG ROM (NROM-128 mirrors $8000-$BFFF)
+/// PPU $0000-$1FFF: 8 KB CHR ROM (or CHR RAM when the cassette has no CHR ROM)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/NROM
+pub struct Nrom {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring, program_ram_size: usize, battery: bool) -> Self {
+        Nrom {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            mirroring,
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xBFFF => self.program_rom[(addr - 0x8000) as usize],
+            0xC000..=0xFFFF if self.program_rom.len() <= 0x4000 => {
+                self.program_rom[(addr - 0xC000) as usize]
+            },
+            0xC000..=0xFFFF => self.program_rom[(addr - 0x8000) as usize],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        if (0x6000..=0x7FFF).contains(&addr) && !self.program_ram.is_empty() {
+            let len = self.program_ram.len();
+            self.program_ram[(addr as usize - 0x6000) % len] = data;
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(addr as usize)
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        self.character_memory.write(addr as usize, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod nrom_test {
+    use super::*;
+
+    #[test]
+    fn read_program_rom_32k() {
+        let program_rom = [vec![0x01; 0x4000], vec![0x02; 0x4000]].concat();
+        let mut nrom = Nrom::new(program_rom, vec![0; 0x2000], Mirroring::Horizontal, 0, false);
+
+        assert_eq!(nrom.read_cpu(0x8000), 0x01);
+        assert_eq!(nrom.read_cpu(0xBFFF), 0x01);
+        assert_eq!(nrom.read_cpu(0xC000), 0x02);
+        assert_eq!(nrom.read_cpu(0xFFFF), 0x02);
+    }
+
+    #[test]
+    fn mirror_program_rom_16k() {
+        let mut program_rom = vec![0; 0x4000];
+        program_rom[0x0000] = 0xFF;
+        let mut nrom = Nrom::new(program_rom, vec![0; 0x2000], Mirroring::Horizontal, 0, false);
+
+        assert_eq!(nrom.read_cpu(0x8000), 0xFF);
+        assert_eq!(nrom.read_cpu(0xC000), 0xFF);
+    }
+
+    #[test]
+    fn ignore_write_to_character_rom() {
+        let mut nrom = Nrom::new(vec![0; 0x4000], vec![0x10; 0x2000], Mirroring::Horizontal, 0, false);
+        nrom.write_ppu(0x0000, 0xFF);
+
+        assert_eq!(nrom.read_ppu(0x0000), 0x10);
+    }
+
+    #[test]
+    fn write_character_ram() {
+        let mut nrom = Nrom::new(vec![0; 0x4000], vec![], Mirroring::Vertical, 0, false);
+        nrom.write_ppu(0x1FFF, 0xFF);
+
+        assert_eq!(nrom.read_ppu(0x1FFF), 0xFF);
+        assert_eq!(nrom.mirroring(), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn program_ram() {
+        let mut nrom = Nrom::new(vec![0; 0x4000], vec![], Mirroring::Vertical, 0x2000, true);
+        nrom.write_cpu(0x6000, 0x12);
+        nrom.write_cpu(0x7FFF, 0x34);
+
+        assert_eq!(nrom.read_cpu(0x6000), 0x12);
+        assert_eq!(nrom.battery_backed_ram().unwrap()[0x1FFF], 0x34);
+
+        // no PRG RAM on the board
+        let mut nrom = Nrom::new(vec![0; 0x4000], vec![], Mirroring::Vertical, 0, false);
+        nrom.write_cpu(0x6000, 0x12);
+        assert_eq!(nrom.read_cpu(0x6000), 0x00);
+        assert!(nrom.battery_backed_ram().is_none());
+    }
+}
@@ -0,0 +1,201 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 33 (Taito TC0190), Mapper 48 (Taito TC0690)
+///
+/// CPU $8000-$9FFF: 8 KB switchable PRG ROM bank ($8000)
+/// CPU $A000-$BFFF: 8 KB switchable PRG ROM bank ($8001)
+/// CPU $C000-$FFFF: two 8 KB PRG ROM banks, fixed to the last two banks
+/// PPU $0000-$0FFF: two 2 KB switchable CHR banks ($8002, $8003)
+/// PPU $1000-$1FFF: four 1 KB switchable CHR banks ($A000-$A003)
+///
+/// TC0190: the mirroring is $8000 bit 6. (0: vertical, 1: horizontal)
+/// TC0690: the mirroring is $E000 bit 6, and a scanline IRQ counter as the MMC3 is added.
+///   $C000: IRQ latch (inverted value), $C001: reloads the counter,
+///   $C002: enables the IRQ, $C003: disables and acknowledges the IRQ
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_033
+///        https://wiki.nesdev.com/w/index.php/INES_Mapper_048
+pub struct TaitoTc0190 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    is_tc0690: bool,
+    program_banks: [u8; 2],
+    character_banks: [u8; 6],
+    mirroring: Mirroring,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+}
+
+impl TaitoTc0190 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring, is_tc0690: bool) -> Self {
+        TaitoTc0190 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            is_tc0690,
+            program_banks: [0, 1],
+            character_banks: [0; 6],
+            mirroring,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+        }
+    }
+
+    fn write_mirroring(&mut self, data: u8) {
+        self.mirroring = if data & 0x40 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank_count = self.program_rom.len() / PROGRAM_BANK_SIZE;
+        let bank = match addr {
+            0x8000..=0x9FFF => self.program_banks[0] as usize,
+            0xA000..=0xBFFF => self.program_banks[1] as usize,
+            0xC000..=0xDFFF => bank_count - 2,
+            _ => bank_count - 1,
+        };
+
+        (bank * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let addr = addr as usize;
+        let bank = match addr {
+            0x0000..=0x0FFF => self.character_banks[addr >> 11] as usize * 2 + ((addr >> 10) & 0x01),
+            _ => self.character_banks[2 + ((addr >> 10) & 0x03)] as usize,
+        };
+
+        bank * CHARACTER_BANK_SIZE + (addr & 0x03FF)
+    }
+}
+
+impl Mapper for TaitoTc0190 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match (addr & 0xE003, self.is_tc0690) {
+            (0x8000, false) => {
+                self.program_banks[0] = data & 0x3F;
+                self.write_mirroring(data);
+            },
+            (0x8000, true) => self.program_banks[0] = data & 0x3F,
+            (0x8001, _) => self.program_banks[1] = data & 0x3F,
+            (0x8002, _) => self.character_banks[0] = data,
+            (0x8003, _) => self.character_banks[1] = data,
+            (register @ 0xA000..=0xA003, _) => self.character_banks[2 + (register & 0x0003) as usize] = data,
+            (0xC000, true) => self.irq_latch = data ^ 0xFF,
+            (0xC001, true) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            (0xC002, true) => self.irq_enabled = true,
+            (0xC003, true) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            },
+            (0xE000, true) => self.write_mirroring(data),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn notify_scanline(&mut self) {
+        if !self.is_tc0690 {
+            return;
+        }
+
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod taito_tc0190_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_taito_tc0190(is_tc0690: bool) -> TaitoTc0190 {
+        TaitoTc0190::new(bank_filled_rom(16, PROGRAM_BANK_SIZE), bank_filled_rom(256, CHARACTER_BANK_SIZE), Mirroring::Vertical, is_tc0690)
+    }
+
+    #[test]
+    fn program_and_character_bank() {
+        let mut taito = new_taito_tc0190(false);
+        taito.write_cpu(0x8000, 0x43);
+        taito.write_cpu(0x8001, 4);
+        taito.write_cpu(0x8002, 5);
+        taito.write_cpu(0xA003, 100);
+
+        assert_eq!(taito.read_cpu(0x8000), 3);
+        assert_eq!(taito.read_cpu(0xA000), 4);
+        assert_eq!(taito.read_cpu(0xC000), 14);
+        assert_eq!(taito.read_cpu(0xE000), 15);
+        // 2 KB bank
+        assert_eq!(taito.read_ppu(0x0000), 10);
+        assert_eq!(taito.read_ppu(0x0400), 11);
+        assert_eq!(taito.read_ppu(0x1C00), 100);
+        assert_eq!(taito.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn tc0690_mirroring_and_irq() {
+        let mut taito = new_taito_tc0190(true);
+        // the mirroring moved to $E000
+        taito.write_cpu(0x8000, 0x40);
+        assert_eq!(taito.mirroring(), Mirroring::Vertical);
+        taito.write_cpu(0xE000, 0x40);
+        assert_eq!(taito.mirroring(), Mirroring::Horizontal);
+
+        // IRQ after 3 scanlines: the latch is written inverted
+        taito.write_cpu(0xC000, 0xFD);
+        taito.write_cpu(0xC001, 0);
+        taito.write_cpu(0xC002, 0);
+
+        taito.notify_scanline();
+        taito.notify_scanline();
+        assert!(!taito.irq());
+        taito.notify_scanline();
+        assert!(taito.irq());
+
+        taito.write_cpu(0xC003, 0);
+        assert!(!taito.irq());
+    }
+}
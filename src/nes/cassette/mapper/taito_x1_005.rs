@@ -0,0 +1,164 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+const INTERNAL_RAM_SIZE: usize = 0x80;
+/// Value of $7EF8 / $7EF9 enabling the internal RAM
+const RAM_PERMISSION: u8 = 0xA3;
+
+/// Mapper 80 (Taito X1-005)
+///
+/// CPU $7EF0-$7EFF: registers
+/// CPU $7F00-$7FFF: 128 byte internal RAM (mirrored once)
+/// CPU $8000-$9FFF, $A000-$BFFF, $C000-$DFFF: 8 KB switchable PRG ROM banks
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$0FFF: two 2 KB switchable CHR banks
+/// PPU $1000-$1FFF: four 1 KB switchable CHR banks
+///
+/// $7EF0, $7EF1      : 2 KB CHR banks (in 1 KB units, the lowest bit is ignored)
+/// $7EF2-$7EF5       : 1 KB CHR banks
+/// $7EF6, $7EF7      : mirroring (bit 0, 0: horizontal, 1: vertical)
+/// $7EF8, $7EF9      : internal RAM permission ($A3: enabled)
+/// $7EFA-$7EFF       : PRG ROM banks (2 addresses per bank)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/INES_Mapper_080
+pub struct TaitoX1005 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    internal_ram: Vec<u8>,
+    battery: bool,
+    program_banks: [u8; 3],
+    character_banks: [u8; 6],
+    mirroring: Mirroring,
+    ram_permission: u8,
+}
+
+impl TaitoX1005 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, battery: bool) -> Self {
+        TaitoX1005 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            internal_ram: vec![0; INTERNAL_RAM_SIZE],
+            battery,
+            program_banks: [0, 1, 0xFE],
+            character_banks: [0; 6],
+            mirroring: Mirroring::Horizontal,
+            ram_permission: 0,
+        }
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xDFFF => self.program_banks[((addr - 0x8000) >> 13) as usize] as usize,
+            _ => self.program_rom.len() / PROGRAM_BANK_SIZE - 1,
+        };
+
+        (bank * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let addr = addr as usize;
+        let bank = match addr {
+            0x0000..=0x0FFF => (self.character_banks[addr >> 11] & 0xFE) as usize + ((addr >> 10) & 0x01),
+            _ => self.character_banks[2 + ((addr >> 10) & 0x03)] as usize,
+        };
+
+        bank * CHARACTER_BANK_SIZE + (addr & 0x03FF)
+    }
+}
+
+impl Mapper for TaitoX1005 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x7F00..=0x7FFF if self.ram_permission == RAM_PERMISSION => self.internal_ram[addr as usize & 0x7F],
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x7EF0..=0x7EF5 => self.character_banks[(addr - 0x7EF0) as usize] = data,
+            0x7EF6..=0x7EF7 => self.mirroring = if data & 0x01 == 0 { Mirroring::Horizontal } else { Mirroring::Vertical },
+            0x7EF8..=0x7EF9 => self.ram_permission = data,
+            0x7EFA..=0x7EFF => self.program_banks[((addr - 0x7EFA) >> 1) as usize] = data,
+            0x7F00..=0x7FFF if self.ram_permission == RAM_PERMISSION => self.internal_ram[addr as usize & 0x7F] = data,
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.internal_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.internal_ram.len() {
+            self.internal_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod taito_x1_005_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_taito_x1_005() -> TaitoX1005 {
+        TaitoX1005::new(bank_filled_rom(16, PROGRAM_BANK_SIZE), bank_filled_rom(256, CHARACTER_BANK_SIZE), true)
+    }
+
+    #[test]
+    fn program_and_character_bank() {
+        let mut taito = new_taito_x1_005();
+        taito.write_cpu(0x7EFA, 3);
+        taito.write_cpu(0x7EFD, 4);
+        taito.write_cpu(0x7EFE, 5);
+        taito.write_cpu(0x7EF0, 9);
+        taito.write_cpu(0x7EF5, 100);
+
+        assert_eq!(taito.read_cpu(0x8000), 3);
+        assert_eq!(taito.read_cpu(0xA000), 4);
+        assert_eq!(taito.read_cpu(0xC000), 5);
+        assert_eq!(taito.read_cpu(0xE000), 15);
+        assert_eq!(taito.read_ppu(0x0000), 8);
+        assert_eq!(taito.read_ppu(0x0400), 9);
+        assert_eq!(taito.read_ppu(0x1C00), 100);
+
+        taito.write_cpu(0x7EF7, 1);
+        assert_eq!(taito.mirroring(), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn internal_ram_permission() {
+        let mut taito = new_taito_x1_005();
+        taito.write_cpu(0x7F00, 0x12);
+        assert_eq!(taito.read_cpu(0x7F00), 0x00);
+
+        taito.write_cpu(0x7EF8, RAM_PERMISSION);
+        taito.write_cpu(0x7F00, 0x12);
+        assert_eq!(taito.read_cpu(0x7F00), 0x12);
+        // mirrored at $7F80
+        assert_eq!(taito.read_cpu(0x7F80), 0x12);
+        assert_eq!(taito.battery_backed_ram().unwrap()[0], 0x12);
+    }
+}
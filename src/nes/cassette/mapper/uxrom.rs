@@ -0,0 +1,123 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+
+const PROGRAM_BANK_SIZE: usize = 0x4000; // 16 KB
+
+/// Mapper 2 (UxROM)
+///
+/// CPU $8000-$BFFF: 16 KB switchable PRG ROM bank
+/// CPU $C000-$FFFF: 16 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: 8 KB CHR RAM (fixed)
+///
+/// Writing to $8000-$FFFF selects the bank. The written value is ANDed with
+/// the ROM byte at the same address (bus conflict).
+/// NES 2.0 submapper 1 is the board without the bus conflict, 2 is with it. (the default)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/UxROM
+pub struct Uxrom {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    mirroring: Mirroring,
+    bus_conflict: bool,
+    program_bank: u8,
+}
+
+impl Uxrom {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, mirroring: Mirroring, submapper: u8) -> Self {
+        Uxrom {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            mirroring,
+            bus_conflict: submapper != 1,
+            program_bank: 0,
+        }
+    }
+
+    fn program_bank_count(&self) -> usize {
+        (self.program_rom.len() / PROGRAM_BANK_SIZE).max(1)
+    }
+}
+
+impl Mapper for Uxrom {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        let bank = match addr {
+            0x8000..=0xBFFF => self.program_bank as usize % self.program_bank_count(),
+            0xC000..=0xFFFF => self.program_bank_count() - 1,
+            _ => return 0,
+        };
+
+        self.program_rom[bank * PROGRAM_BANK_SIZE + (addr as usize & 0x3FFF)]
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        if addr >= 0x8000 {
+            self.program_bank = if self.bus_conflict {
+                data & self.read_cpu(addr)
+            } else {
+                data
+            };
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(addr as usize)
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        self.character_memory.write(addr as usize, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod uxrom_test {
+    use super::*;
+    use crate::nes::cassette::mapper::bank_filled_rom;
+    use crate::nes::cpu::{Bus, CpuBus};
+    use crate::nes::ppu::Ppu;
+    use crate::nes::ram::Ram;
+
+    #[test]
+    fn switch_program_bank() {
+        let mut program_rom = bank_filled_rom(8, PROGRAM_BANK_SIZE);
+        program_rom[0x0000] = 0xFF; // no bus conflict on the first written address
+        let mut uxrom = Uxrom::new(program_rom, vec![], Mirroring::Vertical, 0);
+        let mut ppu = Ppu::new();
+        let mut ram = Ram::new(vec![0; 0x0800]);
+        let mut bus = Bus::new(&mut uxrom, &mut ppu, &mut ram);
+
+        assert_eq!(bus.read(0x8001), 0);
+        assert_eq!(bus.read(0xC000), 7);
+
+        bus.write(0x8000, 5);
+        assert_eq!(bus.read(0x8001), 5);
+        assert_eq!(bus.read(0xBFFF), 5);
+        assert_eq!(bus.read(0xC000), 7);
+    }
+
+    #[test]
+    fn bus_conflict() {
+        // the last bank is filled with 0x07, so the written value is masked
+        let mut uxrom = Uxrom::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], Mirroring::Vertical, 0);
+        let mut ppu = Ppu::new();
+        let mut ram = Ram::new(vec![0; 0x0800]);
+        let mut bus = Bus::new(&mut uxrom, &mut ppu, &mut ram);
+
+        bus.write(0xC000, 0x0E);
+        assert_eq!(bus.read(0x8000), 0x06);
+    }
+
+    #[test]
+    fn without_bus_conflict() {
+        let mut uxrom = Uxrom::new(bank_filled_rom(8, PROGRAM_BANK_SIZE), vec![], Mirroring::Vertical, 1);
+        let mut ppu = Ppu::new();
+        let mut ram = Ram::new(vec![0; 0x0800]);
+        let mut bus = Bus::new(&mut uxrom, &mut ppu, &mut ram);
+
+        bus.write(0xC000, 0x03);
+        assert_eq!(bus.read(0x8000), 0x03);
+    }
+}
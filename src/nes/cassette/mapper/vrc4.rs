@@ -0,0 +1,331 @@
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+use super::vrc_irq::VrcIrq;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// VRC2 / VRC4 board, decided from the mapper and the submapper numbers.
+///
+/// Each board wires different CPU address lines to the chip's register select pins (A0, A1).
+/// Without a submapper (iNES), the lines of both boards sharing the mapper number are used.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct VrcBoard {
+    /// CPU address bits connected to the chip's A0 pin
+    pub a0: u16,
+    /// CPU address bits connected to the chip's A1 pin
+    pub a1: u16,
+    pub is_vrc2: bool,
+    /// VRC2a ignores the lowest bit of the CHR bank numbers
+    pub is_character_bank_shifted: bool,
+}
+
+impl VrcBoard {
+    pub fn new(mapper_number: u8, submapper: u8) -> Self {
+        let (a0, a1, is_vrc2) = match (mapper_number, submapper) {
+            (21, 1) => (0x0002, 0x0004, false), // VRC4a
+            (21, 2) => (0x0040, 0x0080, false), // VRC4c
+            (21, _) => (0x0042, 0x0084, false),
+            (22, _) => (0x0002, 0x0001, true),  // VRC2a
+            (23, 1) => (0x0001, 0x0002, false), // VRC4f
+            (23, 2) => (0x0004, 0x0008, false), // VRC4e
+            (23, 3) => (0x0001, 0x0002, true),  // VRC2b
+            (23, _) => (0x0005, 0x000A, false),
+            (25, 1) => (0x0002, 0x0001, false), // VRC4b
+            (25, 2) => (0x0008, 0x0004, false), // VRC4d
+            (25, 3) => (0x0002, 0x0001, true),  // VRC2c
+            _ => (0x000A, 0x0005, false),
+        };
+
+        VrcBoard {
+            a0,
+            a1,
+            is_vrc2,
+            is_character_bank_shifted: mapper_number == 22,
+        }
+    }
+}
+
+/// Mapper 21, 22, 23, 25 (Konami VRC2 / VRC4)
+///
+/// CPU $6000-$7FFF: 8 KB PRG RAM (VRC2 without PRG RAM: 1 bit latch at $6000-$6FFF)
+/// CPU $8000-$9FFF: 8 KB switchable PRG ROM bank, or fixed to the second-last bank (VRC4 swap mode)
+/// CPU $A000-$BFFF: 8 KB switchable PRG ROM bank
+/// CPU $C000-$DFFF: 8 KB PRG ROM bank, fixed to the second-last bank, or switchable (VRC4 swap mode)
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: eight 1 KB switchable CHR banks
+///
+/// The VRC2 has neither the PRG swap mode nor the IRQ counter, otherwise it is a subset of the VRC4.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/VRC2_and_VRC4
+pub struct Vrc4 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    board: VrcBoard,
+    program_banks: [u8; 2],
+    character_banks: [u16; 8],
+    is_program_mode_swapped: bool,
+    mirroring: Mirroring,
+    latch: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, board: VrcBoard, program_ram_size: usize, battery: bool) -> Self {
+        Vrc4 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            board,
+            program_banks: [0, 0],
+            character_banks: [0; 8],
+            is_program_mode_swapped: false,
+            mirroring: Mirroring::Vertical,
+            latch: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    /// Translates the CPU address into the register address ($x000-$x003) on the chip.
+    fn register_addr(&self, addr: u16) -> u16 {
+        let a0 = (addr & self.board.a0 != 0) as u16;
+        let a1 = (addr & self.board.a1 != 0) as u16;
+        (addr & 0xF000) | a1 << 1 | a0
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match self.register_addr(addr) {
+            0x8000..=0x8003 => self.program_banks[0] = data & 0x1F,
+            0x9000..=0x9003 if self.board.is_vrc2 => {
+                self.mirroring = if data & 0x01 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+            },
+            0x9000 | 0x9001 => {
+                self.mirroring = match data & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            },
+            0x9002 | 0x9003 => self.is_program_mode_swapped = data & 0x02 == 0x02,
+            0xA000..=0xA003 => self.program_banks[1] = data & 0x1F,
+            register @ 0xB000..=0xE003 => {
+                // $B000: bank 0 low, $B001: bank 0 high, $B002: bank 1 low, ... $E003: bank 7 high
+                let index = (((register >> 12) - 0xB) * 2 + ((register & 0x0002) >> 1)) as usize;
+                let bank = self.character_banks[index];
+                self.character_banks[index] = if register & 0x0001 == 0 {
+                    (bank & 0x1F0) | (data as u16 & 0x0F)
+                } else {
+                    (bank & 0x00F) | ((data as u16 & 0x1F) << 4)
+                };
+            },
+            _ if self.board.is_vrc2 => {},
+            0xF000 => self.irq.write_latch_low(data),
+            0xF001 => self.irq.write_latch_high(data),
+            0xF002 => self.irq.write_control(data),
+            0xF003 => self.irq.acknowledge(),
+            _ => {},
+        }
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank_count = (self.program_rom.len() / PROGRAM_BANK_SIZE).max(2);
+        let second_last_bank = bank_count - 2;
+
+        let bank = match (addr, self.is_program_mode_swapped) {
+            (0x8000..=0x9FFF, false) => self.program_banks[0] as usize,
+            (0x8000..=0x9FFF, true) => second_last_bank,
+            (0xA000..=0xBFFF, _) => self.program_banks[1] as usize,
+            (0xC000..=0xDFFF, false) => second_last_bank,
+            (0xC000..=0xDFFF, true) => self.program_banks[0] as usize,
+            _ => bank_count - 1,
+        };
+
+        ((bank % bank_count) * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let bank = self.character_banks[(addr >> 10) as usize] as usize;
+        let bank = if self.board.is_character_bank_shifted { bank >> 1 } else { bank };
+        bank * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x6FFF if self.board.is_vrc2 && self.program_ram.is_empty() => {
+                ((addr >> 8) as u8 & 0xFE) | self.latch
+            },
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x6FFF if self.board.is_vrc2 && self.program_ram.is_empty() => self.latch = data & 0x01,
+            0x6000..=0x7FFF if !self.program_ram.is_empty() => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            0x8000..=0xFFFF => self.write_register(addr, data),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.is_pending()
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        self.irq.clock(cycle);
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod vrc4_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_vrc4(mapper_number: u8, submapper: u8) -> Vrc4 {
+        Vrc4::new(
+            bank_filled_rom(16, PROGRAM_BANK_SIZE),
+            bank_filled_rom(256, CHARACTER_BANK_SIZE),
+            VrcBoard::new(mapper_number, submapper),
+            0x2000,
+            false,
+        )
+    }
+
+    #[test]
+    fn register_pins() {
+        // VRC4a: A1, A2 / VRC4c: A6, A7
+        let vrc4 = new_vrc4(21, 1);
+        assert_eq!(vrc4.register_addr(0xB006), 0xB003);
+        let vrc4 = new_vrc4(21, 2);
+        assert_eq!(vrc4.register_addr(0xB040), 0xB001);
+        assert_eq!(vrc4.register_addr(0xB080), 0xB002);
+        // both boards without the submapper
+        let vrc4 = new_vrc4(21, 0);
+        assert_eq!(vrc4.register_addr(0xB004), 0xB002);
+        assert_eq!(vrc4.register_addr(0xB0C0), 0xB003);
+
+        // VRC4b: A1, A0 (swapped)
+        let vrc4 = new_vrc4(25, 1);
+        assert_eq!(vrc4.register_addr(0xB001), 0xB002);
+        assert_eq!(vrc4.register_addr(0xB002), 0xB001);
+        // VRC4e: A2, A3
+        let vrc4 = new_vrc4(23, 2);
+        assert_eq!(vrc4.register_addr(0xB00C), 0xB003);
+    }
+
+    #[test]
+    fn program_bank_mode() {
+        let mut vrc4 = new_vrc4(23, 1);
+        vrc4.write_cpu(0x8000, 3);
+        vrc4.write_cpu(0xA000, 5);
+
+        assert_eq!(vrc4.read_cpu(0x8000), 3);
+        assert_eq!(vrc4.read_cpu(0xA000), 5);
+        assert_eq!(vrc4.read_cpu(0xC000), 14);
+        assert_eq!(vrc4.read_cpu(0xE000), 15);
+
+        vrc4.write_cpu(0x9002, 0x02);
+        assert_eq!(vrc4.read_cpu(0x8000), 14);
+        assert_eq!(vrc4.read_cpu(0xC000), 3);
+
+        // VRC2 has no swap mode, $9002 is the mirroring
+        let mut vrc2 = new_vrc4(23, 3);
+        vrc2.write_cpu(0x8000, 3);
+        vrc2.write_cpu(0x9002, 0x03);
+        assert_eq!(vrc2.read_cpu(0x8000), 3);
+        assert_eq!(vrc2.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn character_bank() {
+        let mut vrc4 = new_vrc4(25, 1);
+        // bank 0: $B000 low / $B002 high (A0, A1 swapped)
+        vrc4.write_cpu(0xB000, 0x05);
+        vrc4.write_cpu(0xB002, 0x01);
+        // bank 7: $E001 low / $E003 high
+        vrc4.write_cpu(0xE001, 0x0A);
+        vrc4.write_cpu(0xE003, 0x00);
+
+        assert_eq!(vrc4.read_ppu(0x0000), 0x15);
+        assert_eq!(vrc4.read_ppu(0x1C00), 0x0A);
+
+        // VRC2a ignores the lowest bit
+        let mut vrc2 = new_vrc4(22, 0);
+        vrc2.write_cpu(0xB000, 0x05);
+        assert_eq!(vrc2.read_ppu(0x0000), 0x02);
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut vrc4 = new_vrc4(21, 1);
+        vrc4.write_cpu(0x9000, 1);
+        assert_eq!(vrc4.mirroring(), Mirroring::Horizontal);
+        vrc4.write_cpu(0x9000, 3);
+        assert_eq!(vrc4.mirroring(), Mirroring::SingleScreenUpper);
+    }
+
+    #[test]
+    fn irq() {
+        let mut vrc4 = new_vrc4(21, 2);
+        vrc4.write_cpu(0xF000, 0x0E);
+        vrc4.write_cpu(0xF040, 0x0F);
+        vrc4.write_cpu(0xF080, 0x06);
+
+        vrc4.notify_cpu_cycle(1);
+        assert!(!vrc4.irq());
+        vrc4.notify_cpu_cycle(1);
+        assert!(vrc4.irq());
+
+        vrc4.write_cpu(0xF0C0, 0x00);
+        assert!(!vrc4.irq());
+
+        // VRC2 has no IRQ
+        let mut vrc2 = new_vrc4(22, 0);
+        vrc2.write_cpu(0xF000, 0x0F);
+        vrc2.write_cpu(0xF001, 0x0F);
+        vrc2.write_cpu(0xF002, 0x06);
+        vrc2.notify_cpu_cycle(10);
+        assert!(!vrc2.irq());
+    }
+}
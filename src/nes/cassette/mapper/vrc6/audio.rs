@@ -0,0 +1,215 @@
+/// Linear approximation of the APU mixer. (refer: https://wiki.nesdev.com/w/index.php/APU_Mixer)
+/// A VRC6 pulse at a volume is as loud as an APU pulse at the same volume.
+const LEVEL: f32 = 0.00752;
+
+/// Pulse channel of the VRC6: 16 step duty, 4 bit volume.
+#[derive(Default)]
+struct Pulse {
+    volume: u8,
+    duty: u8,
+    is_digitized: bool,
+    period: u16,
+    is_enabled: bool,
+    timer: u16,
+    step: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.is_digitized = data & 0x80 == 0x80;
+                self.duty = (data >> 4) & 0x07;
+                self.volume = data & 0x0F;
+            },
+            1 => self.period = (self.period & 0x0F00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data as u16 & 0x0F) << 8);
+                self.is_enabled = data & 0x80 == 0x80;
+                if !self.is_enabled {
+                    self.step = 15;
+                }
+            },
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.is_enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = self.step.wrapping_sub(1) & 0x0F;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.is_enabled && (self.is_digitized || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+/// Sawtooth channel of the VRC6: the accumulator is added the rate every 2 steps, reset at the 14th step.
+#[derive(Default)]
+struct Sawtooth {
+    rate: u8,
+    period: u16,
+    is_enabled: bool,
+    timer: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Sawtooth {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => self.rate = data & 0x3F,
+            1 => self.period = (self.period & 0x0F00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data as u16 & 0x0F) << 8);
+                self.is_enabled = data & 0x80 == 0x80;
+                if !self.is_enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            },
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.is_enabled {
+            return;
+        }
+
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period >> shift;
+        self.step += 1;
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step & 0x01 == 0 {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+/// VRC6 expansion audio: 2 pulse channels and a sawtooth channel.
+///
+/// $9000-$9002: pulse 1 (mode / duty / volume, period low, enable / period high)
+/// $9003      : frequency control (bit 0: halt, bit 1: 16x frequency, bit 2: 256x frequency)
+/// $A000-$A002: pulse 2
+/// $B000-$B002: sawtooth (accumulator rate, period low, enable / period high)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/VRC6_audio
+#[derive(Default)]
+pub struct Vrc6Audio {
+    pulses: [Pulse; 2],
+    sawtooth: Sawtooth,
+    is_halted: bool,
+    frequency_shift: u8,
+}
+
+impl Vrc6Audio {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// addr is the register address on the chip. ($9000-$B002)
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x9003 => {
+                self.is_halted = data & 0x01 == 0x01;
+                self.frequency_shift = match data & 0x06 {
+                    0x00 => 0,
+                    0x02 => 4,
+                    _ => 8,
+                };
+            },
+            0x9000..=0x9002 => self.pulses[0].write(addr - 0x9000, data),
+            0xA000..=0xA002 => self.pulses[1].write(addr - 0xA000, data),
+            0xB000..=0xB002 => self.sawtooth.write(addr - 0xB000, data),
+            _ => {},
+        }
+    }
+
+    pub fn clock(&mut self, cpu_cycle: usize) {
+        if self.is_halted {
+            return;
+        }
+
+        for _ in 0..cpu_cycle {
+            self.pulses[0].clock(self.frequency_shift);
+            self.pulses[1].clock(self.frequency_shift);
+            self.sawtooth.clock(self.frequency_shift);
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        let level = self.pulses[0].output() + self.pulses[1].output() + self.sawtooth.output();
+        level as f32 * LEVEL
+    }
+}
+
+#[cfg(test)]
+mod audio_test {
+    use super::*;
+
+    #[test]
+    fn pulse_duty() {
+        let mut audio = Vrc6Audio::new();
+        // duty 4 (5/16), volume 15, period 1
+        audio.write(0x9000, 0x4F);
+        audio.write(0x9001, 0x01);
+        audio.write(0x9002, 0x80);
+
+        let outputs: Vec<u8> = (0..16).map(|_| {
+            audio.clock(2);
+            audio.pulses[0].output()
+        }).collect();
+        let high_count = outputs.iter().filter(|output| **output == 15).count();
+        assert_eq!(high_count, 5);
+
+        // digitized mode outputs the volume constantly
+        audio.write(0x9000, 0x88);
+        audio.clock(2);
+        assert_eq!(audio.output(), 8.0 * LEVEL);
+    }
+
+    #[test]
+    fn sawtooth() {
+        let mut audio = Vrc6Audio::new();
+        audio.write(0xB000, 0x08);
+        audio.write(0xB001, 0x00);
+        audio.write(0xB002, 0x80);
+
+        let outputs: Vec<u8> = (0..14).map(|_| {
+            audio.clock(1);
+            audio.sawtooth.output()
+        }).collect();
+        assert_eq!(outputs, vec![0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 0]);
+    }
+
+    #[test]
+    fn halt() {
+        let mut audio = Vrc6Audio::new();
+        audio.write(0xB000, 0x08);
+        audio.write(0xB002, 0x80);
+        audio.write(0x9003, 0x01);
+        audio.clock(10);
+        assert_eq!(audio.output(), 0.0);
+    }
+}
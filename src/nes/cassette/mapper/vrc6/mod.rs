@@ -0,0 +1,264 @@
+pub mod audio;
+
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+use super::vrc_irq::VrcIrq;
+use audio::Vrc6Audio;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 24, 26 (Konami VRC6a / VRC6b)
+///
+/// CPU $6000-$7FFF: 8 KB PRG RAM (enabled by $B003 bit 7)
+/// CPU $8000-$BFFF: 16 KB switchable PRG ROM bank
+/// CPU $C000-$DFFF: 8 KB switchable PRG ROM bank
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: CHR banks by the banking mode of $B003
+///
+/// VRC6b (mapper 26) swaps the A0 and A1 lines.
+/// The CHR A10 rule ($B003 bit 5) and the CHR ROM nametables ($B003 bit 4) are not supported.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/VRC6
+pub struct Vrc6 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    is_pins_swapped: bool,
+    program_banks: [u8; 2],
+    character_banks: [u8; 8],
+    banking_control: u8,
+    irq: VrcIrq,
+    audio: Vrc6Audio,
+}
+
+impl Vrc6 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, is_pins_swapped: bool, program_ram_size: usize, battery: bool) -> Self {
+        Vrc6 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            is_pins_swapped,
+            program_banks: [0, 0],
+            character_banks: [0; 8],
+            banking_control: 0,
+            irq: VrcIrq::new(),
+            audio: Vrc6Audio::new(),
+        }
+    }
+
+    /// Translates the CPU address into the register address ($x000-$x003) on the chip.
+    fn register_addr(&self, addr: u16) -> u16 {
+        let (a0, a1) = if self.is_pins_swapped {
+            ((addr >> 1) & 0x01, addr & 0x01)
+        } else {
+            (addr & 0x01, (addr >> 1) & 0x01)
+        };
+        (addr & 0xF000) | a1 << 1 | a0
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match self.register_addr(addr) {
+            0x8000..=0x8003 => self.program_banks[0] = data & 0x0F,
+            0xB003 => self.banking_control = data,
+            register @ 0x9000..=0xB002 => self.audio.write(register, data),
+            0xC000..=0xC003 => self.program_banks[1] = data & 0x1F,
+            register @ 0xD000..=0xE003 => {
+                let index = (((register >> 12) - 0xD) * 4 + (register & 0x0003)) as usize;
+                self.character_banks[index] = data;
+            },
+            0xF000 => self.irq.write_latch(data),
+            0xF001 => self.irq.write_control(data),
+            0xF002 => self.irq.acknowledge(),
+            _ => {},
+        }
+    }
+
+    fn is_program_ram_enabled(&self) -> bool {
+        self.banking_control & 0x80 == 0x80 && !self.program_ram.is_empty()
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let offset = match addr {
+            0x8000..=0xBFFF => self.program_banks[0] as usize * PROGRAM_BANK_SIZE * 2 + (addr as usize & 0x3FFF),
+            0xC000..=0xDFFF => self.program_banks[1] as usize * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF),
+            _ => self.program_rom.len() - PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF),
+        };
+
+        offset % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        let addr = addr as usize;
+        let banks = &self.character_banks;
+
+        // (bank number in 1 KB, offset in the bank)
+        let (bank, offset) = match (self.banking_control & 0x03, addr) {
+            (0, _) => (banks[addr >> 10] as usize, addr & 0x03FF),
+            (1, _) => (banks[addr >> 11] as usize * 2, addr & 0x07FF),
+            (_, 0x0000..=0x0FFF) => (banks[addr >> 10] as usize, addr & 0x03FF),
+            _ => (banks[4 + ((addr >> 11) & 0x01)] as usize * 2, addr & 0x07FF),
+        };
+
+        bank * CHARACTER_BANK_SIZE + offset
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            0x8000..=0xFFFF => self.write_register(addr, data),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking_control >> 2) & 0x03 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.is_pending()
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        self.irq.clock(cycle);
+        self.audio.clock(cycle);
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod vrc6_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_vrc6(is_pins_swapped: bool) -> Vrc6 {
+        Vrc6::new(
+            bank_filled_rom(16, PROGRAM_BANK_SIZE),
+            bank_filled_rom(256, CHARACTER_BANK_SIZE),
+            is_pins_swapped,
+            0x2000,
+            false,
+        )
+    }
+
+    #[test]
+    fn program_bank() {
+        let mut vrc6 = new_vrc6(false);
+        vrc6.write_cpu(0x8000, 2);
+        vrc6.write_cpu(0xC000, 9);
+
+        assert_eq!(vrc6.read_cpu(0x8000), 4);
+        assert_eq!(vrc6.read_cpu(0xA000), 5);
+        assert_eq!(vrc6.read_cpu(0xC000), 9);
+        assert_eq!(vrc6.read_cpu(0xE000), 15);
+    }
+
+    #[test]
+    fn character_banking_mode() {
+        let mut vrc6 = new_vrc6(true);
+        // VRC6b: $D001 -> R2, $D002 -> R1
+        for (addr, bank) in [(0xD000, 10), (0xD002, 11), (0xD001, 12), (0xD003, 13), (0xE000, 14), (0xE002, 15)].iter() {
+            vrc6.write_cpu(*addr, *bank);
+        }
+        vrc6.write_cpu(0xB003, 0x20);
+
+        assert_eq!(vrc6.read_ppu(0x0000), 10);
+        assert_eq!(vrc6.read_ppu(0x0400), 11);
+        assert_eq!(vrc6.read_ppu(0x0800), 12);
+        assert_eq!(vrc6.read_ppu(0x1400), 15);
+
+        // mode 1: 2 KB banks by R0-R3
+        vrc6.write_cpu(0xB003, 0x21);
+        assert_eq!(vrc6.read_ppu(0x0000), 20);
+        assert_eq!(vrc6.read_ppu(0x0C00), 23);
+
+        // mode 2: 1 KB banks by R0-R3, then 2 KB banks by R4, R5
+        vrc6.write_cpu(0xB003, 0x22);
+        assert_eq!(vrc6.read_ppu(0x0C00), 13);
+        assert_eq!(vrc6.read_ppu(0x1000), 28);
+        assert_eq!(vrc6.read_ppu(0x1C00), 31);
+    }
+
+    #[test]
+    fn mirroring_and_program_ram() {
+        let mut vrc6 = new_vrc6(false);
+        vrc6.write_cpu(0x6000, 0x12);
+        assert_eq!(vrc6.read_cpu(0x6000), 0x00);
+
+        vrc6.write_cpu(0xB003, 0x84);
+        assert_eq!(vrc6.mirroring(), Mirroring::Horizontal);
+        vrc6.write_cpu(0x6000, 0x12);
+        assert_eq!(vrc6.read_cpu(0x6000), 0x12);
+    }
+
+    #[test]
+    fn irq() {
+        let mut vrc6 = new_vrc6(false);
+        vrc6.write_cpu(0xF000, 0xFF);
+        vrc6.write_cpu(0xF001, 0x06);
+        vrc6.notify_cpu_cycle(1);
+        assert!(vrc6.irq());
+        vrc6.write_cpu(0xF002, 0x00);
+        assert!(!vrc6.irq());
+    }
+
+    #[test]
+    fn audio() {
+        let mut vrc6 = new_vrc6(true);
+        // VRC6b: $B002 -> $B001, $B001 -> $B002
+        vrc6.write_cpu(0xB000, 0x3F);
+        vrc6.write_cpu(0xB002, 0x00);
+        vrc6.write_cpu(0xB001, 0x80);
+        vrc6.notify_cpu_cycle(2);
+        assert!(vrc6.audio_output() > 0.0);
+    }
+}
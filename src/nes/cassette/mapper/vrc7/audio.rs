@@ -0,0 +1,482 @@
+use std::f32::consts::PI;
+
+/// Built-in instruments of the VRC7 (the instrument 0 is the custom one, $00-$07).
+///
+/// byte 0 / 1: AM, vibrato, sustained envelope, key scale rate, multiplier (modulator / carrier)
+/// byte 2    : key scale level, total level (modulator)
+/// byte 3    : key scale level (carrier), half wave (carrier, modulator), feedback
+/// byte 4 / 5: attack rate, decay rate (modulator / carrier)
+/// byte 6 / 7: sustain level, release rate (modulator / carrier)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/VRC7_audio
+const INSTRUMENTS: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06],
+];
+
+const MULTIPLIERS: [f32; 16] = [0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0];
+
+/// Key scale attenuation (dB) at the block 7 by the upper 4 bits of F-number, 3 dB / octave.
+const KEY_SCALE_LEVELS: [f32; 16] = [
+    0.0, 9.0, 12.0, 13.875, 15.0, 16.125, 16.875, 17.625,
+    18.0, 18.75, 19.125, 19.5, 19.875, 20.25, 20.625, 21.0,
+];
+const KEY_SCALE_LEVEL_RATES: [f32; 4] = [0.0, 0.5, 1.0, 2.0];
+
+/// The OPLL makes a sample every 72 clocks of 3.58 MHz, every 36 CPU cycles.
+const CPU_CYCLE_PER_SAMPLE: usize = 36;
+const SAMPLE_RATE: f32 = 49716.0;
+
+const MAX_ATTENUATION: f32 = 48.0; // dB
+const AM_DEPTH: f32 = 4.8;         // dB
+const AM_FREQUENCY: f32 = 3.7;     // Hz
+const VIBRATO_DEPTH: f32 = 0.008;  // about 14 cents
+const VIBRATO_FREQUENCY: f32 = 6.4; // Hz
+
+/// Phase modulation (in cycles) by the full modulator output.
+const MODULATION_DEPTH: f32 = 2.0;
+/// Release rate of the channels with the sustain flag after key off.
+const SUSTAIN_RELEASE_RATE: u8 = 5;
+
+/// Mixed level of a channel at full volume, about 2 APU pulses.
+const CHANNEL_LEVEL: f32 = 0.2;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Off,
+}
+
+/// Parameters of an operator decoded from an instrument.
+struct OperatorPatch {
+    is_am: bool,
+    is_vibrato: bool,
+    is_sustained: bool,
+    is_key_scale_rate: bool,
+    multiplier: f32,
+    key_scale_level: u8,
+    is_half_wave: bool,
+    attack_rate: u8,
+    decay_rate: u8,
+    sustain_level: f32,
+    release_rate: u8,
+}
+
+impl OperatorPatch {
+    /// operator 0: modulator, 1: carrier
+    fn new(instrument: &[u8; 8], operator: usize) -> Self {
+        let flags = instrument[operator];
+        let envelope = instrument[4 + operator];
+        let sustain_release = instrument[6 + operator];
+
+        OperatorPatch {
+            is_am: flags & 0x80 == 0x80,
+            is_vibrato: flags & 0x40 == 0x40,
+            is_sustained: flags & 0x20 == 0x20,
+            is_key_scale_rate: flags & 0x10 == 0x10,
+            multiplier: MULTIPLIERS[(flags & 0x0F) as usize],
+            key_scale_level: instrument[2 + operator] >> 6,
+            is_half_wave: instrument[3] & (0x08 << operator) != 0,
+            attack_rate: envelope >> 4,
+            decay_rate: envelope & 0x0F,
+            sustain_level: (sustain_release >> 4) as f32 * 3.0,
+            release_rate: sustain_release & 0x0F,
+        }
+    }
+}
+
+struct Operator {
+    phase: f32,
+    attenuation: f32,
+    state: EnvelopeState,
+}
+
+impl Operator {
+    fn new() -> Self {
+        Operator {
+            phase: 0.0,
+            attenuation: MAX_ATTENUATION,
+            state: EnvelopeState::Off,
+        }
+    }
+
+    fn key_on(&mut self) {
+        self.phase = 0.0;
+        self.state = EnvelopeState::Attack;
+    }
+
+    fn key_off(&mut self) {
+        if self.state != EnvelopeState::Off {
+            self.state = EnvelopeState::Release;
+        }
+    }
+
+    fn clock_envelope(&mut self, patch: &OperatorPatch, key_scale_index: u8, is_channel_sustain: bool) {
+        let rate = |rate: u8| -> u8 {
+            if rate == 0 {
+                return 0;
+            }
+            let key_scale = if patch.is_key_scale_rate { key_scale_index } else { key_scale_index >> 2 };
+            (rate * 4 + key_scale).min(63)
+        };
+
+        match self.state {
+            EnvelopeState::Attack => {
+                let rate = rate(patch.attack_rate);
+                if rate >= 60 {
+                    self.attenuation = 0.0;
+                } else {
+                    self.attenuation -= (self.attenuation * 0.125 + 0.375) * Operator::attack_factor(rate);
+                }
+                if self.attenuation <= 0.0 {
+                    self.attenuation = 0.0;
+                    self.state = EnvelopeState::Decay;
+                }
+            },
+            EnvelopeState::Decay => {
+                self.attenuation += Operator::decay_step(rate(patch.decay_rate));
+                if self.attenuation >= patch.sustain_level {
+                    self.attenuation = patch.sustain_level;
+                    self.state = EnvelopeState::Sustain;
+                }
+            },
+            EnvelopeState::Sustain if patch.is_sustained => {},
+            EnvelopeState::Sustain => self.attenuation += Operator::decay_step(rate(patch.release_rate)),
+            EnvelopeState::Release => {
+                let release_rate = if is_channel_sustain { SUSTAIN_RELEASE_RATE } else { patch.release_rate };
+                self.attenuation += Operator::decay_step(rate(release_rate));
+            },
+            EnvelopeState::Off => {},
+        }
+
+        if self.attenuation >= MAX_ATTENUATION {
+            self.attenuation = MAX_ATTENUATION;
+            if self.state != EnvelopeState::Attack {
+                self.state = EnvelopeState::Off;
+            }
+        }
+    }
+
+    /// Attenuation (dB) per sample while decaying, about 20 sec. to 48 dB at the rate 4.
+    fn decay_step(rate: u8) -> f32 {
+        if rate == 0 {
+            0.0
+        } else {
+            MAX_ATTENUATION / (20.0 * SAMPLE_RATE) * 2f32.powf((rate as f32 - 4.0) / 4.0)
+        }
+    }
+
+    /// Exponential attack, about 2.8 sec. at the rate 4.
+    fn attack_factor(rate: u8) -> f32 {
+        if rate == 0 {
+            0.0
+        } else {
+            1.6e-4 * 2f32.powf((rate as f32 - 4.0) / 4.0)
+        }
+    }
+
+    /// modulation is in cycles, attenuation in dB.
+    fn output(&self, modulation: f32, attenuation: f32, is_half_wave: bool) -> f32 {
+        if self.state == EnvelopeState::Off {
+            return 0.0;
+        }
+
+        let wave = ((self.phase + modulation) * 2.0 * PI).sin();
+        let wave = if is_half_wave && wave < 0.0 { 0.0 } else { wave };
+        wave * 10f32.powf(-attenuation / 20.0)
+    }
+}
+
+struct Channel {
+    f_number: u16,
+    block: u8,
+    is_key_on: bool,
+    is_sustain: bool,
+    instrument: u8,
+    volume: u8,
+    modulator: Operator,
+    carrier: Operator,
+    feedback: [f32; 2],
+    output: f32,
+}
+
+impl Channel {
+    fn new() -> Self {
+        Channel {
+            f_number: 0,
+            block: 0,
+            is_key_on: false,
+            is_sustain: false,
+            instrument: 0,
+            volume: 0,
+            modulator: Operator::new(),
+            carrier: Operator::new(),
+            feedback: [0.0; 2],
+            output: 0.0,
+        }
+    }
+
+    fn set_key_on(&mut self, is_key_on: bool) {
+        if is_key_on && !self.is_key_on {
+            self.modulator.key_on();
+            self.carrier.key_on();
+        } else if !is_key_on && self.is_key_on {
+            self.modulator.key_off();
+            self.carrier.key_off();
+        }
+        self.is_key_on = is_key_on;
+    }
+
+    fn key_scale_level(&self, patch: &OperatorPatch) -> f32 {
+        let level = KEY_SCALE_LEVELS[(self.f_number >> 5) as usize] - 6.0 * (7 - self.block) as f32;
+        level.max(0.0) * KEY_SCALE_LEVEL_RATES[patch.key_scale_level as usize]
+    }
+
+    fn clock(&mut self, instrument: &[u8; 8], am: f32, vibrato: f32) {
+        let modulator_patch = OperatorPatch::new(instrument, 0);
+        let carrier_patch = OperatorPatch::new(instrument, 1);
+        let key_scale_index = self.block << 1 | (self.f_number >> 8) as u8;
+
+        // phase (cycles per sample) = F-number * 2^(block - 1) * multiplier / 2^18
+        let base_step = self.f_number as f32 * 2f32.powi(self.block as i32 - 1) / 262144.0;
+        let is_sustain = self.is_sustain;
+        for (operator, patch) in [(&mut self.modulator, &modulator_patch), (&mut self.carrier, &carrier_patch)].iter_mut() {
+            let vibrato = if patch.is_vibrato { 1.0 + vibrato } else { 1.0 };
+            operator.phase = (operator.phase + base_step * patch.multiplier * vibrato).fract();
+            operator.clock_envelope(patch, key_scale_index, is_sustain);
+        }
+
+        // modulator with self feedback, total level in 0.75 dB
+        let feedback = instrument[3] & 0x07;
+        let feedback_modulation = if feedback == 0 {
+            0.0
+        } else {
+            (self.feedback[0] + self.feedback[1]) / 2.0 * 2f32.powi(feedback as i32 - 1) / 32.0
+        };
+        let modulator_attenuation = self.modulator.attenuation
+            + (instrument[2] & 0x3F) as f32 * 0.75
+            + self.key_scale_level(&modulator_patch)
+            + if modulator_patch.is_am { am } else { 0.0 };
+        let modulator_output = self.modulator.output(feedback_modulation, modulator_attenuation, modulator_patch.is_half_wave);
+        self.feedback = [self.feedback[1], modulator_output];
+
+        // carrier, volume in 3 dB
+        let carrier_attenuation = self.carrier.attenuation
+            + self.volume as f32 * 3.0
+            + self.key_scale_level(&carrier_patch)
+            + if carrier_patch.is_am { am } else { 0.0 };
+        self.output = self.carrier.output(modulator_output * MODULATION_DEPTH, carrier_attenuation, carrier_patch.is_half_wave);
+    }
+}
+
+/// VRC7 expansion audio: 6 FM channels of 2 operators, a derivative of the YM2413 (OPLL).
+///
+/// $9010: register select
+/// $9030: register data
+///
+/// $00-$07: custom instrument
+/// $10-$15: F-number low 8 bits
+/// $20-$25: sustain (bit 5), key on (bit 4), block (bit 1-3), F-number bit 8 (bit 0)
+/// $30-$35: instrument (bit 4-7), volume (bit 0-3)
+///
+/// The envelope and the modulation are approximated in floating point.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/VRC7_audio
+pub struct Vrc7Audio {
+    register_addr: u8,
+    custom_instrument: [u8; 8],
+    channels: Vec<Channel>,
+    is_silenced: bool,
+    cycle: usize,
+    lfo_time: f32,
+}
+
+impl Vrc7Audio {
+    pub fn new() -> Self {
+        Vrc7Audio {
+            register_addr: 0,
+            custom_instrument: [0; 8],
+            channels: (0..6).map(|_| Channel::new()).collect(),
+            is_silenced: false,
+            cycle: 0,
+            lfo_time: 0.0,
+        }
+    }
+
+    pub fn select_register(&mut self, data: u8) {
+        self.register_addr = data;
+    }
+
+    pub fn write_register(&mut self, data: u8) {
+        let index = (self.register_addr & 0x0F) as usize;
+
+        match self.register_addr {
+            0x00..=0x07 => self.custom_instrument[index] = data,
+            0x10..=0x15 => {
+                let channel = &mut self.channels[index];
+                channel.f_number = (channel.f_number & 0x100) | data as u16;
+            },
+            0x20..=0x25 => {
+                let channel = &mut self.channels[index];
+                channel.f_number = (channel.f_number & 0x0FF) | ((data as u16 & 0x01) << 8);
+                channel.block = (data >> 1) & 0x07;
+                channel.is_sustain = data & 0x20 == 0x20;
+                channel.set_key_on(data & 0x10 == 0x10);
+            },
+            0x30..=0x35 => {
+                let channel = &mut self.channels[index];
+                channel.instrument = data >> 4;
+                channel.volume = data & 0x0F;
+            },
+            _ => {},
+        }
+    }
+
+    /// $E000 bit 6 silences and resets the audio.
+    pub fn set_silenced(&mut self, is_silenced: bool) {
+        if is_silenced && !self.is_silenced {
+            self.channels = (0..6).map(|_| Channel::new()).collect();
+        }
+        self.is_silenced = is_silenced;
+    }
+
+    pub fn clock(&mut self, cpu_cycle: usize) {
+        if self.is_silenced {
+            return;
+        }
+
+        self.cycle += cpu_cycle;
+        while self.cycle >= CPU_CYCLE_PER_SAMPLE {
+            self.cycle -= CPU_CYCLE_PER_SAMPLE;
+            self.clock_sample();
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        if self.is_silenced {
+            return 0.0;
+        }
+
+        self.channels.iter().map(|channel| channel.output).sum::<f32>() * CHANNEL_LEVEL
+    }
+
+    fn clock_sample(&mut self) {
+        self.lfo_time = (self.lfo_time + 1.0 / SAMPLE_RATE) % 100.0;
+        let am = AM_DEPTH * (1.0 + (self.lfo_time * AM_FREQUENCY * 2.0 * PI).sin()) / 2.0;
+        let vibrato = VIBRATO_DEPTH * (self.lfo_time * VIBRATO_FREQUENCY * 2.0 * PI).sin();
+
+        let custom_instrument = self.custom_instrument;
+        for channel in self.channels.iter_mut() {
+            let instrument = match channel.instrument {
+                0 => &custom_instrument,
+                n => &INSTRUMENTS[n as usize - 1],
+            };
+            channel.clock(instrument, am, vibrato);
+        }
+    }
+}
+
+#[cfg(test)]
+mod audio_test {
+    use super::*;
+
+    fn write(audio: &mut Vrc7Audio, addr: u8, data: u8) {
+        audio.select_register(addr);
+        audio.write_register(data);
+    }
+
+    /// Counts the rising zero crossings of the output for 1 sec.
+    fn count_cycles(audio: &mut Vrc7Audio) -> usize {
+        let mut count = 0;
+        let mut last = 0.0;
+        for _ in 0..SAMPLE_RATE as usize {
+            audio.clock(CPU_CYCLE_PER_SAMPLE);
+            let output = audio.output();
+            if last < 0.0 && output >= 0.0 {
+                count += 1;
+            }
+            last = output;
+        }
+        count
+    }
+
+    #[test]
+    fn registers() {
+        let mut audio = Vrc7Audio::new();
+        write(&mut audio, 0x03, 0x45);
+        write(&mut audio, 0x12, 0x22);
+        write(&mut audio, 0x22, 0x3B);
+        write(&mut audio, 0x32, 0x5A);
+
+        assert_eq!(audio.custom_instrument[3], 0x45);
+        let channel = &audio.channels[2];
+        assert_eq!(channel.f_number, 0x122);
+        assert_eq!(channel.block, 5);
+        assert!(channel.is_key_on);
+        assert!(channel.is_sustain);
+        assert_eq!(channel.instrument, 5);
+        assert_eq!(channel.volume, 10);
+    }
+
+    #[test]
+    fn frequency() {
+        // a pure sine by the custom instrument: carrier only, no modulation, instant attack
+        let mut audio = Vrc7Audio::new();
+        for (addr, data) in [(0x00, 0x00), (0x01, 0x21), (0x02, 0x3F), (0x03, 0x00), (0x05, 0xF0), (0x07, 0x00)].iter() {
+            write(&mut audio, *addr, *data);
+        }
+
+        // 440 Hz: F-number 290, block 4
+        write(&mut audio, 0x30, 0x00);
+        write(&mut audio, 0x10, (290 & 0xFF) as u8);
+        write(&mut audio, 0x20, 0x10 | 4 << 1 | (290 >> 8) as u8);
+
+        let cycles = count_cycles(&mut audio);
+        assert!((438..=442).contains(&cycles), "{} Hz", cycles);
+    }
+
+    #[test]
+    fn envelope_release() {
+        let mut audio = Vrc7Audio::new();
+        write(&mut audio, 0x30, 0x10); // instrument 1
+        write(&mut audio, 0x10, 0x80);
+        write(&mut audio, 0x20, 0x18);
+        audio.clock(CPU_CYCLE_PER_SAMPLE * 1000);
+        assert_ne!(audio.channels[0].carrier.state, EnvelopeState::Off);
+
+        // key off, then released
+        write(&mut audio, 0x20, 0x08);
+        audio.clock(CPU_CYCLE_PER_SAMPLE * SAMPLE_RATE as usize * 2);
+        assert_eq!(audio.channels[0].carrier.state, EnvelopeState::Off);
+        assert_eq!(audio.output(), 0.0);
+    }
+
+    #[test]
+    fn silence() {
+        let mut audio = Vrc7Audio::new();
+        write(&mut audio, 0x30, 0x10);
+        write(&mut audio, 0x10, 0x80);
+        write(&mut audio, 0x20, 0x18);
+        audio.clock(CPU_CYCLE_PER_SAMPLE * 100);
+
+        audio.set_silenced(true);
+        assert_eq!(audio.output(), 0.0);
+        assert!(!audio.channels[0].is_key_on);
+    }
+}
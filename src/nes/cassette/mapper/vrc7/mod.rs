@@ -0,0 +1,264 @@
+pub mod audio;
+
+use super::{Mapper, Mirroring};
+use super::character_memory::CharacterMemory;
+use super::vrc_irq::VrcIrq;
+use audio::Vrc7Audio;
+
+const PROGRAM_BANK_SIZE: usize = 0x2000;   // 8 KB
+const CHARACTER_BANK_SIZE: usize = 0x0400; // 1 KB
+
+/// Mapper 85 (Konami VRC7)
+///
+/// CPU $6000-$7FFF: 8 KB PRG RAM (enabled by $E000 bit 7)
+/// CPU $8000-$9FFF: 8 KB switchable PRG ROM bank
+/// CPU $A000-$BFFF: 8 KB switchable PRG ROM bank
+/// CPU $C000-$DFFF: 8 KB switchable PRG ROM bank
+/// CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+/// PPU $0000-$1FFF: eight 1 KB switchable CHR banks
+///
+/// The second register of each pair is selected by A4 (VRC7a), or A3 (VRC7b).
+///
+/// refer: https://wiki.nesdev.com/w/index.php/VRC7
+pub struct Vrc7 {
+    program_rom: Vec<u8>,
+    character_memory: CharacterMemory,
+    program_ram: Vec<u8>,
+    battery: bool,
+    /// CPU address bits selecting the second register
+    register_pin: u16,
+    program_banks: [u8; 3],
+    character_banks: [u8; 8],
+    control: u8,
+    irq: VrcIrq,
+    audio: Vrc7Audio,
+}
+
+impl Vrc7 {
+    pub fn new(program_rom: Vec<u8>, character_rom: Vec<u8>, submapper: u8, program_ram_size: usize, battery: bool) -> Self {
+        let register_pin = match submapper {
+            1 => 0x0008, // VRC7b
+            2 => 0x0010, // VRC7a
+            _ => 0x0018,
+        };
+
+        Vrc7 {
+            program_rom,
+            character_memory: CharacterMemory::new(character_rom),
+            program_ram: vec![0; program_ram_size],
+            battery,
+            register_pin,
+            program_banks: [0; 3],
+            character_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::new(),
+            audio: Vrc7Audio::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        // the audio ports are decoded by A4 and A5 on both boards
+        match addr & 0xF030 {
+            0x9010 => return self.audio.select_register(data),
+            0x9030 => return self.audio.write_register(data),
+            _ => {},
+        }
+
+        let register = (addr & 0xF000) | if addr & self.register_pin != 0 { 0x0010 } else { 0x0000 };
+        match register {
+            0x8000 => self.program_banks[0] = data & 0x3F,
+            0x8010 => self.program_banks[1] = data & 0x3F,
+            0x9000 => self.program_banks[2] = data & 0x3F,
+            0xA000..=0xD010 => {
+                let index = (((register >> 12) - 0xA) * 2 + ((register >> 4) & 0x01)) as usize;
+                self.character_banks[index] = data;
+            },
+            0xE000 => {
+                self.control = data;
+                self.audio.set_silenced(data & 0x40 == 0x40);
+            },
+            0xE010 => self.irq.write_latch(data),
+            0xF000 => self.irq.write_control(data),
+            0xF010 => self.irq.acknowledge(),
+            _ => {},
+        }
+    }
+
+    fn is_program_ram_enabled(&self) -> bool {
+        self.control & 0x80 == 0x80 && !self.program_ram.is_empty()
+    }
+
+    fn program_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0x9FFF => self.program_banks[0] as usize,
+            0xA000..=0xBFFF => self.program_banks[1] as usize,
+            0xC000..=0xDFFF => self.program_banks[2] as usize,
+            _ => self.program_rom.len() / PROGRAM_BANK_SIZE - 1,
+        };
+
+        (bank * PROGRAM_BANK_SIZE + (addr as usize & 0x1FFF)) % self.program_rom.len()
+    }
+
+    fn character_offset(&self, addr: u16) -> usize {
+        self.character_banks[(addr >> 10) as usize] as usize * CHARACTER_BANK_SIZE + (addr as usize & 0x03FF)
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                self.program_ram[(addr as usize - 0x6000) % self.program_ram.len()]
+            },
+            0x8000..=0xFFFF => self.program_rom[self.program_offset(addr)],
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.is_program_ram_enabled() => {
+                let len = self.program_ram.len();
+                self.program_ram[(addr as usize - 0x6000) % len] = data;
+            },
+            0x8000..=0xFFFF => self.write_register(addr, data),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, addr: u16) -> u8 {
+        self.character_memory.read(self.character_offset(addr))
+    }
+
+    fn write_ppu(&mut self, addr: u16, data: u8) {
+        let offset = self.character_offset(addr);
+        self.character_memory.write(offset, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.is_pending()
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        self.irq.clock(cycle);
+        self.audio.clock(cycle);
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery_backed_ram(&self) -> Option<&[u8]> {
+        if self.battery {
+            Some(&self.program_ram)
+        } else {
+            None
+        }
+    }
+
+    fn restore_battery_backed_ram(&mut self, data: &[u8]) {
+        if self.battery && data.len() == self.program_ram.len() {
+            self.program_ram.copy_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod vrc7_test {
+    use super::*;
+
+    use crate::nes::cassette::mapper::bank_filled_rom;
+
+    fn new_vrc7(submapper: u8) -> Vrc7 {
+        Vrc7::new(
+            bank_filled_rom(16, PROGRAM_BANK_SIZE),
+            bank_filled_rom(256, CHARACTER_BANK_SIZE),
+            submapper,
+            0x2000,
+            false,
+        )
+    }
+
+    #[test]
+    fn program_bank() {
+        // VRC7a: the second register by A4
+        let mut vrc7 = new_vrc7(2);
+        vrc7.write_cpu(0x8000, 3);
+        vrc7.write_cpu(0x8010, 5);
+        vrc7.write_cpu(0x9000, 7);
+
+        assert_eq!(vrc7.read_cpu(0x8000), 3);
+        assert_eq!(vrc7.read_cpu(0xA000), 5);
+        assert_eq!(vrc7.read_cpu(0xC000), 7);
+        assert_eq!(vrc7.read_cpu(0xE000), 15);
+
+        // VRC7b: the second register by A3
+        let mut vrc7 = new_vrc7(1);
+        vrc7.write_cpu(0x8008, 9);
+        assert_eq!(vrc7.read_cpu(0xA000), 9);
+    }
+
+    #[test]
+    fn character_bank() {
+        let mut vrc7 = new_vrc7(0);
+        for (index, addr) in [0xA000, 0xA010, 0xB000, 0xB008, 0xC000, 0xC010, 0xD000, 0xD008].iter().enumerate() {
+            vrc7.write_cpu(*addr, 0x20 + index as u8);
+        }
+
+        for index in 0..8 {
+            assert_eq!(vrc7.read_ppu(index * 0x0400), 0x20 + index as u8);
+        }
+    }
+
+    #[test]
+    fn control() {
+        let mut vrc7 = new_vrc7(2);
+        vrc7.write_cpu(0x6000, 0x12);
+        assert_eq!(vrc7.read_cpu(0x6000), 0x00);
+
+        vrc7.write_cpu(0xE000, 0x81);
+        assert_eq!(vrc7.mirroring(), Mirroring::Horizontal);
+        vrc7.write_cpu(0x6000, 0x12);
+        assert_eq!(vrc7.read_cpu(0x6000), 0x12);
+    }
+
+    #[test]
+    fn irq() {
+        let mut vrc7 = new_vrc7(2);
+        vrc7.write_cpu(0xE010, 0xFF);
+        vrc7.write_cpu(0xF000, 0x06);
+        vrc7.notify_cpu_cycle(1);
+        assert!(vrc7.irq());
+        vrc7.write_cpu(0xF010, 0x00);
+        assert!(!vrc7.irq());
+    }
+
+    #[test]
+    fn audio() {
+        let mut vrc7 = new_vrc7(1);
+        for (addr, data) in [(0x30, 0x10), (0x10, 0x80), (0x20, 0x18)].iter() {
+            vrc7.write_cpu(0x9010, *addr);
+            vrc7.write_cpu(0x9030, *data);
+        }
+
+        vrc7.notify_cpu_cycle(36 * 2000);
+        let outputs: Vec<f32> = (0..100).map(|_| {
+            vrc7.notify_cpu_cycle(36);
+            vrc7.audio_output()
+        }).collect();
+        assert!(outputs.iter().any(|output| *output != 0.0));
+
+        // silenced by $E000 bit 6
+        vrc7.write_cpu(0xE000, 0x40);
+        assert_eq!(vrc7.audio_output(), 0.0);
+    }
+}
@@ -0,0 +1,151 @@
+/// The prescaler divides CPU cycles by 113.667 (341 / 3) to approximate scanlines.
+const PRESCALER_PERIOD: i16 = 341;
+const PRESCALER_STEP: i16 = 3;
+
+/// IRQ counter shared by the Konami VRC4, VRC6 and VRC7.
+///
+/// The 8 bit counter counts up from the latch value and asserts IRQ on overflow ($FF -> latch).
+/// It is clocked by CPU cycles (cycle mode), or by the prescaler every 341 / 3 CPU cycles (scanline mode).
+/// The counter is independent of the PPU, so it keeps running while rendering is off.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/VRC_IRQ
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    is_enabled: bool,
+    is_enabled_after_acknowledge: bool,
+    is_cycle_mode: bool,
+    is_pending: bool,
+}
+
+impl VrcIrq {
+    pub fn new() -> Self {
+        VrcIrq {
+            latch: 0,
+            counter: 0,
+            prescaler: PRESCALER_PERIOD,
+            is_enabled: false,
+            is_enabled_after_acknowledge: false,
+            is_cycle_mode: false,
+            is_pending: false,
+        }
+    }
+
+    pub fn write_latch(&mut self, data: u8) {
+        self.latch = data;
+    }
+
+    /// VRC4 writes the latch by nibbles.
+    pub fn write_latch_low(&mut self, data: u8) {
+        self.latch = (self.latch & 0xF0) | (data & 0x0F);
+    }
+
+    pub fn write_latch_high(&mut self, data: u8) {
+        self.latch = (self.latch & 0x0F) | (data << 4);
+    }
+
+    /// bit 0: enable after acknowledgement, bit 1: enable, bit 2: cycle mode
+    pub fn write_control(&mut self, data: u8) {
+        self.is_enabled_after_acknowledge = data & 0x01 == 0x01;
+        self.is_enabled = data & 0x02 == 0x02;
+        self.is_cycle_mode = data & 0x04 == 0x04;
+        self.is_pending = false;
+
+        if self.is_enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.is_pending = false;
+        self.is_enabled = self.is_enabled_after_acknowledge;
+    }
+
+    pub fn clock(&mut self, cpu_cycle: usize) {
+        if !self.is_enabled {
+            return;
+        }
+
+        for _ in 0..cpu_cycle {
+            if self.is_cycle_mode {
+                self.clock_counter();
+            } else {
+                self.prescaler -= PRESCALER_STEP;
+                if self.prescaler <= 0 {
+                    self.prescaler += PRESCALER_PERIOD;
+                    self.clock_counter();
+                }
+            }
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.is_pending
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.is_pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod vrc_irq_test {
+    use super::*;
+
+    #[test]
+    fn cycle_mode() {
+        let mut irq = VrcIrq::new();
+        irq.write_latch(0xFD);
+        irq.write_control(0x06);
+
+        irq.clock(2);
+        assert!(!irq.is_pending());
+        irq.clock(1);
+        assert!(irq.is_pending());
+
+        // reloaded from the latch
+        assert_eq!(irq.counter, 0xFD);
+    }
+
+    #[test]
+    fn scanline_mode() {
+        let mut irq = VrcIrq::new();
+        irq.write_latch(0xFF);
+        irq.write_control(0x02);
+
+        // 113.667 CPU cycles per scanline
+        irq.clock(113);
+        assert!(!irq.is_pending());
+        irq.clock(1);
+        assert!(irq.is_pending());
+    }
+
+    #[test]
+    fn acknowledge() {
+        let mut irq = VrcIrq::new();
+        irq.write_latch_low(0x0F);
+        irq.write_latch_high(0x0F);
+        assert_eq!(irq.latch, 0xFF);
+
+        irq.write_control(0x07);
+        irq.clock(1);
+        assert!(irq.is_pending());
+
+        // enabled again by the A flag
+        irq.acknowledge();
+        assert!(!irq.is_pending());
+        irq.clock(1);
+        assert!(irq.is_pending());
+
+        irq.write_control(0x04);
+        irq.clock(256);
+        assert!(!irq.is_pending());
+    }
+}
@@ -1,26 +1,66 @@
-use std::fs::File;
+use std::fmt;
+use std::fs::{self, File};
 use std::io;
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 
 pub mod header;
+pub mod info;
+pub mod mapper;
+pub mod save_file;
 
 use super::cassette::header::INesHeader;
+use super::cassette::mapper::Mapper;
+use super::cassette::mapper::fds::Fds;
+use super::cassette::mapper::fds::disk::{self, Disk};
+use super::cassette::save_file::SaveFile;
 
-#[derive(Debug, PartialEq)]
 pub struct Cassette {
-    pub header: INesHeader,
-    pub program_rom: Vec<u8>,
-    pub character_rom: Vec<u8>,
+    pub mapper: Box<dyn Mapper>,
+    /// Exists when the header has the battery flag.
+    save_file: Option<SaveFile>,
 }
 
 impl Cassette {
-    const HEADER_SIZE: usize = 0x0010;         // 16 byte
-    const PROGRAM_UNIT_SIZE: usize = 0x4000;   // 16384 byte
-    const CHARACTER_UNIT_SIZE: usize = 0x2000; // 8192 byte
+    const HEADER_SIZE: usize = 0x0010;           // 16 byte
+    const PROGRAM_UNIT_SIZE: usize = 0x4000;     // 16384 byte
+    const CHARACTER_UNIT_SIZE: usize = 0x2000;   // 8192 byte
+    const DISK_SYSTEM_BIOS_SIZE: usize = 0x2000; // 8192 byte
+    const DISK_SYSTEM_BIOS_NAME: &'static str = "disksys.rom";
 
     pub fn new(path: &str) -> Result<Self, CassetteInitializeError> {
         let rom_bytes = Self::load_rom_bytes(path)?;
-        let header = INesHeader::new(&rom_bytes)?;
+        let (header, mut mapper) = if disk::is_disk_image(&rom_bytes) {
+            Self::build_disk_system(path, &rom_bytes)?
+        } else {
+            Self::build_cartridge(&rom_bytes)?
+        };
+
+        let save_file = if header.battery {
+            let mut save_file = SaveFile::new(path);
+            if let Some(data) = save_file.load()? {
+                match mapper.battery_backed_ram() {
+                    Some(ram) if ram.len() != data.len() => eprintln!(
+                        "ignored the save file: {} bytes for the battery backed RAM of {} bytes.",
+                        data.len(),
+                        ram.len(),
+                    ),
+                    _ => mapper.restore_battery_backed_ram(&data),
+                }
+            }
+            Some(save_file)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            mapper,
+            save_file,
+        })
+    }
+
+    fn build_cartridge(rom_bytes: &[u8]) -> Result<(INesHeader, Box<dyn Mapper>), CassetteInitializeError> {
+        let header = INesHeader::new(rom_bytes)?;
 
         // <iNES file format>
         // Header (16 bytes)
@@ -30,14 +70,52 @@ impl Cassette {
         //
         // refer: https://wiki.nesdev.com/w/index.php/INES
 
-        let program_rom = Self::split_program_rom(&header, &rom_bytes);
-        let character_rom = Self::split_character_rom(&header, &rom_bytes);
+        let program_rom = Self::split_program_rom(&header, rom_bytes);
+        let character_rom = Self::split_character_rom(&header, rom_bytes);
+        let mapper = mapper::build(&header, program_rom, character_rom)?;
 
-        Ok(Self {
-            header: header,
-            program_rom: program_rom,
-            character_rom: character_rom,
-        })
+        Ok((header, mapper))
+    }
+
+    /// The disk image (.fds, with or without the fwNES header) runs on the BIOS of the disk system,
+    /// "disksys.rom" next to the image or in the working directory.
+    fn build_disk_system(path: &str, image: &[u8]) -> Result<(INesHeader, Box<dyn Mapper>), CassetteInitializeError> {
+        let disk = Disk::new(image)?;
+        let bios = Self::load_disk_system_bios(path)?;
+
+        Ok((INesHeader::disk_system(), Box::new(Fds::new(bios, disk))))
+    }
+
+    fn load_disk_system_bios(path: &str) -> Result<Vec<u8>, CassetteInitializeError> {
+        let candidates = [
+            Path::new(path).with_file_name(Self::DISK_SYSTEM_BIOS_NAME),
+            PathBuf::from(Self::DISK_SYSTEM_BIOS_NAME),
+        ];
+
+        for bios_path in candidates.iter() {
+            match fs::read(bios_path) {
+                Ok(bios) if bios.len() == Self::DISK_SYSTEM_BIOS_SIZE => return Ok(bios),
+                Ok(_) => return Err(CassetteInitializeError::FormatError),
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Err(CassetteInitializeError::MissingDiskSystemBios)
+    }
+
+    /// Writes the battery backed RAM to the save file, when it has changed since the last write.
+    pub fn flush_save(&mut self) -> io::Result<()> {
+        match (self.save_file.as_mut(), self.mapper.battery_backed_ram()) {
+            (Some(save_file), Some(data)) => save_file.write(data),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads only the header, for the ROM information of unsupported mappers too.
+    pub fn read_header(path: &str) -> Result<INesHeader, CassetteInitializeError> {
+        let rom_bytes = Self::load_rom_bytes(path)?;
+        INesHeader::new(&rom_bytes)
     }
 
     fn load_rom_bytes(path: &str) -> Result<Vec<u8>, CassetteInitializeError> {
@@ -48,14 +126,14 @@ impl Cassette {
         Ok(buffer)
     }
 
-    fn split_program_rom(header: &INesHeader, buf: &Vec<u8>) -> Vec<u8> {
+    fn split_program_rom(header: &INesHeader, buf: &[u8]) -> Vec<u8> {
         let pos_from: usize = Self::HEADER_SIZE;
         let pos_to: usize = pos_from + header.prg_size as usize * Self::PROGRAM_UNIT_SIZE;
 
         buf[pos_from..pos_to].to_vec()
     }
 
-    fn split_character_rom(header: &INesHeader, buf: &Vec<u8>) -> Vec<u8> {
+    fn split_character_rom(header: &INesHeader, buf: &[u8]) -> Vec<u8> {
         let pos_from: usize = Self::HEADER_SIZE + header.prg_size as usize * Self::PROGRAM_UNIT_SIZE;
         let pos_to: usize = pos_from + header.chr_size as usize * Self::CHARACTER_UNIT_SIZE;
 
@@ -63,32 +141,26 @@ impl Cassette {
     }
 }
 
-pub trait NesCassette {
-    fn read_program_rom(&self, addr: u16) -> u8;
-    fn read_character_rom(&self, addr: u16) -> u8;
-    fn program_rom_length(&self) -> usize;
-}
-
-impl NesCassette for Cassette {
-    fn read_program_rom(&self, addr: u16) -> u8 {
-        self.program_rom[addr as usize]
-    }
-
-    fn read_character_rom(&self, addr: u16) -> u8 {
-        self.character_rom[addr as usize]
-    }
-
-    fn program_rom_length(&self) -> usize {
-        self.program_rom.len()
-    }
-}
-
-
 #[derive(Debug)]
 pub enum CassetteInitializeError {
     IoError(io::Error),
     /// Rom haven't magic number
     FormatError,
+    /// Mapper number written in the header is not implemented
+    UnsupportedMapper(u8),
+    /// Disk image is given without the BIOS of the disk system (disksys.rom)
+    MissingDiskSystemBios,
+}
+
+impl fmt::Display for CassetteInitializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CassetteInitializeError::IoError(err) => write!(f, "failed to read the rom: {}", err),
+            CassetteInitializeError::FormatError => write!(f, "the rom is not in the iNES format."),
+            CassetteInitializeError::UnsupportedMapper(number) => write!(f, "mapper {} is not supported.", number),
+            CassetteInitializeError::MissingDiskSystemBios => write!(f, "disksys.rom is not found next to the disk image."),
+        }
+    }
 }
 
 impl From<io::Error> for CassetteInitializeError {
@@ -97,6 +169,17 @@ impl From<io::Error> for CassetteInitializeError {
     }
 }
 
+/// Writes the rom to a directory of the test, and returns the path of the rom.
+/// The directory is unique to the name and the process, the test removes it at the end.
+#[cfg(test)]
+pub(crate) fn write_temporary_rom(name: &str, file_name: &str, rom_bytes: &[u8]) -> String {
+    let dir = std::env::temp_dir().join(format!("nes-emulator-rust-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(file_name);
+    std::fs::write(&path, rom_bytes).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
 #[cfg(test)]
 mod cassette_test {
     use super::*;
@@ -105,24 +188,74 @@ mod cassette_test {
     fn new_success() {
         let path = "rom/hello_world.nes";
         let cassette = Cassette::new(path);
-        assert!(
-            match cassette {
-                Ok(_) => true,
-                _ => false,
-            }
-        );
+        assert!(cassette.is_ok());
     }
 
     #[test]
     fn test_faild_wrong_path() {
         let path = "rom/hello_world_wrong.nes";
         let cassette = Cassette::new(path);
-        assert!(
-            match cassette {
-                Err(CassetteInitializeError::IoError(_)) => true,
-                _ => false,
-            }
-        );
+        assert!(matches!(cassette, Err(CassetteInitializeError::IoError(_))));
+    }
+
+    #[test]
+    fn test_faild_unsupported_mapper() {
+        let header = INesHeader::new(&[
+            "NES\x1A".as_bytes().to_vec(),
+            vec![1, 1, 0xF0, 0xF0, 0x00],
+        ].concat()).unwrap();
+
+        let mapper = mapper::build(&header, vec![0; 0x4000], vec![0; 0x2000]);
+        assert!(matches!(mapper, Err(CassetteInitializeError::UnsupportedMapper(0xFF))));
+        assert_eq!(mapper.err().unwrap().to_string(), "mapper 255 is not supported.");
+    }
+
+    #[test]
+    fn battery_backed_ram_persistence() {
+        // NROM with the battery flag
+        let rom_bytes = [
+            "NES\x1A".as_bytes().to_vec(),
+            vec![1, 1, 0b00000010, 0, 0],
+            vec![0; 7],
+            vec![0; 0x4000 + 0x2000],
+        ].concat();
+        let path = write_temporary_rom("cassette", "battery.nes", &rom_bytes);
+        let dir = std::path::Path::new(&path).parent().unwrap();
+
+        let mut cassette = Cassette::new(&path).unwrap();
+        cassette.mapper.write_cpu(0x6000, 0x12);
+        cassette.flush_save().unwrap();
+        assert!(dir.join("battery.sav").exists());
+
+        let mut cassette = Cassette::new(&path).unwrap();
+        assert_eq!(cassette.mapper.read_cpu(0x6000), 0x12);
+
+        // the save file of another size is ignored
+        std::fs::write(dir.join("battery.sav"), vec![0x34; 0x100]).unwrap();
+        let mut cassette = Cassette::new(&path).unwrap();
+        assert_eq!(cassette.mapper.read_cpu(0x6000), 0x00);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn disk_system_image() {
+        let mut side = vec![0; 65500];
+        side[0] = 1;
+        side[1..15].copy_from_slice(b"*NINTENDO-HVC*");
+        let path = write_temporary_rom("disk", "game.fds", &side);
+        let dir = Path::new(&path).parent().unwrap();
+
+        assert!(matches!(Cassette::new(&path), Err(CassetteInitializeError::MissingDiskSystemBios)));
+
+        std::fs::write(dir.join("disksys.rom"), vec![0x12; 0x2000]).unwrap();
+        let mut cassette = Cassette::new(&path).unwrap();
+        assert_eq!(cassette.mapper.read_cpu(0xE000), 0x12);
+        // nothing is saved until the disk is written
+        cassette.flush_save().unwrap();
+        assert!(!dir.join("game.sav").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 
     #[test]
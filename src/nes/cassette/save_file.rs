@@ -0,0 +1,96 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Battery backed RAM persisted next to the rom file. (e.g. "game.nes" -> "game.sav")
+///
+/// The data is written to a temporary file first, then the file is renamed to the save file.
+/// The rename replaces the file atomically, so a crash while writing keeps the previous save.
+pub struct SaveFile {
+    path: PathBuf,
+    /// Contents of the save file, to skip writing the same data.
+    saved: Vec<u8>,
+}
+
+impl SaveFile {
+    pub fn new(rom_path: &str) -> Self {
+        SaveFile {
+            path: Path::new(rom_path).with_extension("sav"),
+            saved: Vec::new(),
+        }
+    }
+
+    /// Reads the save file. None: no save file yet.
+    pub fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => {
+                self.saved = data.clone();
+                Ok(Some(data))
+            },
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes the data, when it has changed since the last load or write.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if data == &self.saved[..] {
+            return Ok(());
+        }
+
+        let temporary_path = self.path.with_extension("sav.tmp");
+        {
+            let mut file = File::create(&temporary_path)?;
+            file.write_all(data)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temporary_path, &self.path)?;
+
+        self.saved = data.to_vec();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod save_file_test {
+    use super::*;
+    use crate::nes::cassette::write_temporary_rom;
+
+    #[test]
+    fn save_path_next_to_rom() {
+        let save_file = SaveFile::new("rom/game.nes");
+        assert_eq!(save_file.path, PathBuf::from("rom/game.sav"));
+    }
+
+    #[test]
+    fn write_and_load() {
+        let rom_path = write_temporary_rom("write_and_load", "game.nes", &[]);
+        let mut save_file = SaveFile::new(&rom_path);
+        assert_eq!(save_file.load().unwrap(), None);
+
+        save_file.write(&[0x12, 0x34]).unwrap();
+        assert!(!save_file.path.with_extension("sav.tmp").exists());
+
+        let mut save_file = SaveFile::new(&rom_path);
+        assert_eq!(save_file.load().unwrap(), Some(vec![0x12, 0x34]));
+
+        fs::remove_dir_all(Path::new(&rom_path).parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn skip_writing_same_data() {
+        let rom_path = write_temporary_rom("skip_writing_same_data", "game.nes", &[]);
+        let mut save_file = SaveFile::new(&rom_path);
+        save_file.write(&[0x12]).unwrap();
+
+        // the same data doesn't touch the file
+        fs::remove_file(&save_file.path).unwrap();
+        save_file.write(&[0x12]).unwrap();
+        assert!(!save_file.path.exists());
+
+        save_file.write(&[0x34]).unwrap();
+        assert!(save_file.path.exists());
+
+        fs::remove_dir_all(Path::new(&rom_path).parent().unwrap()).unwrap();
+    }
+}
@@ -1,11 +1,12 @@
-use crate::nes::cassette::NesCassette;
+use crate::nes::cassette::mapper::Mapper;
 use crate::nes::ppu::Ppu;
 use crate::nes::ram::Ram;
 
-pub struct Bus<'a, T: 'a> where T: NesCassette {
-    cassette: &'a T,
+pub struct Bus<'a, T: 'a + ?Sized> where T: Mapper {
+    mapper: &'a mut T,
     ppu: &'a mut Ppu,
     wram: &'a mut Ram,
+    is_oam_dma_executed: bool,
 }
 
 pub trait CpuBus {
@@ -14,31 +15,55 @@ pub trait CpuBus {
     fn write(&mut self, addr: u16, data: u8);
 }
 
-impl <'a, T: 'a> Bus<'a, T> where T: NesCassette {
-    pub fn new(cassette: &'a T, ppu: &'a mut Ppu, wram: &'a mut Ram) -> Self {
+impl <'a, T: 'a + ?Sized> Bus<'a, T> where T: Mapper {
+    pub fn new(mapper: &'a mut T, ppu: &'a mut Ppu, wram: &'a mut Ram) -> Self {
         Self {
-            cassette: cassette,
-            ppu: ppu,
-            wram: wram,
+            mapper,
+            ppu,
+            wram,
+            is_oam_dma_executed: false,
         }
     }
+
+    /// CPU cycles stalled by the OAM DMA in the last instruction. (0: no DMA)
+    ///
+    /// The DMA takes 1 wait cycle, +1 on an odd CPU cycle, then 256 read / write pairs.
+    /// cpu_cycle: CPU cycle count at the end of the instruction
+    ///
+    /// refer: https://wiki.nesdev.com/w/index.php/PPU_registers#OAMDMA
+    pub fn oam_dma_cycles(&self, cpu_cycle: usize) -> usize {
+        if !self.is_oam_dma_executed {
+            return 0;
+        }
+
+        513 + (cpu_cycle & 0x01)
+    }
+
+    /// Copies $XX00-$XXFF to OAM through OAMDATA, from the current OAMADDR.
+    /// The copy is done at once, the CPU stall is added by the caller.
+    fn oam_dma(&mut self, page: u8) {
+        let start_addr = (page as u16) << 8;
+        for offset in 0..0x0100 {
+            let data = self.read(start_addr + offset);
+            self.ppu.write(0x0004, data, self.mapper);
+        }
+        self.is_oam_dma_executed = true;
+    }
+
+    /// IRQ line state. true: an IRQ source is asserting.
+    pub fn irq(&self) -> bool {
+        self.mapper.irq()
+    }
 }
 
-impl <'a, T: 'a> CpuBus for Bus<'a, T> where T: NesCassette {
+impl <'a, T: 'a + ?Sized> CpuBus for Bus<'a, T> where T: Mapper {
     fn read(&mut self, addr: u16) -> u8 {
         match addr {
             0x0000..=0x07FF => self.wram.read(addr),
             0x0800..=0x1FFF => self.wram.read(addr - 0x0800),
-            0x2000..=0x3FFF => self.ppu.read(addr - 0x2000),
-            // 0x4000..0x401F => unimplemented!(), // APU I/O Keypad
-            // 0x4020..0x5FFF => unimplemented!(), // Expantion Rom
-            // 0x6000..0x7FFF => unimplemented!(), // Expantion Ram
-            0x8000..=0xBFFF => self.cassette.read_program_rom(addr - 0x8000),
-            0xC000..=0xFFFF if self.cassette.program_rom_length() <= 0x4000 => {
-                self.cassette.read_program_rom(addr - 0xC000)
-            },
-            0xC000..=0xFFFF => self.cassette.read_program_rom(addr - 0x8000),
-            _ => panic!("unexpected memory area access!"),
+            0x2000..=0x3FFF => self.ppu.read(addr - 0x2000, self.mapper),
+            0x4000..=0x401F => 0, // APU I/O Keypad (not emulated yet)
+            0x4020..=0xFFFF => self.mapper.read_cpu(addr), // Cassette (Expantion Rom / Ram, Program Rom)
         }
     }
 
@@ -53,11 +78,13 @@ impl <'a, T: 'a> CpuBus for Bus<'a, T> where T: NesCassette {
         match addr {
             0x0000..=0x07FF => self.wram.write(addr, data),
             0x0800..=0x1FFF => self.wram.write(addr - 0x0800, data),
-            0x2000..=0x3FFF => self.ppu.write(addr - 0x2000, data),
-            // 0x4000..0x401F => unimplemented!(), // APU I/O Keypad
-            // 0x4020..0x5FFF => unimplemented!(), // Expantion Rom
-            // 0x6000..0x7FFF => unimplemented!(), // Expantion Ram
-            _ => panic!("unexpected memory area access! Addr: {:x}", addr),
+            0x2000..=0x3FFF => {
+                self.mapper.notify_ppu_register_write(addr, data);
+                self.ppu.write(addr - 0x2000, data, self.mapper);
+            },
+            0x4014 => self.oam_dma(data),
+            0x4000..=0x401F => {}, // APU I/O Keypad (not emulated yet)
+            0x4020..=0xFFFF => self.mapper.write_cpu(addr, data), // Cassette (Expantion Rom / Ram, Mapper registers)
         }
     }
 }
@@ -66,43 +93,55 @@ impl <'a, T: 'a> CpuBus for Bus<'a, T> where T: NesCassette {
 mod cpu_bus_test {
     use super::*;
 
-    struct CassetteMock {
+    use crate::nes::cassette::mapper::Mirroring;
+
+    struct MapperMock {
         program_rom: Vec<u8>,
         character_rom: Vec<u8>,
+        written: Vec<(u16, u8)>,
     }
 
-    impl CassetteMock {
+    impl MapperMock {
         fn new() -> Self {
-            CassetteMock {
-                program_rom: vec![0; 0x5000],
-                character_rom: vec![0; 0x5000],
+            MapperMock {
+                program_rom: vec![0; 0x8000],
+                character_rom: vec![0; 0x2000],
+                written: Vec::new(),
             }
         }
     }
 
-    impl NesCassette for CassetteMock {
-        fn read_program_rom(&self, addr: u16) -> u8 {
-            self.program_rom[addr as usize]
+    impl Mapper for MapperMock {
+        fn read_cpu(&mut self, addr: u16) -> u8 {
+            self.program_rom[(addr - 0x8000) as usize]
+        }
+
+        fn write_cpu(&mut self, addr: u16, data: u8) {
+            self.written.push((addr, data));
         }
 
-        fn read_character_rom(&self, addr: u16) -> u8 {
+        fn read_ppu(&mut self, addr: u16) -> u8 {
             self.character_rom[addr as usize]
         }
 
-        fn program_rom_length(&self) -> usize {
-            self.program_rom.len()
+        fn write_ppu(&mut self, addr: u16, data: u8) {
+            self.character_rom[addr as usize] = data;
+        }
+
+        fn mirroring(&self) -> Mirroring {
+            Mirroring::Horizontal
         }
     }
 
     #[test]
     fn read_from_wram_address() {
-        let cassette = CassetteMock::new();
-        let mut ppu = Ppu::new(cassette.character_rom.clone());
+        let mut mapper = MapperMock::new();
+        let mut ppu = Ppu::new();
         let mut ram = Ram::new(vec![0; 2048]);
         ram.write(0x0002, 0x4F);
 
         let mut cpu_bus = Bus::new(
-            &cassette,
+            &mut mapper,
             &mut ppu,
             &mut ram,
         );
@@ -112,13 +151,13 @@ mod cpu_bus_test {
 
     #[test]
     fn read_from_program_rom_address() {
-        let mut cassette = CassetteMock::new();
-        let mut ppu = Ppu::new(cassette.character_rom.clone());
+        let mut mapper = MapperMock::new();
+        let mut ppu = Ppu::new();
         let mut ram = Ram::new(vec![0; 2048]);
-        cassette.program_rom[0x0000] = 0x78;
+        mapper.program_rom[0x0000] = 0x78;
 
         let mut cpu_bus = Bus::new(
-            &cassette,
+            &mut mapper,
             &mut ppu,
             &mut ram,
         );
@@ -128,11 +167,11 @@ mod cpu_bus_test {
 
     #[test]
     fn write_wram_address() {
-        let cassette = CassetteMock::new();
-        let mut ppu = Ppu::new(cassette.character_rom.clone());
+        let mut mapper = MapperMock::new();
+        let mut ppu = Ppu::new();
         let mut ram = Ram::new(vec![0; 2048]);
         let mut cpu_bus = Bus::new(
-            &cassette,
+            &mut mapper,
             &mut ppu,
             &mut ram,
         );
@@ -143,14 +182,14 @@ mod cpu_bus_test {
 
     #[test]
     fn read_twice_test() {
-        let mut cassette = CassetteMock::new();
-        let mut ppu = Ppu::new(cassette.character_rom.clone());
+        let mut mapper = MapperMock::new();
+        let mut ppu = Ppu::new();
         let mut ram = Ram::new(vec![0; 2048]);
-        cassette.program_rom[0x0000] = 0x78;
-        cassette.program_rom[0x0001] = 0x56;
+        mapper.program_rom[0x0000] = 0x78;
+        mapper.program_rom[0x0001] = 0x56;
 
         let mut cpu_bus = Bus::new(
-            &cassette,
+            &mut mapper,
             &mut ppu,
             &mut ram,
         );
@@ -159,20 +198,49 @@ mod cpu_bus_test {
     }
 
     #[test]
-    fn mirror_first_program_rom_16k() {
-        let mut cassette = CassetteMock::new();
-        let mut ppu = Ppu::new(cassette.character_rom.clone());
+    fn write_mapper_address() {
+        let mut mapper = MapperMock::new();
+        let mut ppu = Ppu::new();
         let mut ram = Ram::new(vec![0; 2048]);
-        cassette.program_rom = vec![0; 0x4000];
-        cassette.program_rom[0x0000] = 0xFF;
+
+        {
+            let mut cpu_bus = Bus::new(
+                &mut mapper,
+                &mut ppu,
+                &mut ram,
+            );
+
+            cpu_bus.write(0x8000, 0x4F);
+            cpu_bus.write(0xFFFF, 0x50);
+        }
+
+        assert_eq!(mapper.written, vec![(0x8000, 0x4F), (0xFFFF, 0x50)]);
+    }
+
+    #[test]
+    fn oam_dma_test() {
+        let mut mapper = MapperMock::new();
+        let mut ppu = Ppu::new();
+        let mut ram = Ram::new(vec![0; 2048]);
+        for offset in 0..0x0100 {
+            ram.write(0x0200 + offset, offset as u8);
+        }
 
         let mut cpu_bus = Bus::new(
-            &cassette,
+            &mut mapper,
             &mut ppu,
             &mut ram,
         );
+        assert_eq!(cpu_bus.oam_dma_cycles(0), 0);
+
+        // from OAMADDR, wraps around
+        cpu_bus.write(0x2003, 0x10);
+        cpu_bus.write(0x4014, 0x02);
+        assert_eq!(cpu_bus.oam_dma_cycles(100), 513);
+        assert_eq!(cpu_bus.oam_dma_cycles(101), 514);
 
-        assert_eq!(cpu_bus.read(0x8000), 0xFF);
-        assert_eq!(cpu_bus.read(0xC000), 0xFF);
+        assert_eq!(ppu.context.sprite_ram.read(0x10), 0x00);
+        assert_eq!(ppu.context.sprite_ram.read(0xFF), 0xEF);
+        assert_eq!(ppu.context.sprite_ram.read(0x00), 0xF0);
     }
 }
\ No newline at end of file
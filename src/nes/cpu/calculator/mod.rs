@@ -2,16 +2,18 @@ use crate::nes::cpu::bus::CpuBus;
 use crate::nes::cpu::registers::Registers;
 use crate::nes::cpu::controller::Controller;
 use crate::nes::cpu::opecode::{Command, OPECODE_MAP, AddressingMode};
+use crate::nes::cpu::CpuError;
 
 pub struct Calculator;
 
 impl Calculator {
-    pub fn execute<T: CpuBus>(registers: &mut Registers, bus: &mut T) -> usize {
+    pub fn execute<T: CpuBus>(registers: &mut Registers, bus: &mut T) -> Result<usize, CpuError> {
+        let addr = registers.PC;
         let run_opecode = Controller::fetch(registers, bus);
-        let opecode_rule = OPECODE_MAP.get(&run_opecode).unwrap();
+        let opecode_rule = OPECODE_MAP.get(&run_opecode).ok_or(CpuError::UnimplementedOpecode(run_opecode, addr))?;
 
         let (command, mode, cycle) = (&opecode_rule.command, &opecode_rule.mode, opecode_rule.cycle);
-        let opeland = Controller::fetch_opeland(registers, bus, &mode);
+        let opeland = Controller::fetch_opeland(registers, bus, mode);
 
         match *command {
             Command::LDA if *mode == AddressingMode::Immediate => Calculator::LDA_immediate(registers, opeland),
@@ -28,16 +30,18 @@ impl Calculator {
             Command::INX => Calculator::INX(registers),
             Command::JMP => Calculator::JMP(registers, opeland),
             Command::JSR => Calculator::JSR(registers, bus, opeland),
+            Command::RTS => Calculator::RTS(registers, bus),
+            Command::RTI => Calculator::RTI(registers, bus),
             Command::SEI => Calculator::SEI(registers),
             Command::TXS => Calculator::TXS(registers),
             Command::TYA => Calculator::TYA(registers),
             Command::CLD => Calculator::CLD(registers),
             Command::CPX => Calculator::CPX(registers, bus, opeland),
             Command::BPL => Calculator::BPL(registers, opeland),
-            _ => panic!("not unimplement command: {:?}", &command),
+            _ => return Err(CpuError::UnimplementedOpecode(run_opecode, addr)),
         };
 
-        cycle
+        Ok(cycle)
     }
 
     fn LDA<T: CpuBus>(registers: &mut Registers, bus: &mut T, opeland: u16) {
@@ -117,6 +121,28 @@ impl Calculator {
         registers.PC = opeland;
     }
 
+    fn RTS<T: CpuBus>(registers: &mut Registers, bus: &mut T) {
+        let lower = Controller::pop(registers, bus) as u16;
+        let upper = Controller::pop(registers, bus) as u16;
+        registers.PC = (upper << 8 | lower) + 1;
+    }
+
+    /// Returns from the interrupt handler. Pulls the status, then the return address.
+    /// The break and reserved flags are not in the register, they are kept as they are.
+    ///
+    /// refer: https://wiki.nesdev.com/w/index.php/Status_flags#The_B_flag
+    fn RTI<T: CpuBus>(registers: &mut Registers, bus: &mut T) {
+        let status = Controller::pop(registers, bus);
+        let (break_mode, reserved) = (registers.P.break_mode, registers.P.reserved);
+        registers.P.set_by_bit(status);
+        registers.P.break_mode = break_mode;
+        registers.P.reserved = reserved;
+
+        let lower = Controller::pop(registers, bus) as u16;
+        let upper = Controller::pop(registers, bus) as u16;
+        registers.PC = upper << 8 | lower;
+    }
+
     fn SEI(registers: &mut Registers) {
         registers.P.interrupt = true;
     }
@@ -146,9 +172,7 @@ impl Calculator {
     }
 
     fn push<T: CpuBus>(data: u8, registers: &mut Registers, bus: &mut T) {
-        let addr = registers.S as u16;
-        bus.write(addr | 0x0100, data);
-        registers.S -= 1;
+        Controller::push(data, registers, bus);
     }
 }
 
@@ -6,5 +6,5 @@ fn CLD_test() {
     registers.P.decimal = true;
 
     Calculator::CLD(&mut registers);
-    assert_eq!(registers.P.decimal, false);
+    assert!(!registers.P.decimal);
 }
\ No newline at end of file
@@ -14,7 +14,7 @@ fn CPX_test() {
         carry: bool,
     }
 
-    let patterns = vec![
+    let patterns = [
         PatternArgs { addr: 0x0010, x: 0x20, opeland: 0x30, negative: true,  zero: false, carry: false },
         PatternArgs { addr: 0x0010, x: 0x20, opeland: 0x20, negative: false, zero: true,  carry: true },
         PatternArgs { addr: 0x0010, x: 0x20, opeland: 0x10, negative: false, zero: false, carry: true  },
@@ -8,8 +8,8 @@ fn DEX_test() {
 
     Calculator::DEX(&mut registers);
     assert_eq!(registers.X, opeland);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, false);
+    assert!(!registers.P.negative);
+    assert!(!registers.P.zero);
 }
 
 #[test]
@@ -19,8 +19,8 @@ fn DEX_test_overflow() {
 
     Calculator::DEX(&mut registers);
     assert_eq!(registers.X, 0xFF); // 0 - decriment-> 255
-    assert_eq!(registers.P.negative, true);
-    assert_eq!(registers.P.zero, false);
+    assert!(registers.P.negative);
+    assert!(!registers.P.zero);
 }
 
 #[test]
@@ -31,8 +31,8 @@ fn DEX_update_zero_test() {
 
     Calculator::DEX(&mut registers);
     assert_eq!(registers.X, opeland);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, true);
+    assert!(!registers.P.negative);
+    assert!(registers.P.zero);
 }
 
 #[test]
@@ -43,6 +43,6 @@ fn DEX_update_negative_test() {
 
     Calculator::DEX(&mut registers);
     assert_eq!(registers.X, opeland);
-    assert_eq!(registers.P.negative, true);
-    assert_eq!(registers.P.zero, false);
+    assert!(registers.P.negative);
+    assert!(!registers.P.zero);
 }
\ No newline at end of file
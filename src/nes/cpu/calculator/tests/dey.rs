@@ -8,8 +8,8 @@ fn DEY_test() {
 
     Calculator::DEY(&mut registers);
     assert_eq!(registers.Y, opeland);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, false);
+    assert!(!registers.P.negative);
+    assert!(!registers.P.zero);
 }
 
 #[test]
@@ -20,8 +20,8 @@ fn DEY_update_zero_test() {
 
     Calculator::DEY(&mut registers);
     assert_eq!(registers.Y, opeland);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, true);
+    assert!(!registers.P.negative);
+    assert!(registers.P.zero);
 }
 
 #[test]
@@ -32,6 +32,6 @@ fn DEY_update_negative_test() {
 
     Calculator::DEY(&mut registers);
     assert_eq!(registers.Y, opeland);
-    assert_eq!(registers.P.negative, true);
-    assert_eq!(registers.P.zero, false);
+    assert!(registers.P.negative);
+    assert!(!registers.P.zero);
 }
\ No newline at end of file
@@ -8,8 +8,8 @@ fn INX_test() {
 
     Calculator::INX(&mut registers);
     assert_eq!(registers.X, opeland);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, false);
+    assert!(!registers.P.negative);
+    assert!(!registers.P.zero);
 }
 
 // INX is never set zero to registers.
@@ -23,8 +23,8 @@ fn INX_test() {
 
 //     Calculator::INX(&mut registers);
 //     assert_eq!(registers.X, opeland);
-//     assert_eq!(registers.P.negative, false);
-//     assert_eq!(registers.P.zero, true);
+//     assert!(!registers.P.negative);
+//     assert!(registers.P.zero);
 // }
 
 #[test]
@@ -35,6 +35,6 @@ fn INX_update_negative_test() {
 
     Calculator::INX(&mut registers);
     assert_eq!(registers.X, opeland);
-    assert_eq!(registers.P.negative, true);
-    assert_eq!(registers.P.zero, false);
+    assert!(registers.P.negative);
+    assert!(!registers.P.zero);
 }
\ No newline at end of file
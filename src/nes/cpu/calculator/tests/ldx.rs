@@ -11,8 +11,8 @@ fn LDX_test() {
 
     Calculator::LDX(&mut registers, &mut bus, addr);
     assert_eq!(registers.X, opeland);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, false);
+    assert!(!registers.P.negative);
+    assert!(!registers.P.zero);
 }
 
 #[test]
@@ -26,8 +26,8 @@ fn LDX_update_zero_test() {
 
     Calculator::LDX(&mut registers, &mut bus, addr);
     assert_eq!(registers.X, opeland);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, true);
+    assert!(!registers.P.negative);
+    assert!(registers.P.zero);
 }
 
 #[test]
@@ -41,8 +41,8 @@ fn LDX_update_negative_test() {
 
     Calculator::LDX(&mut registers, &mut bus, addr);
     assert_eq!(registers.X, opeland);
-    assert_eq!(registers.P.negative, true);
-    assert_eq!(registers.P.zero, false);
+    assert!(registers.P.negative);
+    assert!(!registers.P.zero);
 }
 
 #[test]
@@ -52,8 +52,8 @@ fn LDX_immediate_test() {
 
     Calculator::LDX_immediate(&mut registers, opeland);
     assert_eq!(registers.X, opeland as u8);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, false);
+    assert!(!registers.P.negative);
+    assert!(!registers.P.zero);
 }
 
 #[test]
@@ -63,8 +63,8 @@ fn LDX_immediate_update_zero_test() {
 
     Calculator::LDX_immediate(&mut registers, opeland);
     assert_eq!(registers.X, opeland as u8);
-    assert_eq!(registers.P.negative, false);
-    assert_eq!(registers.P.zero, true);
+    assert!(!registers.P.negative);
+    assert!(registers.P.zero);
 }
 
 #[test]
@@ -74,6 +74,6 @@ fn LDX_immediate_update_negative_test() {
 
     Calculator::LDX_immediate(&mut registers, opeland);
     assert_eq!(registers.X, opeland as u8);
-    assert_eq!(registers.P.negative, true);
-    assert_eq!(registers.P.zero, false);
+    assert!(registers.P.negative);
+    assert!(!registers.P.zero);
 }
@@ -43,6 +43,8 @@ mod txs;
 mod cld;
 mod bpl;
 mod jsr;
+mod rts;
+mod rti;
 mod tya;
 mod dex;
 mod cpx;
\ No newline at end of file
@@ -0,0 +1,24 @@
+use super::*;
+
+#[test]
+fn RTI_test() {
+    let mut registers = Registers::new();
+    let mut bus = BusMock::new();
+
+    // return address 0x1234, status: negative, carry, break (pushed by BRK / PHP)
+    registers.S = 0xFA;
+    bus.write(0x01FB, 0b10010001);
+    bus.write(0x01FC, 0x34);
+    bus.write(0x01FD, 0x12);
+
+    Calculator::RTI(&mut registers, &mut bus);
+
+    assert_eq!(registers.PC, 0x1234);
+    assert_eq!(registers.S, 0xFD);
+    assert!(registers.P.negative);
+    assert!(registers.P.carry);
+    assert!(!registers.P.interrupt);
+    // not changed by the pulled status
+    assert!(registers.P.break_mode);
+    assert!(registers.P.reserved);
+}
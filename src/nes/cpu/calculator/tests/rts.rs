@@ -0,0 +1,16 @@
+use super::*;
+
+#[test]
+fn RTS_test() {
+    let mut registers = Registers::new();
+    let mut bus = BusMock::new();
+
+    registers.PC = 0x02;
+    registers.S = 0x05;
+
+    Calculator::JSR(&mut registers, &mut bus, 0x10);
+    Calculator::RTS(&mut registers, &mut bus);
+
+    assert_eq!(registers.PC, 0x02);
+    assert_eq!(registers.S, 0x05);
+}
@@ -6,5 +6,5 @@ fn SEI_test() {
     registers.P.interrupt = false;
 
     Calculator::SEI(&mut registers);
-    assert_eq!(registers.P.interrupt, true);
+    assert!(registers.P.interrupt);
 }
\ No newline at end of file
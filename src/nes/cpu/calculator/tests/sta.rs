@@ -9,6 +9,6 @@ fn STA_test() {
     registers.A = 0x89;
     Calculator::STA(&registers, &mut bus, opeland);
 
-    let actual = bus.read(opeland as u16);
+    let actual = bus.read(opeland);
     assert_eq!(actual, registers.A);
 }
\ No newline at end of file
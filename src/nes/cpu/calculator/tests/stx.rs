@@ -9,6 +9,6 @@ fn STX_test() {
     registers.X = 0x89;
     Calculator::STX(&registers, &mut bus, opeland);
 
-    let actual = bus.read(opeland as u16);
+    let actual = bus.read(opeland);
     assert_eq!(actual, registers.X);
 }
\ No newline at end of file
@@ -10,6 +10,37 @@ impl Controller {
         registers.PC = bus.read_twice(0xFFFC);
     }
 
+    /// Returns the spent cycles. 0: the interrupt is masked by the I flag.
+    pub fn irq<T: CpuBus>(registers: &mut Registers, bus: &mut T) -> usize {
+        if registers.P.interrupt {
+            return 0;
+        }
+
+        let pc = registers.PC;
+        Controller::push((pc >> 8) as u8, registers, bus);
+        Controller::push(pc as u8, registers, bus);
+
+        // B flag is pushed as 0 by the hardware interrupt
+        let status = registers.P.to_bit() & !0x10;
+        Controller::push(status, registers, bus);
+
+        registers.P.interrupt = true;
+        registers.PC = bus.read_twice(0xFFFE);
+        7
+    }
+
+    pub fn push<T: CpuBus>(data: u8, registers: &mut Registers, bus: &mut T) {
+        let addr = registers.S as u16;
+        bus.write(addr | 0x0100, data);
+        registers.S = registers.S.wrapping_sub(1);
+    }
+
+    pub fn pop<T: CpuBus>(registers: &mut Registers, bus: &mut T) -> u8 {
+        registers.S = registers.S.wrapping_add(1);
+        let addr = registers.S as u16;
+        bus.read(addr | 0x0100)
+    }
+
     pub fn fetch<T: CpuBus>(registers: &mut Registers, bus: &mut T) -> u8 {
         let code = bus.read(registers.PC);
         registers.PC += 1;
@@ -105,7 +136,7 @@ mod controller_test {
 
     impl BusMock {
         fn new() -> Self {
-            Self { ram: vec![0; 0xFFFF] }
+            Self { ram: vec![0; 0x10000] }
         }
     }
 
@@ -166,6 +197,37 @@ mod controller_test {
         assert_eq!(registers, expect_registers);
     }
 
+    #[test]
+    fn irq_test() {
+        let mut bus = BusMock::new();
+        bus.write(0xFFFF, 0x56); // upper address
+        bus.write(0xFFFE, 0x78); // lower address
+
+        let mut registers = Registers::new();
+        registers.PC = 0x1234;
+        registers.P.interrupt = false;
+
+        assert_eq!(Controller::irq(&mut registers, &mut bus), 7);
+        assert_eq!(registers.PC, 0x5678);
+        assert_eq!(registers.S, 0xFD - 3);
+        assert!(registers.P.interrupt);
+        assert_eq!(bus.read(0x01FD), 0x12);
+        assert_eq!(bus.read(0x01FC), 0x34);
+        assert_eq!(bus.read(0x01FB), 0x20); // reserved: 1, break: 0, interrupt: 0
+    }
+
+    #[test]
+    fn irq_masked_test() {
+        let mut bus = BusMock::new();
+        let mut registers = Registers::new();
+        registers.PC = 0x1234;
+        registers.P.interrupt = true;
+
+        assert_eq!(Controller::irq(&mut registers, &mut bus), 0);
+        assert_eq!(registers.PC, 0x1234);
+        assert_eq!(registers.S, 0xFD);
+    }
+
     #[test]
     fn fetch_absolute_test() {
         let mut registers = Registers::new();
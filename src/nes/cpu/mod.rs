@@ -1,5 +1,7 @@
 #![allow(non_snake_case)]
 
+use std::fmt;
+
 pub mod bus;
 pub mod registers;
 
@@ -23,13 +25,34 @@ impl Cpu {
         Self { registers: Registers::new() }
     }
 
-    pub fn run<T: CpuBus>(&mut self, bus: &mut T) -> usize {
+    pub fn run<T: CpuBus>(&mut self, bus: &mut T) -> Result<usize, CpuError> {
         Calculator::execute(&mut self.registers, bus)
     }
 
     pub fn reset<T: CpuBus>(&mut self, bus: &mut T) {
         Controller::reset(&mut self.registers, bus);
     }
+
+    pub fn irq<T: CpuBus>(&mut self, bus: &mut T) -> usize {
+        Controller::irq(&mut self.registers, bus)
+    }
+}
+
+/// The CPU stops at the instruction it can't execute.
+#[derive(Debug, PartialEq)]
+pub enum CpuError {
+    /// Opcode not implemented yet, and its address
+    UnimplementedOpecode(u8, u16),
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::UnimplementedOpecode(opecode, addr) => {
+                write!(f, "opcode ${:02X} at ${:04X} is not implemented.", opecode, addr)
+            },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -42,7 +65,7 @@ mod cpu_test {
 
     impl BusMock {
         fn new() -> Self {
-            Self { ram: vec![0; 0xFFFF] }
+            Self { ram: vec![0; 0x10000] }
         }
     }
 
@@ -78,4 +101,38 @@ mod cpu_test {
         cpu.reset(&mut bus);
         assert_eq!(cpu.registers.PC, 0x8000);
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_irq_and_return() {
+        let mut cpu = Cpu::new();
+        let mut bus = BusMock::new();
+        bus.write(0xFFFE, 0x00);
+        bus.write(0xFFFF, 0x90);
+        bus.write(0x9000, 0x40); // RTI
+
+        cpu.registers.PC = 0x8123;
+        cpu.registers.P.interrupt = false;
+        cpu.registers.P.carry = true;
+
+        assert_eq!(cpu.irq(&mut bus), 7);
+        assert_eq!(cpu.registers.PC, 0x9000);
+        assert!(cpu.registers.P.interrupt);
+
+        cpu.run(&mut bus).unwrap();
+        assert_eq!(cpu.registers.PC, 0x8123);
+        assert_eq!(cpu.registers.S, 0xFD);
+        assert!(!cpu.registers.P.interrupt);
+        assert!(cpu.registers.P.carry);
+    }
+
+    #[test]
+    fn test_unimplemented_opecode() {
+        let mut cpu = Cpu::new();
+        let mut bus = BusMock::new();
+        bus.write(0x8000, 0x00); // BRK
+
+        cpu.registers.PC = 0x8000;
+        let err = cpu.run(&mut bus).unwrap_err();
+        assert_eq!(err, CpuError::UnimplementedOpecode(0x00, 0x8000));
+        assert_eq!(err.to_string(), "opcode $00 at $8000 is not implemented.");
+    }
+}
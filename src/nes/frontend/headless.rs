@@ -0,0 +1,73 @@
+use super::{Frontend, FrontendEvent};
+use crate::nes::ppu::frame_buffer::FrameBuffer;
+
+/// A frontend keeping the output in memory, for the tests and the tools without a window.
+///
+/// Only the last frame is kept, the long runs don't pile up the frames.
+pub struct HeadlessFrontend {
+    /// Completed frames so far
+    pub frame_count: usize,
+    last_frame: Option<FrameBuffer>,
+    pub samples: Vec<f32>,
+    /// Quits after the frames. None: runs until the emulator stops.
+    max_frames: Option<usize>,
+}
+
+impl HeadlessFrontend {
+    pub fn new(max_frames: Option<usize>) -> Self {
+        HeadlessFrontend {
+            frame_count: 0,
+            last_frame: None,
+            samples: Vec::new(),
+            max_frames,
+        }
+    }
+
+    pub fn last_frame(&self) -> Option<&FrameBuffer> {
+        self.last_frame.as_ref()
+    }
+}
+
+impl Frontend for HeadlessFrontend {
+    fn render_frame(&mut self, frame_buffer: &FrameBuffer) {
+        self.frame_count += 1;
+        self.last_frame = Some(frame_buffer.clone());
+    }
+
+    fn play_audio(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+    }
+
+    fn poll_events(&mut self) -> Vec<FrontendEvent> {
+        match self.max_frames {
+            Some(max_frames) if self.frame_count >= max_frames => vec![FrontendEvent::Quit],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod headless_test {
+    use super::*;
+
+    #[test]
+    fn render_frame_test() {
+        let mut frontend = HeadlessFrontend::new(Some(2));
+        let mut frame_buffer = FrameBuffer::new();
+
+        frame_buffer.set(1, 2, 0x21);
+        frontend.render_frame(&frame_buffer);
+        frontend.play_audio(&[0.5, 0.25]);
+        assert_eq!(frontend.poll_events(), vec![]);
+        assert_eq!(frontend.last_frame().unwrap().get(1, 2), 0x21);
+
+        frame_buffer.set(1, 2, 0x16);
+        frontend.render_frame(&frame_buffer);
+        frontend.play_audio(&[0.125]);
+        assert_eq!(frontend.poll_events(), vec![FrontendEvent::Quit]);
+
+        assert_eq!(frontend.frame_count, 2);
+        assert_eq!(frontend.last_frame().unwrap().get(1, 2), 0x16);
+        assert_eq!(frontend.samples, vec![0.5, 0.25, 0.125]);
+    }
+}
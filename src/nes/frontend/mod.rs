@@ -0,0 +1,27 @@
+pub mod headless;
+pub mod sdl;
+
+use crate::nes::ppu::frame_buffer::FrameBuffer;
+
+/// Requests from the user to the emulator.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FrontendEvent {
+    Quit,
+    /// The disk system: flip the disk / insert the next disk
+    SwitchDiskSide,
+}
+
+/// The output side of the emulator, which receives the completed frames and the audio.
+///
+/// The frame buffer is indexed (NES color + emphasis bits),
+/// the frontend converts it to its own pixel format.
+pub trait Frontend {
+    /// Called once per frame, when the PPU finished the frame.
+    fn render_frame(&mut self, frame_buffer: &FrameBuffer);
+
+    /// Audio samples of the frame at audio::SAMPLE_RATE.
+    fn play_audio(&mut self, samples: &[f32]);
+
+    /// Events since the last call.
+    fn poll_events(&mut self) -> Vec<FrontendEvent>;
+}
@@ -0,0 +1,62 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+
+use super::{Frontend, FrontendEvent};
+use crate::nes::audio::speaker::Speaker;
+use crate::nes::ppu::frame_buffer::FrameBuffer;
+use crate::nes::screen::Screen;
+use crate::nes::screen::config::ScreenConfig;
+
+/// A window with the sound, the frames are paced by the vsync.
+///
+/// F8: switch the disk side, F11: toggle the fullscreen
+pub struct SdlFrontend {
+    screen: Screen,
+    /// None: no audio device is available
+    speaker: Option<Speaker>,
+}
+
+impl SdlFrontend {
+    pub fn new(config: ScreenConfig) -> Self {
+        let sdl = sdl2::init().unwrap();
+        let screen = Screen::new(&sdl, config);
+        // keep running without sound when no audio device is available.
+        let speaker = Speaker::new(&sdl).ok();
+
+        SdlFrontend {
+            screen,
+            speaker,
+        }
+    }
+}
+
+impl Frontend for SdlFrontend {
+    fn render_frame(&mut self, frame_buffer: &FrameBuffer) {
+        self.screen.render(frame_buffer);
+    }
+
+    fn play_audio(&mut self, samples: &[f32]) {
+        if let Some(speaker) = self.speaker.as_mut() {
+            speaker.play(samples);
+        }
+    }
+
+    fn poll_events(&mut self) -> Vec<FrontendEvent> {
+        let mut events = Vec::new();
+        let mut is_fullscreen_toggled = false;
+        for event in self.screen.events.poll_iter() {
+            match event {
+                Event::Quit {..} => events.push(FrontendEvent::Quit),
+                Event::KeyDown { keycode: Some(Keycode::F8), repeat: false, .. } => events.push(FrontendEvent::SwitchDiskSide),
+                Event::KeyDown { keycode: Some(Keycode::F11), repeat: false, .. } => is_fullscreen_toggled = !is_fullscreen_toggled,
+                _ => {},
+            }
+        }
+
+        // the window is handled by the frontend itself
+        if is_fullscreen_toggled {
+            self.screen.toggle_fullscreen();
+        }
+        events
+    }
+}
@@ -1,84 +1,155 @@
+pub mod audio;
 pub mod cassette;
 pub mod cpu;
+pub mod frontend;
+pub mod nsf;
 pub mod ppu;
 pub mod ram;
 pub mod screen;
 
+use self::audio::Mixer;
 use self::cassette::Cassette;
+use self::frontend::{Frontend, FrontendEvent};
 use self::ppu::Ppu;
 use self::ppu::PpuRunResult;
 use self::ram::Ram;
-use self::cpu::{Cpu, Bus as CpuBus};
-use self::screen::Screen;
+use self::cpu::{Cpu, CpuError, Bus as CpuBus};
 
-use sdl2::event::Event;
 use console::Term;
-use time;
-
-const WIDTH: u32 = 256;
-const HEIGHT: u32 = 240;
 
 pub struct Nes {
     cpu: Cpu,
     ppu: Ppu,
     cassette: Cassette,
     ram: Ram,
+    /// CPU cycles since the power on
+    cpu_cycle: usize,
 }
 
 impl Nes {
     pub fn new(path: &str) -> Nes {
-        let cassette = Cassette::new(path).unwrap();
+        let cassette = Cassette::new(path).unwrap_or_else(|err| panic!("{}", err));
 
         let mut nes = Nes {
             cpu: Cpu::new(),
-            ppu: Ppu::new(cassette.character_rom.clone()),
-            cassette: cassette,
+            ppu: Ppu::new(),
+            cassette,
             ram: Ram::new(vec![0; 0x0800]),
+            cpu_cycle: 0,
         };
 
         {
-            let mut bus = CpuBus::new(&nes.cassette, &mut nes.ppu, &mut nes.ram);
+            let mut bus = CpuBus::new(&mut *nes.cassette.mapper, &mut nes.ppu, &mut nes.ram);
             nes.cpu.reset(&mut bus);
         }
 
         nes
     }
 
-    pub fn run(&mut self) {
-        let mut screen = Screen::new(WIDTH, HEIGHT);
+    /// Runs until the frontend quits, or the CPU stops at an instruction it can't execute.
+    pub fn run<F: Frontend>(&mut self, frontend: &mut F) -> Result<(), CpuError> {
+        let mut mixer = Mixer::new();
         let mut sec = time::get_time().sec;
         let mut frame = 0;
         let term = Term::stdout();
 
         'main: loop {
             let cycle = {
-                let mut bus = CpuBus::new(&self.cassette, &mut self.ppu, &mut self.ram);
-                self.cpu.run(&mut bus)
+                let mut bus = CpuBus::new(&mut *self.cassette.mapper, &mut self.ppu, &mut self.ram);
+                let mut cycle = match self.cpu.run(&mut bus) {
+                    Ok(cycle) => cycle,
+                    Err(err) => {
+                        self.flush_save();
+                        return Err(err);
+                    },
+                };
+                // the PPU and the mapper keep running while the CPU is stalled
+                cycle += bus.oam_dma_cycles(self.cpu_cycle + cycle);
+                if bus.irq() {
+                    cycle += self.cpu.irq(&mut bus);
+                }
+                cycle
             };
+            self.cpu_cycle += cycle;
+            self.cassette.mapper.notify_cpu_cycle(cycle);
+            mixer.run(cycle, self.cassette.mapper.audio_output());
 
-            let ppu_run_result = self.ppu.run(cycle * 3);
-            match ppu_run_result {
-                PpuRunResult::FinishedBuildAllBackgroundLine => {
-                    let background = &self.ppu.background;
-                    screen.render_background(&background);
-                    frame += 1;
-                },
-                _ => {},
-            };
+            let ppu_run_result = self.ppu.run(cycle * 3, &mut *self.cassette.mapper);
+            if let PpuRunResult::FinishedBuildAllBackgroundLine = ppu_run_result {
+                frontend.render_frame(&self.ppu.frame_buffer);
+                frontend.play_audio(&mixer.take_samples());
+                frame += 1;
+            }
 
-            for event in screen.events.poll_iter() {
+            for event in frontend.poll_events() {
                 match event {
-                    Event::Quit {..} => break 'main,
-                    _ => {}
+                    FrontendEvent::Quit => break 'main,
+                    FrontendEvent::SwitchDiskSide => self.cassette.mapper.switch_disk_side(),
                 }
             }
 
             if sec != time::get_time().sec {
-                term.clear_screen();
-                term.write_line(&format!("{} FPS", frame));
+                // the FPS is only informative, the emulator keeps running without the terminal
+                let _ = term.clear_screen();
+                let _ = term.write_line(&format!("{} FPS", frame));
                 frame = 0;
                 sec = time::get_time().sec;
+                self.flush_save();
             }
         }
+
+        self.flush_save();
+        Ok(())
+    }
+
+    /// Keeps running when the save file can't be written, the next flush retries it.
+    fn flush_save(&mut self) {
+        if let Err(err) = self.cassette.flush_save() {
+            eprintln!("failed to write the save file: {}", err);
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod nes_test {
+    use super::*;
+    use crate::nes::cassette::write_temporary_rom;
+    use crate::nes::frontend::headless::HeadlessFrontend;
+    use crate::nes::ppu::frame_buffer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+    #[test]
+    fn run_headless_test() {
+        // writes the backdrop color 0x21 to $3F00 with the rendering disabled, then loops
+        let program = [
+            0xA9, 0x3F, 0x8D, 0x06, 0x20, // LDA #$3F, STA $2006
+            0xA9, 0x00, 0x8D, 0x06, 0x20, // LDA #$00, STA $2006
+            0xA9, 0x21, 0x8D, 0x07, 0x20, // LDA #$21, STA $2007
+            0xA9, 0x00, 0x8D, 0x06, 0x20, // LDA #$00, STA $2006
+            0x8D, 0x06, 0x20,             // STA $2006
+            0x4C, 0x17, 0x80,             // JMP $8017
+        ];
+        let mut program_rom = vec![0; 0x4000];
+        program_rom[..program.len()].copy_from_slice(&program);
+        program_rom[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]); // reset vector
+        let rom_bytes = [
+            "NES\x1A".as_bytes().to_vec(),
+            vec![1, 1, 0, 0, 0],
+            vec![0; 7],
+            program_rom,
+            vec![0; 0x2000],
+        ].concat();
+        let path = write_temporary_rom("nes", "backdrop.nes", &rom_bytes);
+
+        let mut nes = Nes::new(&path);
+        let mut frontend = HeadlessFrontend::new(Some(3));
+        nes.run(&mut frontend).unwrap();
+
+        assert_eq!(frontend.frame_count, 3);
+        let frame = frontend.last_frame().unwrap();
+        assert_eq!(frame.pixels().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert!(frame.pixels().iter().all(|pixel| *pixel == 0x21));
+        assert!(!frontend.samples.is_empty());
+
+        std::fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,369 @@
+use crate::nes::cassette::mapper::{Mapper, Mirroring};
+use crate::nes::cassette::mapper::fds::audio::FdsAudio;
+use crate::nes::cassette::mapper::fme7::audio::Sunsoft5bAudio;
+use crate::nes::cassette::mapper::mmc5::audio::Mmc5Audio;
+use crate::nes::cassette::mapper::namco163::audio::Namco163Audio;
+use crate::nes::cassette::mapper::vrc6::audio::Vrc6Audio;
+use crate::nes::cassette::mapper::vrc7::audio::Vrc7Audio;
+
+use super::Nsf;
+
+const BANK_SIZE: usize = 0x1000;          // 4 KB
+const RAM_SIZE: usize = 0x2000;           // 8 KB
+const FDS_RAM_SIZE: usize = 0xA000;       // 40 KB
+const EXPANSION_RAM_SIZE: usize = 0x0400; // 1 KB
+
+/// Chip bits of Nsf::chips.
+const VRC6: u8 = 0x01;
+const VRC7: u8 = 0x02;
+const FDS: u8 = 0x04;
+const MMC5: u8 = 0x08;
+const NAMCO163: u8 = 0x10;
+const SUNSOFT5B: u8 = 0x20;
+
+/// The board of the NSF player: 4 KB bank switching and the expansion sound chips.
+///
+/// CPU $5FF8-$5FFF: banks of $8000-$FFFF (4 KB each, when bank switched)
+/// CPU $6000-$7FFF: 8 KB RAM
+/// CPU $8000-$FFFF: the data
+///
+/// With FDS, $6000-$FFFF is RAM, and the bank writes ($5FF6-$5FFF for $6000-$FFFF) copy the data into it.
+///
+/// Expansion chip registers:
+///   VRC6      : $9000-$9003, $A000-$A002, $B000-$B002
+///   VRC7      : $9010 (register select), $9030 (register write)
+///   FDS       : $4040-$4092
+///   MMC5      : $5000-$5015, $5205-$5206 (multiplier), $5C00-$5FF5 (ExRAM)
+///   Namco 163 : $4800 (data), $F800 (address)
+///   Sunsoft 5B: $C000 (register select), $E000 (register write)
+///
+/// The channels to mute are the chips, the output of a muted chip is not mixed.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/NSF
+pub struct NsfMapper {
+    program: Vec<u8>,
+    ram: Vec<u8>,
+    banks: [u8; 8],
+    chips: u8,
+    is_bankswitched: bool,
+    muted_chips: u8,
+    vrc6: Vrc6Audio,
+    vrc7: Vrc7Audio,
+    fds: FdsAudio,
+    mmc5: Mmc5Audio,
+    namco163: Namco163Audio,
+    sunsoft5b: Sunsoft5bAudio,
+    expansion_ram: Vec<u8>,
+    multiplicand: u8,
+    multiplier: u8,
+}
+
+impl NsfMapper {
+    pub fn new(nsf: &Nsf) -> Self {
+        let is_fds = nsf.chips & FDS == FDS;
+        let is_bankswitched = nsf.is_bankswitched();
+
+        // the data is placed in the banks from the load address
+        let padding = if is_bankswitched {
+            nsf.load_address as usize & 0x0FFF
+        } else {
+            (nsf.load_address as usize).saturating_sub(0x8000)
+        };
+        let mut program = [vec![0; padding], nsf.data.clone()].concat();
+        let bank_count = program.len().div_ceil(BANK_SIZE).max(1);
+        program.resize(bank_count * BANK_SIZE, 0);
+
+        let mut mapper = NsfMapper {
+            program,
+            ram: vec![0; if is_fds { FDS_RAM_SIZE } else { RAM_SIZE }],
+            banks: if is_bankswitched { nsf.bankswitch } else { [0, 1, 2, 3, 4, 5, 6, 7] },
+            chips: nsf.chips,
+            is_bankswitched,
+            muted_chips: 0,
+            vrc6: Vrc6Audio::new(),
+            vrc7: Vrc7Audio::new(),
+            fds: FdsAudio::new(),
+            mmc5: Mmc5Audio::new(),
+            namco163: Namco163Audio::new(),
+            sunsoft5b: Sunsoft5bAudio::new(),
+            expansion_ram: vec![0; EXPANSION_RAM_SIZE],
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+        };
+
+        if is_fds {
+            if is_bankswitched {
+                // $6000-$7FFF start with the banks of $E000-$FFFF
+                mapper.load_fds_bank(0, nsf.bankswitch[6]);
+                mapper.load_fds_bank(1, nsf.bankswitch[7]);
+                for slot in 0..8 {
+                    mapper.load_fds_bank(slot + 2, nsf.bankswitch[slot]);
+                }
+            } else {
+                let load_offset = (nsf.load_address as usize).saturating_sub(0x6000);
+                let length = nsf.data.len().min(FDS_RAM_SIZE.saturating_sub(load_offset));
+                mapper.ram[load_offset..load_offset + length].copy_from_slice(&nsf.data[..length]);
+            }
+        }
+
+        mapper
+    }
+
+    /// Power cycles the board for the next track, keeping the muted chips.
+    pub fn reset(&mut self, nsf: &Nsf) {
+        *self = NsfMapper {
+            muted_chips: self.muted_chips,
+            ..NsfMapper::new(nsf)
+        };
+    }
+
+    fn has_chip(&self, chip: u8) -> bool {
+        self.chips & chip == chip
+    }
+
+    /// Copies the 4 KB bank to the RAM slot of the FDS. (slot 0: $6000)
+    fn load_fds_bank(&mut self, slot: usize, bank: u8) {
+        let source = (bank as usize * BANK_SIZE) % self.program.len();
+        let destination = slot * BANK_SIZE;
+        self.ram[destination..destination + BANK_SIZE].copy_from_slice(&self.program[source..source + BANK_SIZE]);
+    }
+
+    fn write_bank(&mut self, addr: u16, data: u8) {
+        if !self.is_bankswitched {
+            return;
+        }
+
+        if self.has_chip(FDS) {
+            self.load_fds_bank(addr as usize - 0x5FF6, data);
+        } else if addr >= 0x5FF8 {
+            self.banks[addr as usize - 0x5FF8] = data;
+        }
+    }
+
+    fn read_program(&self, addr: u16) -> u8 {
+        let bank = self.banks[(addr as usize - 0x8000) / BANK_SIZE] as usize;
+        self.program[(bank * BANK_SIZE + (addr as usize & 0x0FFF)) % self.program.len()]
+    }
+
+    /// Names of the chips used by the rip, with the mute state.
+    pub fn channels(&self) -> Vec<(&'static str, bool)> {
+        super::CHIP_NAMES
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| self.has_chip(1 << bit))
+            .map(|(bit, name)| (*name, self.muted_chips & (1 << bit) != 0))
+            .collect()
+    }
+
+    /// Toggles the mute of the n-th chip in channels().
+    pub fn toggle_mute(&mut self, channel: usize) {
+        let chip = (0..8).map(|bit| 1 << bit).filter(|chip| self.has_chip(*chip)).nth(channel);
+        if let Some(chip) = chip {
+            self.muted_chips ^= chip;
+        }
+    }
+
+    fn is_audible(&self, chip: u8) -> bool {
+        self.has_chip(chip) && self.muted_chips & chip == 0
+    }
+}
+
+impl Mapper for NsfMapper {
+    fn read_cpu(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x4040..=0x4092 if self.has_chip(FDS) => self.fds.read(addr),
+            0x4800 if self.has_chip(NAMCO163) => self.namco163.read_data(),
+            0x5010 | 0x5015 if self.has_chip(MMC5) => self.mmc5.read(addr),
+            0x5205 if self.has_chip(MMC5) => (self.multiplicand as u16 * self.multiplier as u16) as u8,
+            0x5206 if self.has_chip(MMC5) => ((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8,
+            0x5C00..=0x5FF5 if self.has_chip(MMC5) => self.expansion_ram[addr as usize - 0x5C00],
+            0x6000..=0xFFFF if self.has_chip(FDS) => self.ram[addr as usize - 0x6000],
+            0x6000..=0x7FFF => self.ram[addr as usize - 0x6000],
+            0x8000..=0xFFFF => self.read_program(addr),
+            _ => 0,
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, data: u8) {
+        if self.has_chip(VRC6) {
+            if let 0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002 = addr {
+                self.vrc6.write(addr, data);
+            }
+        }
+        if self.has_chip(VRC7) {
+            match addr {
+                0x9010 => self.vrc7.select_register(data),
+                0x9030 => self.vrc7.write_register(data),
+                _ => {},
+            }
+        }
+        if self.has_chip(SUNSOFT5B) {
+            match addr {
+                0xC000 => self.sunsoft5b.select(data),
+                0xE000 => self.sunsoft5b.write(data),
+                _ => {},
+            }
+        }
+
+        match addr {
+            0x4040..=0x408A if self.has_chip(FDS) => self.fds.write(addr, data),
+            0x4800 if self.has_chip(NAMCO163) => self.namco163.write_data(data),
+            0xF800 if self.has_chip(NAMCO163) => self.namco163.write_address(data),
+            0x5000..=0x5015 if self.has_chip(MMC5) => self.mmc5.write(addr, data),
+            0x5205 if self.has_chip(MMC5) => self.multiplicand = data,
+            0x5206 if self.has_chip(MMC5) => self.multiplier = data,
+            0x5C00..=0x5FF5 if self.has_chip(MMC5) => self.expansion_ram[addr as usize - 0x5C00] = data,
+            0x5FF6..=0x5FFF => self.write_bank(addr, data),
+            0x6000..=0xFFFF if self.has_chip(FDS) => self.ram[addr as usize - 0x6000] = data,
+            0x6000..=0x7FFF => self.ram[addr as usize - 0x6000] = data,
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn write_ppu(&mut self, _addr: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Horizontal
+    }
+
+    fn audio_output(&self) -> f32 {
+        let outputs = [
+            (VRC6, self.vrc6.output()),
+            (VRC7, self.vrc7.output()),
+            (FDS, self.fds.output()),
+            (MMC5, self.mmc5.output()),
+            (NAMCO163, self.namco163.output()),
+            (SUNSOFT5B, self.sunsoft5b.output()),
+        ];
+
+        outputs
+            .iter()
+            .filter(|(chip, _)| self.is_audible(*chip))
+            .map(|(_, output)| output)
+            .sum()
+    }
+
+    fn notify_cpu_cycle(&mut self, cycle: usize) {
+        if self.has_chip(VRC6) {
+            self.vrc6.clock(cycle);
+        }
+        if self.has_chip(VRC7) {
+            self.vrc7.clock(cycle);
+        }
+        if self.has_chip(FDS) {
+            self.fds.clock(cycle);
+        }
+        if self.has_chip(MMC5) {
+            self.mmc5.clock(cycle);
+        }
+        if self.has_chip(NAMCO163) {
+            self.namco163.clock(cycle);
+        }
+        if self.has_chip(SUNSOFT5B) {
+            self.sunsoft5b.clock(cycle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod mapper_test {
+    use super::*;
+
+    fn new_nsf(load_address: u16, bankswitch: [u8; 8], chips: u8) -> Nsf {
+        Nsf {
+            title: String::new(),
+            artist: String::new(),
+            copyright: String::new(),
+            track_count: 1,
+            starting_track: 0,
+            load_address,
+            init_address: load_address,
+            play_address: load_address,
+            play_speed: 16666,
+            bankswitch,
+            chips,
+            track_labels: Vec::new(),
+            // the first byte of each 4 KB is the bank number
+            data: (0..0x10000).map(|offset| if offset % BANK_SIZE == 0 { (offset / BANK_SIZE) as u8 } else { 0xEA }).collect(),
+        }
+    }
+
+    #[test]
+    fn without_bankswitch() {
+        let mut mapper = NsfMapper::new(&new_nsf(0x8000, [0; 8], 0));
+        assert_eq!(mapper.read_cpu(0x8000), 0);
+        assert_eq!(mapper.read_cpu(0x9000), 1);
+        assert_eq!(mapper.read_cpu(0xF000), 7);
+        // the bank registers are ignored
+        mapper.write_cpu(0x5FF8, 5);
+        assert_eq!(mapper.read_cpu(0x8000), 0);
+
+        // the data loaded at $C000
+        let mut mapper = NsfMapper::new(&new_nsf(0xC000, [0; 8], 0));
+        assert_eq!(mapper.read_cpu(0xC000), 0);
+        assert_eq!(mapper.read_cpu(0x8000), 0x00);
+
+        mapper.write_cpu(0x6000, 0x12);
+        assert_eq!(mapper.read_cpu(0x6000), 0x12);
+    }
+
+    #[test]
+    fn bankswitch() {
+        let mut mapper = NsfMapper::new(&new_nsf(0x8100, [0, 1, 2, 3, 4, 5, 6, 7], 0));
+        // the data starts at $100 of the bank 0
+        assert_eq!(mapper.read_cpu(0x8100), 0);
+        assert_eq!(mapper.read_cpu(0x9100), 1);
+
+        mapper.write_cpu(0x5FF8, 10);
+        assert_eq!(mapper.read_cpu(0x8100), 10);
+        mapper.write_cpu(0x5FFF, 15);
+        assert_eq!(mapper.read_cpu(0xF100), 15);
+    }
+
+    #[test]
+    fn fds_ram() {
+        let mut mapper = NsfMapper::new(&new_nsf(0x8000, [0, 1, 2, 3, 4, 5, 6, 7], FDS));
+        assert_eq!(mapper.read_cpu(0x6000), 6);
+        assert_eq!(mapper.read_cpu(0x8000), 0);
+
+        // $8000-$DFFF is RAM, the banks are copied into it
+        mapper.write_cpu(0x8000, 0x12);
+        assert_eq!(mapper.read_cpu(0x8000), 0x12);
+        mapper.write_cpu(0x5FF8, 9);
+        assert_eq!(mapper.read_cpu(0x8000), 9);
+        mapper.write_cpu(0x5FF6, 3);
+        assert_eq!(mapper.read_cpu(0x6000), 3);
+    }
+
+    #[test]
+    fn mute_channels() {
+        let mut mapper = NsfMapper::new(&new_nsf(0x8000, [0; 8], VRC6 | MMC5));
+        assert_eq!(mapper.channels(), vec![("VRC6", false), ("MMC5", false)]);
+
+        // VRC6 pulse 1 in the digitized mode at the volume 8
+        mapper.write_cpu(0x9000, 0x88);
+        mapper.write_cpu(0x9002, 0x80);
+        mapper.notify_cpu_cycle(2);
+        assert!(mapper.audio_output() > 0.0);
+
+        mapper.toggle_mute(0);
+        assert_eq!(mapper.channels(), vec![("VRC6", true), ("MMC5", false)]);
+        assert_eq!(mapper.audio_output(), 0.0);
+    }
+
+    #[test]
+    fn mmc5_multiplier_and_expansion_ram() {
+        let mut mapper = NsfMapper::new(&new_nsf(0x8000, [0; 8], MMC5));
+        mapper.write_cpu(0x5205, 0x12);
+        mapper.write_cpu(0x5206, 0x34);
+        assert_eq!(mapper.read_cpu(0x5205), 0xA8);
+        assert_eq!(mapper.read_cpu(0x5206), 0x03);
+
+        mapper.write_cpu(0x5C00, 0x56);
+        assert_eq!(mapper.read_cpu(0x5C00), 0x56);
+    }
+}
@@ -0,0 +1,182 @@
+pub mod mapper;
+pub mod nsfe;
+pub mod player;
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// PLAY period used when the file has none. (about 60 Hz)
+const DEFAULT_PLAY_SPEED: u16 = 16666;
+
+/// Expansion sound chips of the header flags, in the bit order.
+pub const CHIP_NAMES: [&str; 6] = ["VRC6", "VRC7", "FDS", "MMC5", "Namco 163", "Sunsoft 5B"];
+
+/// A music rip, loaded from a .nsf or a .nsfe file.
+#[derive(Debug, PartialEq)]
+pub struct Nsf {
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    pub track_count: u8,
+    /// Track played first (0 origin)
+    pub starting_track: u8,
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    /// PLAY period in microseconds (NTSC)
+    pub play_speed: u16,
+    /// Initial banks of $8000-$FFFF. All 0: the data is not bank switched.
+    pub bankswitch: [u8; 8],
+    /// Expansion sound chips (bit 0: VRC6, 1: VRC7, 2: FDS, 3: MMC5, 4: Namco 163, 5: Sunsoft 5B)
+    pub chips: u8,
+    /// Track titles (NSFe only, empty for NSF)
+    pub track_labels: Vec<String>,
+    pub data: Vec<u8>,
+}
+
+impl Nsf {
+    const HEADER_SIZE: usize = 0x80;
+
+    pub fn open(path: &str) -> Result<Self, NsfLoadError> {
+        let mut f = File::open(path)?;
+        let mut buffer = Vec::new();
+        f.read_to_end(&mut buffer)?;
+
+        Self::new(&buffer)
+    }
+
+    pub fn new(buf: &[u8]) -> Result<Self, NsfLoadError> {
+        if buf.starts_with(b"NSFE") {
+            nsfe::parse(buf)
+        } else if buf.starts_with(b"NESM\x1A") && buf.len() > Self::HEADER_SIZE {
+            Ok(Self::parse_nsf(buf))
+        } else {
+            Err(NsfLoadError::FormatError)
+        }
+    }
+
+    fn parse_nsf(buf: &[u8]) -> Self {
+        // <NSF header>
+        // $00: "NESM" $1A, $05: version, $06: total songs, $07: starting song (1 origin)
+        // $08: load address, $0A: init address, $0C: play address
+        // $0E / $2E / $4E: song name / artist / copyright (32 bytes, null terminated)
+        // $6E: play speed (NTSC, 1/1000000 sec), $70: bankswitch init values
+        // $78: play speed (PAL), $7A: PAL / NTSC bits, $7B: extra sound chip support
+        // $80: data
+        //
+        // refer: https://wiki.nesdev.com/w/index.php/NSF
+        let word = |offset: usize| buf[offset] as u16 | (buf[offset + 1] as u16) << 8;
+
+        Nsf {
+            title: text(&buf[0x0E..0x2E]),
+            artist: text(&buf[0x2E..0x4E]),
+            copyright: text(&buf[0x4E..0x6E]),
+            track_count: buf[0x06],
+            starting_track: buf[0x07].saturating_sub(1),
+            load_address: word(0x08),
+            init_address: word(0x0A),
+            play_address: word(0x0C),
+            play_speed: play_speed(word(0x6E)),
+            bankswitch: *array_ref!(buf, 0x70, 8),
+            chips: buf[0x7B] & 0x3F,
+            track_labels: Vec::new(),
+            data: buf[Self::HEADER_SIZE..].to_vec(),
+        }
+    }
+
+    pub fn is_bankswitched(&self) -> bool {
+        self.bankswitch.iter().any(|bank| *bank != 0)
+    }
+}
+
+/// .nsf / .nsfe by the extension.
+pub fn is_nsf_path(path: &str) -> bool {
+    match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => extension.eq_ignore_ascii_case("nsf") || extension.eq_ignore_ascii_case("nsfe"),
+        None => false,
+    }
+}
+
+/// Null terminated string in the file.
+fn text(buf: &[u8]) -> String {
+    let end = buf.iter().position(|byte| *byte == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..end]).into_owned()
+}
+
+fn play_speed(speed: u16) -> u16 {
+    if speed == 0 { DEFAULT_PLAY_SPEED } else { speed }
+}
+
+#[derive(Debug)]
+pub enum NsfLoadError {
+    IoError(io::Error),
+    /// File is neither NSF nor NSFe
+    FormatError,
+}
+
+impl fmt::Display for NsfLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NsfLoadError::IoError(err) => write!(f, "failed to read the nsf: {}", err),
+            NsfLoadError::FormatError => write!(f, "the file is neither NSF nor NSFe."),
+        }
+    }
+}
+
+impl From<io::Error> for NsfLoadError {
+    fn from(err: io::Error) -> Self {
+        NsfLoadError::IoError(err)
+    }
+}
+
+#[cfg(test)]
+mod nsf_test {
+    use super::*;
+
+    fn nsf_bytes() -> Vec<u8> {
+        let mut header = vec![0; 0x80];
+        header[0..5].copy_from_slice(b"NESM\x1A");
+        header[0x05] = 1;
+        header[0x06] = 12;
+        header[0x07] = 3;
+        header[0x08..0x0E].copy_from_slice(&[0x00, 0x80, 0x03, 0x80, 0x06, 0x80]);
+        header[0x0E..0x13].copy_from_slice(b"Title");
+        header[0x2E..0x34].copy_from_slice(b"Artist");
+        header[0x4E..0x52].copy_from_slice(b"1986");
+        header[0x71] = 1;
+        header[0x7B] = 0x05;
+        [header, vec![0xEA; 0x10]].concat()
+    }
+
+    #[test]
+    fn new_nsf() {
+        let nsf = Nsf::new(&nsf_bytes()).unwrap();
+        assert_eq!(nsf.title, "Title");
+        assert_eq!(nsf.artist, "Artist");
+        assert_eq!(nsf.copyright, "1986");
+        assert_eq!(nsf.track_count, 12);
+        assert_eq!(nsf.starting_track, 2);
+        assert_eq!((nsf.load_address, nsf.init_address, nsf.play_address), (0x8000, 0x8003, 0x8006));
+        assert_eq!(nsf.play_speed, DEFAULT_PLAY_SPEED);
+        assert!(nsf.is_bankswitched());
+        assert_eq!(nsf.chips, 0x05);
+        assert_eq!(nsf.data, vec![0xEA; 0x10]);
+    }
+
+    #[test]
+    fn new_format_error() {
+        assert!(matches!(Nsf::new(b"NES\x1A"), Err(NsfLoadError::FormatError)));
+        assert_eq!(NsfLoadError::FormatError.to_string(), "the file is neither NSF nor NSFe.");
+    }
+
+    #[test]
+    fn nsf_path() {
+        assert!(is_nsf_path("music/game.nsf"));
+        assert!(is_nsf_path("music/game.NSFE"));
+        assert!(!is_nsf_path("rom/game.nes"));
+        assert!(!is_nsf_path("nsf"));
+    }
+}
@@ -0,0 +1,133 @@
+use super::{Nsf, NsfLoadError, play_speed, text};
+
+/// PLAY period of the NSFe without the RATE chunk.
+const DEFAULT_NSFE_PLAY_SPEED: u16 = 16639;
+
+/// Reads the NSFe file. ("NSFE" followed by the chunks)
+///
+/// Chunk: length (4 bytes, little endian), ID (4 bytes), data
+///   INFO: load / init / play address, region, chips, track count, starting track
+///   DATA: the data loaded at the load address
+///   BANK: initial banks (bank switched when exists)
+///   RATE: play speed (NTSC)
+///   auth: title, artist, copyright, ripper (null terminated strings)
+///   tlbl: track titles (null terminated strings)
+///   NEND: end of the file
+///
+/// Unknown chunks of upper case IDs are required to play, those make the file unsupported.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/NSFe
+pub fn parse(buf: &[u8]) -> Result<Nsf, NsfLoadError> {
+    let mut nsf = Nsf {
+        title: String::new(),
+        artist: String::new(),
+        copyright: String::new(),
+        track_count: 1,
+        starting_track: 0,
+        load_address: 0,
+        init_address: 0,
+        play_address: 0,
+        play_speed: DEFAULT_NSFE_PLAY_SPEED,
+        bankswitch: [0; 8],
+        chips: 0,
+        track_labels: Vec::new(),
+        data: Vec::new(),
+    };
+    let mut has_info = false;
+
+    let mut position = 4;
+    while position + 8 <= buf.len() {
+        let length = u32::from_le_bytes(*array_ref!(buf, position, 4)) as usize;
+        let id = &buf[position + 4..position + 8];
+        let chunk = buf.get(position + 8..position + 8 + length).ok_or(NsfLoadError::FormatError)?;
+        position += 8 + length;
+
+        let word = |offset: usize| chunk[offset] as u16 | (chunk[offset + 1] as u16) << 8;
+        match id {
+            b"INFO" => {
+                if chunk.len() < 9 {
+                    return Err(NsfLoadError::FormatError);
+                }
+                nsf.load_address = word(0);
+                nsf.init_address = word(2);
+                nsf.play_address = word(4);
+                nsf.chips = chunk[7] & 0x3F;
+                nsf.track_count = chunk.get(8).copied().unwrap_or(1);
+                nsf.starting_track = chunk.get(9).copied().unwrap_or(0);
+                has_info = true;
+            },
+            b"DATA" => nsf.data = chunk.to_vec(),
+            b"BANK" => {
+                for (bank, data) in nsf.bankswitch.iter_mut().zip(chunk.iter()) {
+                    *bank = *data;
+                }
+            },
+            b"RATE" if chunk.len() >= 2 => nsf.play_speed = play_speed(word(0)),
+            b"auth" => {
+                let mut strings = chunk.split(|byte| *byte == 0).map(text);
+                nsf.title = strings.next().unwrap_or_default();
+                nsf.artist = strings.next().unwrap_or_default();
+                nsf.copyright = strings.next().unwrap_or_default();
+            },
+            b"tlbl" => {
+                nsf.track_labels = chunk.split(|byte| *byte == 0).map(text).take(nsf.track_count as usize).collect();
+            },
+            b"NEND" => break,
+            id if id[0].is_ascii_uppercase() => return Err(NsfLoadError::FormatError),
+            _ => {},
+        }
+    }
+
+    if !has_info || nsf.data.is_empty() {
+        return Err(NsfLoadError::FormatError);
+    }
+    Ok(nsf)
+}
+
+#[cfg(test)]
+mod nsfe_test {
+    use super::*;
+
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        [(data.len() as u32).to_le_bytes().to_vec(), id.to_vec(), data.to_vec()].concat()
+    }
+
+    #[test]
+    fn parse_chunks() {
+        let buf = [
+            b"NSFE".to_vec(),
+            chunk(b"INFO", &[0x00, 0x80, 0x03, 0x80, 0x06, 0x80, 0x00, 0x01, 2, 1]),
+            chunk(b"DATA", &[0xEA; 4]),
+            chunk(b"RATE", &[0x1A, 0x41]),
+            chunk(b"auth", b"Title\0Artist\0(C) 1986\0Ripper\0"),
+            chunk(b"tlbl", b"Opening\0Ending\0"),
+            // unknown optional chunk
+            chunk(b"xtra", &[0x00]),
+            chunk(b"NEND", &[]),
+        ].concat();
+
+        let nsf = Nsf::new(&buf).unwrap();
+        assert_eq!((nsf.load_address, nsf.init_address, nsf.play_address), (0x8000, 0x8003, 0x8006));
+        assert_eq!(nsf.chips, 0x01);
+        assert_eq!((nsf.track_count, nsf.starting_track), (2, 1));
+        assert_eq!(nsf.play_speed, 0x411A);
+        assert_eq!(nsf.title, "Title");
+        assert_eq!(nsf.copyright, "(C) 1986");
+        assert_eq!(nsf.track_labels, vec!["Opening", "Ending"]);
+        assert!(!nsf.is_bankswitched());
+        assert_eq!(nsf.data, vec![0xEA; 4]);
+    }
+
+    #[test]
+    fn parse_unsupported() {
+        let info = chunk(b"INFO", &[0x00, 0x80, 0x03, 0x80, 0x06, 0x80, 0x00, 0x00, 1]);
+
+        // required chunk unknown to the player
+        let buf = [b"NSFE".to_vec(), info.clone(), chunk(b"DATA", &[0xEA]), chunk(b"XTRA", &[0x00])].concat();
+        assert!(Nsf::new(&buf).is_err());
+
+        // without DATA
+        let buf = [b"NSFE".to_vec(), info].concat();
+        assert!(Nsf::new(&buf).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+use crate::nes::audio::{wav, Mixer, CPU_CLOCK, SAMPLE_RATE};
+use crate::nes::audio::speaker::Speaker;
+use crate::nes::cassette::mapper::Mapper;
+use crate::nes::cpu::{Cpu, CpuError, Bus as CpuBus};
+use crate::nes::ppu::Ppu;
+use crate::nes::ram::Ram;
+use crate::nes::screen::Screen;
+use crate::nes::screen::config::ScreenConfig;
+
+use super::Nsf;
+use super::mapper::NsfMapper;
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use console::Term;
+
+
+/// Return address of the INIT / PLAY calls. The player is idle once the routine returns to it,
+/// the CPU stops before fetching there.
+const RETURN_ADDRESS: u16 = 0x4100;
+
+/// CPU cycles per step while idle, to clock the expansion audio in small steps as the instructions do.
+const IDLE_CYCLES: usize = 2;
+
+/// Plays the NSF: calls INIT for the track, then PLAY at the play speed.
+///
+/// The routines are called as JSR from RETURN_ADDRESS. PLAY is skipped while INIT or
+/// the last PLAY is still running. The APU is not emulated yet, the expansion chips are the only audio,
+/// so the rips for the APU only are refused.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/NSF
+pub struct NsfPlayer {
+    nsf: Nsf,
+    cpu: Cpu,
+    ppu: Ppu,
+    ram: Ram,
+    mapper: NsfMapper,
+    mixer: Mixer,
+    /// Playing track (0 origin)
+    track: u8,
+    cycle_remainder: usize,
+    elapsed_samples: usize,
+}
+
+impl NsfPlayer {
+    pub fn new(nsf: Nsf) -> Result<Self, NsfPlayError> {
+        if nsf.chips == 0 {
+            return Err(NsfPlayError::NoExpansionAudio);
+        }
+
+        let mapper = NsfMapper::new(&nsf);
+        let track = nsf.starting_track;
+
+        let mut player = NsfPlayer {
+            nsf,
+            cpu: Cpu::new(),
+            ppu: Ppu::new(),
+            ram: Ram::new(vec![0; 0x0800]),
+            mapper,
+            mixer: Mixer::new(),
+            track,
+            cycle_remainder: 0,
+            elapsed_samples: 0,
+        };
+        player.start_track(track);
+        Ok(player)
+    }
+
+    /// Resets the memory and the chips, then calls INIT with the track number in A. (X = 0: NTSC)
+    pub fn start_track(&mut self, track: u8) {
+        self.track = track;
+        self.ram = Ram::new(vec![0; 0x0800]);
+        self.mapper.reset(&self.nsf);
+        self.elapsed_samples = 0;
+
+        self.cpu.registers.reset();
+        self.cpu.registers.A = track;
+        self.cpu.registers.X = 0;
+        self.call(self.nsf.init_address);
+    }
+
+    fn call(&mut self, addr: u16) {
+        // RTS returns to the pushed address + 1
+        let return_address = RETURN_ADDRESS - 1;
+        let registers = &mut self.cpu.registers;
+        for data in [(return_address >> 8) as u8, return_address as u8].iter() {
+            self.ram.write(0x0100 | registers.S as u16, *data);
+            registers.S = registers.S.wrapping_sub(1);
+        }
+        registers.PC = addr;
+    }
+
+    fn is_idle(&self) -> bool {
+        self.cpu.registers.PC == RETURN_ADDRESS
+    }
+
+    /// Runs a PLAY period, and returns the samples of it.
+    pub fn run_frame(&mut self) -> Result<Vec<f32>, CpuError> {
+        if self.is_idle() {
+            self.call(self.nsf.play_address);
+        }
+
+        self.cycle_remainder += self.nsf.play_speed as usize * CPU_CLOCK;
+        let frame_cycles = self.cycle_remainder / 1_000_000;
+        self.cycle_remainder %= 1_000_000;
+
+        let mut cycles = 0;
+        while cycles < frame_cycles {
+            let cycle = if self.is_idle() {
+                IDLE_CYCLES
+            } else {
+                let mut bus = CpuBus::new(&mut self.mapper, &mut self.ppu, &mut self.ram);
+                self.cpu.run(&mut bus)?
+            };
+            self.mapper.notify_cpu_cycle(cycle);
+            self.mixer.run(cycle, self.mapper.audio_output());
+            cycles += cycle;
+        }
+
+        let samples = self.mixer.take_samples();
+        self.elapsed_samples += samples.len();
+        Ok(samples)
+    }
+
+    /// Renders the track to the WAV file, without the window and the audio device.
+    pub fn render_wav(&mut self, track: u8, seconds: usize, path: &str) -> Result<(), NsfPlayError> {
+        self.start_track(track);
+
+        let sample_count = seconds * SAMPLE_RATE;
+        let mut samples = Vec::with_capacity(sample_count);
+        while samples.len() < sample_count {
+            samples.extend(self.run_frame()?);
+        }
+        samples.truncate(sample_count);
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        wav::write(&mut writer, &samples)?;
+        Ok(())
+    }
+
+    fn track_count(&self) -> u8 {
+        self.nsf.track_count.max(1)
+    }
+
+    /// Player screen on the terminal.
+    fn status_text(&self) -> String {
+        let label = match self.nsf.track_labels.get(self.track as usize) {
+            Some(label) if !label.is_empty() => format!(" {}", label),
+            _ => String::new(),
+        };
+        let seconds = self.elapsed_samples / SAMPLE_RATE;
+
+        let mut lines = vec![
+            format!("Title     : {}", self.nsf.title),
+            format!("Artist    : {}", self.nsf.artist),
+            format!("Copyright : {}", self.nsf.copyright),
+            format!("Track     : {} / {}{}", self.track + 1, self.track_count(), label),
+            format!("Time      : {}:{:02}", seconds / 60, seconds % 60),
+        ];
+
+        lines.push("Channels  :".to_string());
+        for (number, (name, is_muted)) in self.mapper.channels().iter().enumerate() {
+            lines.push(format!("  [{}] {}{}", number + 1, name, if *is_muted { " (muted)" } else { "" }));
+        }
+        lines.push(String::new());
+        lines.push("Left / Right: previous / next track, 1-6: mute the channel, Esc: quit".to_string());
+
+        lines.join("\n")
+    }
+
+    /// Plays until Esc, or the CPU stops at an instruction it can't execute.
+    pub fn run(&mut self) -> Result<(), NsfPlayError> {
+        let sdl = sdl2::init().unwrap();
+        // the window takes the key inputs, the player screen is on the terminal
+        let mut screen = Screen::new(&sdl, ScreenConfig::new());
+        let mut speaker = Speaker::new(&sdl).ok();
+        let term = Term::stdout();
+        let mut shown_text = String::new();
+
+        'main: loop {
+            let samples = self.run_frame()?;
+            if let Some(speaker) = speaker.as_mut() {
+                speaker.play(&samples);
+            }
+            // keeps the pace by the vsync
+            screen.canvas.present();
+
+            for event in screen.events.poll_iter() {
+                match event {
+                    Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                    Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
+                        self.start_track((self.track + 1) % self.track_count());
+                    },
+                    Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
+                        self.start_track((self.track + self.track_count() - 1) % self.track_count());
+                    },
+                    Event::KeyDown { keycode: Some(keycode), repeat: false, .. } => {
+                        if let Some(channel) = channel_of_key(keycode) {
+                            self.mapper.toggle_mute(channel);
+                        }
+                    },
+                    _ => {},
+                }
+            }
+
+            let text = self.status_text();
+            if text != shown_text {
+                term.clear_screen().ok();
+                term.write_line(&text).ok();
+                shown_text = text;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn channel_of_key(keycode: Keycode) -> Option<usize> {
+    match keycode {
+        Keycode::Num1 => Some(0),
+        Keycode::Num2 => Some(1),
+        Keycode::Num3 => Some(2),
+        Keycode::Num4 => Some(3),
+        Keycode::Num5 => Some(4),
+        Keycode::Num6 => Some(5),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum NsfPlayError {
+    /// The rip uses the APU only, which is not emulated yet
+    NoExpansionAudio,
+    CpuError(CpuError),
+    IoError(io::Error),
+}
+
+impl fmt::Display for NsfPlayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NsfPlayError::NoExpansionAudio => write!(f, "the nsf uses no expansion chip, the APU is not emulated yet."),
+            NsfPlayError::CpuError(err) => write!(f, "failed to play the nsf: {}", err),
+            NsfPlayError::IoError(err) => write!(f, "failed to write the wav: {}", err),
+        }
+    }
+}
+
+impl From<CpuError> for NsfPlayError {
+    fn from(err: CpuError) -> Self {
+        NsfPlayError::CpuError(err)
+    }
+}
+
+impl From<io::Error> for NsfPlayError {
+    fn from(err: io::Error) -> Self {
+        NsfPlayError::IoError(err)
+    }
+}
+
+#[cfg(test)]
+mod player_test {
+    use super::*;
+    use crate::nes::cassette::write_temporary_rom;
+
+    /// INIT: STA $6000, RTS / PLAY: LDX $6001, INX, STX $6001, RTS
+    fn new_nsf(chips: u8) -> Nsf {
+        Nsf {
+            title: "Title".to_string(),
+            artist: String::new(),
+            copyright: String::new(),
+            track_count: 3,
+            starting_track: 0,
+            load_address: 0x8000,
+            init_address: 0x8000,
+            play_address: 0x8004,
+            play_speed: 16666,
+            bankswitch: [0; 8],
+            chips,
+            track_labels: vec!["Opening".to_string()],
+            data: vec![0x8D, 0x00, 0x60, 0x60, 0xAE, 0x01, 0x60, 0xE8, 0x8E, 0x01, 0x60, 0x60],
+        }
+    }
+
+    /// With VRC6
+    fn new_player() -> NsfPlayer {
+        NsfPlayer::new(new_nsf(0x01)).unwrap()
+    }
+
+    #[test]
+    fn apu_only_nsf() {
+        let err = NsfPlayer::new(new_nsf(0x00)).err().unwrap();
+        assert!(matches!(err, NsfPlayError::NoExpansionAudio));
+    }
+
+    #[test]
+    fn init_and_play() {
+        let mut player = new_player();
+        player.start_track(2);
+
+        // the first frame runs INIT
+        let samples = player.run_frame().unwrap();
+        assert_eq!(player.mapper.read_cpu(0x6000), 2);
+        assert_eq!(player.mapper.read_cpu(0x6001), 0);
+        assert!(player.is_idle());
+        assert!((734..=735).contains(&samples.len()));
+
+        player.run_frame().unwrap();
+        player.run_frame().unwrap();
+        assert_eq!(player.mapper.read_cpu(0x6001), 2);
+
+        // the RAM is cleared for the next track
+        player.start_track(1);
+        player.run_frame().unwrap();
+        assert_eq!(player.mapper.read_cpu(0x6000), 1);
+        assert_eq!(player.mapper.read_cpu(0x6001), 0);
+    }
+
+    #[test]
+    fn status_text() {
+        let player = new_player();
+        let text = player.status_text();
+        assert!(text.contains("Title     : Title"));
+        assert!(text.contains("Track     : 1 / 3 Opening"));
+        assert!(text.contains("Channels  :\n  [1] VRC6\n"));
+    }
+
+    #[test]
+    fn render_wav() {
+        // the wav is written over the empty file
+        let path = write_temporary_rom("nsf", "track.wav", &[]);
+
+        new_player().render_wav(0, 1, &path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 44 + SAMPLE_RATE as u64 * 2);
+
+        // BRK is not implemented
+        let mut nsf = new_nsf(0x01);
+        nsf.data[0] = 0x00;
+        let err = NsfPlayer::new(nsf).unwrap().render_wav(0, 1, &path).err().unwrap();
+        assert_eq!(err.to_string(), "failed to play the nsf: opcode $00 at $8000 is not implemented.");
+
+        std::fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap()).unwrap();
+    }
+}
@@ -1,22 +1,235 @@
-use std::slice::Iter;
-use super::tile::Tile;
+use super::PpuContext;
+use super::registers::Registers;
+use crate::nes::cassette::mapper::Mapper;
 
+/// The background pipeline, clocked every dot of the rendering lines.
+///
+/// Each tile takes 8 dots to fetch (nametable, attribute, pattern low, pattern high bytes, 2 dots each).
+/// The fetched tile is loaded into the low 8 bits of the 16 bit shift registers,
+/// the registers shift every dot, and the pixel comes out of the bit (15 - fine X).
+///
+/// refer: https://wiki.nesdev.com/w/index.php/PPU_rendering
 pub struct Background {
-    tiles: Vec<Tile>,
+    nametable_latch: u8,
+    /// Palette (0 - 3) of the tile
+    attribute_latch: u8,
+    pattern_low_latch: u8,
+    pattern_high_latch: u8,
+    pattern_low_shift: u16,
+    pattern_high_shift: u16,
+    attribute_low_shift: u16,
+    attribute_high_shift: u16,
 }
 
 impl Background {
     pub fn new() -> Self {
         Background {
-            tiles: Vec::new(),
+            nametable_latch: 0,
+            attribute_latch: 0,
+            pattern_low_latch: 0,
+            pattern_high_latch: 0,
+            pattern_low_shift: 0,
+            pattern_high_shift: 0,
+            attribute_low_shift: 0,
+            attribute_high_shift: 0,
         }
     }
 
-    pub fn push(&mut self, tile: Tile) {
-        self.tiles.push(tile);
+    /// Runs a dot of a rendering line (including the pre-render line).
+    ///
+    /// cycle 1-256     : fetch the tiles of the line (the first 2 are fetched in the previous line)
+    /// cycle 321-336   : fetch the first 2 tiles of the next line
+    /// cycle 2-257, 322-337 : shift the registers
+    /// cycle 9, 17, ..., 257, 329, 337 : load the fetched tile into the shift registers
+    pub fn run<T: Mapper + ?Sized>(&mut self, cycle: usize, registers: &Registers, ppu_context: &PpuContext, mapper: &mut T) {
+        if let 2..=257 | 322..=337 = cycle {
+            self.shift();
+        }
+
+        match cycle {
+            9..=257 | 329..=337 if (cycle - 1) & 0x07 == 0 => self.load(),
+            _ => {},
+        }
+
+        if let 1..=256 | 321..=336 = cycle {
+            match (cycle - 1) & 0x07 {
+                0 => self.fetch_nametable(registers, ppu_context, mapper),
+                2 => self.fetch_attribute(registers, ppu_context, mapper),
+                4 => self.pattern_low_latch = self.fetch_pattern(0, registers, mapper),
+                6 => self.pattern_high_latch = self.fetch_pattern(8, registers, mapper),
+                _ => {},
+            }
+        }
+    }
+
+    /// Palette index (0 - 15) of the current pixel: palette * 4 + palette number. Palette number 0: transparent
+    pub fn pixel(&self, fine_x: u8) -> u8 {
+        let bit = 15 - fine_x as u16;
+        let palette_number = ((self.pattern_low_shift >> bit) & 0x01) | ((self.pattern_high_shift >> bit) & 0x01) << 1;
+        let palette = ((self.attribute_low_shift >> bit) & 0x01) | ((self.attribute_high_shift >> bit) & 0x01) << 1;
+        (palette << 2 | palette_number) as u8
+    }
+
+    fn shift(&mut self) {
+        self.pattern_low_shift <<= 1;
+        self.pattern_high_shift <<= 1;
+        self.attribute_low_shift <<= 1;
+        self.attribute_high_shift <<= 1;
+    }
+
+    fn load(&mut self) {
+        let fill = |bit: bool| if bit { 0x00FF } else { 0x0000 };
+        self.pattern_low_shift = (self.pattern_low_shift & 0xFF00) | self.pattern_low_latch as u16;
+        self.pattern_high_shift = (self.pattern_high_shift & 0xFF00) | self.pattern_high_latch as u16;
+        self.attribute_low_shift = (self.attribute_low_shift & 0xFF00) | fill(self.attribute_latch & 0x01 != 0);
+        self.attribute_high_shift = (self.attribute_high_shift & 0xFF00) | fill(self.attribute_latch & 0x02 != 0);
+    }
+
+    /// Nametable byte of the tile at v.
+    pub fn nametable_addr(registers: &Registers) -> u16 {
+        0x2000 | (registers.vram_addr.read() & 0x0FFF)
+    }
+
+    /// Attribute byte of the tile at v.
+    pub fn attribute_addr(registers: &Registers) -> u16 {
+        let v = registers.vram_addr.read();
+        0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07)
+    }
+
+    /// Pattern byte of the fetched tile on the row of v. plane_offset: 0 for the low byte, 8 for the high byte
+    pub fn pattern_addr(&self, plane_offset: u16, registers: &Registers) -> u16 {
+        registers.get_background_pattern_table_addr()
+            + self.nametable_latch as u16 * 16
+            + plane_offset
+            + registers.vram_addr.fine_y() as u16
+    }
+
+    fn fetch_nametable<T: Mapper + ?Sized>(&mut self, registers: &Registers, ppu_context: &PpuContext, mapper: &mut T) {
+        self.nametable_latch = ppu_context.read_nametable(Background::nametable_addr(registers), mapper);
+    }
+
+    /// An attribute byte has the palettes of 4x4 tiles, 2 bits for each 2x2 tiles.
+    /// +-------+-------+
+    /// | bit   | bit   |
+    /// |   1-0 |   3-2 |
+    /// +-------+-------+
+    /// | bit   | bit   |
+    /// |   5-4 |   7-6 |
+    /// +-------+-------+
+    fn fetch_attribute<T: Mapper + ?Sized>(&mut self, registers: &Registers, ppu_context: &PpuContext, mapper: &mut T) {
+        let attribute = ppu_context.read_nametable(Background::attribute_addr(registers), mapper);
+
+        let shift = ((registers.vram_addr.coarse_y() & 0x02) << 1) | (registers.vram_addr.coarse_x() & 0x02);
+        self.attribute_latch = (attribute >> shift) & 0x03;
+    }
+
+    fn fetch_pattern<T: Mapper + ?Sized>(&mut self, plane_offset: u16, registers: &Registers, mapper: &mut T) -> u8 {
+        let addr = self.pattern_addr(plane_offset, registers);
+        let data = mapper.read_ppu(addr);
+        mapper.notify_pattern_fetch(addr);
+        data
+    }
+}
+
+#[cfg(test)]
+mod background_test {
+    use super::*;
+    use crate::nes::cassette::mapper::Mirroring;
+    use crate::nes::cassette::mapper::nrom::Nrom;
+    use crate::nes::cassette::mapper::mmc2::Mmc2;
+    use crate::nes::ppu::palette_ram::PaletteRam;
+    use crate::nes::ram::Ram;
+
+    fn dummy_ppu_context() -> PpuContext {
+        PpuContext {
+            vram: Ram::new(vec![0;0x2000]),
+            sprite_ram: Ram::new(vec![0;0x20]),
+            palette_ram: PaletteRam::new(),
+        }
     }
 
-    pub fn iter(&self) -> Iter<Tile> {
-        self.tiles.iter()
+    /// Runs the fetches of the first 2 tiles in the pre-render line, then returns the pixels of the line.
+    fn render_line<T: Mapper + ?Sized>(background: &mut Background, registers: &mut Registers, ppu_context: &PpuContext, mapper: &mut T) -> Vec<u8> {
+        for cycle in 321..=340 {
+            background.run(cycle, registers, ppu_context, mapper);
+            if cycle == 328 || cycle == 336 {
+                registers.vram_addr.increment_x();
+            }
+        }
+
+        let mut pixels = Vec::new();
+        for cycle in 1..=256 {
+            background.run(cycle, registers, ppu_context, mapper);
+            pixels.push(background.pixel(registers.vram_addr.fine_x()));
+            if cycle & 0x07 == 0 {
+                registers.vram_addr.increment_x();
+            }
+        }
+        pixels
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn render_tiles_test() {
+        let mut ppu_context = dummy_ppu_context();
+        let mut character_rom = vec![0; 0x2000];
+        // tile 1 at $1000: 0b10000001 / 0b10000000
+        character_rom[0x1010] = 0x81;
+        character_rom[0x1018] = 0x80;
+        let mut mapper = Nrom::new(vec![0;0x4000], character_rom, Mirroring::Horizontal, 0, false);
+
+        // tile 1 at the tile 0 and 3, palette 2 for the tile 2 and 3
+        ppu_context.vram.write(0x0000, 1);
+        ppu_context.vram.write(0x0003, 1);
+        ppu_context.vram.write(0x03C0, 0b00001000);
+
+        let mut registers = Registers::new();
+        registers.ppu_ctrl.write(0x10);
+        let mut background = Background::new();
+        let pixels = render_line(&mut background, &mut registers, &ppu_context, &mut mapper);
+
+        assert_eq!(&pixels[0..8], &[3, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(&pixels[8..16], &[0; 8]);
+        assert_eq!(&pixels[16..24], &[8; 8]);
+        assert_eq!(&pixels[24..32], &[11, 8, 8, 8, 8, 8, 8, 9]);
+        assert_eq!(&pixels[32..40], &[0; 8]);
+    }
+
+    #[test]
+    fn fine_x_test() {
+        let mut ppu_context = dummy_ppu_context();
+        let mut character_rom = vec![0; 0x2000];
+        character_rom[0x0010] = 0x81;
+        let mut mapper = Nrom::new(vec![0;0x4000], character_rom, Mirroring::Horizontal, 0, false);
+        ppu_context.vram.write(0x0001, 1);
+
+        // scroll X 11: the pixels from the 4th pixel of the tile 1
+        let mut registers = Registers::new();
+        registers.vram_addr.write_scroll(11);
+        registers.vram_addr.write_scroll(0);
+        registers.vram_addr.copy_x();
+        registers.vram_addr.copy_y();
+        let mut background = Background::new();
+        let pixels = render_line(&mut background, &mut registers, &ppu_context, &mut mapper);
+
+        assert_eq!(&pixels[0..6], &[0, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn notify_pattern_fetch_test() {
+        let mut ppu_context = dummy_ppu_context();
+        // MMC2: CHR bank 1 for the latch $FD, bank 2 for the latch $FE at $0000-$0FFF
+        let character_rom = [vec![0x11; 0x1000], vec![0x00; 0x1000], vec![0xFF; 0x1000]].concat();
+        let mut mapper = Mmc2::new(vec![0; 0x8000], character_rom, Mirroring::Vertical, false, 0, false);
+        mapper.write_cpu(0xB000, 1);
+        mapper.write_cpu(0xC000, 2);
+        ppu_context.vram.write(0x0000, 0xFD);
+
+        // the tile $FD switches the bank after its fetch
+        let mut registers = Registers::new();
+        let mut background = Background::new();
+        let pixels = render_line(&mut background, &mut registers, &ppu_context, &mut mapper);
+
+        assert_eq!(&pixels[0..8], &[3; 8]);
+        assert_eq!(&pixels[8..16], &[0; 8]);
+    }
+}
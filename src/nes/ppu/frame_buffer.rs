@@ -0,0 +1,27 @@
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+/// Output of the PPU. Each pixel is a color (0x00 - 0x3F) of the NES palette
+/// with the emphasis bits of PPUMASK. (bit 6: red, 7: green, 8: blue)
+#[derive(Clone)]
+pub struct FrameBuffer(Vec<u16>);
+
+impl FrameBuffer {
+    pub fn new() -> Self {
+        FrameBuffer(vec![0; SCREEN_WIDTH * SCREEN_HEIGHT])
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, x: usize, y: usize) -> u16 {
+        self.0[y * SCREEN_WIDTH + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, pixel: u16) {
+        self.0[y * SCREEN_WIDTH + x] = pixel;
+    }
+
+    /// Pixels from the top left, line by line
+    pub fn pixels(&self) -> &[u16] {
+        &self.0
+    }
+}
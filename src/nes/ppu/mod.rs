@@ -1,39 +1,65 @@
 pub mod background;
-pub mod sprite;
+pub mod frame_buffer;
 pub mod sprite_with_ctx;
 pub mod palette;
-pub mod tile;
-pub mod tile_position;
 
 mod registers;
 mod palette_ram;
 
 use self::registers::Registers;
 use self::palette_ram::PaletteRam;
-use self::tile::Tile;
-use self::tile_position::TilePosition;
 use self::background::Background;
-use self::sprite_with_ctx::SpritesWithCtx;
+use self::frame_buffer::FrameBuffer;
+use self::sprite_with_ctx::{SpriteWithCtx, SpriteSlot, SpritesWithCtx, SPRITE_ATTRIBUTE_BYTES, MAX_SPRITES_IN_LINE};
 
+use crate::nes::cassette::mapper::Mapper;
 use crate::nes::ram::Ram;
 
 pub struct Ppu {
     pub cycle: usize,
     pub line: usize,
+    /// PPU clock count at the start of the current line
+    pub elapsed_cycle: usize,
     pub registers: Registers,
+    /// Sprites of the current line, built for the next line at cycle 257-320
     pub sprites: SpritesWithCtx,
+    /// Sprites found for the next line by the sprite evaluation
+    sprite_slots: Vec<SpriteSlot>,
+    /// Pattern low byte of the sprite slot being fetched
+    sprite_pattern_low: u8,
     pub background: Background,
+    pub frame_buffer: FrameBuffer,
     pub context: PpuContext,
 }
 
 pub struct PpuContext {
-    pub cram: Ram,
+    /// Nametables, 2KB CIRAM + 2KB for the four-screen
     pub vram: Ram,
     pub sprite_ram: Ram,
     pub palette_ram: PaletteRam,
 }
 
+impl PpuContext {
+    /// Nametable read ($2000-$3EFF), served by the cassette or the VRAM by the mirroring of the cassette.
+    pub fn read_nametable<T: Mapper + ?Sized>(&self, addr: u16, mapper: &mut T) -> u8 {
+        let addr = 0x2000 | (addr & 0x0FFF);
+        mapper
+            .read_nametable(addr)
+            .unwrap_or_else(|| self.vram.read(mapper.mirroring().nametable_to_vram(addr)))
+    }
+
+    /// Nametable write ($2000-$3EFF), taken by the cassette or the VRAM by the mirroring of the cassette.
+    pub fn write_nametable<T: Mapper + ?Sized>(&mut self, addr: u16, data: u8, mapper: &mut T) {
+        let addr = 0x2000 | (addr & 0x0FFF);
+        if !mapper.write_nametable(addr, data) {
+            self.vram.write(mapper.mirroring().nametable_to_vram(addr), data);
+        }
+    }
+}
+
 const CLOCK_TO_RENDER_LINE: usize = 341;
+const VISIBLE_LINES: usize = 240;
+const PRE_RENDER_LINE: usize = 261;
 
 pub enum PpuRunResult {
     CountUpCycle,
@@ -42,16 +68,19 @@ pub enum PpuRunResult {
 }
 
 impl Ppu {
-    pub fn new(character_rom: Vec<u8>) -> Self {
+    pub fn new() -> Self {
         Ppu {
             cycle: 0,
             line: 0,
+            elapsed_cycle: 0,
             registers: Registers::new(),
             background: Background::new(),
+            frame_buffer: FrameBuffer::new(),
             sprites: Vec::new(),
+            sprite_slots: Vec::new(),
+            sprite_pattern_low: 0,
             context: PpuContext {
-                cram: Ram::new(character_rom),
-                vram: Ram::new(vec![0; 0x2000]),
+                vram: Ram::new(vec![0; 0x1000]),
                 palette_ram: PaletteRam::new(),
                 sprite_ram: Ram::new(vec![0; 0x0100]),
             }
@@ -59,69 +88,704 @@ impl Ppu {
         }
     }
 
-    pub fn read(&mut self, addr: u16) -> u8 {
-        self.registers.read(addr, &mut self.context)
+    /// addr: $2000-$3FFF as 0x0000-0x1FFF. The 8 registers are mirrored every 8 bytes.
+    pub fn read<T: Mapper + ?Sized>(&mut self, addr: u16, mapper: &mut T) -> u8 {
+        let addr = addr & 0x0007;
+        let data = self.registers.read(addr, &mut self.context, mapper);
+        if addr == 0x0007 {
+            self.notify_vram_addr(mapper);
+        }
+
+        data
     }
 
-    pub fn write(&mut self, addr: u16, data: u8) {
-        self.registers.write(addr, data, &mut self.context);
+    /// addr: $2000-$3FFF as 0x0000-0x1FFF. The 8 registers are mirrored every 8 bytes.
+    pub fn write<T: Mapper + ?Sized>(&mut self, addr: u16, data: u8, mapper: &mut T) {
+        let addr = addr & 0x0007;
+        if addr == 0x0004 && self.is_rendering() {
+            self.registers.write_oam_data_while_rendering(data);
+            return;
+        }
+
+        self.registers.write(addr, data, &mut self.context, mapper);
+        if addr == 0x0006 || addr == 0x0007 {
+            self.notify_vram_addr(mapper);
+        }
     }
 
-    pub fn run(&mut self, cycle: usize) -> PpuRunResult {
+    pub fn run<T: Mapper + ?Sized>(&mut self, cycle: usize, mapper: &mut T) -> PpuRunResult {
+        let start_cycle = self.cycle;
         self.cycle += cycle;
 
         if self.cycle < CLOCK_TO_RENDER_LINE {
+            self.run_dots(start_cycle, self.cycle, mapper);
             return PpuRunResult::CountUpCycle;
         }
 
+        self.run_dots(start_cycle, CLOCK_TO_RENDER_LINE, mapper);
+        if self.is_rendering() {
+            // cycle 257-320
+            self.registers.reset_oam_addr();
+        }
         self.cycle -= CLOCK_TO_RENDER_LINE;
+        self.elapsed_cycle += CLOCK_TO_RENDER_LINE;
         self.line += 1;
 
-        // is need building a background line.
-        if self.line <= 240 && self.line % 8 == 0 {
-            /*
-                The name table has such a structure.
-
-                nametable: has 4 nametable. (ID: 0-3)
-                x: 32 tiles(8x8 pixel) in 256 pixel.
-                y: 30 tiles(8x8 pixel) in 240 pixel.
-                +------------+------------+
-                |            |            |
-                |  0(0x2000) |  1(0x2400) |
-                |            |            |
-                +------------+------------+
-                |            |            |
-                |  2(0x2800) |  3(0x2C00) |
-                |            |            |
-                +------------+------------+
-
-                name table: 0, 2 -> start x position is 0.
-                name table: 1, 3 -> start x position is 32.
-            */
-
-            let nametable_id = self.registers.get_nametable_id();
-            let pos_x_start = (nametable_id % 2) * 32;
-            let pos_x_end = pos_x_start + 32;
-            let pos_y = (self.line / 8) as u8;
-
-            for pos_x in pos_x_start..pos_x_end {
-                let tile_pos = TilePosition::new(pos_x, pos_y);
-                let tile = Tile::build(tile_pos, &self.context);
-                self.background.push(tile);
-            }
+        if self.line <= 240 {
+            mapper.notify_scanline();
         }
 
         if self.line == 241 {
             self.registers.set_vblank();
+            self.registers.decay_io_latch();
+        }
+
+        if self.line == PRE_RENDER_LINE {
+            self.registers.clear_vblank();
             self.registers.clear_sprite_hit();
+            self.registers.clear_sprite_overflow();
+            if self.registers.is_rendering_enabled() {
+                self.corrupt_oam();
+            }
         }
 
         // is not finished building all the background lines.
-        if self.line < 262 {
-            return PpuRunResult::FinishedBuildBackgroundLine;
+        let result = if self.line < 262 {
+            PpuRunResult::FinishedBuildBackgroundLine
+        } else {
+            self.line = 0;
+            PpuRunResult::FinishedBuildAllBackgroundLine
+        };
+
+        self.run_dots(0, self.cycle, mapper);
+        result
+    }
+
+    /// Runs the dots (PPU cycles) of the current line.
+    fn run_dots<T: Mapper + ?Sized>(&mut self, from_cycle: usize, to_cycle: usize, mapper: &mut T) {
+        for cycle in from_cycle..to_cycle.min(CLOCK_TO_RENDER_LINE) {
+            if self.is_rendering() {
+                if let Some(addr) = self.fetch_address(cycle) {
+                    mapper.notify_ppu_address(addr, self.elapsed_cycle + cycle);
+                }
+                self.background.run(cycle, &self.registers, &self.context, mapper);
+            }
+
+            if cycle == 257 {
+                self.sprites.clear();
+                self.sprite_slots.clear();
+                if self.is_rendering() && self.line < VISIBLE_LINES - 1 {
+                    self.evaluate_sprites(self.line);
+                }
+            }
+            if self.is_rendering() && (257..=320).contains(&cycle) {
+                self.fetch_sprite_pattern(cycle, mapper);
+            }
+
+            if self.line < VISIBLE_LINES && (1..=256).contains(&cycle) {
+                self.output_pixel(cycle - 1);
+            }
+
+            if self.is_rendering() {
+                self.update_vram_addr(cycle);
+            }
+        }
+    }
+
+    /// Puts a pixel into the frame buffer with the effects of PPUMASK.
+    ///
+    /// While rendering is disabled, the backdrop color is shown,
+    /// or the palette entry at v when v points to the palette RAM. ("background palette hack")
+    ///
+    /// refer: https://wiki.nesdev.com/w/index.php/PPU_palettes#The_background_palette_hack
+    fn output_pixel(&mut self, x: usize) {
+        let color = if self.registers.is_rendering_enabled() {
+            self.render_pixel(x)
+        } else {
+            let addr = self.registers.get_vram_addr();
+            self.context.palette_ram.read(if addr >= 0x3F00 { addr } else { 0x00 })
+        };
+        let color = if self.registers.is_grayscale() { color & 0x30 } else { color };
+
+        self.frame_buffer.set(x, self.line, (self.registers.get_emphasis() as u16) << 6 | color as u16);
+    }
+
+    /// Color of the background and the sprites at x of the line.
+    ///
+    /// The first opaque sprite in OAM wins among the sprites, and it's hidden by the opaque background
+    /// when it's behind the background. The backdrop color shows through the transparent pixels.
+    ///
+    /// Sprite 0 hit: an opaque pixel of the sprite 0 overlaps an opaque background pixel, except at x = 255.
+    ///
+    /// refer: https://wiki.nesdev.com/w/index.php/PPU_rendering#Preface
+    fn render_pixel(&mut self, x: usize) -> u8 {
+        let registers = &self.registers;
+        let background = if registers.is_background_enabled() && (x >= 8 || registers.is_background_leftmost_shown()) {
+            self.background.pixel(registers.vram_addr.fine_x())
+        } else {
+            0
+        };
+        let is_background_opaque = background & 0x03 != 0;
+
+        let sprite = if registers.is_sprite_enabled() && (x >= 8 || registers.is_sprite_leftmost_shown()) {
+            self.sprites.iter().find_map(|sprite| {
+                sprite.palette_number(x).map(|palette_number| (sprite, palette_number))
+            })
+        } else {
+            None
+        };
+
+        let color = match sprite {
+            Some((sprite, palette_number)) if !is_background_opaque || !sprite.is_behind_background => {
+                sprite.palettes.get(palette_number as usize).get_palette_number()
+            },
+            _ if is_background_opaque => self.context.palette_ram.read(background as u16),
+            _ => self.context.palette_ram.read(0x00),
+        };
+        let is_sprite_zero_hit = match sprite {
+            Some((sprite, _)) => sprite.is_sprite_zero && is_background_opaque && x != 255,
+            None => false,
+        };
+
+        if is_sprite_zero_hit {
+            self.registers.set_sprite_hit();
+        }
+        color
+    }
+
+    /// Scrolling: the PPU walks the VRAM address (v) through the nametables while rendering.
+    ///
+    /// cycle 8, 16, ..., 256, 328, 336: increment the coarse X
+    /// cycle 256: increment the Y
+    /// cycle 257: copy the horizontal bits of t to v
+    /// cycle 280-304 of the pre-render line: copy the vertical bits of t to v
+    ///
+    /// refer: https://wiki.nesdev.com/w/index.php/PPU_scrolling#Wrapping_around
+    fn update_vram_addr(&mut self, cycle: usize) {
+        let vram_addr = &mut self.registers.vram_addr;
+        match cycle {
+            1..=255 | 328 | 336 if cycle & 0x07 == 0 => vram_addr.increment_x(),
+            256 => {
+                vram_addr.increment_x();
+                vram_addr.increment_y();
+            },
+            257 => vram_addr.copy_x(),
+            280..=304 if self.line == PRE_RENDER_LINE => vram_addr.copy_y(),
+            _ => {},
+        }
+    }
+
+    fn is_rendering(&self) -> bool {
+        let is_rendering_line = self.line < VISIBLE_LINES || self.line == PRE_RENDER_LINE;
+        is_rendering_line && self.registers.is_rendering_enabled()
+    }
+
+    /// Sprite evaluation for the next line. The first 8 sprites in the range are drawn.
+    ///
+    /// After 8 sprites are found, the PPU checks the rest for the sprite overflow with a bug:
+    /// the byte index (m) is incremented with the sprite index (n), so the Y of the rest is not read correctly.
+    ///
+    /// refer: https://wiki.nesdev.com/w/index.php/PPU_sprite_evaluation
+    fn evaluate_sprites(&mut self, line: usize) {
+        let is_8x16 = self.registers.is_sprite_8x16();
+        let height = if is_8x16 { 16 } else { 8 };
+        let pattern_table_addr = self.registers.get_sprite_pattern_table_addr();
+        let is_in_range = |y: u8| line >= y as usize && line - (y as usize) < height;
+
+        let mut n = 0;
+        let mut found = 0;
+        while n < 64 && found < MAX_SPRITES_IN_LINE {
+            let addr = n * SPRITE_ATTRIBUTE_BYTES;
+            if is_in_range(self.context.sprite_ram.read(addr as u16)) {
+                let mut oam = [0; SPRITE_ATTRIBUTE_BYTES];
+                oam.copy_from_slice(self.context.sprite_ram.read_range(addr..addr + SPRITE_ATTRIBUTE_BYTES));
+                let pattern_addr = SpriteWithCtx::pattern_addr(line, &oam, is_8x16, pattern_table_addr);
+                self.sprite_slots.push(SpriteSlot { oam, is_sprite_zero: n == 0, pattern_addr });
+                found += 1;
+            }
+            n += 1;
+        }
+
+        let mut m = 0;
+        while n < 64 {
+            if is_in_range(self.context.sprite_ram.read((n * SPRITE_ATTRIBUTE_BYTES + m) as u16)) {
+                self.registers.set_sprite_overflow();
+                return;
+            }
+            n += 1;
+            m = (m + 1) & 0x03;
+        }
+    }
+
+    /// Sprite pattern fetches of cycle 257-320, the low and the high byte of a slot in every 8 cycles.
+    /// The sprite is built on its high byte, the empty slots fetch the tile $FF and are dropped.
+    fn fetch_sprite_pattern<T: Mapper + ?Sized>(&mut self, cycle: usize, mapper: &mut T) {
+        let slot = (cycle - 257) / 8;
+        let is_low = match (cycle - 257) % 8 {
+            4 => true,
+            6 => false,
+            _ => return,
+        };
+
+        let addr = self.sprite_pattern_addr(slot) + if is_low { 0 } else { 8 };
+        let data = mapper.read_ppu(addr);
+        mapper.notify_pattern_fetch(addr);
+
+        if is_low {
+            self.sprite_pattern_low = data;
+        } else if let Some(sprite_slot) = self.sprite_slots.get(slot) {
+            let sprite = SpriteWithCtx::build(&sprite_slot.oam, sprite_slot.is_sprite_zero, self.sprite_pattern_low, data, &self.context);
+            self.sprites.push(sprite);
+        }
+    }
+
+    /// Address of the pattern low byte of the sprite slot, the tile $FF for the empty slots.
+    fn sprite_pattern_addr(&self, slot: usize) -> u16 {
+        match self.sprite_slots.get(slot) {
+            Some(sprite_slot) => sprite_slot.pattern_addr,
+            None if self.registers.is_sprite_8x16() => 0x1FE0,
+            None => self.registers.get_sprite_pattern_table_addr() | 0x0FF0,
+        }
+    }
+
+    /// When rendering starts with OAMADDR 8 or more, the 8 bytes at OAMADDR & 0xF8 are copied to the first 8 bytes.
+    ///
+    /// refer: https://wiki.nesdev.com/w/index.php/PPU_registers#OAMADDR
+    fn corrupt_oam(&mut self) {
+        let addr = self.registers.get_oam_addr();
+        if addr < 8 {
+            return;
+        }
+
+        let start = addr & 0xF8;
+        for offset in 0..8 {
+            let data = self.context.sprite_ram.read(start + offset);
+            self.context.sprite_ram.write(offset, data);
+        }
+    }
+
+    /// While not rendering, the PPU address bus holds the VRAM address set through $2006 / $2007.
+    fn notify_vram_addr<T: Mapper + ?Sized>(&self, mapper: &mut T) {
+        if !self.is_rendering() {
+            mapper.notify_ppu_address(self.registers.get_vram_addr(), self.elapsed_cycle + self.cycle);
+        }
+    }
+
+    fn fetch_address(&self, cycle: usize) -> Option<u16> {
+        /*
+            Memory fetches in a rendering line. (each fetch takes 2 cycles)
+
+            cycle   1-256 : nametable, attribute, background pattern low / high (x 32 tiles)
+            cycle 257-320 : garbage nametable x 2, sprite pattern low / high (x 8 sprites)
+            cycle 321-336 : nametable, attribute, background pattern low / high (x 2 tiles for the next line)
+            cycle 337-340 : unused nametable x 2
+
+            The garbage and the unused nametable fetches read the tile at v too.
+        */
+        let nametable_addr = Background::nametable_addr(&self.registers);
+
+        match cycle {
+            1..=256 | 321..=336 => match (cycle - 1) % 8 {
+                0 => Some(nametable_addr),
+                2 => Some(Background::attribute_addr(&self.registers)),
+                4 => Some(self.background.pattern_addr(0, &self.registers)),
+                6 => Some(self.background.pattern_addr(8, &self.registers)),
+                _ => None,
+            },
+            257..=320 => match (cycle - 257) % 8 {
+                0 | 2 => Some(nametable_addr),
+                4 => Some(self.sprite_pattern_addr((cycle - 257) / 8)),
+                6 => Some(self.sprite_pattern_addr((cycle - 257) / 8) + 8),
+                _ => None,
+            },
+            337 | 339 => Some(nametable_addr),
+            _ => None,
+        }
+    }
+}
+#[cfg(test)]
+mod ppu_test {
+    use super::*;
+    use crate::nes::cassette::mapper::Mirroring;
+    use crate::nes::cassette::mapper::nrom::Nrom;
+
+    #[test]
+    fn register_mirror_test() {
+        let mut ppu = Ppu::new();
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
+
+        // $3FFE: PPUADDR, $3FFF: PPUDATA
+        ppu.write(0x1FFE, 0x20, &mut mapper);
+        ppu.write(0x1FFE, 0x10, &mut mapper);
+        ppu.write(0x1FFF, 0xAB, &mut mapper);
+        assert_eq!(ppu.context.vram.read(0x0010), 0xAB);
+
+        // $2008: PPUCTRL
+        ppu.write(0x0008, 0x04, &mut mapper);
+        assert_eq!(ppu.read(0x0008, &mut mapper), 0x04);
+    }
+
+    #[test]
+    fn evaluate_sprites_test() {
+        let mut ppu = Ppu::new();
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![0xFF; 0x2000], Mirroring::Horizontal, 0, false);
+        ppu.write(0x0001, 0x18, &mut mapper);
+
+        // 10 sprites on the line 21-28
+        for n in 0..64 {
+            let y = if n < 10 { 20 } else { 0xFF };
+            ppu.context.sprite_ram.write(n * 4, y);
+            ppu.context.sprite_ram.write(n * 4 + 3, (n * 10) as u8);
+        }
+        run_lines(&mut ppu, 21, &mut mapper);
+
+        // the first 8 sprites
+        assert_eq!(ppu.sprites.len(), 8);
+        assert!(ppu.sprites.iter().all(|sprite| sprite.x < 80));
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x20, 0x20);
+
+        run_lines(&mut ppu, 7, &mut mapper);
+        assert_eq!(ppu.sprites.len(), 8);
+        run_lines(&mut ppu, 1, &mut mapper);
+        assert_eq!(ppu.sprites.len(), 0);
+    }
+
+    #[test]
+    fn sprite_size_test() {
+        let mut character_rom = vec![0; 0x2000];
+        // tile 4 at $0000, tile 5 at $1000: opaque at the top left
+        character_rom[0x0040] = 0x80;
+        character_rom[0x1050] = 0x80;
+        let mut mapper = Nrom::new(vec![0;0x4000], character_rom, Mirroring::Horizontal, 0, false);
+        let sprite_line = |ppu: &Ppu| -> Vec<Option<u8>> {
+            ppu.sprites.iter().map(|sprite| sprite.palette_number(40)).collect()
+        };
+
+        // the sprite of the tile 5 on the line 11-
+        let mut ppu = Ppu::new();
+        for (offset, data) in [10, 5, 0x00, 40].iter().enumerate() {
+            ppu.context.sprite_ram.write(offset as u16, *data);
+        }
+        for n in 1..64 {
+            ppu.context.sprite_ram.write(n * 4, 0xFF);
         }
 
-        self.line = 0;
-        PpuRunResult::FinishedBuildAllBackgroundLine
+        // 8x8, pattern table $0000
+        ppu.write(0x0001, 0x18, &mut mapper);
+        run_lines(&mut ppu, 11, &mut mapper);
+        assert_eq!(sprite_line(&ppu), vec![None]);
+        run_lines(&mut ppu, 8, &mut mapper);
+        assert_eq!(sprite_line(&ppu), vec![]);
+
+        // 8x16: $1000 by the bit 0, the tile 4 and 5, the pattern table of PPUCTRL is ignored
+        ppu.write(0x0000, 0x20, &mut mapper);
+        run_lines(&mut ppu, 262, &mut mapper);
+        assert_eq!(sprite_line(&ppu), vec![Some(1)]);
+        run_lines(&mut ppu, 8, &mut mapper);
+        assert_eq!(sprite_line(&ppu), vec![]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scroll_test() {
+        let mut ppu = Ppu::new();
+        let mut character_rom = vec![0; 0x2000];
+        // tile 1: opaque at the top row
+        character_rom[0x10] = 0xFF;
+        let mut mapper = Nrom::new(vec![0;0x4000], character_rom, Mirroring::Vertical, 0, false);
+        ppu.context.palette_ram.write(0x00, 0x0F);
+        ppu.context.palette_ram.write(0x01, 0x21);
+
+        // tile 1 at the tile (1, 0) and (0, 2) of the nametable 1
+        ppu.context.vram.write(0x0401, 1);
+        ppu.context.vram.write(0x0440, 1);
+
+        // scroll X 248: the screen starts at the tile 31 of the nametable 0
+        ppu.read(0x0002, &mut mapper);
+        ppu.write(0x0005, 248, &mut mapper);
+        ppu.write(0x0005, 0, &mut mapper);
+        ppu.write(0x0001, 0x0A, &mut mapper);
+        run_lines(&mut ppu, 262 + 1, &mut mapper);
+        assert_eq!(ppu.frame_buffer.get(15, 0), 0x0F);
+        assert_eq!(ppu.frame_buffer.get(16, 0), 0x21);
+        assert_eq!(ppu.frame_buffer.get(23, 0), 0x21);
+        assert_eq!(ppu.frame_buffer.get(24, 0), 0x0F);
+
+        // split: the nametable 1 without the scroll X from the next line
+        ppu.write(0x0000, 0x01, &mut mapper);
+        ppu.write(0x0005, 0, &mut mapper);
+        ppu.write(0x0005, 0, &mut mapper);
+        run_lines(&mut ppu, 16, &mut mapper);
+        assert_eq!(ppu.frame_buffer.get(0, 16), 0x21);
+        assert_eq!(ppu.frame_buffer.get(7, 16), 0x21);
+        assert_eq!(ppu.frame_buffer.get(8, 16), 0x0F);
+    }
+
+    #[test]
+    fn sprite_priority_test() {
+        let mut ppu = Ppu::new();
+        let mut character_rom = vec![0; 0x2000];
+        // tile 1: opaque at the left half of the top row
+        character_rom[0x10] = 0xF0;
+        let mut mapper = Nrom::new(vec![0;0x4000], character_rom, Mirroring::Horizontal, 0, false);
+        ppu.context.palette_ram.write(0x00, 0x0F);
+        ppu.context.palette_ram.write(0x01, 0x21);
+        ppu.context.palette_ram.write(0x11, 0x16);
+        ppu.context.palette_ram.write(0x15, 0x2A);
+
+        // background: tile 1 at the tile (2, 2)
+        ppu.context.vram.write(2 * 32 + 2, 1);
+        // sprite 0 in front of the background, sprite 1 behind the background on the line 16
+        for (offset, data) in [15, 1, 0x00, 14, 15, 1, 0x21, 12].iter().enumerate() {
+            ppu.context.sprite_ram.write(offset as u16, *data);
+        }
+        for n in 2..64 {
+            ppu.context.sprite_ram.write(n * 4, 0xFF);
+        }
+        ppu.write(0x0001, 0x1E, &mut mapper);
+        run_lines(&mut ppu, 17, &mut mapper);
+
+        let line: Vec<u16> = (12..22).map(|x| ppu.frame_buffer.get(x, 16)).collect();
+        assert_eq!(line, vec![0x2A, 0x2A, 0x16, 0x16, 0x16, 0x16, 0x21, 0x21, 0x0F, 0x0F]);
+    }
+
+    #[test]
+    fn ppu_mask_test() {
+        let mut ppu = Ppu::new();
+        let mut character_rom = vec![0; 0x2000];
+        // tile 1: opaque
+        for row in 0..8 {
+            character_rom[0x10 + row] = 0xFF;
+        }
+        let mut mapper = Nrom::new(vec![0;0x4000], character_rom, Mirroring::Horizontal, 0, false);
+        ppu.context.palette_ram.write(0x00, 0x0F);
+        ppu.context.palette_ram.write(0x01, 0x21);
+        for x in 0..32 {
+            ppu.context.vram.write(x, 1);
+        }
+        let pixels = |ppu: &Ppu| -> Vec<u16> { [0, 7, 8].iter().map(|x| ppu.frame_buffer.get(*x, 0)).collect() };
+
+        // the leftmost 8 pixels are hidden
+        ppu.write(0x0001, 0x18, &mut mapper);
+        run_lines(&mut ppu, 262 + 1, &mut mapper);
+        assert_eq!(pixels(&ppu), vec![0x0F, 0x0F, 0x21]);
+
+        // the background is shown in the leftmost 8 pixels
+        ppu.write(0x0001, 0x1A, &mut mapper);
+        run_lines(&mut ppu, 262, &mut mapper);
+        assert_eq!(pixels(&ppu), vec![0x21, 0x21, 0x21]);
+
+        // the grayscale and the emphasis of the red and the blue
+        ppu.write(0x0001, 0xBB, &mut mapper);
+        run_lines(&mut ppu, 262, &mut mapper);
+        assert_eq!(pixels(&ppu), vec![0x160, 0x160, 0x160]);
+
+        // the background is disabled
+        ppu.write(0x0001, 0x10, &mut mapper);
+        run_lines(&mut ppu, 262, &mut mapper);
+        assert_eq!(pixels(&ppu), vec![0x0F, 0x0F, 0x0F]);
+    }
+
+    #[test]
+    fn rendering_disabled_test() {
+        let mut ppu = Ppu::new();
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
+        ppu.context.palette_ram.write(0x00, 0x0F);
+        ppu.context.palette_ram.write(0x05, 0x25);
+
+        // the backdrop
+        run_lines(&mut ppu, 1, &mut mapper);
+        assert_eq!(ppu.frame_buffer.get(0, 0), 0x0F);
+
+        // the palette entry at v
+        ppu.write(0x0006, 0x3F, &mut mapper);
+        ppu.write(0x0006, 0x05, &mut mapper);
+        run_lines(&mut ppu, 1, &mut mapper);
+        assert_eq!(ppu.frame_buffer.get(0, 1), 0x25);
+    }
+
+    #[test]
+    fn sprite_zero_hit_test() {
+        let mut ppu = Ppu::new();
+        let mut character_rom = vec![0; 0x2000];
+        // tile 1: opaque at the right half
+        for row in 0..8 {
+            character_rom[0x10 + row] = 0x0F;
+        }
+        let mut mapper = Nrom::new(vec![0;0x4000], character_rom, Mirroring::Horizontal, 0, false);
+        ppu.write(0x0001, 0x1E, &mut mapper);
+
+        // background: tile 1 at the tile (2, 2), opaque at the pixels 20-23 on the line 16-23
+        ppu.context.vram.write(2 * 32 + 2, 1);
+
+        // sprite 0 on the line 21-28, opaque at the pixels 16-19
+        for (offset, data) in [20, 1, 0x00, 12].iter().enumerate() {
+            ppu.context.sprite_ram.write(offset as u16, *data);
+        }
+        run_lines(&mut ppu, 30, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x40, 0x00);
+
+        // the pixels 20-23 overlap with the background
+        ppu.context.sprite_ram.write(3, 16);
+        run_lines(&mut ppu, 262 - 9, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x40, 0x00);
+        run_lines(&mut ppu, 1, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x40, 0x40);
+
+        // kept through the reads and the vblank, cleared at the pre-render line
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x40, 0x40);
+        run_lines(&mut ppu, 241 - 22, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0xC0, 0xC0);
+        run_lines(&mut ppu, PRE_RENDER_LINE - 241 - 1, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x40, 0x40);
+        run_lines(&mut ppu, 1, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x40, 0x00);
+    }
+
+    /// NROM recording the addresses put on the PPU address bus.
+    struct FetchRecorder {
+        nrom: Nrom,
+        /// (PPU clock, address)
+        addresses: Vec<(usize, u16)>,
+    }
+
+    impl Mapper for FetchRecorder {
+        fn read_cpu(&mut self, addr: u16) -> u8 {
+            self.nrom.read_cpu(addr)
+        }
+
+        fn write_cpu(&mut self, addr: u16, data: u8) {
+            self.nrom.write_cpu(addr, data)
+        }
+
+        fn read_ppu(&mut self, addr: u16) -> u8 {
+            self.nrom.read_ppu(addr)
+        }
+
+        fn write_ppu(&mut self, addr: u16, data: u8) {
+            self.nrom.write_ppu(addr, data)
+        }
+
+        fn mirroring(&self) -> Mirroring {
+            self.nrom.mirroring()
+        }
+
+        fn notify_ppu_address(&mut self, addr: u16, ppu_cycle: usize) {
+            self.addresses.push((ppu_cycle, addr));
+        }
+    }
+
+    #[test]
+    fn sprite_pattern_fetch_test() {
+        let mut ppu = Ppu::new();
+        let mut mapper = FetchRecorder {
+            nrom: Nrom::new(vec![0;0x4000], vec![0; 0x2000], Mirroring::Horizontal, 0, false),
+            addresses: Vec::new(),
+        };
+
+        // 8x16 sprite of the tile $02 / $03 at $1000 on the line 10-25, the only one
+        for n in 0..64 {
+            ppu.context.sprite_ram.write(n * 4, 0xFF);
+        }
+        for (offset, data) in [9, 0x03, 0x00, 0].iter().enumerate() {
+            ppu.context.sprite_ram.write(offset as u16, *data);
+        }
+        ppu.write(0x0000, 0x20, &mut mapper);
+        ppu.write(0x0001, 0x18, &mut mapper);
+        run_lines(&mut ppu, 10, &mut mapper);
+
+        // fetched at cycle 257-320 of the line 9 for the line 10: the row 0 of the tile $02, then the empty slots
+        let line_start = 9 * CLOCK_TO_RENDER_LINE;
+        let sprite_fetches: Vec<(usize, u16)> = mapper.addresses.iter()
+            .filter(|(ppu_cycle, addr)| (line_start + 257..=line_start + 320).contains(ppu_cycle) && *addr < 0x2000)
+            .map(|(ppu_cycle, addr)| (ppu_cycle - line_start, *addr))
+            .collect();
+        assert_eq!(&sprite_fetches[0..4], &[(261, 0x1020), (263, 0x1028), (269, 0x1FE0), (271, 0x1FE8)]);
+        assert_eq!(sprite_fetches.len(), 16);
+        assert_eq!(ppu.sprites.len(), 1);
+    }
+
+    #[test]
+    fn background_fetch_address_test() {
+        let mut ppu = Ppu::new();
+        let mut mapper = FetchRecorder {
+            nrom: Nrom::new(vec![0;0x4000], vec![0; 0x2000], Mirroring::Horizontal, 0, false),
+            addresses: Vec::new(),
+        };
+
+        // tile 5 at the tile (2, 0), the background at $1000
+        ppu.context.vram.write(0x0002, 5);
+        ppu.write(0x0000, 0x10, &mut mapper);
+        ppu.write(0x0001, 0x08, &mut mapper);
+        run_lines(&mut ppu, 262 + 2, &mut mapper);
+
+        let line_start = 262 * CLOCK_TO_RENDER_LINE;
+        let line_addresses: Vec<(usize, u16)> = mapper.addresses.iter()
+            .filter(|(ppu_cycle, _)| (line_start..line_start + CLOCK_TO_RENDER_LINE + 8).contains(ppu_cycle))
+            .map(|(ppu_cycle, addr)| (ppu_cycle - line_start, *addr))
+            .collect();
+
+        // the tile 2 of the line 0, the first 2 tiles are fetched in the pre-render line
+        assert_eq!(&line_addresses[0..4], &[(1, 0x2002), (3, 0x23C0), (5, 0x1050), (7, 0x1058)]);
+        // the unused nametable fetches and the first fetch of the line 1 read the same tile
+        let line_end = &line_addresses[line_addresses.len() - 6..];
+        assert_eq!(line_end, &[(337, 0x2002), (339, 0x2002), (342, 0x2002), (344, 0x23C0), (346, 0x1051), (348, 0x1059)]);
+    }
+
+    fn run_lines<T: Mapper + ?Sized>(ppu: &mut Ppu, lines: usize, mapper: &mut T) {
+        for _ in 0..lines {
+            ppu.run(CLOCK_TO_RENDER_LINE, mapper);
+        }
+    }
+
+    #[test]
+    fn sprite_overflow_test() {
+        let mut ppu = Ppu::new();
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
+        ppu.write(0x0001, 0x18, &mut mapper);
+
+        // 9 sprites on the line 20-27
+        for n in 0..64 {
+            let y = if n < 9 { 20 } else { 0xFF };
+            ppu.context.sprite_ram.write(n * 4, y);
+        }
+        run_lines(&mut ppu, 20, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x20, 0x00);
+        run_lines(&mut ppu, 1, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x20, 0x20);
+
+        // cleared at the pre-render line
+        run_lines(&mut ppu, PRE_RENDER_LINE - 21 + 1, &mut mapper);
+        assert_eq!(ppu.read(0x0002, &mut mapper) & 0x20, 0x00);
+    }
+
+    #[test]
+    fn oam_data_while_rendering_test() {
+        let mut ppu = Ppu::new();
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
+        ppu.write(0x0001, 0x18, &mut mapper);
+
+        ppu.write(0x0003, 0x01, &mut mapper);
+        ppu.write(0x0004, 0xAA, &mut mapper);
+        assert_eq!(ppu.context.sprite_ram.read(0x01), 0x00);
+        assert_eq!(ppu.registers.get_oam_addr(), 0x05);
+
+        // OAMADDR is reset at the end of the line
+        run_lines(&mut ppu, 1, &mut mapper);
+        assert_eq!(ppu.registers.get_oam_addr(), 0x00);
+    }
+
+    #[test]
+    fn oam_corruption_test() {
+        let mut ppu = Ppu::new();
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
+        for offset in 0..8 {
+            ppu.context.sprite_ram.write(0x28 + offset, 0x10 + offset as u8);
+        }
+
+        run_lines(&mut ppu, PRE_RENDER_LINE - 1, &mut mapper);
+        ppu.write(0x0003, 0x2B, &mut mapper);
+        ppu.write(0x0001, 0x18, &mut mapper);
+        run_lines(&mut ppu, 1, &mut mapper);
+        assert_eq!(ppu.context.sprite_ram.read_range(0..8), &[0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17]);
+    }
+}
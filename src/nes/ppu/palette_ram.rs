@@ -1,12 +1,20 @@
-use std::ops::Range;
 use crate::nes::ram::Ram;
 use super::palette::PaletteGroup;
 
 pub enum PaletteType {
     Sprite,
+    /// The background reads the entries by the pixel, only the tests read them as groups.
+    #[cfg(test)]
     Background,
 }
 
+/// Palette RAM at $3F00-$3F1F, mirrored up to $3FFF. The entries are 6 bits.
+///
+/// $3F00-$3F0F: background palettes
+/// $3F10-$3F1F: sprite palettes
+///   $3F10/$3F14/$3F18/$3F1C are mirrors of $3F00/$3F04/$3F08/$3F0C.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/PPU_palettes#Memory_Map
 pub struct PaletteRam(Ram);
 impl PaletteRam {
     pub fn new() -> Self {
@@ -14,29 +22,28 @@ impl PaletteRam {
     }
 
     pub fn write(&mut self, addr: u16, data: u8) {
-        self.0.write(addr, data);
+        self.0.write(PaletteRam::mirror(addr), data & 0x3F);
     }
 
     pub fn read(&self, addr: u16) -> u8 {
-        self.0.read(addr)
+        self.0.read(PaletteRam::mirror(addr))
     }
 
-    pub fn read_range(&self, range: Range<usize>) -> &[u8] {
-        &self.0.read_range(range)
+    fn mirror(addr: u16) -> u16 {
+        let addr = addr & 0x1F;
+        if addr & 0x13 == 0x10 { addr & 0x0F } else { addr }
     }
 
     pub fn get_palettes(&self, palette_id: u8, palette_type: PaletteType) -> PaletteGroup {
         let offset = match palette_type {
             PaletteType::Sprite => 0x10,
+            #[cfg(test)]
             PaletteType::Background => 0x00,
         };
 
-        let start_idx = (palette_id * 4 + offset) as usize;
-        let end_idx = start_idx + 4;
-
-        let palette_numbers = self.read_range(start_idx..end_idx);
-        let palette_numbers = array_ref!(palette_numbers, 0, 4);
-        PaletteGroup::build(palette_numbers)
+        let start = (palette_id * 4 + offset) as u16;
+        let palette_numbers = [0, 1, 2, 3].map(|index| self.read(start + index));
+        PaletteGroup::build(&palette_numbers)
     }
 }
 
@@ -47,19 +54,31 @@ mod palette_ram_test {
     #[test]
     fn can_be_read_and_write_test() {
         let mut ram = PaletteRam::new();
-        ram.write(0x0002, 0x4F);
+        ram.write(0x0002, 0x2F);
 
-        assert_eq!(ram.read(0x0002), 0x4F);
+        assert_eq!(ram.read(0x0002), 0x2F);
     }
 
     #[test]
-    fn read_range_test() {
+    fn mirror_test() {
         let mut ram = PaletteRam::new();
-        ram.write(0x0000, 0x00);
-        ram.write(0x0001, 0x01);
-        ram.write(0x0002, 0x02);
+        ram.write(0x0010, 0x11);
+        ram.write(0x0014, 0x12);
+        ram.write(0x0018, 0x13);
+        ram.write(0x003C, 0x14);
+        assert_eq!([ram.read(0x0000), ram.read(0x0004), ram.read(0x0008), ram.read(0x000C)], [0x11, 0x12, 0x13, 0x14]);
+
+        // the other sprite entries are not mirrored
+        ram.write(0x0011, 0x15);
+        assert_eq!(ram.read(0x0001), 0x00);
+        assert_eq!(ram.read(0x00F1), 0x15);
+    }
 
-        assert_eq!(ram.read_range(0..3), &[0x00, 0x01, 0x02]);
+    #[test]
+    fn six_bits_test() {
+        let mut ram = PaletteRam::new();
+        ram.write(0x0002, 0xFF);
+        assert_eq!(ram.read(0x0002), 0x3F);
     }
 
     #[test]
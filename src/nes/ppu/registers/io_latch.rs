@@ -0,0 +1,91 @@
+/// Frames until a bit of the latch decays to 0. (about 600ms)
+const DECAY_FRAMES: usize = 36;
+
+/// The PPU I/O data bus latch (open bus).
+///
+/// Every write to the registers fills the latch, and reads of the write-only registers
+/// return it. The bits are driven by the capacitance of the bus, so each bit decays to 0
+/// when it is not refreshed for a while.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/PPU_registers#Ports
+pub struct IoLatch {
+    value: u8,
+    /// Frames left until each bit decays (bit 0 - 7)
+    decay_frames: [usize; 8],
+}
+
+impl IoLatch {
+    pub fn new() -> Self {
+        IoLatch {
+            value: 0,
+            decay_frames: [0; 8],
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        self.value
+    }
+
+    /// Drives all the bits.
+    pub fn write(&mut self, data: u8) {
+        self.refresh(data, 0xFF);
+    }
+
+    /// Drives the bits of the mask, the other bits keep the current values.
+    pub fn refresh(&mut self, data: u8, mask: u8) {
+        self.value = (self.value & !mask) | (data & mask);
+        for (bit, frames) in self.decay_frames.iter_mut().enumerate() {
+            if mask & (1 << bit) != 0 {
+                *frames = DECAY_FRAMES;
+            }
+        }
+    }
+
+    /// Called once per frame.
+    pub fn decay(&mut self) {
+        for (bit, frames) in self.decay_frames.iter_mut().enumerate() {
+            if *frames > 0 {
+                *frames -= 1;
+                if *frames == 0 {
+                    self.value &= !(1 << bit);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod io_latch_test {
+    use super::*;
+
+    #[test]
+    fn refresh_test() {
+        let mut latch = IoLatch::new();
+        latch.write(0xA5);
+        assert_eq!(latch.read(), 0xA5);
+
+        latch.refresh(0x1F, 0xE0);
+        assert_eq!(latch.read(), 0x05);
+    }
+
+    #[test]
+    fn decay_test() {
+        let mut latch = IoLatch::new();
+        latch.write(0xFF);
+        for _ in 0..(DECAY_FRAMES / 2) {
+            latch.decay();
+        }
+        latch.refresh(0x0F, 0x0F);
+        for _ in 0..(DECAY_FRAMES / 2) {
+            latch.decay();
+        }
+
+        // the refreshed bits are left
+        assert_eq!(latch.read(), 0x0F);
+
+        for _ in 0..(DECAY_FRAMES / 2) {
+            latch.decay();
+        }
+        assert_eq!(latch.read(), 0x00);
+    }
+}
@@ -1,29 +1,30 @@
 mod ppu_ctrl;
 mod ppu_mask;
-mod ppu_scroll;
-mod ppu_addr;
 mod ppu_data;
 mod ppu_status;
 mod oam;
+mod io_latch;
+mod vram_addr;
 
 use self::ppu_ctrl::PpuCtrl;
 use self::ppu_mask::PpuMask;
-use self::ppu_scroll::PpuScroll;
-use self::ppu_addr::PpuAddr;
 use self::ppu_data::PpuData;
 use self::ppu_status::PpuStatus;
 use self::oam::Oam;
+use self::io_latch::IoLatch;
+use self::vram_addr::VramAddr;
 
+use crate::nes::cassette::mapper::Mapper;
 use crate::nes::ppu::PpuContext;
 
 pub struct Registers {
     pub ppu_ctrl: PpuCtrl,
     pub ppu_mask: PpuMask,
-    pub ppu_addr: PpuAddr,
     pub ppu_data: PpuData,
-    pub ppu_scroll: PpuScroll,
+    pub vram_addr: VramAddr,
     pub ppu_status: PpuStatus,
     pub oam: Oam,
+    io_latch: IoLatch,
 }
 
 impl Registers {
@@ -31,62 +32,158 @@ impl Registers {
         Registers {
             ppu_ctrl: PpuCtrl::new(),
             ppu_mask: PpuMask::new(),
-            ppu_scroll: PpuScroll::new(),
-            ppu_addr: PpuAddr::new(),
             ppu_data: PpuData::new(),
+            vram_addr: VramAddr::new(),
             ppu_status: PpuStatus::new(),
             oam: Oam::new(),
+            io_latch: IoLatch::new(),
         }
     }
 
-    pub fn write(&mut self, addr: u16, data: u8, ppu_context: &mut PpuContext) {
+    /// addr: $2000-$2007 as 0x0000-0x0007
+    pub fn write<T: Mapper + ?Sized>(&mut self, addr: u16, data: u8, ppu_context: &mut PpuContext, mapper: &mut T) {
+        self.io_latch.write(data);
+
         match addr {
-            0x0000 => self.ppu_ctrl.write(data),
+            0x0000 => {
+                self.ppu_ctrl.write(data);
+                self.vram_addr.write_ctrl(data);
+            },
             0x0001 => self.ppu_mask.write(data),
-            0x0005 => self.ppu_scroll.write(data),
-            0x0006 => self.ppu_addr.write(data as u16),
-            0x0007 => self.ppu_data_write(data, ppu_context),
-            _ => panic!("unimplement write address: {}", addr),
+            0x0003 => self.oam.write_addr(data),
+            0x0004 => self.oam.write_data(&mut ppu_context.sprite_ram, data),
+            0x0005 => self.vram_addr.write_scroll(data),
+            0x0006 => self.vram_addr.write_addr(data),
+            0x0007 => self.ppu_data_write(data, ppu_context, mapper),
+            // PPUSTATUS is read only
+            _ => {},
         }
     }
 
-    pub fn read(&mut self, addr: u16, ppu_context: &mut PpuContext) -> u8 {
+    /// addr: $2000-$2007 as 0x0000-0x0007
+    /// The write-only registers return the open bus.
+    pub fn read<T: Mapper + ?Sized>(&mut self, addr: u16, ppu_context: &mut PpuContext, mapper: &mut T) -> u8 {
         match addr {
-            0x0002 => self.read_status(),
-            0x0007 => self.ppu_data_read(ppu_context),
-            _ => panic!("unimplement read address: {}", addr),
+            0x0002 => {
+                // the lower 5 bits are not driven by PPUSTATUS
+                let data = self.read_status() | (self.io_latch.read() & 0x1F);
+                self.io_latch.refresh(data, 0xE0);
+                data
+            },
+            0x0004 => {
+                let data = self.oam.read_data(&ppu_context.sprite_ram);
+                self.io_latch.write(data);
+                data
+            },
+            0x0007 => {
+                let is_palette = self.vram_addr.read() >= 0x3F00;
+                let data = self.ppu_data_read(ppu_context, mapper);
+                if is_palette {
+                    // the palette entries are 6 bits, the upper 2 bits are the open bus
+                    let data = if self.ppu_mask.is_grayscale() { data & 0x30 } else { data };
+                    let data = data | (self.io_latch.read() & 0xC0);
+                    self.io_latch.refresh(data, 0x3F);
+                    data
+                } else {
+                    self.io_latch.write(data);
+                    data
+                }
+            },
+            _ => self.io_latch.read(),
         }
     }
 
-    pub fn get_nametable_id(&self) -> u8 {
-        self.ppu_ctrl.get_nametable_id()
+    /// OAMDATA write while rendering. OAM is not written, the address is bumped instead.
+    pub fn write_oam_data_while_rendering(&mut self, data: u8) {
+        self.io_latch.write(data);
+        self.oam.write_data_while_rendering();
+    }
+
+    pub fn get_oam_addr(&self) -> u16 {
+        self.oam.get_addr()
+    }
+
+    /// OAMADDR is set to 0 at the sprite tile loading of the rendering lines.
+    pub fn reset_oam_addr(&mut self) {
+        self.oam.reset_addr();
+    }
+
+    /// Called once per frame to decay the open bus.
+    pub fn decay_io_latch(&mut self) {
+        self.io_latch.decay();
+    }
+
+    pub fn get_background_pattern_table_addr(&self) -> u16 {
+        self.ppu_ctrl.get_background_pattern_table_addr()
+    }
+
+    pub fn get_sprite_pattern_table_addr(&self) -> u16 {
+        self.ppu_ctrl.get_sprite_pattern_table_addr()
+    }
+
+    pub fn is_sprite_8x16(&self) -> bool {
+        self.ppu_ctrl.is_sprite_8x16()
+    }
+
+    pub fn is_rendering_enabled(&self) -> bool {
+        self.ppu_mask.is_rendering_enabled()
     }
 
-    fn ppu_data_read(&mut self, ppu_context: &mut PpuContext) -> u8 {
-        let addr = self.ppu_addr.read();
-        let data = self.ppu_data.read(addr, ppu_context);
+    pub fn is_grayscale(&self) -> bool {
+        self.ppu_mask.is_grayscale()
+    }
+
+    /// Emphasis bits of PPUMASK, bit 0: red, 1: green, 2: blue
+    pub fn get_emphasis(&self) -> u8 {
+        self.ppu_mask.get_emphasis()
+    }
+
+    pub fn is_background_enabled(&self) -> bool {
+        self.ppu_mask.is_background_enabled()
+    }
+
+    pub fn is_sprite_enabled(&self) -> bool {
+        self.ppu_mask.is_sprite_enabled()
+    }
+
+    /// The background is shown in the leftmost 8 pixels of the screen.
+    pub fn is_background_leftmost_shown(&self) -> bool {
+        self.ppu_mask.is_background_leftmost_shown()
+    }
+
+    /// The sprites are shown in the leftmost 8 pixels of the screen.
+    pub fn is_sprite_leftmost_shown(&self) -> bool {
+        self.ppu_mask.is_sprite_leftmost_shown()
+    }
+
+    /// The address currently on the PPU address bus while not rendering.
+    pub fn get_vram_addr(&self) -> u16 {
+        self.vram_addr.read()
+    }
+
+    fn ppu_data_read<T: Mapper + ?Sized>(&mut self, ppu_context: &mut PpuContext, mapper: &mut T) -> u8 {
+        let addr = self.vram_addr.read();
+        let data = self.ppu_data.read(addr, ppu_context, mapper);
         self.increment_vram();
 
         data
     }
 
-    fn ppu_data_write(&mut self, data: u8, ppu_context: &mut PpuContext) {
-        let addr = self.ppu_addr.read();
-        self.ppu_data.write(addr, data, ppu_context);
+    fn ppu_data_write<T: Mapper + ?Sized>(&mut self, data: u8, ppu_context: &mut PpuContext, mapper: &mut T) {
+        let addr = self.vram_addr.read();
+        self.ppu_data.write(addr, data, ppu_context, mapper);
         self.increment_vram();
     }
 
     fn increment_vram(&mut self) {
         let offset = self.ppu_ctrl.get_vram_increment_offset();
-        self.ppu_addr.update(offset);
+        self.vram_addr.update(offset);
     }
 
     fn read_status(&mut self) -> u8 {
         let data = self.ppu_status.to_u8();
-        self.ppu_scroll.enable_target_x();
-        self.ppu_addr.reset_latch();
+        self.vram_addr.reset_latch();
         self.ppu_status.vblank_flag = false;
-        self.ppu_status.sprite_hit = false;
         data
     }
 
@@ -101,94 +198,235 @@ impl Registers {
     pub fn clear_sprite_hit(&mut self) {
         self.ppu_status.sprite_hit = false;
     }
+
+    pub fn set_sprite_hit(&mut self) {
+        self.ppu_status.sprite_hit = true;
+    }
+
+    pub fn set_sprite_overflow(&mut self) {
+        self.ppu_status.sprite_overflow = true;
+    }
+
+    pub fn clear_sprite_overflow(&mut self) {
+        self.ppu_status.sprite_overflow = false;
+    }
 }
 
 #[cfg(test)]
 mod registers_test {
     use super::*;
+    use crate::nes::cassette::mapper::Mirroring;
+    use crate::nes::cassette::mapper::nrom::Nrom;
     use crate::nes::ppu::palette_ram::PaletteRam;
     use crate::nes::ram::Ram;
 
     fn dummy_ppu_context() -> PpuContext {
         PpuContext {
             vram: Ram::new(vec![0;0x20]),
-            cram: Ram::new(vec![0;0x20]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         }
     }
 
+    fn dummy_mapper() -> Nrom {
+        Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false)
+    }
+
     #[test]
     fn increment_vram_test() {
         let mut registers = Registers::new();
         registers.increment_vram();
-        assert_eq!(registers.ppu_addr.read(), 0x0000 + 1); // increment 1
+        assert_eq!(registers.vram_addr.read(), 0x0001); // increment 1
 
         let mut registers = Registers::new();
         registers.ppu_ctrl.write(0b00000100);
         registers.increment_vram();
-        assert_eq!(registers.ppu_addr.read(), 0x0000 + 32); // increment 32
+        assert_eq!(registers.vram_addr.read(), 0x0020); // increment 32
     }
 
     #[test]
     fn write_ppu_ctrl_test() {
         let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
         let mut registers = Registers::new();
-        registers.write(0x0000, 0xFF, &mut ppu_context);
+        registers.write(0x0000, 0xFF, &mut ppu_context, &mut mapper);
         assert_eq!(registers.ppu_ctrl.read(), 0xFF);
     }
 
     #[test]
     fn write_ppu_mask_test() {
         let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
         let mut registers = Registers::new();
-        registers.write(0x0001, 0xFF, &mut ppu_context);
+        registers.write(0x0001, 0xFF, &mut ppu_context, &mut mapper);
         assert_eq!(registers.ppu_mask.read(), 0xFF);
     }
 
     #[test]
     fn write_ppu_scroll_test() {
         let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
         let mut registers = Registers::new();
 
-        registers.write(0x0005, 0xFF, &mut ppu_context);
-        registers.write(0x0005, 0xEE, &mut ppu_context);
-        assert_eq!(registers.ppu_scroll.x, 0xFF);
-        assert_eq!(registers.ppu_scroll.y, 0xEE);
+        registers.write(0x0005, 0xFF, &mut ppu_context, &mut mapper);
+        registers.write(0x0005, 0xEE, &mut ppu_context, &mut mapper);
+
+        // t is copied to v while rendering
+        registers.vram_addr.copy_x();
+        registers.vram_addr.copy_y();
+        assert_eq!(registers.vram_addr.coarse_x(), 31);
+        assert_eq!(registers.vram_addr.fine_x(), 7);
+        assert_eq!(registers.vram_addr.coarse_y(), 29);
+        assert_eq!(registers.vram_addr.fine_y(), 6);
     }
 
     #[test]
     fn write_ppu_addr_test() {
         let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
         let mut registers = Registers::new();
 
-        registers.write(0x0006, 0xFF, &mut ppu_context);
-        assert_eq!(registers.ppu_addr.read(), 0xFF00);
+        registers.write(0x0006, 0x3F, &mut ppu_context, &mut mapper);
+        registers.write(0x0006, 0x10, &mut ppu_context, &mut mapper);
+        assert_eq!(registers.vram_addr.read(), 0x3F10);
     }
 
     #[test]
     fn write_ppu_data_test() {
         let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
         let mut registers = Registers::new();
 
         registers.ppu_data.buf = 0x10;
-        registers.write(0x0007, 0xFF, &mut ppu_context);
-        assert_eq!(registers.ppu_addr.read(), 0x00 + 1); // incremented
+        registers.write(0x0007, 0xFF, &mut ppu_context, &mut mapper);
+        assert_eq!(registers.vram_addr.read(), 0x0001); // incremented
 
-        assert_eq!(registers.ppu_data.read(0x0000, &mut ppu_context), 0x10); // read PpuData buf
-        assert_eq!(registers.ppu_data.read(0x0000, &mut ppu_context), 0xFF); // read wrote data
+        assert_eq!(registers.ppu_data.read(0x0000, &mut ppu_context, &mut mapper), 0x10); // read PpuData buf
+        assert_eq!(registers.ppu_data.read(0x0000, &mut ppu_context, &mut mapper), 0xFF); // read wrote data
     }
 
     #[test]
     fn read_ppu_data_test() {
         let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
         let mut registers = Registers::new();
-        registers.ppu_addr.update(0x0F);
-        registers.ppu_data.write(0x000F, 0xEE, &mut ppu_context);
+        registers.vram_addr.update(0x0F);
+        registers.ppu_data.write(0x000F, 0xEE, &mut ppu_context, &mut mapper);
 
-        assert_eq!(registers.read(0x0007, &mut ppu_context), 0x00); // read PpuData buf
-        assert_eq!(registers.ppu_addr.read(), 0x0F + 1);            // incremented
-        assert_eq!(registers.read(0x0007, &mut ppu_context), 0xEE); // read wrote data
-        assert_eq!(registers.ppu_addr.read(), 0x0F + 2);            // incremented
+        assert_eq!(registers.read(0x0007, &mut ppu_context, &mut mapper), 0x00); // read PpuData buf
+        assert_eq!(registers.vram_addr.read(), 0x0F + 1);            // incremented
+        assert_eq!(registers.read(0x0007, &mut ppu_context, &mut mapper), 0xEE); // read wrote data
+        assert_eq!(registers.vram_addr.read(), 0x0F + 2);            // incremented
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn read_open_bus_test() {
+        let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
+        let mut registers = Registers::new();
+
+        registers.write(0x0000, 0x3A, &mut ppu_context, &mut mapper);
+        assert_eq!(registers.read(0x0000, &mut ppu_context, &mut mapper), 0x3A);
+        assert_eq!(registers.read(0x0005, &mut ppu_context, &mut mapper), 0x3A);
+
+        // PPUSTATUS: the upper 3 bits from the status, the lower 5 bits from the latch
+        registers.set_vblank();
+        assert_eq!(registers.read(0x0002, &mut ppu_context, &mut mapper), 0x9A);
+        assert_eq!(registers.read(0x0006, &mut ppu_context, &mut mapper), 0x9A);
+        assert_eq!(registers.read(0x0002, &mut ppu_context, &mut mapper), 0x1A);
+    }
+
+    #[test]
+    fn oam_data_test() {
+        let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
+        let mut registers = Registers::new();
+
+        registers.write(0x0003, 0x10, &mut ppu_context, &mut mapper);
+        registers.write(0x0004, 0xAA, &mut ppu_context, &mut mapper);
+        registers.write(0x0004, 0xBB, &mut ppu_context, &mut mapper);
+        assert_eq!(ppu_context.sprite_ram.read(0x10), 0xAA);
+        assert_eq!(ppu_context.sprite_ram.read(0x11), 0xBB);
+
+        registers.write(0x0003, 0x11, &mut ppu_context, &mut mapper);
+        assert_eq!(registers.read(0x0004, &mut ppu_context, &mut mapper), 0xBB);
+        assert_eq!(registers.get_oam_addr(), 0x11);
+    }
+
+    #[test]
+    fn read_sprite_overflow_test() {
+        let mut ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
+        let mut registers = Registers::new();
+
+        registers.set_sprite_overflow();
+        assert_eq!(registers.read(0x0002, &mut ppu_context, &mut mapper), 0x20);
+        registers.clear_sprite_overflow();
+        assert_eq!(registers.read(0x0002, &mut ppu_context, &mut mapper), 0x00);
+    }
+
+    /// The steps of rom/palette_ram.nes
+    #[test]
+    fn palette_ram_test() {
+        let mut ppu_context = PpuContext {
+            vram: Ram::new(vec![0;0x1000]),
+            sprite_ram: Ram::new(vec![0;0x20]),
+            palette_ram: PaletteRam::new(),
+        };
+        let mut mapper = dummy_mapper();
+        let mut registers = Registers::new();
+        let set_addr = |registers: &mut Registers, ppu_context: &mut PpuContext, mapper: &mut Nrom, index: u8| {
+            registers.read(0x0002, ppu_context, mapper);
+            registers.write(0x0006, 0x3F, ppu_context, mapper);
+            registers.write(0x0006, index, ppu_context, mapper);
+        };
+
+        // 2) palette read shouldn't be buffered like other VRAM
+        set_addr(&mut registers, &mut ppu_context, &mut mapper, 0x00);
+        registers.write(0x0007, 0x12, &mut ppu_context, &mut mapper);
+        registers.write(0x0007, 0x34, &mut ppu_context, &mut mapper);
+        set_addr(&mut registers, &mut ppu_context, &mut mapper, 0x00);
+        assert_eq!(registers.read(0x0007, &mut ppu_context, &mut mapper) & 0x3F, 0x12);
+
+        // 4) palette should be mirrored within $3f00-$3fff
+        set_addr(&mut registers, &mut ppu_context, &mut mapper, 0xE0);
+        registers.write(0x0007, 0x34, &mut ppu_context, &mut mapper);
+        set_addr(&mut registers, &mut ppu_context, &mut mapper, 0x00);
+        assert_eq!(registers.read(0x0007, &mut ppu_context, &mut mapper) & 0x3F, 0x34);
+
+        // 5) write to $10 should be mirrored at $00
+        set_addr(&mut registers, &mut ppu_context, &mut mapper, 0x10);
+        registers.write(0x0007, 0x12, &mut ppu_context, &mut mapper);
+        set_addr(&mut registers, &mut ppu_context, &mut mapper, 0x00);
+        assert_eq!(registers.read(0x0007, &mut ppu_context, &mut mapper) & 0x3F, 0x12);
+
+        // 6) write to $00 should be mirrored at $10
+        set_addr(&mut registers, &mut ppu_context, &mut mapper, 0x00);
+        registers.write(0x0007, 0x34, &mut ppu_context, &mut mapper);
+        set_addr(&mut registers, &mut ppu_context, &mut mapper, 0x10);
+        assert_eq!(registers.read(0x0007, &mut ppu_context, &mut mapper) & 0x3F, 0x34);
+    }
+
+    #[test]
+    fn read_palette_bits_test() {
+        let mut ppu_context = PpuContext {
+            vram: Ram::new(vec![0;0x1000]),
+            sprite_ram: Ram::new(vec![0;0x20]),
+            palette_ram: PaletteRam::new(),
+        };
+        let mut mapper = dummy_mapper();
+        let mut registers = Registers::new();
+        ppu_context.palette_ram.write(0x01, 0x2A);
+
+        // the upper 2 bits from the open bus
+        registers.write(0x0006, 0x3F, &mut ppu_context, &mut mapper);
+        registers.write(0x0006, 0xC1, &mut ppu_context, &mut mapper);
+        assert_eq!(registers.read(0x0007, &mut ppu_context, &mut mapper), 0xEA);
+
+        // grayscale: the hue bits are cleared
+        registers.write(0x0001, 0x01, &mut ppu_context, &mut mapper);
+        registers.write(0x0006, 0x3F, &mut ppu_context, &mut mapper);
+        registers.write(0x0006, 0x01, &mut ppu_context, &mut mapper);
+        assert_eq!(registers.read(0x0007, &mut ppu_context, &mut mapper), 0x20);
+    }
+}
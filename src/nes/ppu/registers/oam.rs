@@ -1,5 +1,8 @@
 use crate::nes::ram::Ram;
 
+/// OAMADDR ($2003) / OAMDATA ($2004)
+///
+/// refer: https://wiki.nesdev.com/w/index.php/PPU_registers#OAMADDR
 pub struct Oam {
     addr: u16,
 }
@@ -25,10 +28,64 @@ impl Oam {
 
     pub fn write_data(&mut self, ram: &mut Ram, data: u8) {
         ram.write(self.addr, data);
-        self.addr += 1;
+        self.addr = (self.addr + 1) & 0xFF;
+    }
+
+    /// Writes while rendering don't reach OAM, but bump the upper 6 bits of the address.
+    pub fn write_data_while_rendering(&mut self) {
+        self.addr = (self.addr + 4) & 0xFF;
     }
 
     pub fn read_data(&self, ram: &Ram) -> u8 {
-        ram.read(self.addr)
+        let data = ram.read(self.addr);
+        // bit 2-4 of the sprite attribute are not implemented in OAM, those read back as 0
+        if self.addr & 0x03 == 0x02 {
+            data & 0xE3
+        } else {
+            data
+        }
+    }
+}
+
+#[cfg(test)]
+mod oam_test {
+    use super::*;
+
+    #[test]
+    fn write_data_test() {
+        let mut ram = Ram::new(vec![0; 0x0100]);
+        let mut oam = Oam::new();
+
+        oam.write_addr(0xFE);
+        oam.write_data(&mut ram, 0x11);
+        oam.write_data(&mut ram, 0x22);
+        oam.write_data(&mut ram, 0x33);
+        assert_eq!(ram.read(0xFE), 0x11);
+        assert_eq!(ram.read(0xFF), 0x22);
+        assert_eq!(ram.read(0x00), 0x33); // wraps around
+        assert_eq!(oam.get_addr(), 0x01);
+    }
+
+    #[test]
+    fn read_data_test() {
+        let mut ram = Ram::new(vec![0; 0x0100]);
+        let mut oam = Oam::new();
+        ram.write(0x01, 0xFF);
+        ram.write(0x02, 0xFF);
+
+        oam.write_addr(0x01);
+        assert_eq!(oam.read_data(&ram), 0xFF);
+        assert_eq!(oam.get_addr(), 0x01); // not incremented
+
+        oam.write_addr(0x02);
+        assert_eq!(oam.read_data(&ram), 0xE3);
+    }
+
+    #[test]
+    fn write_data_while_rendering_test() {
+        let mut oam = Oam::new();
+        oam.write_addr(0xFD);
+        oam.write_data_while_rendering();
+        assert_eq!(oam.get_addr(), 0x01);
     }
 }
@@ -38,6 +38,7 @@ impl PpuCtrl {
         self.read_backdrop_from_ext           = (data & 0b10000000) >> 7 == 1;
     }
 
+    #[cfg(test)]
     pub fn read(&self) -> u8 {
          self.nametable_address |
         (self.vram_address_increment_ppudata as u8)   << 2 |
@@ -48,8 +49,24 @@ impl PpuCtrl {
         (self.read_backdrop_from_ext as u8)           << 7
     }
 
-    pub fn get_nametable_id(&self) -> u8 {
-        self.nametable_address
+    pub fn get_background_pattern_table_addr(&self) -> u16 {
+        if self.background_pattern_table_address {
+            0x1000
+        } else {
+            0x0000
+        }
+    }
+
+    pub fn get_sprite_pattern_table_addr(&self) -> u16 {
+        if self.sprite_pattern_table_address_8x8 {
+            0x1000
+        } else {
+            0x0000
+        }
+    }
+
+    pub fn is_sprite_8x16(&self) -> bool {
+        self.sprite_size
     }
 
     pub fn get_vram_increment_offset(&self) -> u8 {
@@ -71,12 +88,24 @@ mod ppu_ctrl_test {
         ppu_ctrl.write(0b10101010);
 
         assert_eq!(ppu_ctrl.nametable_address, 2);
-        assert_eq!(ppu_ctrl.vram_address_increment_ppudata, false);
-        assert_eq!(ppu_ctrl.sprite_pattern_table_address_8x8, true);
-        assert_eq!(ppu_ctrl.background_pattern_table_address, false);
-        assert_eq!(ppu_ctrl.sprite_size, true);
-        assert_eq!(ppu_ctrl.ppu_select, false);
-        assert_eq!(ppu_ctrl.read_backdrop_from_ext, true);
+        assert!(!ppu_ctrl.vram_address_increment_ppudata);
+        assert!(ppu_ctrl.sprite_pattern_table_address_8x8);
+        assert!(!ppu_ctrl.background_pattern_table_address);
+        assert!(ppu_ctrl.sprite_size);
+        assert!(!ppu_ctrl.ppu_select);
+        assert!(ppu_ctrl.read_backdrop_from_ext);
+    }
+
+    #[test]
+    fn pattern_table_addr_test() {
+        let mut ppu_ctrl = PpuCtrl::new();
+        ppu_ctrl.write(0b00001000);
+        assert_eq!(ppu_ctrl.get_background_pattern_table_addr(), 0x0000);
+        assert_eq!(ppu_ctrl.get_sprite_pattern_table_addr(), 0x1000);
+
+        ppu_ctrl.write(0b00010000);
+        assert_eq!(ppu_ctrl.get_background_pattern_table_addr(), 0x1000);
+        assert_eq!(ppu_ctrl.get_sprite_pattern_table_addr(), 0x0000);
     }
 
     #[test]
@@ -1,3 +1,4 @@
+use crate::nes::cassette::mapper::Mapper;
 use crate::nes::ppu::PpuContext;
 
 enum MapType {
@@ -32,25 +33,26 @@ impl PpuData {
         PpuData { buf: 0 }
     }
 
-    pub fn write(&mut self, addr: u16, data: u8, ppu_context: &mut PpuContext) {
+    pub fn write<T: Mapper + ?Sized>(&mut self, addr: u16, data: u8, ppu_context: &mut PpuContext, mapper: &mut T) {
         let calibrated_addr = self.calibrate_address(addr);
 
         match PpuMemoryMapRule::address_to_map_type(addr) {
-            MapType::PatternTable => ppu_context.cram.write(calibrated_addr, data),
-            MapType::Vram | MapType::VramMirror => ppu_context.vram.write(calibrated_addr, data),
+            MapType::PatternTable => mapper.write_ppu(calibrated_addr, data),
+            MapType::Vram | MapType::VramMirror => ppu_context.write_nametable(addr, data, mapper),
             MapType::Palette | MapType::PaletteMirror => ppu_context.palette_ram.write(calibrated_addr, data),
         };
     }
 
-    pub fn read(&mut self, addr: u16, ppu_context: &mut PpuContext) -> u8 {
+    pub fn read<T: Mapper + ?Sized>(&mut self, addr: u16, ppu_context: &mut PpuContext, mapper: &mut T) -> u8 {
         let buf = self.buf;
         let calibrated_addr = self.calibrate_address(addr);
 
         match PpuMemoryMapRule::address_to_map_type(addr) {
-            MapType::PatternTable => self.buf = ppu_context.cram.read(calibrated_addr),
-            MapType::Vram | MapType::VramMirror => self.buf = ppu_context.vram.read(calibrated_addr),
+            MapType::PatternTable => self.buf = mapper.read_ppu(calibrated_addr),
+            MapType::Vram | MapType::VramMirror => self.buf = ppu_context.read_nametable(addr, mapper),
+            // not buffered, the buffer is filled with the nametable under the palette
             MapType::Palette | MapType::PaletteMirror => {
-                self.buf = ppu_context.vram.read(calibrated_addr);
+                self.buf = ppu_context.read_nametable(addr - 0x1000, mapper);
                 return ppu_context.palette_ram.read(calibrated_addr)
             },
         };
@@ -76,22 +78,24 @@ impl PpuData {
 #[cfg(test)]
 mod ppu_data_test {
     use super::*;
+    use crate::nes::cassette::mapper::Mirroring;
+    use crate::nes::cassette::mapper::nrom::Nrom;
     use crate::nes::ppu::palette_ram::PaletteRam;
     use crate::nes::ram::Ram;
 
     #[test]
     fn read_pattern_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
             vram: Ram::new(vec![0;0x20]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
-        ppu_context.cram.write(0x00, 0xFF);
+        mapper.write_ppu(0x00, 0xFF);
 
         let mut ppu_data = PpuData { buf: 0xEE };
-        let read_data = ppu_data.read(0x0000, &mut ppu_context);
+        let read_data = ppu_data.read(0x0000, &mut ppu_context, &mut mapper);
 
         assert_eq!(read_data, 0xEE);
         assert_eq!(ppu_data.buf, 0xFF);
@@ -100,16 +104,16 @@ mod ppu_data_test {
     #[test]
     fn read_vram_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
             vram: Ram::new(vec![0;0x20]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
         ppu_context.vram.write(0x00, 0xFF);
 
         let mut ppu_data = PpuData { buf: 0xEE };
-        let read_data = ppu_data.read(0x2000, &mut ppu_context);
+        let read_data = ppu_data.read(0x2000, &mut ppu_context, &mut mapper);
 
         assert_eq!(read_data, 0xEE);
         assert_eq!(ppu_data.buf, 0xFF);
@@ -118,16 +122,16 @@ mod ppu_data_test {
     #[test]
     fn read_vram_mirror_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
             vram: Ram::new(vec![0;0x20]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
         ppu_context.vram.write(0x00, 0xFF);
 
         let mut ppu_data = PpuData { buf: 0xEE };
-        let read_data = ppu_data.read(0x3000, &mut ppu_context);
+        let read_data = ppu_data.read(0x3000, &mut ppu_context, &mut mapper);
 
         assert_eq!(read_data, 0xEE);
         assert_eq!(ppu_data.buf, 0xFF);
@@ -136,67 +140,67 @@ mod ppu_data_test {
     #[test]
     fn read_palette_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
-            vram: Ram::new(vec![0;0x20]),
+            vram: Ram::new(vec![0;0x1000]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
-        ppu_context.vram.write(0x00, 0xFF);
-        ppu_context.palette_ram.write(0x00, 0xEE);
+        ppu_context.vram.write(0x0700, 0xFF); // $2F00
+        ppu_context.palette_ram.write(0x00, 0x2E);
 
         let mut ppu_data = PpuData::new();
-        let read_data = ppu_data.read(0x3F00, &mut ppu_context);
+        let read_data = ppu_data.read(0x3F00, &mut ppu_context, &mut mapper);
 
-        assert_eq!(read_data, 0xEE);
+        assert_eq!(read_data, 0x2E);
         assert_eq!(ppu_data.buf, 0xFF);
     }
 
     #[test]
     fn read_palette_mirror_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
-            vram: Ram::new(vec![0;0x20]),
+            vram: Ram::new(vec![0;0x1000]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
-        ppu_context.vram.write(0x00, 0xFF);
-        ppu_context.palette_ram.write(0x00, 0xEE);
+        ppu_context.vram.write(0x0720, 0xFF); // $2F20
+        ppu_context.palette_ram.write(0x00, 0x2E);
 
         let mut ppu_data = PpuData::new();
-        let read_data = ppu_data.read(0x3F20, &mut ppu_context);
+        let read_data = ppu_data.read(0x3F20, &mut ppu_context, &mut mapper);
 
-        assert_eq!(read_data, 0xEE);
+        assert_eq!(read_data, 0x2E);
         assert_eq!(ppu_data.buf, 0xFF);
     }
 
     #[test]
     fn write_pattern_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
             vram: Ram::new(vec![0;0x20]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
         let mut ppu_data = PpuData::new();
-        ppu_data.write(0x0000, 0xFF, &mut ppu_context);
+        ppu_data.write(0x0000, 0xFF, &mut ppu_context, &mut mapper);
 
-        assert_eq!(ppu_context.cram.read(0x0000), 0xFF);
+        assert_eq!(mapper.read_ppu(0x0000), 0xFF);
     }
 
     #[test]
     fn write_vram_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
             vram: Ram::new(vec![0;0x20]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
         let mut ppu_data = PpuData::new();
-        ppu_data.write(0x2000, 0xFF, &mut ppu_context);
+        ppu_data.write(0x2000, 0xFF, &mut ppu_context, &mut mapper);
 
         assert_eq!(ppu_context.vram.read(0x0000), 0xFF);
     }
@@ -204,14 +208,14 @@ mod ppu_data_test {
     #[test]
     fn write_vram_mirror_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
             vram: Ram::new(vec![0;0x20]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
         let mut ppu_data = PpuData::new();
-        ppu_data.write(0x3000, 0xFF, &mut ppu_context);
+        ppu_data.write(0x3000, 0xFF, &mut ppu_context, &mut mapper);
 
         assert_eq!(ppu_context.vram.read(0x0000), 0xFF);
     }
@@ -219,30 +223,57 @@ mod ppu_data_test {
     #[test]
     fn write_palette_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
-            vram: Ram::new(vec![0;0x20]),
+            vram: Ram::new(vec![0;0x1000]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
         let mut ppu_data = PpuData::new();
-        ppu_data.write(0x3F00, 0xFF, &mut ppu_context);
+        ppu_data.write(0x3F00, 0x3F, &mut ppu_context, &mut mapper);
 
-        assert_eq!(ppu_context.palette_ram.read(0x0000), 0xFF);
+        assert_eq!(ppu_context.palette_ram.read(0x0000), 0x3F);
     }
 
     #[test]
     fn write_palette_mirror_test() {
         let mut ppu_context = PpuContext {
-            cram: Ram::new(vec![0;0x20]),
-            vram: Ram::new(vec![0;0x20]),
+            vram: Ram::new(vec![0;0x1000]),
             sprite_ram: Ram::new(vec![0;0x20]),
             palette_ram: PaletteRam::new(),
         };
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
 
         let mut ppu_data = PpuData::new();
-        ppu_data.write(0x3F20, 0xFF, &mut ppu_context);
+        ppu_data.write(0x3F20, 0x3F, &mut ppu_context, &mut mapper);
+
+        assert_eq!(ppu_context.palette_ram.read(0x0000), 0x3F);
+    }
+
+    #[test]
+    fn nametable_mirroring_test() {
+        let mut ppu_context = PpuContext {
+            vram: Ram::new(vec![0;0x1000]),
+            sprite_ram: Ram::new(vec![0;0x20]),
+            palette_ram: PaletteRam::new(),
+        };
+        let mut ppu_data = PpuData::new();
 
-        assert_eq!(ppu_context.palette_ram.read(0x0000), 0xFF);
+        // horizontal: $2400 is $2000, $2800 is the second page
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Horizontal, 0, false);
+        ppu_data.write(0x2401, 0x11, &mut ppu_context, &mut mapper);
+        ppu_data.write(0x2801, 0x22, &mut ppu_context, &mut mapper);
+        assert_eq!(ppu_context.vram.read(0x0001), 0x11);
+        assert_eq!(ppu_context.vram.read(0x0401), 0x22);
+
+        // vertical: $2C00 is $2400, through the mirror at $3000-$3EFF
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::Vertical, 0, false);
+        ppu_data.read(0x3C01, &mut ppu_context, &mut mapper);
+        assert_eq!(ppu_data.buf, 0x22);
+
+        // four-screen: the 4 nametables in the VRAM
+        let mut mapper = Nrom::new(vec![0;0x4000], vec![], Mirroring::FourScreen, 0, false);
+        ppu_data.write(0x2C01, 0x33, &mut ppu_context, &mut mapper);
+        assert_eq!(ppu_context.vram.read(0x0C01), 0x33);
     }
 }
@@ -42,6 +42,36 @@ impl PpuMask {
         self.emphasize_blue              = (data & 0b10000000) >> 7 == 1;
     }
 
+    pub fn is_grayscale(&self) -> bool {
+        self.grayscale
+    }
+
+    /// Emphasis bits, bit 0: red, 1: green, 2: blue
+    pub fn get_emphasis(&self) -> u8 {
+        (self.emphasize_red as u8) | (self.emphasize_green as u8) << 1 | (self.emphasize_blue as u8) << 2
+    }
+
+    pub fn is_rendering_enabled(&self) -> bool {
+        self.show_background || self.show_sprites
+    }
+
+    pub fn is_background_enabled(&self) -> bool {
+        self.show_background
+    }
+
+    pub fn is_sprite_enabled(&self) -> bool {
+        self.show_sprites
+    }
+
+    pub fn is_background_leftmost_shown(&self) -> bool {
+        self.show_background_in_leftmost
+    }
+
+    pub fn is_sprite_leftmost_shown(&self) -> bool {
+        self.show_sprites_in_leftmost
+    }
+
+    #[cfg(test)]
     pub fn read(self) -> u8 {
         (self.grayscale as u8) |
         (self.show_background_in_leftmost as u8) << 1 |
@@ -64,14 +94,14 @@ mod ppu_mask_test {
         let mut ppu_mask = PpuMask::new();
         ppu_mask.write(0b10101010);
 
-        assert_eq!(ppu_mask.grayscale, false);
-        assert_eq!(ppu_mask.show_background_in_leftmost, true);
-        assert_eq!(ppu_mask.show_sprites_in_leftmost, false);
-        assert_eq!(ppu_mask.show_background, true);
-        assert_eq!(ppu_mask.show_sprites, false);
-        assert_eq!(ppu_mask.emphasize_red, true);
-        assert_eq!(ppu_mask.emphasize_green, false);
-        assert_eq!(ppu_mask.emphasize_blue, true);
+        assert!(!ppu_mask.grayscale);
+        assert!(ppu_mask.show_background_in_leftmost);
+        assert!(!ppu_mask.show_sprites_in_leftmost);
+        assert!(ppu_mask.show_background);
+        assert!(!ppu_mask.show_sprites);
+        assert!(ppu_mask.emphasize_red);
+        assert!(!ppu_mask.emphasize_green);
+        assert!(ppu_mask.emphasize_blue);
     }
 
     #[test]
@@ -0,0 +1,236 @@
+/// The internal registers shared by PPUCTRL, PPUSCROLL and PPUADDR.
+///
+/// v: current VRAM address (15 bits)
+/// t: temporary VRAM address (15 bits), the top left onscreen tile
+/// x: fine X scroll (3 bits)
+/// w: first or second write toggle, shared by PPUSCROLL and PPUADDR
+///
+/// v and t while rendering:
+///   yyy NN YYYYY XXXXX
+///   ||| || ||||| +++++-- coarse X scroll
+///   ||| || +++++-------- coarse Y scroll
+///   ||| ++-------------- nametable select
+///   +++----------------- fine Y scroll
+///
+/// refer: https://wiki.nesdev.com/w/index.php/PPU_scrolling
+pub struct VramAddr {
+    v: u16,
+    t: u16,
+    x: u8,
+    w: bool,
+}
+
+impl VramAddr {
+    pub fn new() -> Self {
+        VramAddr {
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+        }
+    }
+
+    /// The address on the PPU address bus. (14 bits)
+    pub fn read(&self) -> u16 {
+        self.v & 0x3FFF
+    }
+
+    /// $2000 write: t: ...GH.. ........ <- d: ......GH
+    pub fn write_ctrl(&mut self, data: u8) {
+        self.t = (self.t & !0x0C00) | ((data as u16 & 0x03) << 10);
+    }
+
+    /// $2005 first write:  t: ....... ...ABCDE <- d: ABCDE...
+    ///                     x:              FGH <- d: .....FGH
+    /// $2005 second write: t: FGH..AB CDE..... <- d: ABCDEFGH
+    pub fn write_scroll(&mut self, data: u8) {
+        if self.w {
+            self.t = (self.t & !0x73E0) | ((data as u16 & 0x07) << 12) | ((data as u16 & 0xF8) << 2);
+        } else {
+            self.t = (self.t & !0x001F) | (data as u16 >> 3);
+            self.x = data & 0x07;
+        }
+
+        self.w = !self.w;
+    }
+
+    /// $2006 first write:  t: .CDEFGH ........ <- d: ..CDEFGH (the bit 14 is cleared)
+    /// $2006 second write: t: ....... ABCDEFGH <- d: ABCDEFGH, then v = t
+    pub fn write_addr(&mut self, data: u8) {
+        if self.w {
+            self.t = (self.t & 0xFF00) | data as u16;
+            self.v = self.t;
+        } else {
+            self.t = (self.t & 0x00FF) | ((data as u16 & 0x3F) << 8);
+        }
+
+        self.w = !self.w;
+    }
+
+    /// $2002 read resets the write toggle.
+    pub fn reset_latch(&mut self) {
+        self.w = false;
+    }
+
+    /// $2007 access outside of rendering
+    pub fn update(&mut self, offset: u8) {
+        self.v = (self.v + offset as u16) & 0x7FFF;
+    }
+
+    /// Coarse X increment, wraps to the next nametable horizontally.
+    pub fn increment_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    /// Fine Y increment, overflows to coarse Y. Row 29 wraps to the next nametable vertically,
+    /// row 31 (in the attribute table) wraps without switching the nametable.
+    pub fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !0x7000;
+        let coarse_y = match self.coarse_y() {
+            29 => {
+                self.v ^= 0x0800;
+                0
+            },
+            31 => 0,
+            coarse_y => coarse_y + 1,
+        };
+        self.v = (self.v & !0x03E0) | ((coarse_y as u16) << 5);
+    }
+
+    /// Dot 257: v: ....A.. ...BCDEF <- t: ....A.. ...BCDEF
+    pub fn copy_x(&mut self) {
+        self.v = (self.v & !0x041F) | (self.t & 0x041F);
+    }
+
+    /// Dot 280-304 of the pre-render line: v: GHIA.BC DEF..... <- t: GHIA.BC DEF.....
+    pub fn copy_y(&mut self) {
+        self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0);
+    }
+
+    pub fn coarse_x(&self) -> u8 {
+        (self.v & 0x001F) as u8
+    }
+
+    pub fn coarse_y(&self) -> u8 {
+        ((self.v >> 5) & 0x001F) as u8
+    }
+
+    pub fn fine_y(&self) -> u8 {
+        ((self.v >> 12) & 0x07) as u8
+    }
+
+    pub fn fine_x(&self) -> u8 {
+        self.x
+    }
+}
+
+#[cfg(test)]
+mod vram_addr_test {
+    use super::*;
+
+    #[test]
+    fn write_addr_test() {
+        let mut vram_addr = VramAddr::new();
+
+        vram_addr.write_addr(0x23);
+        assert_eq!(vram_addr.read(), 0x0000); // v is updated by the second write
+        vram_addr.write_addr(0x45);
+        assert_eq!(vram_addr.read(), 0x2345);
+
+        // the bit 14 is cleared
+        vram_addr.write_addr(0xFF);
+        vram_addr.write_addr(0x00);
+        assert_eq!(vram_addr.v, 0x3F00);
+    }
+
+    #[test]
+    fn write_scroll_test() {
+        let mut vram_addr = VramAddr::new();
+        vram_addr.write_ctrl(0x02);
+        vram_addr.write_scroll(0x7D); // coarse X: 15, fine X: 5
+        vram_addr.write_scroll(0x5E); // coarse Y: 11, fine Y: 6
+        assert_eq!(vram_addr.t, 0x696F); // yyy: 6, NN: 2, YYYYY: 11, XXXXX: 15
+        assert_eq!(vram_addr.fine_x(), 5);
+    }
+
+    #[test]
+    fn shared_write_toggle_test() {
+        let mut vram_addr = VramAddr::new();
+
+        // $2006 then $2005: the second write goes to PPUSCROLL Y
+        vram_addr.write_addr(0x04);
+        vram_addr.write_scroll(0x3E);
+        assert_eq!(vram_addr.t, 0x64E0); // yyy: 6, NN: 1, YYYYY: 7
+        assert!(!vram_addr.w);
+
+        // reset by $2002 read
+        vram_addr.write_scroll(0x08);
+        vram_addr.reset_latch();
+        vram_addr.write_scroll(0x10);
+        assert_eq!(vram_addr.t & 0x001F, 0x02);
+    }
+
+    #[test]
+    fn increment_x_test() {
+        let mut vram_addr = VramAddr::new();
+        vram_addr.v = 0x001E;
+        vram_addr.increment_x();
+        assert_eq!(vram_addr.v, 0x001F);
+
+        // wraps to the next nametable
+        vram_addr.increment_x();
+        assert_eq!(vram_addr.v, 0x0400);
+    }
+
+    #[test]
+    fn increment_y_test() {
+        let mut vram_addr = VramAddr::new();
+        vram_addr.increment_y();
+        assert_eq!(vram_addr.fine_y(), 1);
+
+        // row 29 wraps to the next nametable
+        vram_addr.v = 0x7000 | (29 << 5);
+        vram_addr.increment_y();
+        assert_eq!(vram_addr.v, 0x0800);
+
+        // row 31 wraps without switching the nametable
+        vram_addr.v = 0x7000 | (31 << 5);
+        vram_addr.increment_y();
+        assert_eq!(vram_addr.v, 0x0000);
+
+        vram_addr.v = 0x7000 | (3 << 5);
+        vram_addr.increment_y();
+        assert_eq!(vram_addr.coarse_y(), 4);
+    }
+
+    #[test]
+    fn copy_test() {
+        let mut vram_addr = VramAddr::new();
+        vram_addr.t = 0x7FFF;
+
+        vram_addr.copy_x();
+        assert_eq!(vram_addr.v, 0x041F);
+
+        vram_addr.copy_y();
+        assert_eq!(vram_addr.v, 0x7FFF);
+    }
+
+    #[test]
+    fn update_test() {
+        let mut vram_addr = VramAddr::new();
+        vram_addr.update(1);
+        assert_eq!(vram_addr.read(), 0x0001);
+        vram_addr.update(32);
+        assert_eq!(vram_addr.read(), 0x0021);
+    }
+}
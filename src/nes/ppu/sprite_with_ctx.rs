@@ -1,18 +1,207 @@
-use crate::nes::ram::Ram;
-use super::sprite::Sprite;
+use super::PpuContext;
+use super::palette::PaletteGroup;
+use super::palette_ram::PaletteType;
 
 pub type SpritesWithCtx = Vec<SpriteWithCtx>;
 
+/// Bytes of a sprite in OAM
+pub const SPRITE_ATTRIBUTE_BYTES: usize = 4;
+/// Sprites drawn in a line at most
+pub const MAX_SPRITES_IN_LINE: usize = 8;
+
+/// A sprite on a line, evaluated from OAM.
+///
+/// <OAM>
+/// byte 0: Y position of top of sprite - 1
+/// byte 1: tile index number
+///   8x8 sprites: the tile in the pattern table of PPUCTRL
+///   8x16 sprites:
+///     76543210
+///     ||||||||
+///     |||||||+- Bank ($0000 or $1000) of tiles
+///     +++++++-- Tile number of top of sprite (the bottom half gets the next tile)
+/// byte 2: attributes
+///   76543210
+///   ||||||||
+///   ||||||++- Palette (4 to 7) of sprite
+///   |||+++--- Unimplemented
+///   ||+------ Priority (0: in front of background; 1: behind background)
+///   |+------- Flip sprite horizontally
+///   +-------- Flip sprite vertically
+/// byte 3: X position of left side of sprite.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/PPU_OAM
 #[derive(Debug)]
 pub struct SpriteWithCtx {
-    pub sprite: Sprite,
+    pub x: u8,
+    /// Palette numbers (0 - 3) of the line from the left, flipped already. 0: transparent
+    pub pattern: [u8; 8],
+    pub palettes: PaletteGroup,
+    pub is_behind_background: bool,
+    /// Sprite 0 of OAM, for the sprite 0 hit
+    pub is_sprite_zero: bool,
+}
+
+/// A sprite found by the sprite evaluation, waiting for its pattern fetch. (an entry of the secondary OAM)
+#[derive(Debug)]
+pub struct SpriteSlot {
+    pub oam: [u8; SPRITE_ATTRIBUTE_BYTES],
+    pub is_sprite_zero: bool,
+    /// Address of the pattern low byte, the high byte is 8 bytes after it
+    pub pattern_addr: u16,
 }
 
 impl SpriteWithCtx {
+    /// Address of the pattern low byte of the sprite row on the line.
+    ///
+    /// oam: 4 bytes of the sprite in OAM, the sprite is in the range of the line
+    /// pattern_table_addr: the sprite pattern table of PPUCTRL, ignored for 8x16 sprites
+    pub fn pattern_addr(line: usize, oam: &[u8], is_8x16: bool, pattern_table_addr: u16) -> u16 {
+        let y = oam[0] as usize;
+        let tile_number = oam[1];
+        let is_flip_vertical = oam[2] & 0x80 == 0x80;
+
+        let height = if is_8x16 { 16 } else { 8 };
+        let row = line - y;
+        // the vertical flip swaps the 2 tiles of 8x16 sprites too
+        let row = if is_flip_vertical { height - 1 - row } else { row };
+
+        let (pattern_table_addr, tile_number) = if is_8x16 {
+            ((tile_number as u16 & 0x01) * 0x1000, (tile_number & 0xFE) + (row / 8) as u8)
+        } else {
+            (pattern_table_addr, tile_number)
+        };
+        pattern_table_addr + tile_number as u16 * 16 + (row % 8) as u16
+    }
+
+    /// low / high: the pattern bytes fetched from pattern_addr()
+    pub fn build(oam: &[u8], is_sprite_zero: bool, low: u8, high: u8, ppu_context: &PpuContext) -> Self {
+        let attribute = oam[2];
+        let x = oam[3];
+        let is_flip_horizontal = attribute & 0x40 == 0x40;
+
+        let mut pattern = [0; 8];
+        for (pixel, palette_number) in pattern.iter_mut().enumerate() {
+            let shift = if is_flip_horizontal { pixel } else { 7 - pixel };
+            *palette_number = ((low >> shift) & 0x01) | ((high >> shift) & 0x01) << 1;
+        }
+
+        SpriteWithCtx {
+            x,
+            pattern,
+            palettes: ppu_context.palette_ram.get_palettes(attribute & 0x03, PaletteType::Sprite),
+            is_behind_background: attribute & 0x20 == 0x20,
+            is_sprite_zero,
+        }
+    }
+
+    /// Palette number (1 - 3) at the screen x. None: transparent or out of the sprite.
+    pub fn palette_number(&self, x: usize) -> Option<u8> {
+        let pixel = x.checked_sub(self.x as usize)?;
+        match self.pattern.get(pixel) {
+            Some(0) | None => None,
+            Some(palette_number) => Some(*palette_number),
+        }
+    }
 }
 
 #[cfg(test)]
 mod sprite_with_ctx_test {
     use super::*;
+    use crate::nes::cassette::mapper::{Mapper, Mirroring};
+    use crate::nes::cassette::mapper::nrom::Nrom;
+    use crate::nes::ppu::palette_ram::PaletteRam;
+    use crate::nes::ram::Ram;
+
+    fn dummy_ppu_context() -> PpuContext {
+        let mut palette_ram = PaletteRam::new();
+        palette_ram.write(0x19, 0x21);
+        palette_ram.write(0x1A, 0x22);
+        palette_ram.write(0x1B, 0x23);
+
+        PpuContext {
+            vram: Ram::new(vec![0;0x20]),
+            sprite_ram: Ram::new(vec![0;0x20]),
+            palette_ram,
+        }
+    }
 
-}
\ No newline at end of file
+    /// tile 1 at $1000: the row 0 is 0b11000001 / 0b10000000, the row 7 is 0b00000001 / 0
+    fn dummy_mapper() -> Nrom {
+        let mut character_rom = vec![0; 0x2000];
+        character_rom[0x1010] = 0b11000001;
+        character_rom[0x1018] = 0b10000000;
+        character_rom[0x1017] = 0b00000001;
+        Nrom::new(vec![0;0x4000], character_rom, Mirroring::Horizontal, 0, false)
+    }
+
+    /// Fetches the pattern of the sprite row on the line, and builds the sprite.
+    fn build_on_line(line: usize, oam: &[u8], is_sprite_zero: bool, is_8x16: bool, pattern_table_addr: u16, ppu_context: &PpuContext, mapper: &mut Nrom) -> SpriteWithCtx {
+        let addr = SpriteWithCtx::pattern_addr(line, oam, is_8x16, pattern_table_addr);
+        let (low, high) = (mapper.read_ppu(addr), mapper.read_ppu(addr + 8));
+        SpriteWithCtx::build(oam, is_sprite_zero, low, high, ppu_context)
+    }
+
+    #[test]
+    fn build_test() {
+        let ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
+
+        let sprite = build_on_line(10, &[10, 1, 0x22, 100], true, false, 0x1000, &ppu_context, &mut mapper);
+        assert_eq!(sprite.x, 100);
+        assert_eq!(sprite.pattern, [3, 1, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(sprite.palettes, PaletteGroup::build(&[0x00, 0x21, 0x22, 0x23]));
+        assert!(sprite.is_behind_background);
+        assert!(sprite.is_sprite_zero);
+    }
+
+    #[test]
+    fn build_flip_test() {
+        let ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
+
+        // horizontal
+        let sprite = build_on_line(10, &[10, 1, 0x40, 0], false, false, 0x1000, &ppu_context, &mut mapper);
+        assert_eq!(sprite.pattern, [1, 0, 0, 0, 0, 0, 1, 3]);
+        assert!(!sprite.is_behind_background);
+
+        // vertical: the row 7 at the top
+        let sprite = build_on_line(10, &[10, 1, 0x80, 0], false, false, 0x1000, &ppu_context, &mut mapper);
+        assert_eq!(sprite.pattern, [0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn build_8x16_test() {
+        let ppu_context = dummy_ppu_context();
+        let mut character_rom = vec![0; 0x2000];
+        // tile $22 / $23 at $1000
+        character_rom[0x1220] = 0b10000000;
+        character_rom[0x1237] = 0b00000001;
+        let mut mapper = Nrom::new(vec![0;0x4000], character_rom, Mirroring::Horizontal, 0, false);
+
+        // tile $23: $1000, the top tile $22, the pattern table of PPUCTRL is ignored
+        let sprite = build_on_line(10, &[10, 0x23, 0x00, 0], false, true, 0x0000, &ppu_context, &mut mapper);
+        assert_eq!(sprite.pattern, [1, 0, 0, 0, 0, 0, 0, 0]);
+        let sprite = build_on_line(25, &[10, 0x23, 0x00, 0], false, true, 0x0000, &ppu_context, &mut mapper);
+        assert_eq!(sprite.pattern, [0, 0, 0, 0, 0, 0, 0, 1]);
+
+        // the vertical flip swaps the tiles
+        let sprite = build_on_line(10, &[10, 0x23, 0x80, 0], false, true, 0x0000, &ppu_context, &mut mapper);
+        assert_eq!(sprite.pattern, [0, 0, 0, 0, 0, 0, 0, 1]);
+        let sprite = build_on_line(25, &[10, 0x23, 0x80, 0], false, true, 0x0000, &ppu_context, &mut mapper);
+        assert_eq!(sprite.pattern, [1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn palette_number_test() {
+        let ppu_context = dummy_ppu_context();
+        let mut mapper = dummy_mapper();
+
+        let sprite = build_on_line(10, &[10, 1, 0x00, 100], false, false, 0x1000, &ppu_context, &mut mapper);
+        assert_eq!(sprite.palette_number(99), None);
+        assert_eq!(sprite.palette_number(100), Some(3));
+        assert_eq!(sprite.palette_number(102), None);
+        assert_eq!(sprite.palette_number(107), Some(1));
+        assert_eq!(sprite.palette_number(108), None);
+    }
+}
@@ -7,7 +7,7 @@ pub struct Ram {
 impl Ram {
     pub fn new(buf: Vec<u8>) -> Self {
         Self {
-            buf: buf,
+            buf,
         }
     }
 
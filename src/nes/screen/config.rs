@@ -0,0 +1,175 @@
+use crate::nes::ppu::frame_buffer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+/// Width / height of a pixel on an NTSC TV
+const NTSC_PIXEL_ASPECT: f64 = 8.0 / 7.0;
+
+/// Lines / pixels cropped at each edge of the frame.
+/// Most TVs hide about 8 lines at the top and the bottom.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/Overscan
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Overscan {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+/// How the frame is placed in the window.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ScreenConfig {
+    /// Initial window size in the frame size
+    pub scale: usize,
+    /// Scales the frame by whole numbers, the rest of the window is letterboxed.
+    pub integer_scaling: bool,
+    /// Stretches the pixels horizontally to 8:7 like an NTSC TV.
+    pub pixel_aspect: bool,
+    pub overscan: Overscan,
+}
+
+/// A rectangle in the frame or the window. (x, y, width, height)
+pub type Area = (i32, i32, u32, u32);
+
+impl ScreenConfig {
+    pub fn new() -> Self {
+        ScreenConfig {
+            scale: 2,
+            integer_scaling: false,
+            pixel_aspect: false,
+            overscan: Overscan { top: 8, bottom: 8, left: 0, right: 0 },
+        }
+    }
+
+    /// Takes the screen options out of the command line arguments, and returns the rest.
+    ///
+    /// --scale=<n>             : initial window size (2 by default)
+    /// --integer-scaling       : scale by whole numbers with letterboxing
+    /// --pixel-aspect          : 8:7 pixel aspect
+    /// --overscan=<t>,<b>,<l>,<r> : lines / pixels cropped at the top, bottom, left, right (8,8,0,0 by default)
+    pub fn from_args(args: &[String]) -> Result<(ScreenConfig, Vec<String>), String> {
+        let mut config = ScreenConfig::new();
+        let mut rest = Vec::new();
+
+        for arg in args {
+            match arg.split_once('=') {
+                Some(("--scale", scale)) => {
+                    config.scale = scale.parse().map_err(|_| format!("scale is not a number: {}", scale))?;
+                    if config.scale == 0 {
+                        return Err("scale must be 1 or more.".to_string());
+                    }
+                },
+                Some(("--overscan", edges)) => {
+                    let edges = edges
+                        .split(',')
+                        .map(|edge| edge.parse::<usize>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| format!("overscan is not numbers: {}", edges))?;
+                    config.overscan = match edges[..] {
+                        [top, bottom, left, right] if top + bottom < SCREEN_HEIGHT && left + right < SCREEN_WIDTH => {
+                            Overscan { top, bottom, left, right }
+                        },
+                        _ => return Err("overscan must be 4 edges in the frame: <top>,<bottom>,<left>,<right>".to_string()),
+                    };
+                },
+                _ if arg == "--integer-scaling" => config.integer_scaling = true,
+                _ if arg == "--pixel-aspect" => config.pixel_aspect = true,
+                _ => rest.push(arg.clone()),
+            }
+        }
+
+        Ok((config, rest))
+    }
+
+    /// The part of the frame on the screen, without the overscan.
+    pub fn source(&self) -> Area {
+        let overscan = &self.overscan;
+        (
+            overscan.left as i32,
+            overscan.top as i32,
+            (SCREEN_WIDTH - overscan.left - overscan.right) as u32,
+            (SCREEN_HEIGHT - overscan.top - overscan.bottom) as u32,
+        )
+    }
+
+    pub fn window_size(&self) -> (u32, u32) {
+        let (width, height) = self.display_size();
+        ((width * self.scale as f64).round() as u32, (height * self.scale as f64).round() as u32)
+    }
+
+    /// Where the frame is drawn in the window, centered with the aspect ratio kept.
+    pub fn destination(&self, window_width: u32, window_height: u32) -> Area {
+        let (width, height) = self.display_size();
+        let scale = (window_width as f64 / width).min(window_height as f64 / height);
+        let scale = if self.integer_scaling { scale.floor().max(1.0) } else { scale };
+
+        let (width, height) = ((width * scale).round() as u32, (height * scale).round() as u32);
+        (
+            (window_width as i32 - width as i32) / 2,
+            (window_height as i32 - height as i32) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// Size of the frame in the square pixels at the scale 1.
+    fn display_size(&self) -> (f64, f64) {
+        let (_, _, width, height) = self.source();
+        let pixel_aspect = if self.pixel_aspect { NTSC_PIXEL_ASPECT } else { 1.0 };
+        (width as f64 * pixel_aspect, height as f64)
+    }
+}
+
+#[cfg(test)]
+mod config_test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn from_args_test() {
+        let (config, rest) = ScreenConfig::from_args(&args(&["nes", "rom.nes"])).unwrap();
+        assert_eq!(config, ScreenConfig::new());
+        assert_eq!(rest, args(&["nes", "rom.nes"]));
+
+        let (config, rest) = ScreenConfig::from_args(&args(&[
+            "nes", "--scale=3", "--integer-scaling", "--pixel-aspect", "--overscan=8,16,4,0", "rom.nes",
+        ])).unwrap();
+        assert_eq!(config.scale, 3);
+        assert!(config.integer_scaling);
+        assert!(config.pixel_aspect);
+        assert_eq!(config.overscan, Overscan { top: 8, bottom: 16, left: 4, right: 0 });
+        assert_eq!(rest, args(&["nes", "rom.nes"]));
+
+        assert!(ScreenConfig::from_args(&args(&["--scale=0"])).is_err());
+        assert!(ScreenConfig::from_args(&args(&["--overscan=8,8"])).is_err());
+        assert!(ScreenConfig::from_args(&args(&["--overscan=120,120,0,0"])).is_err());
+    }
+
+    #[test]
+    fn window_size_test() {
+        let mut config = ScreenConfig::new();
+        assert_eq!(config.source(), (0, 8, 256, 224));
+        assert_eq!(config.window_size(), (512, 448));
+
+        config.pixel_aspect = true;
+        config.overscan = Overscan { top: 0, bottom: 0, left: 0, right: 0 };
+        assert_eq!(config.window_size(), (585, 480));
+    }
+
+    #[test]
+    fn destination_test() {
+        let mut config = ScreenConfig::new();
+
+        // fits the width, letterboxed at the top and the bottom
+        assert_eq!(config.destination(1024, 1000), (0, 52, 1024, 896));
+
+        // integer scaling: 3x in 800x700, letterboxed around
+        config.integer_scaling = true;
+        assert_eq!(config.destination(800, 700), (16, 14, 768, 672));
+
+        // 1x at least in a small window
+        assert_eq!(config.destination(200, 200), (-28, -12, 256, 224));
+    }
+}
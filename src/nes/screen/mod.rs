@@ -1,26 +1,46 @@
 extern crate sdl2;
 
+pub mod config;
+
+use sdl2::Sdl;
 use sdl2::EventPump;
-use sdl2::pixels::Color;
-use sdl2::render::WindowCanvas;
-use sdl2::rect::Point;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::{Texture, WindowCanvas};
+use sdl2::rect::{Point, Rect};
+use sdl2::video::FullscreenType;
+
+use self::config::{Area, ScreenConfig};
+
+use crate::nes::ppu::frame_buffer::{FrameBuffer, SCREEN_WIDTH, SCREEN_HEIGHT};
 
-use crate::nes::ppu::background::Background;
+/// Bytes of an RGBA pixel
+const BYTES_PER_PIXEL: usize = 4;
 
 pub struct Screen {
     pub canvas: WindowCanvas,
     pub events: EventPump,
+    config: ScreenConfig,
+    /// The frame is uploaded to the texture at once, then the texture is copied to the window.
+    /// It's owned by the canvas. (unsafe_textures of sdl2)
+    texture: Texture,
+    /// RGB of the pixels in the frame buffer (color + emphasis bits)
+    palette: Vec<[u8; 3]>,
+    /// The palette in the pixel format of the texture
+    rgba_palette: Vec<u32>,
+    /// Pixels of the texture
+    pixels: Vec<u8>,
 }
 
 impl Screen {
-    pub fn new(width: u32, height: u32) -> Screen {
-        let sdl = sdl2::init().unwrap();
+    pub fn new(sdl: &Sdl, config: ScreenConfig) -> Screen {
         let video_subsystem = sdl.video().unwrap();
         let events = sdl.event_pump().unwrap();
 
+        let (width, height) = config.window_size();
         let window = video_subsystem
             .window("Rust NES Emulator", width, height)
             .position_centered()
+            .resizable()
             .build()
             .unwrap();
 
@@ -35,38 +55,105 @@ impl Screen {
         canvas.clear();
         canvas.present();
 
+        let texture = canvas
+            .create_texture_streaming(PixelFormatEnum::RGBA8888, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+            .unwrap();
+        let palette = build_palette();
+
         Screen {
-            canvas: canvas,
-            events: events,
+            canvas,
+            events,
+            config,
+            texture,
+            rgba_palette: build_rgba_palette(&palette),
+            palette,
+            pixels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * BYTES_PER_PIXEL],
         }
     }
 
-    pub fn render_background(&mut self, background: &Background) {
-        for tile in background.iter() {
-            let sprite = &tile.sprite;
-            let position = &tile.position;
-
-            let start_pos_x = position.x as i32 * 8;
-            let start_pos_y = position.y as i32 * 8;
-
-            for (y, sprite_line) in sprite.to_vec().iter().enumerate() {
-                for (x, palette_number) in sprite_line.iter().enumerate() {
-                    let point_x = start_pos_x + x as i32;
-                    let point_y = start_pos_y + y as i32;
-                    let draw_point = Point::new(point_x, point_y);
-
-                    let palette = &tile.palettes.get(*palette_number as usize);
-                    let color = NES_COLORS[palette.get_palette_number() as usize];
-                    self.canvas.set_draw_color(Color::RGB(color[0], color[1], color[2]));
-                    self.canvas.draw_point(draw_point).unwrap();
-                }
-            }
+    pub fn render(&mut self, frame_buffer: &FrameBuffer) {
+        self.draw(frame_buffer);
+        self.canvas.present();
+    }
+
+    /// Draws the frame with a texture update, without presenting it.
+    /// The frame without the overscan is scaled to the window, the rest is black.
+    pub fn draw(&mut self, frame_buffer: &FrameBuffer) {
+        to_rgba(frame_buffer, &self.rgba_palette, &mut self.pixels);
+        self.texture.update(None, &self.pixels, SCREEN_WIDTH * BYTES_PER_PIXEL).unwrap();
+
+        let (window_width, window_height) = self.canvas.output_size().unwrap();
+        let source = to_rect(self.config.source());
+        let destination = to_rect(self.config.destination(window_width, window_height));
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        self.canvas.copy(&self.texture, source, destination).unwrap();
+    }
+
+    /// Switches between the window and the desktop fullscreen.
+    pub fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        if let Err(err) = window.set_fullscreen(fullscreen) {
+            eprintln!("failed to switch the fullscreen: {}", err);
         }
+    }
 
-        self.canvas.present();
+    /// Draws the frame point by point, without presenting it.
+    /// Too slow for 60 FPS, kept as the baseline of the benchmark.
+    pub fn draw_points(&mut self, frame_buffer: &FrameBuffer) {
+        for (i, pixel) in frame_buffer.pixels().iter().enumerate() {
+            let color = self.palette[*pixel as usize & 0x01FF];
+            self.canvas.set_draw_color(Color::RGB(color[0], color[1], color[2]));
+            self.canvas.draw_point(Point::new((i % SCREEN_WIDTH) as i32, (i / SCREEN_WIDTH) as i32)).unwrap();
+        }
     }
 }
 
+fn to_rect((x, y, width, height): Area) -> Rect {
+    Rect::new(x, y, width, height)
+}
+
+/// Converts the frame buffer to the pixels of the texture by the lookup table.
+/// The pixels are u32 of RGBA8888 in the native byte order.
+fn to_rgba(frame_buffer: &FrameBuffer, rgba_palette: &[u32], pixels: &mut [u8]) {
+    for (pixel, rgba) in frame_buffer.pixels().iter().zip(pixels.chunks_exact_mut(BYTES_PER_PIXEL)) {
+        rgba.copy_from_slice(&rgba_palette[*pixel as usize & 0x01FF].to_ne_bytes());
+    }
+}
+
+fn build_rgba_palette(palette: &[[u8; 3]]) -> Vec<u32> {
+    palette
+        .iter()
+        .map(|[r, g, b]| (*r as u32) << 24 | (*g as u32) << 16 | (*b as u32) << 8 | 0xFF)
+        .collect()
+}
+
+/// Amount of a color component left by the emphasis of the other components
+const EMPHASIS_ATTENUATION: f32 = 0.816;
+
+/// 512 colors: 64 colors x 8 combinations of the emphasis bits.
+///
+/// Each emphasis bit darkens the other 2 components, so the emphasized component stands out.
+///
+/// refer: https://wiki.nesdev.com/w/index.php/NTSC_video#Color_Tint_Bits
+fn build_palette() -> Vec<[u8; 3]> {
+    (0..0x200)
+        .map(|pixel: usize| {
+            let emphasis = pixel >> 6;
+            let mut rgb = NES_COLORS[pixel & 0x3F];
+            for (component, value) in rgb.iter_mut().enumerate() {
+                let attenuations = (0..3).filter(|bit| *bit != component && emphasis & (1 << bit) != 0).count();
+                *value = (*value as f32 * EMPHASIS_ATTENUATION.powi(attenuations as i32)) as u8;
+            }
+            rgb
+        })
+        .collect()
+}
+
 static NES_COLORS: [[u8; 3]; 64] = [
     [0x80, 0x80, 0x80], [0x00, 0x3D, 0xA6], [0x00, 0x12, 0xB0], [0x44, 0x00, 0x96],
     [0xA1, 0x00, 0x5E], [0xC7, 0x00, 0x28], [0xBA, 0x06, 0x00], [0x8C, 0x17, 0x00],
@@ -84,4 +171,42 @@ static NES_COLORS: [[u8; 3]; 64] = [
     [0xFF, 0xA8, 0xF9], [0xFF, 0xAB, 0xB3], [0xFF, 0xD2, 0xB0], [0xFF, 0xEF, 0xA6],
     [0xFF, 0xF7, 0x9C], [0xD7, 0xE8, 0x95], [0xA6, 0xED, 0xAF], [0xA2, 0xF2, 0xDA],
     [0x99, 0xFF, 0xFC], [0xDD, 0xDD, 0xDD], [0x11, 0x11, 0x11], [0x11, 0x11, 0x11],
-];
\ No newline at end of file
+];
+
+#[cfg(test)]
+mod screen_test {
+    use super::*;
+
+    #[test]
+    fn build_palette_test() {
+        let palette = build_palette();
+        assert_eq!(palette.len(), 0x200);
+        assert_eq!(palette[0x30], [0xFF, 0xFF, 0xFF]);
+
+        // red: the green and the blue are darkened
+        assert_eq!(palette[0x40 | 0x30], [0xFF, 0xD0, 0xD0]);
+        // red + blue: the green is darkened twice
+        assert_eq!(palette[0x140 | 0x30], [0xD0, 0xA9, 0xD0]);
+        // all: all the components are darkened twice
+        assert_eq!(palette[0x1C0 | 0x30], [0xA9, 0xA9, 0xA9]);
+    }
+
+    #[test]
+    fn to_rgba_test() {
+        let rgba_palette = build_rgba_palette(&build_palette());
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer.set(1, 0, 0x30);
+        frame_buffer.set(0, 1, 0x40 | 0x30);
+
+        let mut pixels = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * BYTES_PER_PIXEL];
+        to_rgba(&frame_buffer, &rgba_palette, &mut pixels);
+
+        let pixel = |x: usize, y: usize| {
+            let offset = (y * SCREEN_WIDTH + x) * BYTES_PER_PIXEL;
+            u32::from_ne_bytes(*array_ref!(pixels, offset, 4))
+        };
+        assert_eq!(pixel(0, 0), 0x808080FF);
+        assert_eq!(pixel(1, 0), 0xFFFFFFFF);
+        assert_eq!(pixel(0, 1), 0xFFD0D0FF);
+    }
+}